@@ -0,0 +1,63 @@
+//! Dispatch cost of `fn` pointer vs generic `F: Fn` vs `Box<dyn Fn>`
+//!
+//! ```sh
+//! cargo bench -p async_stuff --bench callbacks
+//! ```
+//!
+//! - `call/*`: the callback alone in a tight loop. Generic can be inlined (~2x faster here), the
+//!   other two are an indirect call each.
+//! - `executor/*`: the callback as the mini executor's `on_poll` hook. Polling dominates so the
+//!   difference mostly disappears.
+
+use async_stuff::callbacks::{count_poll, counting_boxed, counting_closure, run_with};
+use async_stuff::mini_executor::TaskId;
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::cell::Cell;
+use std::hint::black_box;
+use std::rc::Rc;
+
+const CALLS: u64 = 1_000;
+
+fn mix(acc: u64, x: u64) -> u64 {
+    acc.rotate_left(5) ^ x.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+fn call_fn_ptr(f: fn(u64, u64) -> u64) -> u64 {
+    (0..CALLS).fold(0, f)
+}
+
+fn call_generic<F: Fn(u64, u64) -> u64>(f: F) -> u64 {
+    (0..CALLS).fold(0, f)
+}
+
+fn call_dyn(f: &dyn Fn(u64, u64) -> u64) -> u64 {
+    (0..CALLS).fold(0, f)
+}
+
+fn bench_call(c: &mut Criterion) {
+    let mut group = c.benchmark_group("call");
+    // NB: black_box() hides the pointer's value, otherwise the compiler sees through it
+    group.bench_function("fn_ptr", |b| b.iter(|| call_fn_ptr(black_box(mix))));
+    group.bench_function("generic", |b| b.iter(|| call_generic(mix)));
+    let boxed: Box<dyn Fn(u64, u64) -> u64> = Box::new(mix);
+    group.bench_function("dyn", |b| b.iter(|| call_dyn(black_box(&*boxed))));
+    group.finish();
+}
+
+fn bench_executor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("executor");
+    group.bench_function("fn_ptr", |b| {
+        b.iter(|| run_with(black_box(count_poll as fn(TaskId)), 10, 10))
+    });
+    let polls = Rc::new(Cell::new(0));
+    group.bench_function("generic", |b| {
+        b.iter(|| run_with(counting_closure(polls.clone()), 10, 10))
+    });
+    group.bench_function("dyn", |b| {
+        b.iter(|| run_with(counting_boxed(polls.clone()), 10, 10))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_call, bench_executor);
+criterion_main!(benches);
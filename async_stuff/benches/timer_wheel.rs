@@ -0,0 +1,76 @@
+//! Insert 10k timers with random delays up to 1 s, then expire them all
+//!
+//! ```sh
+//! cargo bench -p async_stuff --bench timer_wheel
+//! ```
+//!
+//! - `wheel/*`: [TimerWheel] with that many slots, advanced by hand \[no clock\]. 1 slot is a
+//!   list scanned on every tick.
+//! - `delay_queue`: `tokio_util::time::DelayQueue` drained on a paused current-thread runtime,
+//!   which jumps straight to the next deadline.
+
+use async_stuff::timer_wheel::TimerWheel;
+use criterion::{Criterion, criterion_group, criterion_main};
+use futures::StreamExt;
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+use std::hint::black_box;
+use std::time::Duration;
+use tokio_util::time::DelayQueue;
+
+const TIMERS: usize = 10_000;
+const MS: Duration = Duration::from_millis(1);
+
+fn delays() -> Vec<Duration> {
+    let mut rng = StdRng::seed_from_u64(42);
+    (0..TIMERS)
+        .map(|_| rng.random_range(1..=1000) * MS)
+        .collect()
+}
+
+fn bench_wheel(c: &mut Criterion) {
+    let delays = delays();
+    let mut group = c.benchmark_group("wheel");
+    for slots in [1, 64, 1024] {
+        group.bench_function(slots.to_string(), |b| {
+            b.iter(|| {
+                let mut wheel = TimerWheel::new(MS, slots);
+                for (i, &d) in delays.iter().enumerate() {
+                    wheel.insert(d, i);
+                }
+                let mut expired = Vec::with_capacity(TIMERS);
+                while !wheel.is_empty() {
+                    wheel.advance(1, &mut expired);
+                }
+                black_box(expired)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_delay_queue(c: &mut Criterion) {
+    let delays = delays();
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    c.bench_function("delay_queue", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut queue = DelayQueue::with_capacity(TIMERS);
+                for (i, &d) in delays.iter().enumerate() {
+                    queue.insert(i, d);
+                }
+                let mut expired = Vec::with_capacity(TIMERS);
+                while let Some(e) = queue.next().await {
+                    expired.push(e.into_inner());
+                }
+                black_box(expired)
+            })
+        })
+    });
+}
+
+criterion_group!(benches, bench_wheel, bench_delay_queue);
+criterion_main!(benches);
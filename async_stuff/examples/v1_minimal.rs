@@ -0,0 +1,26 @@
+//! Same as [async_stuff::fasterthanlime_pin::v1] but with the minimal dependency set
+//!
+//! | crate | features | why |
+//! | --- | --- | --- |
+//! | tokio | `rt`, `macros` | single-threaded runtime + `#[tokio::main]` |
+//! | tokio | `fs`, `io-util` | `File` and `AsyncReadExt::read_exact()` |
+//!
+//! NB: No anyhow (plain [std::io::Result]) and no `rt-multi-thread` (`flavor = "current_thread"`).
+//! Cargo unifies features across the package, so building this example still compiles the
+//! full tokio feature set from the workspace. The table is what the concept actually needs.
+//!
+//! ```sh
+//! cargo run -p async_stuff --example v1_minimal
+//! ```
+
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> std::io::Result<()> {
+    let mut f = File::open("/dev/urandom").await?;
+    let mut buf = [0u8; 32];
+    let read_len = f.read_exact(&mut buf).await?;
+    println!("v1 Read {} bytes {:?}", read_len, buf);
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! Same as [async_stuff::fasterthanlime_pin::v2] but on [futures::io::AsyncRead] \[instead of tokio's\]
+//!
+//! | crate | features | why |
+//! | --- | --- | --- |
+//! | futures | `std` | `AsyncRead`, `AsyncReadExt`, `AllowStdIo` |
+//! | futures | `executor` | `block_on()` \[instead of a runtime\] |
+//!
+//! A pass-through wrapper needs no runtime, no timer and no reactor at all:
+//! [futures::io::AllowStdIo] adapts a blocking [std::fs::File] and `block_on()` drives the future.
+//!
+//! NB: futures' [AsyncRead::poll_read] takes `&mut [u8]` \[instead of tokio's `ReadBuf`\]
+//! and returns how many bytes were read.
+//!
+//! ```sh
+//! cargo run -p async_stuff --example v2_futures_only
+//! ```
+
+use futures::io::{AllowStdIo, AsyncRead, AsyncReadExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub struct ReadWrap<R> {
+    read: R,
+}
+
+impl<R> ReadWrap<R> {
+    pub fn new(read: R) -> Self {
+        Self { read }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ReadWrap<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.read).poll_read(cx, buf)
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    futures::executor::block_on(async {
+        let f = AllowStdIo::new(std::fs::File::open("/dev/urandom")?);
+        let mut f = ReadWrap::new(f);
+        let mut buf = [0u8; 32];
+        f.read_exact(&mut buf).await?;
+        println!("v2 (futures only) Read {} bytes {:?}", buf.len(), buf);
+        Ok(())
+    })
+}
@@ -0,0 +1,73 @@
+//! Same as [async_stuff::fasterthanlime_pin::v5] but with the minimal dependency set
+//!
+//! | crate | features | why |
+//! | --- | --- | --- |
+//! | tokio | `rt`, `macros` | single-threaded runtime + `#[tokio::main]` |
+//! | tokio | `time` | [tokio::time::Sleep] \[the delay needs a timer driver\] |
+//! | tokio | `io-util` | `AsyncReadExt::read_exact()` |
+//! | pin-project-lite | | safe pin projection \[instead of `unsafe`\] |
+//!
+//! NB: Compared to v1 the `fs` feature is gone as the reader is just an in-memory `&[u8]`,
+//! but `time` is now needed. Unlike `v2_futures_only`, `futures::executor::block_on()` would
+//! *not* work here because [tokio::time::Sleep] panics outside of a tokio runtime.
+//!
+//! ```sh
+//! cargo run -p async_stuff --example v5_minimal
+//! ```
+
+use pin_project_lite::pin_project;
+use std::pin::{Pin, pin};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tokio::time::{self, Instant, Sleep};
+
+pin_project! {
+    pub struct ReadWrap<R> {
+        #[pin]
+        read: R,
+        #[pin]
+        sleep: Sleep,
+    }
+}
+
+impl<R> ReadWrap<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read,
+            sleep: time::sleep(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for ReadWrap<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let mut this = self.project();
+        match this.sleep.as_mut().poll(cx) {
+            Poll::Ready(_) => {
+                this.sleep.reset(Instant::now() + Duration::from_secs(1));
+                this.read.poll_read(cx, buf)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> std::io::Result<()> {
+    let mut f = pin!(ReadWrap::new(&b"hello, minimal world"[..]));
+    let mut buf = [0u8; 20];
+    let now = Instant::now();
+    let read_len = f.read_exact(&mut buf).await?;
+    println!(
+        "v5 (minimal) Read {} bytes {:?} after {:?}",
+        read_len,
+        String::from_utf8_lossy(&buf),
+        now.elapsed()
+    );
+    Ok(())
+}
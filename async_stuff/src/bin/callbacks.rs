@@ -0,0 +1,49 @@
+//! Register the same `on_poll` callback on the mini executor as a `fn` pointer, a closure and a
+//! `Box<dyn Fn>`. See [async_stuff::callbacks] for the comparison table.
+
+use async_stuff::callbacks::{
+    FN_PTR_POLLS, count_poll, counting_boxed, counting_closure, run_with,
+};
+use async_stuff::mini_executor::TaskId;
+use std::cell::Cell;
+use std::mem::size_of_val;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+
+const TASKS: usize = 3;
+const YIELDS: usize = 4;
+
+pub fn main() {
+    let fn_ptr: fn(TaskId) = count_poll;
+    let polls = run_with(fn_ptr, TASKS, YIELDS);
+    println!(
+        "fn pointer:   size {:>2}, polls {polls}, counted {}",
+        size_of_val(&fn_ptr),
+        FN_PTR_POLLS.load(Ordering::Relaxed)
+    );
+
+    let counter = Rc::new(Cell::new(0));
+    let closure = counting_closure(counter.clone());
+    let size = size_of_val(&closure);
+    let polls = run_with(closure, TASKS, YIELDS);
+    println!(
+        "closure:      size {size:>2}, polls {polls}, counted {}",
+        counter.get()
+    );
+
+    let counter = Rc::new(Cell::new(0));
+    let boxed = counting_boxed(counter.clone());
+    let size = size_of_val(&boxed);
+    let polls = run_with(boxed, TASKS, YIELDS);
+    println!(
+        "Box<dyn Fn>:  size {size:>2}, polls {polls}, counted {}",
+        counter.get()
+    );
+
+    let ctx = String::from("only closures can see me");
+    let polls = run_with(|id| println!("  task {id} polled ({ctx})"), 1, 1);
+    println!("closure capturing a String by reference: polls {polls}");
+    // TODO Uncomment for compiler error:
+    //      error[E0308]: mismatched types
+    // let fn_ptr: fn(TaskId) = |id| println!("  task {id} polled ({ctx})");
+}
@@ -0,0 +1,31 @@
+//! Print CRC32, FNV-1a, SipHash and SHA-256 of a file. See [async_stuff::digest].
+
+use anyhow::Context;
+use async_stuff::digest::{Crc32, Fnv1a, Sha256, SipHash, StreamDigest, digest_of};
+use async_stuff::profiling;
+use simple::exit::{self, Exit};
+use std::process::ExitCode;
+use tokio::fs::File;
+
+async fn print<H: StreamDigest>(name: &str, path: &str, digest: H) -> anyhow::Result<()> {
+    let file = File::open(path).await.with_context(|| path.to_string())?;
+    let (digest, len) = digest_of(file, digest).await?;
+    println!("{name:<8} {} ({len} bytes)", digest.hex());
+    Ok(())
+}
+
+async fn run() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or_else(|| Exit::usage("usage: checksum <file>"))?;
+    print("crc32", &path, Crc32::default()).await?;
+    print("fnv1a", &path, Fnv1a::default()).await?;
+    print("siphash", &path, SipHash::default()).await?;
+    print("sha256", &path, Sha256::default()).await?;
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    exit::report(profiling::profiled("checksum", run()).await)
+}
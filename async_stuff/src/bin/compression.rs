@@ -0,0 +1,59 @@
+//! Sizes of gzip, zstd and RLE, then a throttled decode. See [async_stuff::compression].
+
+use async_compression::tokio::bufread::{GzipDecoder, GzipEncoder, ZstdEncoder};
+use async_stuff::compression::{CountingReader, rle_encode};
+use async_stuff::fasterthanlime_pin::v5::ReadWrap;
+use async_stuff::profiling;
+use async_stuff::seeded::SeededReader;
+use simple::exit;
+use std::pin::pin;
+use std::process::ExitCode;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+use tokio::time::Instant;
+
+async fn read_all(r: impl AsyncRead) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    pin!(r).read_to_end(&mut out).await?;
+    Ok(out)
+}
+
+async fn sizes(name: &str, data: &[u8]) -> std::io::Result<()> {
+    let gz = read_all(GzipEncoder::new(data)).await?.len();
+    let zst = read_all(ZstdEncoder::new(data)).await?.len();
+    let rle = rle_encode(data).len();
+    println!(
+        "{name:<8} raw {:>6}  gzip {gz:>6}  zstd {zst:>6}  rle {rle:>6}",
+        data.len()
+    );
+    Ok(())
+}
+
+async fn run() -> std::io::Result<()> {
+    let runs: Vec<u8> = (0..64u8).flat_map(|b| [b; 100]).collect();
+    let text = include_str!("../compression.rs").as_bytes();
+    let mut random = vec![0u8; 6_400];
+    SeededReader::new(1).read_exact(&mut random).await?;
+
+    sizes("runs", &runs).await?;
+    sizes("text", text).await?;
+    sizes("random", &random).await?;
+
+    let gz = read_all(GzipEncoder::new(text)).await?;
+    let mut counting = CountingReader::new(Box::pin(ReadWrap::new(GzipDecoder::new(
+        BufReader::new(&gz[..]),
+    ))));
+    let now = Instant::now();
+    let out = read_all(&mut counting).await?;
+    println!(
+        "throttled gunzip: {} -> {} bytes in {:?}",
+        gz.len(),
+        out.len(),
+        now.elapsed()
+    );
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    exit::report(profiling::profiled("compression", run()).await)
+}
@@ -0,0 +1,58 @@
+//! Throughput of file to socket copies, `[MiB]` of data, median of 5 runs each. See
+//! [async_stuff::copy] \[unix only\].
+
+#[cfg(unix)]
+use anyhow::Context;
+#[cfg(unix)]
+use async_stuff::copy::{self, Method};
+#[cfg(unix)]
+use async_stuff::digest::{Crc32, StreamDigest};
+use async_stuff::profiling;
+#[cfg(not(unix))]
+use simple::exit::Code;
+use simple::exit::{self, Exit};
+use std::process::ExitCode;
+
+#[cfg(unix)]
+const RUNS: usize = 5;
+
+#[cfg(unix)]
+async fn run() -> anyhow::Result<()> {
+    let mib = match std::env::args().nth(1) {
+        None => 256,
+        Some(arg) => arg
+            .parse::<usize>()
+            .map_err(|_| Exit::usage(format!("usage: copy [MiB], got {arg:?}")))?,
+    };
+    let path = std::env::temp_dir().join(format!("copy-bench-{}.bin", std::process::id()));
+    let data: Vec<u8> = (0..mib << 20).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &data).with_context(|| path.display().to_string())?;
+    let mut expected = Crc32::default();
+    expected.update(&data);
+    let expected = expected.hex();
+    drop(data);
+    for method in Method::ALL {
+        let mut rates = Vec::with_capacity(RUNS);
+        for _ in 0..RUNS {
+            let out = copy::run(method, &path)
+                .await
+                .with_context(|| format!("{method:?}"))?;
+            anyhow::ensure!(out.crc32 == expected, "{method:?}: corrupted");
+            rates.push(out.mb_per_sec());
+        }
+        rates.sort_by(f64::total_cmp);
+        println!("{:<8} {:>7.0} MB/s", format!("{method:?}"), rates[RUNS / 2]);
+    }
+    std::fs::remove_file(&path).with_context(|| path.display().to_string())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn run() -> anyhow::Result<()> {
+    Err(Exit::new(Code::Unavailable, "copy needs unix sockets").into())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    exit::report(profiling::profiled("copy", run()).await)
+}
@@ -0,0 +1,61 @@
+//! Two tasks locking `a` and `b` in opposite order: tokio's mutexes hang \[cut short by a
+//! timeout\], [async_stuff::deadlock::TrackedMutex] reports the cycle.
+
+use async_stuff::deadlock::{Deadlock, LockGraph};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Barrier, Mutex};
+use tokio::task::JoinSet;
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+async fn plain() {
+    let a = Arc::new(Mutex::new(()));
+    let b = Arc::new(Mutex::new(()));
+    let barrier = Arc::new(Barrier::new(2));
+    let mut tasks = JoinSet::new();
+    for (name, first, second) in [("a, b", a.clone(), b.clone()), ("b, a", b, a)] {
+        let barrier = barrier.clone();
+        tasks.spawn(async move {
+            let _first = first.lock().await;
+            // Both hold their first lock before either asks for the second
+            barrier.wait().await;
+            let _second = second.lock().await;
+            name
+        });
+    }
+    match tokio::time::timeout(TIMEOUT, tasks.join_next()).await {
+        Ok(done) => println!("tokio::sync::Mutex: {done:?} finished?!"),
+        Err(_) => println!("tokio::sync::Mutex: nothing finished within {TIMEOUT:?}, hung"),
+    }
+    // Dropping the set aborts them
+}
+
+async fn tracked() {
+    let graph = LockGraph::new();
+    let a = Arc::new(graph.mutex("a", ()));
+    let b = Arc::new(graph.mutex("b", ()));
+    let barrier = Arc::new(Barrier::new(2));
+    let mut tasks = JoinSet::new();
+    for (first, second) in [(a.clone(), b.clone()), (b, a)] {
+        let barrier = barrier.clone();
+        tasks.spawn(async move {
+            let _first = first.lock().await?;
+            barrier.wait().await;
+            let _second = second.lock().await?;
+            Ok::<_, Deadlock>(tokio::task::id())
+        });
+    }
+    while let Some(done) = tasks.join_next().await {
+        match done.unwrap() {
+            Ok(id) => println!("TrackedMutex: task {id} got both"),
+            Err(deadlock) => println!("TrackedMutex: {deadlock}"),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    plain().await;
+    tracked().await;
+}
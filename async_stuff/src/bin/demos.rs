@@ -0,0 +1,76 @@
+//! Run the demos of [async_stuff::runner], configured via [async_stuff::config] and
+//! [async_stuff::cli]. `demos list` shows them instead, see [async_stuff::registry].
+
+use anyhow::Context;
+use async_stuff::lesson::Tutor;
+use async_stuff::registry::{self, List};
+use async_stuff::{cli, config, runner};
+use simple::alloc_counter::CountingAlloc;
+use simple::exit::{self, Exit};
+use std::io;
+use std::process::ExitCode;
+
+/// For the allocation counts of `--check`
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+async fn run() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|a| a == "list").is_some() {
+        match List::parse(args) {
+            Ok(list) => print!("{}", list.render()),
+            Err(config::Error::Help(help)) => println!("{help}"),
+            Err(e) => return Err(Exit::from(e).into()),
+        }
+        return Ok(());
+    }
+    let env: Vec<(String, String)> = std::env::vars().collect();
+    let cli = match cli::parse(args) {
+        Ok(cli) => cli,
+        Err(config::Error::Help(help)) => {
+            println!("{help}");
+            return Ok(());
+        }
+        Err(e) => return Err(Exit::from(e).into()),
+    };
+    let config =
+        config::load(cli, env.iter().map(|(k, v)| (k.as_str(), v.as_str()))).map_err(Exit::from)?;
+    let names = runner::selected(&config).map_err(|e| Exit::usage(e.to_string()))?;
+    if let Some(seed) = config.chaos {
+        eprintln!("chaos seed {seed}, repeat with --chaos {seed}");
+    }
+    // The pauses block, but only the main thread, which has nothing else to do meanwhile
+    let mut tutor = config
+        .lesson
+        .then(|| Tutor::new(io::stdin().lock(), io::stderr()));
+    let mut failed = 0;
+    for name in names {
+        let lesson = registry::find(name).map(|d| d.lesson);
+        if let Some((tutor, lesson)) = tutor.as_mut().zip(lesson) {
+            tutor.start(name)?;
+            tutor.steps(lesson.before)?;
+        }
+        let (report, mismatches) = if config.check {
+            runner::check(name, &config).await.context(name)?
+        } else {
+            (runner::run(name, &config).await.context(name)?, Vec::new())
+        };
+        runner::print(&report, config.format);
+        mismatches
+            .iter()
+            .for_each(|m| eprintln!("check failed: {m}"));
+        failed += mismatches.len();
+        if let Some((tutor, lesson)) = tutor.as_mut().zip(lesson) {
+            tutor.steps(lesson.after)?;
+        }
+    }
+    if failed > 0 {
+        return Err(Exit::failure(format!("{failed} check(s) failed")).into());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    exit::report(run().await)
+}
@@ -0,0 +1,28 @@
+//! The drift table of [async_stuff::drift] on the real clock, with the work blocking the thread.
+//! Same shape as the paused-clock numbers, plus a few ms of timer and scheduling jitter.
+
+use async_stuff::drift::{self, Strategy, Work};
+use std::time::Duration;
+
+const PERIOD: Duration = Duration::from_millis(100);
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let work = drift::spiky(12);
+    let ms = |d: Duration| d.as_secs_f64() * 1e3;
+    println!("strategy           last start ms  drift ms  min gap ms");
+    for strategy in Strategy::ALL {
+        let r = drift::run(strategy, PERIOD, &work, Work::Block).await;
+        let name = match strategy {
+            Strategy::SleepLoop => "sleep loop".to_string(),
+            Strategy::Interval(missed) => format!("interval {missed:?}"),
+        };
+        // Off the grid is all of them, at ns resolution
+        println!(
+            "{name:<18} {:>13.1} {:>9.1} {:>11.1}",
+            ms(r.starts.last().copied().unwrap_or_default()),
+            ms(r.drift()),
+            ms(r.min_gap()),
+        );
+    }
+}
@@ -1,321 +1,15 @@
-//! Explore the code examples in fasterthanlime "Pin and suffering"
-//! [article](https://fasterthanli.me/articles/pin-and-suffering) to understand
-//! [std::future::Future] and [std::pin]
+//! Run the code examples in fasterthanlime "Pin and suffering"
+//! [article](https://fasterthanli.me/articles/pin-and-suffering).
 //!
-//! | version | underlying AsyncRead is Unpin? | wrapper ReadWrap is Unpin? | approach |
-//! | --- | --- | --- | --- |
-//! | [v1] | n/a | n/a | no wrapper |
-//! | [v2::ReadWrap] | ✅ yes | ✅ yes (via auto marker trait) | wraps AsyncRead |
-//! | [v3::ReadWrap] | ✅ yes | ✅ yes (via auto marker trait) | wraps AsyncRead w/ delays; box pin internal fields |
-//! | [v4::ReadWrap] | ✅ yes | ❌ no | wraps AsyncRead w/ delay _without_ bin pin |
-//! | [v5::ReadWrap] | ✅ yes / ❌ no | same as underlying AsyncRead ([pin_project_lite! macro](https://crates.io/crates/pin-project-lite) will [conditionally](v5/struct.ReadWrap.html#impl-Unpin-for-ReadWrap<R>) `impl Unpin` if underlying AsyncRead is Unpin) | wraps AsyncRead w/delay and using external crate |
+//! See [async_stuff::fasterthanlime_pin] for the wrappers themselves.
 
 use anyhow::Result;
-// NB: Following import only needed for older Rust so that
-//      Pin<...>.as_mut().poll()
-// works.  Rust 2024 does _not_ need it as Future is now part of the prelude.
-// re: https://doc.rust-lang.org/edition-guide/rust-2024/prelude.html
-use std::future::Future;
+#[expect(unused_imports)]
+use async_stuff::fasterthanlime_pin::{v1, v2, v3, v4, v5};
+use simple::exit;
+use std::process::ExitCode;
 
-/// Read a file using vanilla [tokio::io::AsyncRead]
-#[expect(dead_code)]
-mod v1 {
-    use super::*;
-    use tokio::fs::File;
-    use tokio::io::AsyncReadExt;
-
-    pub async fn do_it() -> Result<()> {
-        // TODO Question: When do_it() is invoked will the "locals" here be allocated on the heap or stack?
-        let mut f = File::open("/dev/urandom").await?;
-        let mut buf = [0u8; 32];
-        let read_len = f.read_exact(&mut buf).await?;
-        println!("v1 Read {} bytes {:?}", read_len, buf);
-        Ok(())
-    }
-}
-
-/// Pass through to [tokio::io::AsyncRead]
-#[expect(dead_code)]
-mod v2 {
-    use super::*;
-    use std::pin::Pin;
-    use std::task::{Context, Poll};
-    use tokio::fs::File;
-    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
-
-    pub struct ReadWrap<R> {
-        read: R,
-    }
-
-    impl<R> ReadWrap<R> {
-        pub fn new(read: R) -> Self {
-            Self { read }
-        }
-    }
-
-    impl<R: AsyncRead + Unpin> AsyncRead for ReadWrap<R> {
-        fn poll_read(
-            mut self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-            buf: &mut ReadBuf<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Pin::new(&mut self.read).poll_read(cx, buf)
-        }
-    }
-
-    pub async fn do_it() -> Result<()> {
-        let f = File::open("/dev/urandom").await?;
-        let mut f: ReadWrap<File> = ReadWrap::new(f);
-        let mut buf = [0u8; 32];
-        let read_len = f.read_exact(&mut buf).await?;
-        println!("v2 Read {} bytes {:?}", read_len, buf);
-        Ok(())
-    }
-}
-
-/// Pass through to [tokio::io::AsyncRead] with delay and making wrapper [Unpin]
-/// \[which forces some of its !Unpin fields to go onto the heap\].
-///
-/// TODO Verify above statement is true
-///
-/// Recall that Box always puts what it points to on the heap
-///
-/// re: this [Google Doc](https://docs.google.com/presentation/d/1q-c7UAyrUlM-eZyTo1pd8SZ0qwA_wYxmPZVOQkoDmH4/edit#slide=id.p)
-#[expect(dead_code)]
-mod v3 {
-    use super::*;
-    use std::pin::Pin;
-    use std::task::{Context, Poll};
-    use std::time::Duration;
-    use tokio::fs::File;
-    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
-    use tokio::time::{self, Instant, Sleep};
-
-    /// NB: The size of [ReadWrap] as returned by
-    /// [AsyncReadExt::read_exact()] will _not directly_ include the size of [Sleep]
-    /// but instead just a pointer to Sleep.
-    ///
-    /// TODO Question: So if [ReadWrap] is on the stack, its [ReadWrap::sleep] is on the heap
-    /// \[because Box is always on the heap\]?
-    pub struct ReadWrap<R> {
-        read: Pin<Box<R>>,
-        sleep: Pin<Box<Sleep>>,
-    }
-
-    impl<R> ReadWrap<R> {
-        pub fn new(read: R) -> Self {
-            Self {
-                read: Box::pin(read),
-                sleep: Box::pin(time::sleep(Duration::from_secs(1))),
-            }
-        }
-    }
-
-    impl<R: AsyncRead> AsyncRead for ReadWrap<R> {
-        fn poll_read(
-            mut self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-            buf: &mut ReadBuf<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            match self.sleep.as_mut().poll(cx) {
-                Poll::Ready(_) => {
-                    // woke up => read into buffer
-                    self.sleep
-                        .as_mut()
-                        .reset(Instant::now() + Duration::from_secs(1));
-                    self.read.as_mut().poll_read(cx, buf)
-                }
-                // continue sleeping
-                Poll::Pending => Poll::Pending,
-            }
-        }
-    }
-
-    pub async fn do_it() -> Result<()> {
-        let f = File::open("/dev/urandom").await?;
-        let mut f = ReadWrap::new(f);
-
-        // TODO Question: Will ReadWrap be on stack as it does _not_ cross await points?
-        let mut f: Pin<&mut ReadWrap<File>> = Pin::new(&mut f);
-
-        let mut buf = [0u8; 32];
-        let now = Instant::now();
-        let read_len = f.read_exact(&mut buf).await?;
-        println!(
-            "v3 Read {} bytes {:?} after {:?}",
-            read_len,
-            buf,
-            now.elapsed()
-        );
-        Ok(())
-    }
-}
-
-/// Pass through to [tokio::io::AsyncRead] with delay but make wrapper *not* [Unpin]
-/// \[so that its !Unpin fields can stay on the stack\]
-///
-/// TODO Verify above statement is true
-#[expect(dead_code)]
-mod v4 {
-    use super::*;
-    use std::pin::Pin;
-    use std::task::{Context, Poll};
-    use std::time::Duration;
-    use tokio::fs::File;
-    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
-    use tokio::time::{self, Instant, Sleep};
-
-    /// NB: The size of [ReadWrap] as returned by
-    /// [AsyncReadExt::read_exact()] will include the size of [Sleep]
-    ///
-    /// TODO Question: So if [ReadWrap] is on the stack, so will its [ReadWrap::sleep]?
-    pub struct ReadWrap<R> {
-        read: R,
-        sleep: Sleep,
-    }
-
-    impl<R> ReadWrap<R> {
-        pub fn new(read: R) -> Self {
-            Self {
-                read,
-                sleep: time::sleep(Duration::from_secs(1)),
-            }
-        }
-    }
-
-    impl<R: AsyncRead + Unpin> AsyncRead for ReadWrap<R> {
-        fn poll_read(
-            self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-            buf: &mut ReadBuf<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            // NB: See v5 which replaces "unsafe" with macro
-            // SAFETY: We never move out from ReadWrap. Instead, we only return Pin on borrowed fields.
-            let (mut read, mut sleep) = unsafe {
-                let this = self.get_unchecked_mut();
-                (
-                    Pin::new(&mut this.read),
-                    Pin::new_unchecked(&mut this.sleep),
-                )
-            };
-            match sleep.as_mut().poll(cx) {
-                Poll::Ready(_) => {
-                    // woke up => read into buffer
-                    sleep.reset(Instant::now() + Duration::from_secs(1));
-                    read.as_mut().poll_read(cx, buf)
-                }
-                // continue sleeping
-                Poll::Pending => Poll::Pending,
-            }
-        }
-    }
-
-    pub async fn do_it() -> Result<()> {
-        let f = File::open("/dev/urandom").await?;
-        let mut f = ReadWrap::new(f);
-
-        // NB: Unlike v3, the usage of ReadWrap is more complicated
-        // NB: See v5 which replaces "unsafe" with macro
-        // TODO Question: Will ReadWrap be on stack as it does _not_ cross await points?
-        // SAFETY: We trivially never move from ReadWrap because we shadow it (varname is "f") with a Pin<&mut ReadWrap>
-        let mut f: Pin<&mut ReadWrap<File>> = unsafe { Pin::new_unchecked(&mut f) };
-
-        let mut buf = [0u8; 32];
-        let now = Instant::now();
-        let read_len = f.read_exact(&mut buf).await?;
-        println!(
-            "v4 Read {} bytes {:?} after {:?}",
-            read_len,
-            buf,
-            now.elapsed()
-        );
-        Ok(())
-    }
-}
-
-/// Pass through to [tokio::io::AsyncRead] with delay but make wrapper *not* [Unpin]
-/// ... and use 3rd party macros to avoid "unsafe"
-mod v5 {
-    use super::*;
-    use pin_project_lite::pin_project;
-    use std::pin::{Pin, pin};
-    use std::task::{Context, Poll};
-    use std::time::Duration;
-    use tokio::fs::File;
-    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
-    use tokio::time::{self, Instant, Sleep};
-
-    pin_project! {
-        /// NB: The size of [ReadWrap] as returned by
-        /// [AsyncReadExt::read_exact()] will include the size of [Sleep]
-        ///
-        /// TODO Question: So if [ReadWrap] is on the stack, so will its [ReadWrap::sleep]?
-        pub struct ReadWrap<R> {
-            #[pin]
-            read: R,
-
-            // Make ReadWrap.project().sleep return a Pin<Sleep>
-            #[pin]
-            sleep: Sleep,
-        }
-    }
-
-    impl<R> ReadWrap<R> {
-        pub fn new(read: R) -> Self {
-            Self {
-                read,
-                sleep: time::sleep(Duration::from_secs(1)),
-            }
-        }
-    }
-
-    impl<R: AsyncRead> AsyncRead for ReadWrap<R> {
-        fn poll_read(
-            self: Pin<&mut Self>,
-            cx: &mut Context<'_>,
-            buf: &mut ReadBuf<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            let mut this = self.project();
-            match this.sleep.as_mut().poll(cx) {
-                Poll::Ready(_) => {
-                    // woke up => read into buffer
-                    this.sleep.reset(Instant::now() + Duration::from_secs(1));
-                    this.read.poll_read(cx, buf)
-                }
-                // continue sleeping
-                Poll::Pending => Poll::Pending,
-            }
-        }
-    }
-
-    pub async fn do_it() -> Result<()> {
-        let f = File::open("/dev/urandom").await?;
-        let f_before_pin = ReadWrap::new(f);
-
-        // NB: Unlike v3, the usage of ReadWrap is more complicated
-        // TODO Question: Will ReadWrap be on stack as it does _not_ cross await points?
-        let mut f: Pin<&mut ReadWrap<File>> = pin!(f_before_pin);
-
-        // NB: Following
-        //      std::hint::black_box(f_before_pin);
-        // will *not* compile because pin!() at https://doc.rust-lang.org/beta/src/core/pin.rs.html#2035
-        // uses "super let" to move it to an inaccessible var
-
-        let mut buf = [0u8; 32];
-        let now = Instant::now();
-        let read_len = f.read_exact(&mut buf).await?;
-        println!(
-            "v5 Read {} bytes {:?} after {:?}",
-            read_len,
-            buf,
-            now.elapsed()
-        );
-        Ok(())
-    }
-}
-
-#[tokio::main]
-pub async fn main() -> Result<()> {
+async fn run() -> Result<()> {
     // v1::do_it().await?;
     // v2::do_it().await?;
     // v3::do_it().await?;
@@ -323,3 +17,8 @@ pub async fn main() -> Result<()> {
     v5::do_it().await?;
     Ok(())
 }
+
+#[tokio::main]
+pub async fn main() -> ExitCode {
+    exit::report(run().await)
+}
@@ -0,0 +1,26 @@
+//! Print the frames in a file, by default the fixture. See [async_stuff::framing].
+
+use anyhow::Context;
+use async_stuff::framing;
+use simple::exit;
+use std::process::ExitCode;
+use tokio::fs::File;
+
+async fn run() -> anyhow::Result<()> {
+    let path = std::env::args().nth(1).unwrap_or_else(|| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../simple/fixtures/frames.bin").to_string()
+    });
+    let mut f = File::open(&path).await.with_context(|| path.clone())?;
+    let frames = framing::read_all(&mut f)
+        .await
+        .with_context(|| path.clone())?;
+    for frame in frames {
+        println!("{:?} payload {:02x?}", frame.header, frame.payload);
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    exit::report(run().await)
+}
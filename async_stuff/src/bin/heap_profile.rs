@@ -0,0 +1,64 @@
+//! Heap profile of constructing and reading through the throttling wrappers of
+//! [async_stuff::fasterthanlime_pin] \[only with `--features dhat-heap`\]
+//!
+//! ```sh
+//! cargo run -p async_stuff --features dhat-heap --bin heap_profile
+//! # then load target/profiles/dhat-heap.json in https://nnethercote.github.io/dh_view/dh_view.html
+//! ```
+//!
+//! In the viewer v3's two `Box::pin`s per wrapper show up under `v3::ReadWrap::new`, while v4
+//! and v5 have no allocation sites of their own.
+
+use anyhow::Context;
+use async_stuff::fasterthanlime_pin::{v3, v4, v5};
+use dhat::HeapStats;
+use simple::exit;
+use std::pin::pin;
+use std::process::ExitCode;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+const WRAPPERS: usize = 1000;
+const DATA: &[u8] = b"hello";
+
+/// Build many wrappers, then read through one of them \[each read waits 1s\]
+async fn phase<W: AsyncRead>(name: &str, new: impl Fn(&'static [u8]) -> W) -> anyhow::Result<()> {
+    let mut wrappers: Vec<W> = Vec::with_capacity(WRAPPERS);
+    let empty = HeapStats::get();
+    wrappers.extend((0..WRAPPERS).map(|_| new(DATA)));
+    let built = HeapStats::get();
+
+    let mut read = pin!(wrappers.pop().expect("WRAPPERS > 0"));
+    let mut buf = [0u8; DATA.len()];
+    read.read_exact(&mut buf)
+        .await
+        .with_context(|| format!("reading through {name}"))?;
+    let done = HeapStats::get();
+
+    println!(
+        "{name}: {WRAPPERS} wrappers {:>5} blocks {:>7} bytes, read {} blocks",
+        built.total_blocks - empty.total_blocks,
+        built.total_bytes - empty.total_bytes,
+        done.total_blocks - built.total_blocks,
+    );
+    Ok(())
+}
+
+async fn run() -> anyhow::Result<()> {
+    std::fs::create_dir_all("target/profiles").context("target/profiles")?;
+    let _profiler = dhat::Profiler::builder()
+        .file_name("target/profiles/dhat-heap.json")
+        .build();
+
+    phase("v3", v3::ReadWrap::new).await?;
+    phase("v4", v4::ReadWrap::new).await?;
+    phase("v5", v5::ReadWrap::new).await?;
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    exit::report(run().await)
+}
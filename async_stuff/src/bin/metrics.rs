@@ -0,0 +1,61 @@
+//! The wake_batching consumer in both modes, measured through [async_stuff::metrics] instead of
+//! by hand: progress every 100 ms, then the whole registry as a JSON report.
+
+use async_stuff::metrics::{self, Registry, Value};
+use async_stuff::wake_batching::{Wake, channel};
+use simple::alloc_counter::CountingAlloc;
+use std::thread;
+use std::time::Duration;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const PRODUCERS: u64 = 4;
+const EVENTS: u64 = 250_000;
+
+async fn run(registry: &Registry, mode: Wake) {
+    let name = format!("{mode:?}").to_lowercase();
+    let received = registry.counter(&format!("{name}.events"));
+    let batch = registry.histogram(&format!("{name}.batch_len"));
+    let (tx, mut rx) = channel(mode);
+    let consumer = metrics::instrument(registry, &name, async move {
+        while let Some(events) = rx.recv_batch().await {
+            received.add(events.len() as u64);
+            batch.record(events.len() as u64);
+        }
+    });
+    let producers: Vec<_> = (0..PRODUCERS)
+        .map(|p| {
+            let tx = tx.clone();
+            thread::spawn(move || (0..EVENTS).for_each(|i| tx.send(p * EVENTS + i)))
+        })
+        .collect();
+    drop(tx);
+    consumer.await;
+    producers.into_iter().for_each(|p| p.join().unwrap());
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let registry = Registry::new();
+    let reporter = tokio::spawn(metrics::report_every(
+        registry.clone(),
+        Duration::from_millis(100),
+        |s| {
+            let events: Vec<_> =
+                s.0.iter()
+                    .filter(|(name, _)| name.ends_with(".events"))
+                    .map(|(name, v)| match v {
+                        Value::Counter(n) => format!("{name} {n}"),
+                        other => format!("{name} {other:?}"),
+                    })
+                    .collect();
+            eprintln!("[progress] {}", events.join(", "));
+        },
+    ));
+    for mode in [Wake::PerEvent, Wake::Coalesced] {
+        run(&registry, mode).await;
+    }
+    reporter.abort();
+    println!("{}", registry.snapshot().report("metrics").to_json());
+}
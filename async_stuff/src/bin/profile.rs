@@ -0,0 +1,69 @@
+//! Throttled reads and a parallel hashing workload under pprof and tracing-flame. See
+//! [async_stuff::profiling].
+
+use anyhow::Result;
+use async_stuff::digest::{HashingReader, Sha256, StreamDigest};
+use async_stuff::fasterthanlime_pin::{v3, v4, v5};
+use async_stuff::profiling::Profiler;
+use async_stuff::seeded::SeededReader;
+use simple::exit;
+use std::pin::pin;
+use std::process::ExitCode;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{Instrument, info_span};
+
+const READS: usize = 3;
+const CHUNK: usize = 1 << 20;
+const TASKS: u64 = 8;
+const PER_TASK: u64 = 16 << 20;
+
+fn source(seed: u64) -> HashingReader<SeededReader, Sha256> {
+    HashingReader::new(SeededReader::new(seed), Sha256::default())
+}
+
+/// Each read waits 1s in the wrapper, then hashes a chunk
+async fn throttled(read: impl AsyncRead) -> Result<()> {
+    let mut read = pin!(read);
+    let mut buf = vec![0u8; CHUNK];
+    for _ in 0..READS {
+        read.read_exact(&mut buf).await?;
+    }
+    Ok(())
+}
+
+async fn hash_task(seed: u64) -> Result<String> {
+    let mut reader = HashingReader::new(SeededReader::new(seed).take(PER_TASK), Sha256::default());
+    tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok(reader.into_digest().hex())
+}
+
+async fn run() -> Result<()> {
+    let profiler = Profiler::start("profile", "target/profiles")?;
+
+    throttled(v3::ReadWrap::new(source(3)))
+        .instrument(info_span!("throttled_v3"))
+        .await?;
+    throttled(v4::ReadWrap::new(source(4)))
+        .instrument(info_span!("throttled_v4"))
+        .await?;
+    throttled(v5::ReadWrap::new(source(5)))
+        .instrument(info_span!("throttled_v5"))
+        .await?;
+
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|seed| tokio::spawn(hash_task(seed).instrument(info_span!("hash_task", seed))))
+        .collect();
+    for task in tasks {
+        task.await??;
+    }
+
+    for svg in profiler.finish()? {
+        println!("wrote {}", svg.display());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    exit::report(run().await)
+}
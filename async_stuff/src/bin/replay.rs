@@ -0,0 +1,68 @@
+//! Record a session with random chunks and gaps, save and load it, then replay it through each
+//! wrapper twice: the live source differs every run, the replays don't. See
+//! [async_stuff::replay].
+
+use anyhow::Context;
+use async_stuff::io::duplex;
+use async_stuff::replay::{self, Pace, Recorder, Recording};
+use rand::RngExt;
+use simple::exit;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const CHUNKS: usize = 4;
+
+/// Chunks of 8 to 32 random bytes, 0 to 400 ms apart
+async fn record() -> anyhow::Result<Recording> {
+    let (mut tx, rx) = duplex(1024);
+    let writer = tokio::spawn(async move {
+        for _ in 0..CHUNKS {
+            let (gap, data) = {
+                let mut rng = rand::rng();
+                let len = rng.random_range(8..=32);
+                let data: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+                (Duration::from_millis(rng.random_range(0..=400)), data)
+            };
+            tokio::time::sleep(gap).await;
+            tx.write_all(&data).await?;
+        }
+        anyhow::Ok(())
+    });
+    let mut recorder = Recorder::new(rx);
+    recorder.read_to_end(&mut Vec::new()).await?;
+    writer.await??;
+    Ok(recorder.into_recording())
+}
+
+async fn run() -> anyhow::Result<()> {
+    let recording = record().await?;
+    let path = std::env::temp_dir().join(format!("replay-{}.bin", std::process::id()));
+    recording.save(&path).context(path.display().to_string())?;
+    let recording = Recording::load(&path)?;
+    std::fs::remove_file(&path)?;
+    let at: Vec<_> = recording.chunks.iter().map(|c| c.at.as_millis()).collect();
+    println!(
+        "recorded {} bytes in chunks at {at:?} ms, EOF at {:?}",
+        recording.bytes(),
+        recording.eof_at.unwrap_or_default()
+    );
+    for (label, pace) in [
+        ("replay 1", Pace::AsRecorded),
+        ("replay 2", Pace::AsRecorded),
+        ("no delay", Pace::NoDelay),
+    ] {
+        for r in replay::compare(&recording, pace, 64).await? {
+            println!(
+                "{label} {}: {} bytes in {} reads, crc32 {}, {:.2?}",
+                r.variant, r.bytes, r.reads, r.crc32, r.elapsed
+            );
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    exit::report(run().await)
+}
@@ -0,0 +1,55 @@
+//! The throttled demos of [async_stuff::runner] in a loop, instrumented with
+//! [async_stuff::metrics] and scrapeable at `/metrics` \[only with `--features prometheus`\]
+//!
+//! ```sh
+//! cargo run -p async_stuff --features prometheus --bin serve_metrics -- 127.0.0.1:9898
+//! curl -s localhost:9898/metrics | grep v4
+//! ```
+
+use anyhow::Context;
+use async_stuff::config::Config;
+use async_stuff::metrics::{self, Registry};
+use async_stuff::{prometheus, runner};
+use simple::alloc_counter::CountingAlloc;
+use simple::exit;
+use std::process::ExitCode;
+use tokio::net::TcpListener;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:9898";
+/// v2 doesn't wait, it would only spin
+const THROTTLED: [&str; 3] = ["v3", "v4", "v5"];
+
+async fn run() -> anyhow::Result<()> {
+    let addr = std::env::args().nth(1);
+    let addr = addr.as_deref().unwrap_or(DEFAULT_ADDR);
+    let listener = TcpListener::bind(addr).await.context(addr.to_string())?;
+    println!("serving http://{}/metrics", listener.local_addr()?);
+
+    let registry = Registry::new();
+    let server = tokio::spawn(prometheus::serve(listener, registry.clone(), "demos"));
+    for name in THROTTLED {
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            let config = Config::default();
+            let runs = registry.counter(&format!("{name}.runs"));
+            let run_ns = registry.histogram(&format!("{name}.run_ns"));
+            loop {
+                let report = metrics::instrument(&registry, name, runner::run(name, &config))
+                    .await
+                    .expect("demos don't fail on seeded input");
+                runs.inc();
+                run_ns.record_duration(report.elapsed);
+            }
+        });
+    }
+    server.await??;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    exit::report(run().await)
+}
@@ -0,0 +1,55 @@
+//! Producer threads waking one tokio consumer task, per event vs coalesced, polls counted with
+//! `inspect_poll`. See [async_stuff::wake_batching].
+
+use async_stuff::futcomb::FutureCombExt;
+use async_stuff::profiling;
+use async_stuff::wake_batching::{Wake, channel};
+use simple::exit;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Instant;
+
+const PRODUCERS: u64 = 8;
+const EVENTS: u64 = 100_000;
+
+async fn run(mode: Wake) {
+    let (tx, mut rx) = channel(mode);
+    let start = Instant::now();
+    let mut polls = 0;
+    let consumer = async {
+        let (mut events, mut batches) = (0, 0);
+        while let Some(batch) = rx.recv_batch().await {
+            events += batch.len();
+            batches += 1;
+        }
+        (events, batches)
+    }
+    .inspect_poll(|_| polls += 1);
+    let producers: Vec<_> = (0..PRODUCERS)
+        .map(|p| {
+            let tx = tx.clone();
+            thread::spawn(move || (0..EVENTS).for_each(|i| tx.send(p * EVENTS + i)))
+        })
+        .collect();
+    drop(tx);
+    let (events, batches) = consumer.await;
+    let elapsed = start.elapsed();
+    producers.into_iter().for_each(|p| p.join().unwrap());
+    assert_eq!(events as u64, PRODUCERS * EVENTS);
+    println!(
+        "{:<10} events {events}, wakes {:>7}, consumer polls {polls:>7}, batches {batches:>7}, {elapsed:.2?}",
+        format!("{mode:?}"),
+        rx.wakes()
+    );
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let modes = async {
+        for mode in [Wake::PerEvent, Wake::Coalesced] {
+            run(mode).await;
+        }
+        anyhow::Ok(())
+    };
+    exit::report(profiling::profiled("wake_batching", modes).await)
+}
@@ -0,0 +1,60 @@
+//! A ticker and a task that blocks in `poll` on one current-thread runtime, watched by
+//! [async_stuff::watchdog::Watchdog]. Then the same work on `spawn_blocking`, which the ticker
+//! doesn't notice.
+
+use async_stuff::watchdog::Watchdog;
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_millis(50);
+const WORK: Duration = Duration::from_millis(400);
+
+async fn run(blocking: bool) {
+    let watchdog = Watchdog::start(Duration::from_millis(100), Duration::from_millis(10), |s| {
+        println!(
+            "  stall: task {:?} has been in poll #{} for {:.1?} without yielding",
+            s.task, s.poll, s.blocked_for
+        )
+    });
+    let ticker = tokio::spawn(watchdog.track("ticker", async {
+        let mut interval = tokio::time::interval(TICK);
+        let start = Instant::now();
+        let mut late = Duration::ZERO;
+        for i in 0..20 {
+            interval.tick().await;
+            late = late.max(start.elapsed().saturating_sub(TICK * i));
+        }
+        late
+    }));
+    let worker = tokio::spawn(watchdog.track("worker", async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        if blocking {
+            // The hazard: blocks the only worker thread
+            std::thread::sleep(WORK);
+        } else {
+            tokio::task::spawn_blocking(|| std::thread::sleep(WORK))
+                .await
+                .unwrap();
+        }
+    }));
+    let late = ticker.await.unwrap();
+    worker.await.unwrap();
+    let (stalls, stats) = watchdog.stop();
+    println!(
+        "  ticker at most {late:.1?} late, {} stall(s)",
+        stalls.len()
+    );
+    for t in stats {
+        println!(
+            "  {:<7} {:>3} polls, longest {:.1?}",
+            t.name, t.polls, t.longest_poll
+        );
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    println!("std::thread::sleep in poll:");
+    run(true).await;
+    println!("spawn_blocking:");
+    run(false).await;
+}
@@ -0,0 +1,105 @@
+//! Callbacks three ways: `fn` pointer vs generic `F: Fn` vs `Box<dyn Fn>`
+//!
+//! All three are registered as the `on_poll` hook of [Executor].
+//!
+//! | | `fn(TaskId)` | `F: Fn(TaskId)` | `Box<dyn Fn(TaskId)>` |
+//! | --- | --- | --- | --- |
+//! | size | 1 pointer | size of the captures (0 if none) | 2 pointers (data + vtable) |
+//! | dispatch | indirect call | direct call, can be inlined | indirect call through vtable |
+//! | can capture | ❌ no \[only statics\] | ✅ yes | ✅ yes |
+//! | heap allocation | no | no | yes \[unless zero-sized\] |
+//! | mix different callbacks in one `Vec` | ✅ yes | ❌ no \[one type per `Vec`\] | ✅ yes |
+//! | nameable type | ✅ yes | ❌ no \[closures are anonymous\] | ✅ yes |
+//!
+//! Dispatch cost is measured by:
+//! ```sh
+//! cargo bench -p async_stuff --bench callbacks
+//! ```
+
+use crate::mini_executor::{Executor, TaskId};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A `fn` pointer cannot capture, so state has to live in a static
+pub static FN_PTR_POLLS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn count_poll(_id: TaskId) {
+    FN_PTR_POLLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Closure capturing its own counter \[each call to this creates an independent counter\]
+///
+/// Non-capturing closures coerce to `fn` pointers, capturing ones don't:
+/// ```compile_fail,E0308
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+/// let polls = Rc::new(Cell::new(0));
+/// let f: fn(usize) = move |_id| polls.set(polls.get() + 1); // error[E0308]: mismatched types
+/// ```
+pub fn counting_closure(polls: Rc<Cell<usize>>) -> impl Fn(TaskId) {
+    move |_id| polls.set(polls.get() + 1)
+}
+
+/// Same closure, type-erased
+pub fn counting_boxed(polls: Rc<Cell<usize>>) -> Box<dyn Fn(TaskId)> {
+    Box::new(counting_closure(polls))
+}
+
+/// Runs `tasks` tasks that each yield `yields` times, returning the number of polls
+pub fn run_with<H: Fn(TaskId)>(on_poll: H, tasks: usize, yields: usize) -> usize {
+    let mut ex = Executor::with_on_poll(on_poll);
+    for _ in 0..tasks {
+        ex.spawn(crate::mini_executor::yield_n(yields));
+    }
+    ex.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::size_of_val;
+
+    #[test]
+    fn test_sizes() {
+        let fn_ptr: fn(TaskId) = count_poll;
+        assert_eq!(size_of_val(&fn_ptr), size_of::<usize>());
+
+        let no_capture = |_id: TaskId| {};
+        assert_eq!(size_of_val(&no_capture), 0);
+
+        let closure = counting_closure(Rc::default());
+        assert_eq!(size_of_val(&closure), size_of::<Rc<Cell<usize>>>());
+
+        let boxed = counting_boxed(Rc::default());
+        assert_eq!(size_of_val(&boxed), 2 * size_of::<usize>());
+    }
+
+    #[test]
+    fn test_all_three_with_executor() {
+        let before = FN_PTR_POLLS.load(Ordering::Relaxed);
+        assert_eq!(run_with(count_poll as fn(TaskId), 3, 2), 9);
+        assert_eq!(FN_PTR_POLLS.load(Ordering::Relaxed) - before, 9);
+
+        let polls = Rc::new(Cell::new(0));
+        assert_eq!(run_with(counting_closure(polls.clone()), 3, 2), 9);
+        assert_eq!(polls.get(), 9);
+
+        let polls = Rc::new(Cell::new(0));
+        assert_eq!(run_with(counting_boxed(polls.clone()), 3, 2), 9);
+        assert_eq!(polls.get(), 9);
+    }
+
+    #[test]
+    fn test_heterogeneous() {
+        let polls = Rc::new(Cell::new(0));
+        // Different closure types only fit in one Vec once boxed
+        let hooks: Vec<Box<dyn Fn(TaskId)>> = vec![
+            Box::new(count_poll),
+            counting_boxed(polls.clone()),
+            Box::new(|_id| {}),
+        ];
+        run_with(|id| hooks.iter().for_each(|h| h(id)), 1, 1);
+        assert_eq!(polls.get(), 2);
+    }
+}
@@ -0,0 +1,306 @@
+//! The flags of the `demos` runner parsed three ways, all producing the same [Cli]
+//!
+//! | parser | feature | `--help` | release binary \[stripped\] | startup \[`demos --reads 0 v2`, median\] |
+//! | --- | --- | --- | --- | --- |
+//! | [manual] over [std::env::args] | \[default\] | hand-written [manual::USAGE] | 1.49 MB \[1.09 MB\] | 0.80 ms |
+//! | `builder`, clap's builder API | `cli-builder` | generated | 2.04 MB \[1.54 MB\] | 0.82 ms |
+//! | `derive`, clap's derive API | `cli-derive` | generated, doc comments become help | 2.08 MB \[1.56 MB\] | 0.87 ms |
+//!
+//! ```sh
+//! cargo build --release --bin demos --features cli-derive && ls -l target/release/demos
+//! ```
+//!
+//! Measured on Linux x86_64, startup over 300 runs. clap adds about half a megabyte while
+//! startup stays under a millisecond either way \[the differences are within the noise\].
+//! The derive expands to builder calls, so both clap variants cost about the same. What clap
+//! buys is `--help`, suggestions for typos, `--seed=3` syntax and error messages pointing at
+//! the bad flag, all of which [manual] would have to grow by hand.
+
+use crate::config::{Cli, Error};
+
+/// Parser selected by the features, arguments without the program name
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, Error> {
+    #[cfg(feature = "cli-derive")]
+    return derive::parse(args);
+    #[cfg(all(feature = "cli-builder", not(feature = "cli-derive")))]
+    return builder::parse(args);
+    #[cfg(not(any(feature = "cli-builder", feature = "cli-derive")))]
+    return manual::parse(args);
+}
+
+/// `--help` isn't an error
+#[cfg(any(feature = "cli-builder", feature = "cli-derive"))]
+fn from_clap(e: clap::Error) -> Error {
+    use clap::error::ErrorKind;
+    match e.kind() {
+        ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => Error::Help(e.to_string()),
+        _ => Error::Usage(e.to_string()),
+    }
+}
+
+pub mod manual {
+    use super::*;
+    use crate::config::{ChaosSeed, parse_value};
+
+    pub const USAGE: &str = "usage: demos [--config FILE] [--seed N] [--reads N] \
+                             [--chunk BYTES] [--format text|json] [--chaos SEED|random] \
+                             [--lesson] [--check] [DEMO...]";
+
+    /// Positional arguments are the demos to run
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, Error> {
+        let mut cli = Cli::default();
+        let mut run = Vec::new();
+        let mut it = args.into_iter();
+        while let Some(arg) = it.next() {
+            let mut value = || {
+                it.next()
+                    .ok_or_else(|| Error::Usage(format!("missing value for {arg}\n{USAGE}")))
+            };
+            let o = &mut cli.overrides;
+            match arg.as_str() {
+                "--config" => cli.config = Some(value()?.into()),
+                "--seed" => o.seed = Some(parse_value(&arg, &value()?)?),
+                "--reads" => o.reads = Some(parse_value(&arg, &value()?)?),
+                "--chunk" => o.chunk = Some(parse_value(&arg, &value()?)?),
+                "--format" => o.format = Some(parse_value(&arg, &value()?)?),
+                "--chaos" => o.chaos = Some(parse_value::<ChaosSeed>(&arg, &value()?)?.0),
+                "--lesson" => o.lesson = Some(true),
+                "--check" => o.check = Some(true),
+                "-h" | "--help" => return Err(Error::Help(USAGE.into())),
+                flag if flag.starts_with('-') => {
+                    return Err(Error::Usage(format!("unknown flag {flag}\n{USAGE}")));
+                }
+                _ => run.push(arg),
+            }
+        }
+        if !run.is_empty() {
+            cli.overrides.run = Some(run);
+        }
+        Ok(cli)
+    }
+}
+
+#[cfg(feature = "cli-builder")]
+pub mod builder {
+    use super::*;
+    use crate::config::{ChaosSeed, Format, Overrides};
+    use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+    use std::path::PathBuf;
+
+    pub fn command() -> Command {
+        Command::new("demos")
+            .about("Run the async demos")
+            .arg(
+                Arg::new("config")
+                    .long("config")
+                    .value_name("FILE")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("TOML file, instead of $DEMOS_CONFIG or ./demos.toml"),
+            )
+            .arg(
+                Arg::new("seed")
+                    .long("seed")
+                    .value_parser(value_parser!(u64))
+                    .help("Seed of the input data"),
+            )
+            .arg(
+                Arg::new("reads")
+                    .long("reads")
+                    .value_parser(value_parser!(usize))
+                    .help("Reads per demo"),
+            )
+            .arg(
+                Arg::new("chunk")
+                    .long("chunk")
+                    .value_name("BYTES")
+                    .value_parser(value_parser!(usize))
+                    .help("Bytes per read"),
+            )
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(|s: &str| s.parse::<Format>())
+                    .help("text or json"),
+            )
+            .arg(
+                Arg::new("chaos")
+                    .long("chaos")
+                    .value_name("SEED")
+                    .value_parser(|s: &str| s.parse::<ChaosSeed>().map(|c| c.0))
+                    .help("Inject I/O faults and latency, seeded, or random"),
+            )
+            .arg(
+                Arg::new("lesson")
+                    .long("lesson")
+                    .action(ArgAction::SetTrue)
+                    .help("Explain each demo step by step, waiting for Enter"),
+            )
+            .arg(
+                Arg::new("check")
+                    .long("check")
+                    .action(ArgAction::SetTrue)
+                    .help("Fail unless each demo shows what it teaches"),
+            )
+            .arg(
+                Arg::new("demos")
+                    .value_name("DEMO")
+                    .num_args(0..)
+                    .help("Demos to run, all if none"),
+            )
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, Error> {
+        let m = command()
+            .try_get_matches_from(std::iter::once("demos".to_string()).chain(args))
+            .map_err(from_clap)?;
+        Ok(from_matches(&m))
+    }
+
+    fn from_matches(m: &ArgMatches) -> Cli {
+        Cli {
+            config: m.get_one::<PathBuf>("config").cloned(),
+            overrides: Overrides {
+                run: m
+                    .get_many::<String>("demos")
+                    .map(|names| names.cloned().collect()),
+                seed: m.get_one("seed").copied(),
+                reads: m.get_one("reads").copied(),
+                chunk: m.get_one("chunk").copied(),
+                format: m.get_one("format").copied(),
+                chaos: m.get_one("chaos").copied(),
+                // NB: A flag is never "not given", false has to mean that
+                lesson: m.get_flag("lesson").then_some(true),
+                check: m.get_flag("check").then_some(true),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "cli-derive")]
+pub mod derive {
+    use super::*;
+    use crate::config::{ChaosSeed, Format, Overrides};
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    /// Run the async demos
+    #[derive(Debug, Parser)]
+    #[command(name = "demos")]
+    pub struct Args {
+        /// TOML file, instead of $DEMOS_CONFIG or ./demos.toml
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+        /// Seed of the input data
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Reads per demo
+        #[arg(long)]
+        reads: Option<usize>,
+        /// Bytes per read
+        #[arg(long, value_name = "BYTES")]
+        chunk: Option<usize>,
+        /// text or json
+        #[arg(long)]
+        format: Option<Format>,
+        /// Inject I/O faults and latency, seeded, or random
+        #[arg(long, value_name = "SEED", value_parser = |s: &str| s.parse::<ChaosSeed>().map(|c| c.0))]
+        chaos: Option<u64>,
+        /// Explain each demo step by step, waiting for Enter
+        #[arg(long)]
+        lesson: bool,
+        /// Fail unless each demo shows what it teaches
+        #[arg(long)]
+        check: bool,
+        /// Demos to run, all if none
+        #[arg(value_name = "DEMO")]
+        demos: Vec<String>,
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, Error> {
+        let args = Args::try_parse_from(std::iter::once("demos".to_string()).chain(args))
+            .map_err(from_clap)?;
+        Ok(Cli {
+            config: args.config,
+            overrides: Overrides {
+                // NB: A Vec is never "not given", empty has to mean that
+                run: (!args.demos.is_empty()).then_some(args.demos),
+                seed: args.seed,
+                reads: args.reads,
+                chunk: args.chunk,
+                format: args.format,
+                chaos: args.chaos,
+                lesson: args.lesson.then_some(true),
+                check: args.check.then_some(true),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "cli-builder", feature = "cli-derive"))]
+    const CASES: &[&str] = &[
+        "",
+        "v3 v4",
+        "--seed 3 --format json",
+        "--config demos.toml --reads 2 --chunk 8 v5",
+        "--chaos 7 v2",
+        "--lesson v3 v4",
+        "--check --reads 0",
+    ];
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_manual() {
+        let cli = manual::parse(args("--reads 2 v3 --seed 7 v4")).unwrap();
+        assert_eq!(cli.overrides.reads, Some(2));
+        assert_eq!(cli.overrides.seed, Some(7));
+        assert_eq!(cli.overrides.run, Some(vec!["v3".into(), "v4".into()]));
+        let cli = manual::parse(args("--chaos 9")).unwrap();
+        assert_eq!(cli.overrides.chaos, Some(9));
+        assert!(matches!(
+            manual::parse(args("--reeds 2")),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    #[cfg(feature = "cli-builder")]
+    #[test]
+    fn test_builder_same_as_manual() {
+        builder::command().debug_assert();
+        for case in CASES {
+            assert_eq!(
+                builder::parse(args(case)).unwrap(),
+                manual::parse(args(case)).unwrap(),
+                "{case}"
+            );
+        }
+        for bad in ["--reeds 2", "--format yaml", "--seed", "--chaos lots"] {
+            assert!(builder::parse(args(bad)).is_err(), "{bad}");
+        }
+    }
+
+    #[cfg(feature = "cli-derive")]
+    #[test]
+    fn test_derive_same_as_manual() {
+        use clap::CommandFactory;
+        derive::Args::command().debug_assert();
+        for case in CASES {
+            assert_eq!(
+                derive::parse(args(case)).unwrap(),
+                manual::parse(args(case)).unwrap(),
+                "{case}"
+            );
+        }
+        // Suggests the flag that was probably meant
+        let Err(Error::Usage(e)) = derive::parse(args("--reeds 2")) else {
+            panic!("expected usage error");
+        };
+        assert!(e.contains("--reads"), "{e}");
+    }
+}
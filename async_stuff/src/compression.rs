@@ -0,0 +1,294 @@
+//! Stacking [AsyncRead] layers: decompression under a throttle, plus a hand-written RLE codec
+//!
+//! Each layer only knows the one below it, so they compose in any order:
+//! ```text
+//! HashingReader          verify what comes out          digest::HashingReader
+//!   v5::ReadWrap         throttle: 1s before each read  fasterthanlime_pin::v5
+//!     GzipDecoder        decompress                      async-compression
+//!       BufReader        decoders need AsyncBufRead     tokio
+//!         source         file, socket, &[u8], ...
+//! ```
+//!
+//! | codec | how | good at |
+//! | --- | --- | --- |
+//! | gzip \[`async_compression::tokio::bufread::GzipDecoder`\] | DEFLATE: LZ77 + Huffman, CRC32 trailer | anything, widely supported |
+//! | zstd \[`async_compression::tokio::bufread::ZstdDecoder`\] | LZ77 + FSE, larger window | same, faster to decode \[similar size at default levels for small inputs\] |
+//! | [RleDecoder] \[hand-written\] | `(count, byte)` pairs | long runs of one byte only, doubles random data |
+//!
+//! NB: [crate::fasterthanlime_pin::v5::ReadWrap] is `!Unpin` \[it holds a `Sleep`\], which makes
+//! every layer above it `!Unpin` too. `Box::pin()` it to get an `Unpin` stack that can be passed
+//! by `&mut` and taken apart again \[e.g. `HashingReader::into_digest()`\].
+//!
+//! NB: The decoders take an [AsyncBufRead] so they can look at buffered input without consuming
+//! it \[a compressed block may end mid-buffer\]. Wrap plain readers in [tokio::io::BufReader].
+//!
+//! Compare sizes and watch the throttle:
+//! ```sh
+//! cargo run -p async_stuff --bin compression
+//! ```
+
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
+
+/// Runs of up to 255 bytes as `(count, byte)` pairs
+pub fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for run in data.chunk_by(|a, b| a == b) {
+        for part in run.chunks(u8::MAX as usize) {
+            out.extend([part.len() as u8, part[0]]);
+        }
+    }
+    out
+}
+
+pin_project! {
+    /// Streaming inverse of [rle_encode()]
+    ///
+    /// Pairs may be split across reads of the inner reader, so the count byte is kept until its
+    /// value byte arrives.
+    pub struct RleDecoder<R> {
+        #[pin]
+        read: R,
+        count: Option<u8>,
+        // Rest of the current run still to hand out
+        run: (u8, u8),
+        // Error of the inner reader that came after output, for the next read
+        error: Option<io::Error>,
+    }
+}
+
+impl<R: AsyncBufRead> RleDecoder<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read,
+            count: None,
+            run: (0, 0),
+            error: None,
+        }
+    }
+}
+
+impl<R: AsyncBufRead> AsyncRead for RleDecoder<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        let start = buf.filled().len();
+        // NB: An error must not come with data [tokio's read_to_end() asserts on it], so bytes
+        //     already decoded go out first and the error on the next call. Polling the inner
+        //     reader again instead would lose errors it only returns once. Pending is simply
+        //     hit again.
+        let wrote = |buf: &ReadBuf<'_>| buf.filled().len() > start;
+        while buf.remaining() > 0 {
+            let (byte, left) = *this.run;
+            if left > 0 {
+                let n = buf.remaining().min(left as usize);
+                buf.put_slice(&[byte; u8::MAX as usize][..n]);
+                this.run.1 -= n as u8;
+                continue;
+            }
+            let input = match this.read.as_mut().poll_fill_buf(cx) {
+                Poll::Ready(Ok(input)) => input,
+                Poll::Ready(Err(e)) if wrote(buf) => {
+                    *this.error = Some(e);
+                    break;
+                }
+                Poll::Pending if wrote(buf) => break,
+                other => return other.map_ok(|_| ()),
+            };
+            let err = match (input.first(), *this.count) {
+                // EOF of the inner reader
+                (None, None) => break,
+                (None, Some(_)) => (io::ErrorKind::UnexpectedEof, "RLE input ends mid pair"),
+                (Some(0), None) => (io::ErrorKind::InvalidData, "RLE run of length 0"),
+                (Some(&b), None) => {
+                    this.read.as_mut().consume(1);
+                    *this.count = Some(b);
+                    continue;
+                }
+                (Some(&b), Some(count)) => {
+                    this.read.as_mut().consume(1);
+                    *this.count = None;
+                    *this.run = (b, count);
+                    continue;
+                }
+            };
+            if wrote(buf) {
+                break;
+            }
+            return Poll::Ready(Err(io::Error::new(err.0, err.1)));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+pin_project! {
+    /// Counts the bytes read through it \[to compare compressed vs decompressed sizes\]
+    pub struct CountingReader<R> {
+        #[pin]
+        read: R,
+        count: u64,
+    }
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(read: R) -> Self {
+        Self { read, count: 0 }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for CountingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let before = buf.filled().len();
+        ready!(this.read.poll_read(cx, buf))?;
+        *this.count += (buf.filled().len() - before) as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::{HashingReader, Sha256, StreamDigest};
+    use crate::fasterthanlime_pin::v5::ReadWrap;
+    use crate::faulty::{Faults, FaultyIo};
+    use async_compression::tokio::bufread::{GzipDecoder, GzipEncoder, ZstdDecoder, ZstdEncoder};
+    use std::pin::pin;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, BufReader};
+    use tokio::time::Instant;
+
+    fn sample() -> Vec<u8> {
+        let mut v = Vec::new();
+        for i in 0..200u32 {
+            v.extend(std::iter::repeat_n(
+                b'a' + (i % 7) as u8,
+                (i % 300) as usize,
+            ));
+            v.extend(format!("line {i}\n").bytes());
+        }
+        v
+    }
+
+    async fn read_all(r: impl AsyncRead) -> Vec<u8> {
+        let mut out = Vec::new();
+        pin!(r).read_to_end(&mut out).await.unwrap();
+        out
+    }
+
+    async fn sha256(data: &[u8]) -> String {
+        let mut r = HashingReader::new(data, Sha256::default());
+        tokio::io::copy(&mut r, &mut tokio::io::sink())
+            .await
+            .unwrap();
+        r.into_digest().hex()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gzip_under_throttle() {
+        let data = sample();
+        let gz = read_all(GzipEncoder::new(&data[..])).await;
+        assert!(gz.len() < data.len() / 4, "{} vs {}", gz.len(), data.len());
+
+        let decoder = GzipDecoder::new(BufReader::new(&gz[..]));
+        // Box::pin as v5::ReadWrap is !Unpin and the layers above want to be moved around
+        let mut hashing = HashingReader::new(Box::pin(ReadWrap::new(decoder)), Sha256::default());
+        let now = Instant::now();
+        let out = read_all(&mut hashing).await;
+        assert_eq!(out, data);
+        assert_eq!(hashing.into_digest().hex(), sha256(&data).await);
+        // read_to_end() needs at least 2 reads [data, then EOF]
+        assert!(now.elapsed() >= Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_zstd_under_throttle() {
+        let data = sample();
+        let zst = read_all(ZstdEncoder::new(&data[..])).await;
+        let out = read_all(ReadWrap::new(ZstdDecoder::new(BufReader::new(&zst[..])))).await;
+        assert_eq!(out, data);
+    }
+
+    #[tokio::test]
+    async fn test_rle() {
+        let data = sample();
+        let rle = rle_encode(&data);
+        assert_eq!(rle_encode(b"aaab"), [3, b'a', 1, b'b']);
+        assert_eq!(rle_encode(&[7; 300]), [255, 7, 45, 7]);
+
+        // Small BufReader capacity splits pairs across fills
+        let out = read_all(RleDecoder::new(BufReader::with_capacity(3, &rle[..]))).await;
+        assert_eq!(out, data);
+    }
+
+    /// RLE inside gzip: gzip -> BufReader -> RLE -> count
+    #[tokio::test]
+    async fn test_layers() {
+        let data = sample();
+        let gz = read_all(GzipEncoder::new(&rle_encode(&data)[..])).await;
+        let gunzip = GzipDecoder::new(BufReader::new(&gz[..]));
+        let mut counting = CountingReader::new(RleDecoder::new(BufReader::new(gunzip)));
+        let out = read_all(&mut counting).await;
+        assert_eq!(out, data);
+        assert_eq!(counting.count(), data.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_rle_errors() {
+        let mut out = Vec::new();
+        let err = RleDecoder::new(&[3, b'a', 2][..])
+            .read_to_end(&mut out)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(out, b"aaa");
+
+        let err = RleDecoder::new(&[0, b'a'][..])
+            .read_to_end(&mut Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Errors of the inner reader that come after some output aren't dropped
+    #[tokio::test]
+    async fn test_rle_inner_errors() {
+        let data = sample();
+        let rle = rle_encode(&data);
+        let faults = Faults {
+            error: 0.3,
+            ..Faults::default()
+        };
+        let mut faulty = FaultyIo::new(&rle[..], faults, 1);
+        let mut decoder = RleDecoder::new(BufReader::with_capacity(5, &mut faulty));
+        let (mut out, mut errors) = (Vec::<u8>::new(), 0);
+        let mut buf = [0; 64];
+        loop {
+            match decoder.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => out.extend_from_slice(&buf[..n]),
+                Err(_) => errors += 1,
+            }
+        }
+        assert_eq!(out, data);
+        assert!(errors > 0);
+        assert_eq!(errors, faulty.counts().error);
+    }
+}
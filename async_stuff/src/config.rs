@@ -0,0 +1,380 @@
+//! Configuration of the `demos` runner, layered from four sources
+//!
+//! | precedence | source | example |
+//! | --- | --- | --- |
+//! | 1 \[lowest\] | [Config::default] | `reads = 1` |
+//! | 2 | TOML file: `--config FILE`, else `$DEMOS_CONFIG`, else `./demos.toml` if it exists | `reads = 2` |
+//! | 3 | env vars `DEMOS_<KEY>` | `DEMOS_READS=3` |
+//! | 4 \[highest\] | CLI flags | `--reads 4` |
+//!
+//! ```sh
+//! printf 'reads = 2\nformat = "json"\n' > /tmp/demos.toml
+//! DEMOS_CONFIG=/tmp/demos.toml DEMOS_SEED=7 cargo run --bin demos -- --reads 3 v3 v4
+//! ```
+//!
+//! Every source parses into the same [Overrides] \[all fields `Option`\], so layering is just
+//! "a later `Some` wins". figment and config-rs do the same on a generic value tree, which also
+//! gets them nested keys and error messages naming the source of a bad value.
+//!
+//! NB: Lists replace rather than merge, `DEMOS_RUN=v3` runs only v3 whatever the file says.
+
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+use simple::exit::{Code, Exit};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const ENV_PREFIX: &str = "DEMOS_";
+pub const DEFAULT_FILE: &str = "demos.toml";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Config {
+    /// Names of the demos to run, all of them if empty
+    pub run: Vec<String>,
+    /// Seed of the input data
+    pub seed: u64,
+    /// Reads per demo
+    pub reads: usize,
+    /// Bytes per read
+    pub chunk: usize,
+    pub format: Format,
+    /// Seed of the faults and latency injected under every demo, none without `--chaos`
+    pub chaos: Option<u64>,
+    /// Explain each demo step by step, see [crate::lesson]
+    pub lesson: bool,
+    /// Fail unless each demo shows what it teaches, see [crate::runner::check]
+    pub check: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            run: Vec::new(),
+            seed: 0,
+            reads: 1,
+            chunk: 32,
+            format: Format::Text,
+            chaos: None,
+            lesson: false,
+            check: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Text,
+    /// A [simple::report::Report] per demo
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err("expected text or json".into()),
+        }
+    }
+}
+
+/// How `--chaos` and `DEMOS_CHAOS` are given: a seed, or `random` for a fresh one. A file can
+/// only give a seed, `chaos = 7`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChaosSeed(pub u64);
+
+impl FromStr for ChaosSeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self(rand::rng().random())),
+            _ => s
+                .parse()
+                .map(Self)
+                .map_err(|e| format!("expected a seed or random, {e}")),
+        }
+    }
+}
+
+/// One layer, `None` keeps the value of the layers below
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Overrides {
+    pub run: Option<Vec<String>>,
+    pub seed: Option<u64>,
+    pub reads: Option<usize>,
+    pub chunk: Option<usize>,
+    pub format: Option<Format>,
+    pub chaos: Option<u64>,
+    pub lesson: Option<bool>,
+    pub check: Option<bool>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Toml(PathBuf, toml::de::Error),
+    /// `origin` is the env var or flag, e.g. `DEMOS_READS` or `--reads`
+    Invalid {
+        origin: String,
+        value: String,
+        reason: String,
+    },
+    Usage(String),
+    /// Not an error, `--help` was given
+    Help(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Toml(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Invalid {
+                origin,
+                value,
+                reason,
+            } => write!(f, "{origin}={value:?}: {reason}"),
+            Self::Usage(msg) | Self::Help(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Bad flags are [Code::Usage], bad files and env vars [Code::Config]
+impl From<Error> for Exit {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(path, e) => Exit::new(Code::Config, Exit::context(path.display(), e)),
+            Error::Toml(path, e) => Exit::new(Code::Config, Exit::context(path.display(), e)),
+            Error::Invalid { ref origin, .. } if origin.starts_with("--") => Exit::usage(e),
+            Error::Invalid { .. } => Exit::new(Code::Config, e),
+            Error::Usage(_) | Error::Help(_) => Exit::usage(e),
+        }
+    }
+}
+
+/// `origin` names the flag or env var in the error
+pub(crate) fn parse_value<T: FromStr<Err: fmt::Display>>(
+    origin: &str,
+    value: &str,
+) -> Result<T, Error> {
+    value.parse().map_err(|e: T::Err| Error::Invalid {
+        origin: origin.into(),
+        value: value.into(),
+        reason: e.to_string(),
+    })
+}
+
+impl Overrides {
+    pub fn from_toml(path: &Path, s: &str) -> Result<Self, Error> {
+        toml::from_str(s).map_err(|e| Error::Toml(path.into(), e))
+    }
+
+    /// `DEMOS_<KEY>` vars, `DEMOS_RUN` is comma separated. Others, e.g. `PATH`, are ignored.
+    pub fn from_env<'a>(vars: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self, Error> {
+        let mut o = Self::default();
+        for (key, value) in vars {
+            let Some(name) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            match name {
+                "RUN" => o.run = Some(value.split(',').map(|s| s.trim().to_string()).collect()),
+                "SEED" => o.seed = Some(parse_value(key, value)?),
+                "READS" => o.reads = Some(parse_value(key, value)?),
+                "CHUNK" => o.chunk = Some(parse_value(key, value)?),
+                "FORMAT" => o.format = Some(parse_value(key, value)?),
+                "CHAOS" => o.chaos = Some(parse_value::<ChaosSeed>(key, value)?.0),
+                "LESSON" => o.lesson = Some(parse_value(key, value)?),
+                "CHECK" => o.check = Some(parse_value(key, value)?),
+                // Not a setting [and handled by load()]
+                "CONFIG" => {}
+                _ => {
+                    return Err(Error::Invalid {
+                        origin: key.into(),
+                        value: value.into(),
+                        reason: "unknown setting".into(),
+                    });
+                }
+            }
+        }
+        Ok(o)
+    }
+
+    /// `self` with `later` on top
+    pub fn merge(self, later: Self) -> Self {
+        Self {
+            run: later.run.or(self.run),
+            seed: later.seed.or(self.seed),
+            reads: later.reads.or(self.reads),
+            chunk: later.chunk.or(self.chunk),
+            format: later.format.or(self.format),
+            chaos: later.chaos.or(self.chaos),
+            lesson: later.lesson.or(self.lesson),
+            check: later.check.or(self.check),
+        }
+    }
+}
+
+impl Config {
+    pub fn apply(mut self, o: Overrides) -> Self {
+        if let Some(run) = o.run {
+            self.run = run;
+        }
+        self.seed = o.seed.unwrap_or(self.seed);
+        self.reads = o.reads.unwrap_or(self.reads);
+        self.chunk = o.chunk.unwrap_or(self.chunk);
+        self.format = o.format.unwrap_or(self.format);
+        self.chaos = o.chaos.or(self.chaos);
+        self.lesson = o.lesson.unwrap_or(self.lesson);
+        self.check = o.check.unwrap_or(self.check);
+        self
+    }
+}
+
+/// What the command line says: where the file is plus the top layer, see [crate::cli] for parsers
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Cli {
+    pub config: Option<PathBuf>,
+    pub overrides: Overrides,
+}
+
+/// Layer all four sources. An explicit file \[flag or env\] must exist, `./demos.toml` may not,
+/// but if it does it must be readable.
+pub fn load<'a>(
+    cli: Cli,
+    env: impl IntoIterator<Item = (&'a str, &'a str)> + Clone,
+) -> Result<Config, Error> {
+    let env_file = env
+        .clone()
+        .into_iter()
+        .find(|(k, _)| *k == "DEMOS_CONFIG")
+        .map(|(_, v)| PathBuf::from(v));
+    let file = match cli.config.or(env_file) {
+        Some(path) => {
+            let s = std::fs::read_to_string(&path).map_err(|e| Error::Io(path.clone(), e))?;
+            Overrides::from_toml(&path, &s)?
+        }
+        None => match std::fs::read_to_string(DEFAULT_FILE) {
+            Ok(s) => Overrides::from_toml(Path::new(DEFAULT_FILE), &s)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Overrides::default(),
+            Err(e) => return Err(Error::Io(DEFAULT_FILE.into(), e)),
+        },
+    };
+    let layers = file.merge(Overrides::from_env(env)?).merge(cli.overrides);
+    Ok(Config::default().apply(layers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::manual::parse;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_precedence() {
+        let path = std::env::temp_dir().join(format!("demos-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "reads = 2\nchunk = 8\nseed = 1\nrun = [\"v2\", \"v3\"]\n",
+        )
+        .unwrap();
+        let env = [
+            ("DEMOS_CONFIG", path.to_str().unwrap()),
+            ("DEMOS_CHUNK", "16"),
+            ("DEMOS_SEED", "2"),
+            ("PATH", "/usr/bin"),
+        ];
+        let cli = parse(args("--seed 3 --format json")).unwrap();
+        let config = load(cli, env).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                // file
+                run: vec!["v2".into(), "v3".into()],
+                reads: 2,
+                // env over file
+                chunk: 16,
+                // flag over env over file
+                seed: 3,
+                format: Format::Json,
+                chaos: None,
+                lesson: false,
+                check: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = load(Cli::default(), []).unwrap();
+        assert_eq!(config, Config::default());
+        // Positional args replace the list rather than adding to it
+        let cli = parse(args("v4 v5")).unwrap();
+        let config = load(cli, [("DEMOS_RUN", "v2, v3")]).unwrap();
+        assert_eq!(config.run, ["v4", "v5"]);
+        // The flag can only turn it on, DEMOS_LESSON=false can turn a file's lesson = true off
+        let env = [("DEMOS_LESSON", "false")];
+        let config = load(parse(args("--lesson")).unwrap(), env).unwrap();
+        assert!(config.lesson);
+        let file = Overrides::from_toml(Path::new("demos.toml"), "lesson = true").unwrap();
+        assert!(
+            !Config::default()
+                .apply(file.merge(Overrides::from_env(env).unwrap()))
+                .lesson
+        );
+    }
+
+    #[test]
+    fn test_chaos() {
+        let cli = parse(args("--chaos 3")).unwrap();
+        assert_eq!(load(cli, [("DEMOS_CHAOS", "4")]).unwrap().chaos, Some(3));
+        assert_eq!(
+            Overrides::from_toml(Path::new("demos.toml"), "chaos = 7")
+                .unwrap()
+                .chaos,
+            Some(7)
+        );
+        // A fresh seed, printed by the runner so the run can be repeated
+        let random = load(Cli::default(), [("DEMOS_CHAOS", "random")]).unwrap();
+        assert!(random.chaos.is_some());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |r: Result<Config, Error>| r.unwrap_err().to_string();
+        assert_eq!(
+            err(load(Cli::default(), [("DEMOS_READS", "many")])),
+            r#"DEMOS_READS="many": invalid digit found in string"#
+        );
+        assert_eq!(
+            err(load(Cli::default(), [("DEMOS_READZ", "1")])),
+            r#"DEMOS_READZ="1": unknown setting"#
+        );
+        assert!(matches!(
+            parse(args("--format yaml")),
+            Err(Error::Invalid { .. })
+        ));
+        assert!(matches!(parse(args("--seed")), Err(Error::Usage(_))));
+        assert_eq!(
+            err(load(Cli::default(), [("DEMOS_CHAOS", "lots")])),
+            r#"DEMOS_CHAOS="lots": expected a seed or random, invalid digit found in string"#
+        );
+        let cli = parse(args("--config /nonexistent/demos.toml")).unwrap();
+        assert!(matches!(load(cli, []), Err(Error::Io(..))));
+        // Typos in the file are errors too, not silently ignored
+        let e = Overrides::from_toml(Path::new("demos.toml"), "reeds = 2").unwrap_err();
+        assert!(e.to_string().contains("unknown field `reeds`"));
+    }
+}
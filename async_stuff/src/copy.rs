@@ -0,0 +1,189 @@
+//! File to socket three ways: [tokio::io::copy], [tokio::io::copy_buf] and `splice(2)`
+//!
+//! The socket is one end of a [UnixStream::pair], a thread drains the other end and checksums
+//! it. So the numbers are the sender's cost plus one `read` per chunk on the receiving side.
+//!
+//! | [Method] | copies through userspace | per chunk |
+//! | --- | --- | --- |
+//! | [Method::Copy] | yes, 8 KiB buffer inside `copy` | file read on the blocking pool, then socket write |
+//! | [Method::CopyBuf] | yes, the [BufReader]'s buffer \[[COPY_BUF]\] | same, with fewer and bigger reads |
+//! | [Method::Splice] | no, file → pipe → socket in the kernel | two `splice` calls, on one blocking thread |
+//!
+//! 256 MiB from a file in the page cache, release, median of 5 \[±10% between invocations\]:
+//!
+//! | [Method] | MB/s |
+//! | --- | --- |
+//! | [Method::Copy] | 720 |
+//! | [Method::CopyBuf] | 2860 |
+//! | [Method::Splice] | 5880 |
+//!
+//! [Method::Copy] is slow mostly because every 8 KiB read of a [tokio::fs::File] is a trip to
+//! the blocking pool, not because of the copying. [Method::CopyBuf] fixes that with big reads.
+//! ```sh
+//! cargo run -p async_stuff --release --bin copy -- 256
+//! ```
+//!
+//! NB: `splice` needs a pipe on one side, hence the detour. Linux only, [Method::Splice] is
+//! [io::ErrorKind::Unsupported] elsewhere. `sendfile(2)` would do file → socket in one call.
+
+use crate::digest::{Crc32, StreamDigest};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWriteExt, BufReader};
+
+/// Buffer of [Method::CopyBuf], and chunk of [Method::Splice]
+pub const COPY_BUF: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Copy,
+    CopyBuf,
+    Splice,
+}
+
+impl Method {
+    pub const ALL: [Method; 3] = [Method::Copy, Method::CopyBuf, Method::Splice];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub bytes: u64,
+    /// [Crc32] hex of what arrived on the other end
+    pub crc32: String,
+    pub elapsed: Duration,
+}
+
+impl Outcome {
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes as f64 / 1e6 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Send the file at `path` over a socket with `method`
+pub async fn run(method: Method, path: &Path) -> io::Result<Outcome> {
+    let (tx, mut rx) = UnixStream::pair()?;
+    let drain = thread::spawn(move || {
+        // NB: A blocking thread, so StreamDigest by hand rather than a HashingReader
+        let mut crc = Crc32::default();
+        let mut buf = vec![0u8; COPY_BUF];
+        loop {
+            match rx.read(&mut buf)? {
+                0 => return io::Result::Ok(crc.hex()),
+                n => crc.update(&buf[..n]),
+            }
+        }
+    });
+    let start = Instant::now();
+    let sent = match method {
+        Method::Copy | Method::CopyBuf => {
+            tx.set_nonblocking(true)?;
+            let mut sock = tokio::net::UnixStream::from_std(tx)?;
+            let mut file = tokio::fs::File::open(path).await?;
+            let sent = if method == Method::Copy {
+                tokio::io::copy(&mut file, &mut sock).await?
+            } else {
+                let mut file = BufReader::with_capacity(COPY_BUF, file);
+                tokio::io::copy_buf(&mut file, &mut sock).await?
+            };
+            sock.shutdown().await?;
+            sent
+        }
+        Method::Splice => {
+            let path = path.to_owned();
+            tokio::task::spawn_blocking(move || splice_all(&File::open(path)?, &tx))
+                .await
+                .map_err(io::Error::other)??
+        }
+    };
+    let crc32 = drain.join().expect("drain thread panicked")?;
+    Ok(Outcome {
+        bytes: sent,
+        crc32,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Blocking file → pipe → `sock` until EOF, returns the bytes sent
+#[cfg(target_os = "linux")]
+pub fn splice_all(file: &File, sock: &UnixStream) -> io::Result<u64> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    let check = |n: isize| usize::try_from(n).map_err(|_| io::Error::last_os_error());
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: Fresh from pipe2 and owned by nothing else, closed on drop
+    let (pipe_r, pipe_w) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    let flags = libc::SPLICE_F_MOVE | libc::SPLICE_F_MORE;
+    let mut sent = 0u64;
+    loop {
+        // SAFETY: Valid descriptors, null offsets use and advance the file position
+        let n = check(unsafe {
+            libc::splice(
+                file.as_raw_fd(),
+                std::ptr::null_mut(),
+                pipe_w.as_raw_fd(),
+                std::ptr::null_mut(),
+                COPY_BUF,
+                flags,
+            )
+        })?;
+        if n == 0 {
+            return Ok(sent);
+        }
+        let mut left = n;
+        while left > 0 {
+            // SAFETY: as above
+            left -= check(unsafe {
+                libc::splice(
+                    pipe_r.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    sock.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    left,
+                    flags,
+                )
+            })?;
+        }
+        sent += n as u64;
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn splice_all(_: &File, _: &UnixStream) -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::digest_of;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_same_bytes_arrive() {
+        // Not a multiple of any buffer size
+        let data: Vec<u8> = (0..3 * COPY_BUF + 12345).map(|i| (i % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("copy-{}.bin", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let crc32 = digest_of(&data[..], Crc32::default())
+            .await
+            .unwrap()
+            .0
+            .hex();
+        for method in Method::ALL {
+            if cfg!(not(target_os = "linux")) && method == Method::Splice {
+                continue;
+            }
+            let out = run(method, &path).await.unwrap();
+            assert_eq!(out.bytes, data.len() as u64, "{method:?}");
+            assert_eq!(out.crc32, crc32, "{method:?}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+//! Two tasks locking two async mutexes in opposite order hang forever, [TrackedMutex] reports
+//! the cycle instead
+//!
+//! | | task 1 | task 2 |
+//! | --- | --- | --- |
+//! | 1 | locks `a` | locks `b` |
+//! | 2 | waits for `b` | waits for `a` |
+//! | [tokio::sync::Mutex] | hangs | hangs |
+//! | [TrackedMutex] | waits | `Err(`[Deadlock]`)`, drops `b` and gives up |
+//! | | gets `b`, done | |
+//!
+//! A [LockGraph] knows which task holds each of its locks and which lock each task waits for. A
+//! task about to wait follows holder → the lock it waits for → its holder … and if that leads
+//! back to itself, waiting would close the cycle: [TrackedMutex::lock] fails with the cycle
+//! rather than park. Exactly one task of the cycle sees it, the last one to wait.
+//!
+//! ```sh
+//! cargo run -p async_stuff --bin deadlock
+//! ```
+//!
+//! NB: Tasks are told apart by [tokio::task::id], so locking outside of a spawned task panics
+//! \[`#[tokio::main]`'s body is not a task\]. Only cycles of these locks are found, not one
+//! through e.g. a channel.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::task::Id;
+
+type LockId = usize;
+
+#[derive(Default)]
+struct Graph {
+    names: Vec<String>,
+    holders: HashMap<LockId, Id>,
+    waiting: HashMap<Id, LockId>,
+}
+
+impl Graph {
+    /// Locks from `lock` on, each waited for by the holder of the previous one, if it ends up
+    /// at `task`
+    fn cycle(&self, task: Id, lock: LockId) -> Option<Vec<Wait>> {
+        let mut cycle = Vec::new();
+        let mut next = lock;
+        while let Some(&holder) = self.holders.get(&next) {
+            cycle.push(Wait {
+                lock: self.names[next].clone(),
+                holder,
+            });
+            if holder == task {
+                return Some(cycle);
+            }
+            next = *self.waiting.get(&holder)?;
+            // A cycle that doesn't go through `task`, it is not the one to report it
+            if cycle.len() > self.names.len() {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// Makes [TrackedMutex]es that can find a cycle among themselves
+#[derive(Clone, Default)]
+pub struct LockGraph(Arc<Mutex<Graph>>);
+
+impl LockGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mutex<T>(&self, name: impl Into<String>, value: T) -> TrackedMutex<T> {
+        let mut graph = self.0.lock().unwrap();
+        graph.names.push(name.into());
+        TrackedMutex {
+            id: graph.names.len() - 1,
+            graph: self.clone(),
+            inner: tokio::sync::Mutex::new(value),
+        }
+    }
+}
+
+/// One edge of a cycle: `lock` is held by `holder`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wait {
+    pub lock: String,
+    pub holder: Id,
+}
+
+/// Waiting for the lock would never end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deadlock {
+    pub task: Id,
+    /// Starts with the lock `task` asked for, ends with one `task` holds
+    pub cycle: Vec<Wait>,
+}
+
+impl fmt::Display for Deadlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadlock: task {}", self.task)?;
+        for w in &self.cycle {
+            write!(f, " waits for {} held by task {}", w.lock, w.holder)?;
+            if w.holder != self.task {
+                write!(f, ", which")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Deadlock {}
+
+/// A [tokio::sync::Mutex] registered in a [LockGraph]
+pub struct TrackedMutex<T> {
+    id: LockId,
+    graph: LockGraph,
+    inner: tokio::sync::Mutex<T>,
+}
+
+/// Takes the task off the waiting list however the wait ends, including the lock future being
+/// dropped
+struct Waiting<'a> {
+    graph: &'a LockGraph,
+    task: Id,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.graph.0.lock().unwrap().waiting.remove(&self.task);
+    }
+}
+
+impl<T> TrackedMutex<T> {
+    /// Like [tokio::sync::Mutex::lock], except for waiting on a cycle
+    pub async fn lock(&self) -> Result<TrackedGuard<'_, T>, Deadlock> {
+        let task = tokio::task::try_id().expect("TrackedMutex locked outside of a tokio task");
+        let guard = match self.inner.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                let waiting = {
+                    let mut graph = self.graph.0.lock().unwrap();
+                    if let Some(cycle) = graph.cycle(task, self.id) {
+                        return Err(Deadlock { task, cycle });
+                    }
+                    graph.waiting.insert(task, self.id);
+                    Waiting {
+                        graph: &self.graph,
+                        task,
+                    }
+                };
+                let guard = self.inner.lock().await;
+                drop(waiting);
+                guard
+            }
+        };
+        self.graph.0.lock().unwrap().holders.insert(self.id, task);
+        Ok(TrackedGuard { mutex: self, guard })
+    }
+}
+
+pub struct TrackedGuard<'a, T> {
+    mutex: &'a TrackedMutex<T>,
+    guard: tokio::sync::MutexGuard<'a, T>,
+}
+
+impl<T> Deref for TrackedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TrackedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for TrackedGuard<'_, T> {
+    fn drop(&mut self) {
+        // Before the inner guard unlocks, so the next holder's insert comes after this
+        let graph = &self.mutex.graph;
+        graph.0.lock().unwrap().holders.remove(&self.mutex.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::Barrier;
+
+    /// Two tasks taking `a` and `b` in opposite order, both holding their first lock before
+    /// either asks for the second
+    #[tokio::test]
+    async fn test_opposite_order() {
+        let graph = LockGraph::new();
+        let a = Arc::new(graph.mutex("a", 0));
+        let b = Arc::new(graph.mutex("b", 0));
+        let barrier = Arc::new(Barrier::new(2));
+
+        let spawn = |first: Arc<TrackedMutex<i32>>, second: Arc<TrackedMutex<i32>>| {
+            let barrier = barrier.clone();
+            tokio::spawn(async move {
+                let mut first = first.lock().await.unwrap();
+                barrier.wait().await;
+                let mut second = second.lock().await?;
+                *first += 1;
+                *second += 1;
+                Ok::<_, Deadlock>(tokio::task::id())
+            })
+        };
+        let t1 = spawn(a.clone(), b.clone());
+        let t2 = spawn(b.clone(), a.clone());
+        let (r1, r2) = (t1.await.unwrap(), t2.await.unwrap());
+
+        // One of them finds the cycle, the other one completes once it gave up
+        let (ok, err) = match (r1, r2) {
+            (Ok(ok), Err(err)) | (Err(err), Ok(ok)) => (ok, err),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(err.cycle.len(), 2);
+        assert_eq!(err.cycle[0].holder, ok);
+        assert_eq!(err.cycle[1].holder, err.task);
+        let msg = err.to_string();
+        assert!(msg.contains("held by task"), "{msg}");
+        assert_eq!(*a.inner.lock().await + *b.inner.lock().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_plain_tokio_hangs() {
+        let a = Arc::new(tokio::sync::Mutex::new(()));
+        let b = Arc::new(tokio::sync::Mutex::new(()));
+        let barrier = Arc::new(Barrier::new(2));
+        let mut tasks = Vec::new();
+        for (first, second) in [(a.clone(), b.clone()), (b, a)] {
+            let barrier = barrier.clone();
+            tasks.push(tokio::spawn(async move {
+                let _first = first.lock().await;
+                barrier.wait().await;
+                let _second = second.lock().await;
+            }));
+        }
+        for task in &mut tasks {
+            let done = tokio::time::timeout(Duration::from_millis(100), task).await;
+            assert!(done.is_err(), "no deadlock");
+        }
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn test_contention_is_not_a_deadlock() {
+        let graph = LockGraph::new();
+        let m = Arc::new(graph.mutex("m", 0));
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let m = m.clone();
+                tokio::spawn(async move {
+                    for _ in 0..100 {
+                        let mut n = m.lock().await.unwrap();
+                        tokio::task::yield_now().await;
+                        *n += 1;
+                    }
+                })
+            })
+            .collect();
+        for t in tasks {
+            t.await.unwrap();
+        }
+        assert_eq!(*m.inner.lock().await, 800);
+        let g = graph.0.lock().unwrap();
+        assert!(g.holders.is_empty() && g.waiting.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+//! Checksums and hashes computed while the data streams past
+//!
+//! [HashingReader] wraps any [AsyncRead] and feeds every byte it hands out into a
+//! [StreamDigest], so verifying a copy costs no second pass over the data:
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> std::io::Result<()> {
+//! use async_stuff::digest::{HashingReader, Sha256, StreamDigest};
+//!
+//! let mut reader = HashingReader::new(&b"abc"[..], Sha256::default());
+//! let mut out = Vec::new();
+//! tokio::io::copy(&mut reader, &mut out).await?;
+//! assert_eq!(out, b"abc");
+//! assert_eq!(
+//!     reader.into_digest().hex(),
+//!     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! | algorithm | bits | purpose | speed |
+//! | --- | --- | --- | --- |
+//! | [Crc32] | 32 | detect accidental corruption \[zip, gzip, ethernet\] | fastest \[SIMD\] |
+//! | [Fnv1a] | 64 | hash table keys for short inputs | fast for short, slow for long \[a byte at a time\] |
+//! | [SipHash] | 64 | std `HashMap` default \[keyed, resists HashDoS\] | medium |
+//! | [Sha256] | 256 | integrity against tampering \[cryptographic\] | slowest |
+//!
+//! Checksum a file with all four:
+//! ```sh
+//! cargo run -p async_stuff --bin checksum -- simple/fixtures/frames.bin
+//! ```
+//!
+//! NB: Only SHA-256 protects against a deliberately modified file, the others just catch
+//! accidents.
+
+use pin_project_lite::pin_project;
+use sha2::Digest;
+use std::hash::{DefaultHasher, Hasher};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Incremental hash: feed chunks, get the same result as hashing everything at once
+pub trait StreamDigest {
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Big endian bytes of the result
+    fn finish(self) -> Vec<u8>;
+
+    fn hex(self) -> String
+    where
+        Self: Sized,
+    {
+        self.finish().iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Crc32(crc32fast::Hasher);
+
+impl StreamDigest for Crc32 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0.finalize().to_be_bytes().to_vec()
+    }
+}
+
+/// 64 bit FNV-1a, written out as it's only a few lines
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StreamDigest for Fnv1a {
+    fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+}
+
+/// SipHash-1-3 via std's [DefaultHasher]
+///
+/// NB: `DefaultHasher::new()` uses fixed keys so the result is reproducible \[`HashMap` uses
+/// random keys from `RandomState` instead\]. The algorithm itself is not guaranteed to stay the
+/// same across Rust releases, so don't persist these values.
+#[derive(Default)]
+pub struct SipHash(DefaultHasher);
+
+impl StreamDigest for SipHash {
+    fn update(&mut self, bytes: &[u8]) {
+        // NB: Hasher::write() not Hash::hash() which would also mix in the slice length, making
+        //     the result depend on how the stream was chunked
+        self.0.write(bytes);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0.finish().to_be_bytes().to_vec()
+    }
+}
+
+#[derive(Default)]
+pub struct Sha256(sha2::Sha256);
+
+impl StreamDigest for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0.finalize().to_vec()
+    }
+}
+
+pin_project! {
+    /// Pass-through [AsyncRead] that hashes what it reads
+    pub struct HashingReader<R, H> {
+        #[pin]
+        read: R,
+        digest: H,
+        len: u64,
+    }
+}
+
+impl<R, H: StreamDigest> HashingReader<R, H> {
+    pub fn new(read: R, digest: H) -> Self {
+        Self {
+            read,
+            digest,
+            len: 0,
+        }
+    }
+
+    /// Bytes hashed so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn into_digest(self) -> H {
+        self.digest
+    }
+}
+
+impl<R: AsyncRead, H: StreamDigest> AsyncRead for HashingReader<R, H> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        // Only the bytes added by this read, buf may have been partly filled already
+        let before = buf.filled().len();
+        let res = this.read.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = res {
+            let new = &buf.filled()[before..];
+            this.digest.update(new);
+            *this.len += new.len() as u64;
+        }
+        res
+    }
+}
+
+/// Read `r` to the end, returning the digest and length
+pub async fn digest_of<R, H>(r: R, digest: H) -> io::Result<(H, u64)>
+where
+    R: AsyncRead + Unpin,
+    H: StreamDigest,
+{
+    let mut reader = HashingReader::new(r, digest);
+    let len = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    debug_assert_eq!(len, reader.len());
+    Ok((reader.into_digest(), len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded::SeededReader;
+    use tokio::io::AsyncReadExt;
+
+    async fn hex_of<H: StreamDigest>(bytes: &[u8], digest: H) -> String {
+        digest_of(bytes, digest).await.unwrap().0.hex()
+    }
+
+    #[tokio::test]
+    async fn test_known_values() {
+        assert_eq!(hex_of(b"abc", Crc32::default()).await, "352441c2");
+        assert_eq!(hex_of(b"a", Fnv1a::default()).await, "af63dc4c8601ec8c");
+        assert_eq!(
+            hex_of(b"", Sha256::default()).await,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        // Same as hashing in one go
+        let mut h = DefaultHasher::new();
+        h.write(b"abc");
+        assert_eq!(
+            hex_of(b"abc", SipHash::default()).await,
+            format!("{:016x}", h.finish())
+        );
+    }
+
+    /// Reading in odd sized chunks gives the same result as reading in one go
+    #[tokio::test]
+    async fn test_chunking() {
+        let (whole, len) = digest_of(SeededReader::new(9).take(1_000), Sha256::default())
+            .await
+            .unwrap();
+        assert_eq!(len, 1_000);
+
+        let mut reader = HashingReader::new(SeededReader::new(9).take(1_000), Sha256::default());
+        let mut buf = [0u8; 7];
+        while reader.read(&mut buf).await.unwrap() > 0 {}
+        assert_eq!(reader.len(), 1_000);
+        assert_eq!(reader.into_digest().hex(), whole.hex());
+
+        // std's SipHasher buffers partial blocks between write() calls too
+        let mut reader = HashingReader::new(SeededReader::new(9).take(1_000), SipHash::default());
+        while reader.read(&mut buf).await.unwrap() > 0 {}
+        let (whole, _) = digest_of(SeededReader::new(9).take(1_000), SipHash::default())
+            .await
+            .unwrap();
+        assert_eq!(reader.into_digest().hex(), whole.hex());
+    }
+
+    #[tokio::test]
+    async fn test_detects_corruption() {
+        let mut data = vec![0u8; 4096];
+        SeededReader::new(1).read_exact(&mut data).await.unwrap();
+        let before = hex_of(&data, Crc32::default()).await;
+        data[100] ^= 1;
+        assert_ne!(hex_of(&data, Crc32::default()).await, before);
+    }
+}
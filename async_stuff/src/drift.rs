@@ -0,0 +1,161 @@
+//! How far a periodic loop drifts from `start + i × period` when each iteration does some work:
+//! `sleep(period)` in a loop vs [tokio::time::interval] with each [MissedTickBehavior]
+//!
+//! 100 ms period, 10 ms of work per iteration and one 350 ms spike in iteration 3, 12 iterations,
+//! on tokio's paused clock \[`cargo test` numbers, exact\]:
+//!
+//! | [Strategy] | last iteration starts at | drift | shortest gap | off the 100 ms grid | |
+//! | --- | --- | --- | --- | --- | --- |
+//! | [Strategy::SleepLoop] | 1550 ms | +450 ms | 110 ms | 10 of 12 | every iteration adds its work |
+//! | [Strategy::Interval] \[Burst\] | 1100 ms | 0 | 10 ms | 3 | catches up with back-to-back ticks |
+//! | [Strategy::Interval] \[Delay\] | 1350 ms | +250 ms | 100 ms | 9 | the spike shifts all later ticks once |
+//! | [Strategy::Interval] \[Skip\] | 1300 ms | +200 ms | 50 ms | 1 | one late tick at 550 ms, then back on the grid at 600 ms |
+//!
+//! `drift` is of the last iteration. Skip's is 2 periods, the ticks at 300 to 500 ms never happen.
+//!
+//! ```sh
+//! cargo run -p async_stuff --bin drift   # the real clock, work as thread::sleep
+//! ```
+//!
+//! NB: Burst is the default. A 1 s spike in a 10 ms interval is followed by 100 ticks as fast as
+//! the loop can go, which is rarely wanted for e.g. polling or keepalives.
+
+use std::time::Duration;
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    SleepLoop,
+    Interval(MissedTickBehavior),
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::SleepLoop,
+        Strategy::Interval(MissedTickBehavior::Burst),
+        Strategy::Interval(MissedTickBehavior::Delay),
+        Strategy::Interval(MissedTickBehavior::Skip),
+    ];
+}
+
+/// How the injected work takes its time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Work {
+    /// `thread::sleep`, blocking the task like CPU-bound work
+    Block,
+    /// [time::sleep], like awaiting something slow. The same to a single loop, and works on a
+    /// paused clock \[which auto-advances by exactly that much\]
+    Sleep,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub period: Duration,
+    /// When each iteration started, relative to the first
+    pub starts: Vec<Duration>,
+}
+
+impl Report {
+    /// Of the last iteration against `i × period`, negative never happens
+    pub fn drift(&self) -> Duration {
+        match self.starts.last() {
+            Some(last) => last.saturating_sub(self.period * (self.starts.len() - 1) as u32),
+            None => Duration::ZERO,
+        }
+    }
+
+    pub fn min_gap(&self) -> Duration {
+        self.starts
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Iterations that didn't start on a multiple of `period`
+    pub fn off_grid(&self) -> usize {
+        self.starts
+            .iter()
+            .filter(|s| s.as_nanos() % self.period.as_nanos() != 0)
+            .count()
+    }
+}
+
+/// One iteration per element of `work`, each doing that much
+pub async fn run(strategy: Strategy, period: Duration, work: &[Duration], how: Work) -> Report {
+    let start = Instant::now();
+    let mut starts = Vec::with_capacity(work.len());
+    let mut interval = match strategy {
+        Strategy::SleepLoop => None,
+        Strategy::Interval(missed) => {
+            let mut interval = time::interval(period);
+            interval.set_missed_tick_behavior(missed);
+            Some(interval)
+        }
+    };
+    for (i, &w) in work.iter().enumerate() {
+        match &mut interval {
+            // The first tick completes right away, like the first iteration of the loop
+            Some(interval) => _ = interval.tick().await,
+            None if i > 0 => time::sleep(period).await,
+            None => {}
+        }
+        starts.push(start.elapsed());
+        match how {
+            Work::Block => std::thread::sleep(w),
+            Work::Sleep => time::sleep(w).await,
+        }
+    }
+    Report { period, starts }
+}
+
+/// The work of the table: `iterations` × 10 ms, 350 ms in iteration 3
+pub fn spiky(iterations: usize) -> Vec<Duration> {
+    let mut work = vec![Duration::from_millis(10); iterations];
+    if let Some(w) = work.get_mut(2) {
+        *w = Duration::from_millis(350);
+    }
+    work
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(100);
+    const MS: Duration = Duration::from_millis(1);
+
+    #[tokio::test(start_paused = true)]
+    async fn test_table() {
+        let work = spiky(12);
+        let mut rows = Vec::new();
+        for strategy in Strategy::ALL {
+            let r = run(strategy, PERIOD, &work, Work::Sleep).await;
+            rows.push((
+                *r.starts.last().unwrap(),
+                r.drift(),
+                r.min_gap(),
+                r.off_grid(),
+            ));
+        }
+        assert_eq!(
+            rows,
+            [
+                (1550 * MS, 450 * MS, 110 * MS, 10),
+                (1100 * MS, Duration::ZERO, 10 * MS, 3),
+                (1350 * MS, 250 * MS, 100 * MS, 9),
+                (1300 * MS, 200 * MS, 50 * MS, 1),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_work_no_drift() {
+        let work = vec![Duration::ZERO; 10];
+        for strategy in Strategy::ALL {
+            let r = run(strategy, PERIOD, &work, Work::Sleep).await;
+            assert_eq!(r.drift(), Duration::ZERO, "{strategy:?}");
+            assert_eq!(r.min_gap(), PERIOD, "{strategy:?}");
+        }
+    }
+}
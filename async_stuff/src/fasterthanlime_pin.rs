@@ -0,0 +1,547 @@
+//! Explore the code examples in fasterthanlime "Pin and suffering"
+//! [article](https://fasterthanli.me/articles/pin-and-suffering) to understand
+//! [std::future::Future] and [std::pin]
+//!
+//! | version | underlying AsyncRead is Unpin? | wrapper ReadWrap is Unpin? | approach |
+//! | --- | --- | --- | --- |
+//! | [v1] | n/a | n/a | no wrapper |
+//! | [v2::ReadWrap] | ✅ yes | ✅ yes (via auto marker trait) | wraps AsyncRead |
+//! | [v3::ReadWrap] | ✅ yes | ✅ yes (via auto marker trait) | wraps AsyncRead w/ delays; box pin internal fields |
+//! | [v4::ReadWrap] | ✅ yes | ❌ no | wraps AsyncRead w/ delay _without_ bin pin |
+//! | [v5::ReadWrap] | ✅ yes / ❌ no | ❌ no ([pin_project_lite! macro](https://crates.io/crates/pin-project-lite) will [conditionally](v5/struct.ReadWrap.html#impl-Unpin-for-ReadWrap<R>) `impl Unpin` only if _all_ `#[pin]` fields are Unpin, but [tokio::time::Sleep] never is) | wraps AsyncRead w/delay and using external crate |
+//!
+//! The claims in the table are verified by the doctests on each `ReadWrap`:
+//! ```sh
+//! cargo test --doc -p async_stuff
+//! ```
+
+use crate::lesson::Lesson;
+use anyhow::Result;
+// NB: Following import only needed for older Rust so that
+//      Pin<...>.as_mut().poll()
+// works.  Rust 2024 does _not_ need it as Future is now part of the prelude.
+// re: https://doc.rust-lang.org/edition-guide/rust-2024/prelude.html
+use std::future::Future;
+
+/// Read a file using vanilla [tokio::io::AsyncRead]
+pub mod v1 {
+    use super::*;
+    use tokio::fs::File;
+    use tokio::io::AsyncReadExt;
+
+    pub async fn do_it() -> Result<()> {
+        // TODO Question: When do_it() is invoked will the "locals" here be allocated on the heap or stack?
+        let mut f = File::open("/dev/urandom").await?;
+        let mut buf = [0u8; 32];
+        let read_len = f.read_exact(&mut buf).await?;
+        println!("v1 Read {} bytes {:?}", read_len, buf);
+        Ok(())
+    }
+}
+
+/// Pass through to [tokio::io::AsyncRead]
+pub mod v2 {
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::fs::File;
+    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+    /// [Unpin] whenever `R` is, so it can be used directly without any pinning:
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> std::io::Result<()> {
+    /// use async_stuff::fasterthanlime_pin::v2::ReadWrap;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// let mut f = ReadWrap::new(&b"hello"[..]);
+    /// let mut buf = [0u8; 5];
+    /// f.read_exact(&mut buf).await?;
+    /// assert_eq!(&buf, b"hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub struct ReadWrap<R> {
+        read: R,
+    }
+
+    impl<R> ReadWrap<R> {
+        pub fn new(read: R) -> Self {
+            Self { read }
+        }
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for ReadWrap<R> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.read).poll_read(cx, buf)
+        }
+    }
+
+    /// `demos --lesson v2`, see [crate::lesson]
+    pub const LESSON: Lesson = Lesson {
+        before: &[
+            "v2::ReadWrap passes poll_read straight through to the reader it wraps, \
+             without any delay of its own.",
+            "It needs R: Unpin, which makes Pin::new(&mut self.read) safe, and the wrapper is \
+             then Unpin too: read_exact() works on it without any pinning.",
+        ],
+        after: &[
+            "No sleeps, so it took microseconds. The crc32 is that of the seeded input, \
+             v3 to v5 must print the same one.",
+        ],
+    };
+
+    crate::demo! {
+        name: "v2",
+        wrapper: ReadWrap,
+        summary: "passes reads through, Unpin like its reader",
+        tags: ["pin", "async-read"],
+        difficulty: Beginner,
+        per_read: Duration::ZERO,
+        wrapper_allocs: 0,
+        lesson: LESSON,
+    }
+
+    pub async fn do_it() -> Result<()> {
+        let f = File::open("/dev/urandom").await?;
+        let mut f: ReadWrap<File> = ReadWrap::new(f);
+        let mut buf = [0u8; 32];
+        let read_len = f.read_exact(&mut buf).await?;
+        println!("v2 Read {} bytes {:?}", read_len, buf);
+        Ok(())
+    }
+}
+
+/// Pass through to [tokio::io::AsyncRead] with delay and making wrapper [Unpin]
+/// \[which forces some of its !Unpin fields to go onto the heap\].
+///
+/// Verified: [v3::ReadWrap] is [Unpin] even though [tokio::time::Sleep] is not
+/// ```
+/// use async_stuff::fasterthanlime_pin::v3::ReadWrap;
+/// fn assert_unpin<T: Unpin>() {}
+/// assert_unpin::<ReadWrap<tokio::fs::File>>();
+/// ```
+///
+/// Recall that Box always puts what it points to on the heap
+///
+/// re: this [Google Doc](https://docs.google.com/presentation/d/1q-c7UAyrUlM-eZyTo1pd8SZ0qwA_wYxmPZVOQkoDmH4/edit#slide=id.p)
+pub mod v3 {
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::fs::File;
+    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+    use tokio::time::{self, Instant, Sleep};
+
+    /// NB: The size of [ReadWrap] as returned by
+    /// [AsyncReadExt::read_exact()] will _not directly_ include the size of [Sleep]
+    /// but instead just a pointer to Sleep.
+    ///
+    /// So if [ReadWrap] is on the stack, its [ReadWrap::sleep] is still on the heap
+    /// \[because Box is always on the heap\]. Verified: [ReadWrap] is just two thin pointers
+    /// ```
+    /// use async_stuff::fasterthanlime_pin::v3::ReadWrap;
+    /// use std::mem::size_of;
+    /// use tokio::time::Sleep;
+    ///
+    /// assert_eq!(size_of::<ReadWrap<tokio::fs::File>>(), 2 * size_of::<usize>());
+    /// assert!(size_of::<ReadWrap<tokio::fs::File>>() < size_of::<Sleep>());
+    /// ```
+    ///
+    /// Because it is [Unpin], it can be used without any pinning ceremony.
+    /// Each read waits for the 1s delay first:
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() -> std::io::Result<()> {
+    /// use async_stuff::fasterthanlime_pin::v3::ReadWrap;
+    /// use std::time::Duration;
+    /// use tokio::io::AsyncReadExt;
+    /// use tokio::time::Instant;
+    ///
+    /// let mut f = ReadWrap::new(&b"hello"[..]);
+    /// let mut buf = [0u8; 5];
+    /// let now = Instant::now();
+    /// f.read_exact(&mut buf).await?;
+    /// assert_eq!(&buf, b"hello");
+    /// assert!(now.elapsed() >= Duration::from_secs(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub struct ReadWrap<R> {
+        read: Pin<Box<R>>,
+        sleep: Pin<Box<Sleep>>,
+    }
+
+    impl<R> ReadWrap<R> {
+        pub fn new(read: R) -> Self {
+            Self {
+                read: Box::pin(read),
+                sleep: Box::pin(time::sleep(Duration::from_secs(1))),
+            }
+        }
+    }
+
+    impl<R: AsyncRead> AsyncRead for ReadWrap<R> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            match self.sleep.as_mut().poll(cx) {
+                Poll::Ready(_) => {
+                    // woke up => read into buffer
+                    self.sleep
+                        .as_mut()
+                        .reset(Instant::now() + Duration::from_secs(1));
+                    self.read.as_mut().poll_read(cx, buf)
+                }
+                // continue sleeping
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    /// `demos --lesson v3`, see [crate::lesson]
+    pub const LESSON: Lesson = Lesson {
+        before: &[
+            "v3::ReadWrap sleeps a second before every read of the reader it wraps, with a \
+             tokio::time::Sleep.",
+            "Sleep is !Unpin. v3 boxes it, and the reader too: Pin<Box<_>> is Unpin whatever it \
+             points to, so the wrapper stays Unpin and needs no pinning to be used.",
+            "The price is two heap allocations, the wrapper itself is just two pointers.",
+        ],
+        after: &[
+            "About a second per read, each read_exact() was a single read of the seeded input.",
+            "The same crc32 as v2, the wrapper only changes the timing.",
+        ],
+    };
+
+    crate::demo! {
+        name: "v3",
+        wrapper: ReadWrap,
+        summary: "a second before each read, the Sleep boxed to stay Unpin",
+        tags: ["pin", "async-read", "timers", "heap", "editions"],
+        difficulty: Intermediate,
+        per_read: Duration::from_secs(1),
+        wrapper_allocs: 2,
+        lesson: LESSON,
+    }
+
+    pub async fn do_it() -> Result<()> {
+        let f = File::open("/dev/urandom").await?;
+        let mut f = ReadWrap::new(f);
+
+        // TODO Question: Will ReadWrap be on stack as it does _not_ cross await points?
+        let mut f: Pin<&mut ReadWrap<File>> = Pin::new(&mut f);
+
+        let mut buf = [0u8; 32];
+        let now = Instant::now();
+        let read_len = f.read_exact(&mut buf).await?;
+        println!(
+            "v3 Read {} bytes {:?} after {:?}",
+            read_len,
+            buf,
+            now.elapsed()
+        );
+        Ok(())
+    }
+}
+
+/// Pass through to [tokio::io::AsyncRead] with delay but make wrapper *not* [Unpin]
+/// \[so that its !Unpin fields can stay on the stack\]
+///
+/// Verified: [v4::ReadWrap] is _not_ [Unpin] (because [tokio::time::Sleep] is not)
+/// ```compile_fail,E0277
+/// use async_stuff::fasterthanlime_pin::v4::ReadWrap;
+/// fn assert_unpin<T: Unpin>() {}
+/// assert_unpin::<ReadWrap<tokio::fs::File>>();
+/// ```
+pub mod v4 {
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::fs::File;
+    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+    use tokio::time::{self, Instant, Sleep};
+
+    /// NB: The size of [ReadWrap] as returned by
+    /// [AsyncReadExt::read_exact()] will include the size of [Sleep]
+    ///
+    /// So if [ReadWrap] is on the stack, so will its [ReadWrap::sleep]. Verified:
+    /// ```
+    /// use async_stuff::fasterthanlime_pin::v4::ReadWrap;
+    /// use std::mem::size_of;
+    /// use tokio::fs::File;
+    /// use tokio::time::Sleep;
+    ///
+    /// assert!(size_of::<ReadWrap<File>>() >= size_of::<File>() + size_of::<Sleep>());
+    /// ```
+    ///
+    /// Pin misuse: [ReadWrap] must be pinned before [AsyncReadExt] methods can be used
+    /// ```compile_fail,E0277
+    /// # async fn f() -> std::io::Result<()> {
+    /// use async_stuff::fasterthanlime_pin::v4::ReadWrap;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// let mut f = ReadWrap::new(&b"hello"[..]);
+    /// let mut buf = [0u8; 5];
+    /// f.read_exact(&mut buf).await?; // error: `PhantomPinned` cannot be unpinned
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Pin misuse: [std::pin::Pin::new()] only accepts [Unpin] pointees
+    /// ```compile_fail,E0277
+    /// use async_stuff::fasterthanlime_pin::v4::ReadWrap;
+    /// use std::pin::Pin;
+    ///
+    /// let mut f = ReadWrap::new(&b"hello"[..]);
+    /// let _f = Pin::new(&mut f);
+    /// ```
+    ///
+    /// Correct usage via [std::pin::pin!] \[instead of the `unsafe` in [do_it()]\]
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() -> std::io::Result<()> {
+    /// use async_stuff::fasterthanlime_pin::v4::ReadWrap;
+    /// use std::pin::pin;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// let mut f = pin!(ReadWrap::new(&b"hello"[..]));
+    /// let mut buf = [0u8; 5];
+    /// f.read_exact(&mut buf).await?;
+    /// assert_eq!(&buf, b"hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub struct ReadWrap<R> {
+        read: R,
+        sleep: Sleep,
+    }
+
+    impl<R> ReadWrap<R> {
+        pub fn new(read: R) -> Self {
+            Self {
+                read,
+                sleep: time::sleep(Duration::from_secs(1)),
+            }
+        }
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for ReadWrap<R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            // NB: See v5 which replaces "unsafe" with macro
+            // SAFETY: We never move out from ReadWrap. Instead, we only return Pin on borrowed fields.
+            let (mut read, mut sleep) = unsafe {
+                let this = self.get_unchecked_mut();
+                (
+                    Pin::new(&mut this.read),
+                    Pin::new_unchecked(&mut this.sleep),
+                )
+            };
+            match sleep.as_mut().poll(cx) {
+                Poll::Ready(_) => {
+                    // woke up => read into buffer
+                    sleep.reset(Instant::now() + Duration::from_secs(1));
+                    read.as_mut().poll_read(cx, buf)
+                }
+                // continue sleeping
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    /// `demos --lesson v4`, see [crate::lesson]
+    pub const LESSON: Lesson = Lesson {
+        before: &[
+            "v4::ReadWrap keeps its Sleep inline rather than boxed, so the wrapper is !Unpin.",
+            "poll_read gets a Pin<&mut Self> and splits it into its fields with the unsafe \
+             get_unchecked_mut(), promising never to move the Sleep.",
+            "Callers have to pin it first, the runner uses std::pin::pin!, Pin::new() wouldn't \
+             compile.",
+        ],
+        after: &["The timing of v3 without its heap allocations."],
+    };
+
+    crate::demo! {
+        name: "v4",
+        wrapper: ReadWrap,
+        summary: "v3 with the Sleep inline, pinned by hand with unsafe",
+        tags: ["pin", "async-read", "timers", "unsafe", "editions"],
+        difficulty: Advanced,
+        per_read: Duration::from_secs(1),
+        wrapper_allocs: 0,
+        lesson: LESSON,
+    }
+
+    pub async fn do_it() -> Result<()> {
+        let f = File::open("/dev/urandom").await?;
+        let mut f = ReadWrap::new(f);
+
+        // NB: Unlike v3, the usage of ReadWrap is more complicated
+        // NB: See v5 which replaces "unsafe" with macro
+        // TODO Question: Will ReadWrap be on stack as it does _not_ cross await points?
+        // SAFETY: We trivially never move from ReadWrap because we shadow it (varname is "f") with a Pin<&mut ReadWrap>
+        let mut f: Pin<&mut ReadWrap<File>> = unsafe { Pin::new_unchecked(&mut f) };
+
+        let mut buf = [0u8; 32];
+        let now = Instant::now();
+        let read_len = f.read_exact(&mut buf).await?;
+        println!(
+            "v4 Read {} bytes {:?} after {:?}",
+            read_len,
+            buf,
+            now.elapsed()
+        );
+        Ok(())
+    }
+}
+
+/// Pass through to [tokio::io::AsyncRead] with delay but make wrapper *not* [Unpin]
+/// ... and use 3rd party macros to avoid "unsafe"
+///
+/// Verified: [v5::ReadWrap] is _not_ [Unpin] even for an [Unpin] reader like `&[u8]`
+/// \[because its `#[pin] sleep` field is not\]
+/// ```compile_fail,E0277
+/// use async_stuff::fasterthanlime_pin::v5::ReadWrap;
+/// fn assert_unpin<T: Unpin>() {}
+/// assert_unpin::<ReadWrap<&[u8]>>();
+/// ```
+pub mod v5 {
+    use super::*;
+    use pin_project_lite::pin_project;
+    use std::pin::{Pin, pin};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::fs::File;
+    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+    use tokio::time::{self, Instant, Sleep};
+
+    pin_project! {
+        /// NB: The size of [ReadWrap] as returned by
+        /// [AsyncReadExt::read_exact()] will include the size of [Sleep]
+        ///
+        /// So if [ReadWrap] is on the stack, so will its [ReadWrap::sleep]. Verified:
+        /// ```
+        /// use async_stuff::fasterthanlime_pin::v5::ReadWrap;
+        /// use std::mem::size_of;
+        /// use tokio::fs::File;
+        /// use tokio::time::Sleep;
+        ///
+        /// assert!(size_of::<ReadWrap<File>>() >= size_of::<File>() + size_of::<Sleep>());
+        /// ```
+        ///
+        /// Each read waits for the 1s delay first:
+        /// ```
+        /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+        /// # async fn main() -> std::io::Result<()> {
+        /// use async_stuff::fasterthanlime_pin::v5::ReadWrap;
+        /// use std::pin::pin;
+        /// use std::time::Duration;
+        /// use tokio::io::AsyncReadExt;
+        /// use tokio::time::Instant;
+        ///
+        /// let mut f = pin!(ReadWrap::new(&b"hello"[..]));
+        /// let mut buf = [0u8; 5];
+        /// let now = Instant::now();
+        /// f.read_exact(&mut buf).await?;
+        /// assert_eq!(&buf, b"hello");
+        /// assert!(now.elapsed() >= Duration::from_secs(1));
+        /// # Ok(())
+        /// # }
+        /// ```
+        pub struct ReadWrap<R> {
+            #[pin]
+            read: R,
+
+            // Make ReadWrap.project().sleep return a Pin<Sleep>
+            #[pin]
+            sleep: Sleep,
+        }
+    }
+
+    impl<R> ReadWrap<R> {
+        pub fn new(read: R) -> Self {
+            Self {
+                read,
+                sleep: time::sleep(Duration::from_secs(1)),
+            }
+        }
+    }
+
+    impl<R: AsyncRead> AsyncRead for ReadWrap<R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let mut this = self.project();
+            match this.sleep.as_mut().poll(cx) {
+                Poll::Ready(_) => {
+                    // woke up => read into buffer
+                    this.sleep.reset(Instant::now() + Duration::from_secs(1));
+                    this.read.poll_read(cx, buf)
+                }
+                // continue sleeping
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    /// `demos --lesson v5`, see [crate::lesson]
+    pub const LESSON: Lesson = Lesson {
+        before: &[
+            "v5::ReadWrap is v4 with the unsafe projection generated by pin_project_lite.",
+            "project() turns #[pin] fields into Pin<&mut _> and the others into &mut _. The \
+             wrapper is Unpin only if every #[pin] field is, which Sleep never is.",
+        ],
+        after: &["The same timing and crc32 as v4, the macro writes what v4 did by hand."],
+    };
+
+    crate::demo! {
+        name: "v5",
+        wrapper: ReadWrap,
+        summary: "v4 with the projection generated by pin_project_lite",
+        tags: ["pin", "async-read", "timers", "macros", "editions"],
+        difficulty: Intermediate,
+        per_read: Duration::from_secs(1),
+        wrapper_allocs: 0,
+        lesson: LESSON,
+    }
+
+    pub async fn do_it() -> Result<()> {
+        let f = File::open("/dev/urandom").await?;
+        let f_before_pin = ReadWrap::new(f);
+
+        // NB: Unlike v3, the usage of ReadWrap is more complicated
+        // TODO Question: Will ReadWrap be on stack as it does _not_ cross await points?
+        let mut f: Pin<&mut ReadWrap<File>> = pin!(f_before_pin);
+
+        // NB: Following
+        //      std::hint::black_box(f_before_pin);
+        // will *not* compile because pin!() at https://doc.rust-lang.org/beta/src/core/pin.rs.html#2035
+        // uses "super let" to move it to an inaccessible var
+
+        let mut buf = [0u8; 32];
+        let now = Instant::now();
+        let read_len = f.read_exact(&mut buf).await?;
+        println!(
+            "v5 Read {} bytes {:?} after {:?}",
+            read_len,
+            buf,
+            now.elapsed()
+        );
+        Ok(())
+    }
+}
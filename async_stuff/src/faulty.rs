@@ -0,0 +1,428 @@
+//! [FaultyIo] injects the awkward results a real socket or pipe can give, on a seeded schedule
+//!
+//! | [Fault] | [AsyncRead] / [AsyncWrite] gets | a correct caller |
+//! | --- | --- | --- |
+//! | [Fault::WouldBlock] | `Pending` \[after waking itself, what `EWOULDBLOCK` becomes in async\] | polls again |
+//! | [Fault::Interrupted] | `Err(Interrupted)`, nothing read or written | retries |
+//! | [Fault::Short] | 1 to n - 1 bytes instead of up to n | loops for the rest \[`read_exact`, `write_all`\] |
+//! | [Fault::Error] | `Err(Other)`, nothing read or written | gives up, without having returned wrong data |
+//!
+//! Each `poll_read` / `poll_write` draws one fault from the [Faults] probabilities, the same
+//! sequence for the same seed. `poll_flush` and `poll_shutdown` pass through.
+//!
+//! [LatencyIo] is the slow rather than broken peer: every read and write first waits a random
+//! delay up to a maximum, also seeded. The runner's `--chaos` puts both under each demo, see
+//! [crate::runner].
+//!
+//! The tests run the framing codec, [RleDecoder](crate::compression::RleDecoder),
+//! [HashingReader](crate::digest::HashingReader) and the throttling wrappers on top of it,
+//! across many seeds.
+//!
+//! NB: tokio's helpers \[`read_exact`, `read_to_end`, `copy`\] don't retry `Interrupted` the way
+//! std's do, they return it. Nothing in tokio's own I/O produces it \[the reactor retries
+//! `EINTR`\], so only a wrapper like this one would.
+
+use pin_project_lite::pin_project;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    WouldBlock,
+    Interrupted,
+    Short,
+    Error,
+}
+
+/// Probability of each [Fault] per operation, the rest go through untouched
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Faults {
+    pub would_block: f64,
+    pub interrupted: f64,
+    pub short: f64,
+    pub error: f64,
+}
+
+impl Faults {
+    /// `WouldBlock` and short operations, which a correct caller never notices
+    pub fn partial() -> Self {
+        Self {
+            would_block: 0.2,
+            short: 0.5,
+            ..Self::default()
+        }
+    }
+}
+
+/// How many of each were injected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultCounts {
+    pub clean: u64,
+    pub would_block: u64,
+    pub interrupted: u64,
+    pub short: u64,
+    pub error: u64,
+}
+
+pin_project! {
+    pub struct FaultyIo<T> {
+        #[pin]
+        io: T,
+        faults: Faults,
+        rng: StdRng,
+        counts: FaultCounts,
+    }
+}
+
+impl<T> FaultyIo<T> {
+    pub fn new(io: T, faults: Faults, seed: u64) -> Self {
+        Self {
+            io,
+            faults,
+            rng: StdRng::seed_from_u64(seed),
+            counts: FaultCounts::default(),
+        }
+    }
+
+    pub fn counts(&self) -> FaultCounts {
+        self.counts
+    }
+
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+/// The fault for the next operation, counted. `Short` only where there is more than a byte to
+/// shorten.
+fn draw(faults: &Faults, rng: &mut StdRng, counts: &mut FaultCounts, len: usize) -> Option<Fault> {
+    let x: f64 = rng.random();
+    let mut at = 0.0;
+    let mut next = |p: f64| {
+        at += p;
+        x < at
+    };
+    let fault = if next(faults.would_block) {
+        Some(Fault::WouldBlock)
+    } else if next(faults.interrupted) {
+        Some(Fault::Interrupted)
+    } else if next(faults.short) {
+        (len > 1).then_some(Fault::Short)
+    } else if next(faults.error) {
+        Some(Fault::Error)
+    } else {
+        None
+    };
+    *match fault {
+        None => &mut counts.clean,
+        Some(Fault::WouldBlock) => &mut counts.would_block,
+        Some(Fault::Interrupted) => &mut counts.interrupted,
+        Some(Fault::Short) => &mut counts.short,
+        Some(Fault::Error) => &mut counts.error,
+    } += 1;
+    fault
+}
+
+/// `Pending` or `Err` for the faults that do no I/O at all
+fn no_io<T>(fault: Option<Fault>, cx: &mut Context<'_>) -> Option<Poll<io::Result<T>>> {
+    match fault? {
+        Fault::WouldBlock => {
+            cx.waker().wake_by_ref();
+            Some(Poll::Pending)
+        }
+        Fault::Interrupted => Some(Poll::Ready(Err(io::ErrorKind::Interrupted.into()))),
+        Fault::Error => Some(Poll::Ready(Err(io::Error::other("injected fault")))),
+        Fault::Short => None,
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for FaultyIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let fault = draw(this.faults, this.rng, this.counts, buf.remaining());
+        if let Some(poll) = no_io(fault, cx) {
+            return poll;
+        }
+        if fault != Some(Fault::Short) {
+            return this.io.poll_read(cx, buf);
+        }
+        let n = this.rng.random_range(1..buf.remaining());
+        let mut short = vec![0; n];
+        let mut short = ReadBuf::new(&mut short);
+        let poll = this.io.poll_read(cx, &mut short);
+        buf.put_slice(short.filled());
+        poll
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for FaultyIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let fault = draw(this.faults, this.rng, this.counts, buf.len());
+        if let Some(poll) = no_io(fault, cx) {
+            return poll;
+        }
+        let n = match fault {
+            Some(Fault::Short) => this.rng.random_range(1..buf.len()),
+            _ => buf.len(),
+        };
+        this.io.poll_write(cx, &buf[..n])
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+}
+
+pin_project! {
+    /// [Unpin] if `T` is \[the [Sleep] is boxed\]
+    pub struct LatencyIo<T> {
+        #[pin]
+        io: T,
+        max: Duration,
+        rng: StdRng,
+        // The delay of the operation in progress
+        sleep: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+impl<T> LatencyIo<T> {
+    /// Delays of 0 to `max`, uniformly
+    pub fn new(io: T, max: Duration, seed: u64) -> Self {
+        Self {
+            io,
+            max,
+            rng: StdRng::seed_from_u64(seed),
+            sleep: None,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+/// Ready once the delay of the current operation is over, the next one gets a new delay
+fn delay(
+    sleep: &mut Option<Pin<Box<Sleep>>>,
+    rng: &mut StdRng,
+    max: Duration,
+    cx: &mut Context<'_>,
+) -> Poll<()> {
+    let sleep = sleep.get_or_insert_with(|| {
+        let nanos = rng.random_range(0..=max.as_nanos() as u64);
+        Box::pin(tokio::time::sleep(Duration::from_nanos(nanos)))
+    });
+    sleep.as_mut().poll(cx)
+}
+
+impl<T: AsyncRead> AsyncRead for LatencyIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        ready!(delay(this.sleep, this.rng, *this.max, cx));
+        let poll = ready!(this.io.poll_read(cx, buf));
+        *this.sleep = None;
+        Poll::Ready(poll)
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for LatencyIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        ready!(delay(this.sleep, this.rng, *this.max, cx));
+        let poll = ready!(this.io.poll_write(cx, buf));
+        *this.sleep = None;
+        Poll::Ready(poll)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::{RleDecoder, rle_encode};
+    use crate::digest::{Crc32, HashingReader, StreamDigest};
+    use crate::fasterthanlime_pin::{v2, v5};
+    use crate::framing::{Frame, read_all, write_frame};
+    use std::pin::pin;
+    use tokio::io::{AsyncReadExt, BufReader};
+
+    const SEEDS: u64 = 50;
+
+    fn frames() -> Vec<Frame> {
+        (0..20)
+            .map(|i| Frame::new(1, i, vec![i as u8; (i * 7 % 40) as usize]))
+            .collect()
+    }
+
+    /// Like std's `read_to_end`, which retries `Interrupted`
+    async fn read_retrying(mut r: impl AsyncRead + Unpin) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut buf = [0u8; 16];
+        loop {
+            match r.read(&mut buf).await {
+                Ok(0) => return Ok(out),
+                Ok(n) => out.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seeded() {
+        let data = vec![7u8; 1000];
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            let mut r = FaultyIo::new(&data[..], Faults::partial(), 42);
+            let mut out = Vec::new();
+            r.read_to_end(&mut out).await.unwrap();
+            assert_eq!(out, data);
+            runs.push(r.counts());
+        }
+        assert_eq!(runs[0], runs[1]);
+        let c = runs[0];
+        assert!(c.short > 0 && c.would_block > 0 && c.clean > 0, "{c:?}");
+        assert_eq!((c.interrupted, c.error), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_framing_partial_io() {
+        let expected = frames();
+        for seed in 0..SEEDS {
+            let mut w = FaultyIo::new(Vec::new(), Faults::partial(), seed);
+            for frame in &expected {
+                write_frame(&mut w, frame).await.unwrap();
+            }
+            assert!(w.counts().short > 0);
+            let bytes = w.into_inner();
+            let mut r = FaultyIo::new(&bytes[..], Faults::partial(), seed + 1000);
+            assert_eq!(read_all(&mut r).await.unwrap(), expected, "seed {seed}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_framing_errors_surface() {
+        let expected = frames();
+        let mut bytes = Vec::new();
+        for frame in &expected {
+            write_frame(&mut bytes, frame).await.unwrap();
+        }
+        let faults = Faults {
+            error: 0.002,
+            interrupted: 0.002,
+            ..Faults::partial()
+        };
+        let mut failed = 0;
+        for seed in 0..SEEDS {
+            let mut r = FaultyIo::new(&bytes[..], faults, seed);
+            // Never wrong frames, either all of them or the injected error
+            match read_all(&mut r).await {
+                Ok(frames) => assert_eq!(frames, expected, "seed {seed}"),
+                Err(e) => {
+                    assert!(
+                        matches!(e.kind(), io::ErrorKind::Other | io::ErrorKind::Interrupted),
+                        "seed {seed}: {e}"
+                    );
+                    failed += 1;
+                }
+            }
+        }
+        assert!(failed > 0 && failed < SEEDS, "{failed}");
+    }
+
+    #[tokio::test]
+    async fn test_rle_and_hash_resume_after_interrupted() {
+        let data: Vec<u8> = (0..2_000u32).map(|i| (i / 37 % 5) as u8).collect();
+        let encoded = rle_encode(&data);
+        let mut clean = Crc32::default();
+        clean.update(&data);
+        let clean = clean.hex();
+        let faults = Faults {
+            interrupted: 0.1,
+            ..Faults::partial()
+        };
+        for seed in 0..SEEDS {
+            let faulty = FaultyIo::new(&encoded[..], faults, seed);
+            // A small buffer splits (count, byte) pairs across fills
+            let decoder = RleDecoder::new(BufReader::with_capacity(3, faulty));
+            let mut hashing = HashingReader::new(decoder, Crc32::default());
+            let out = read_retrying(&mut hashing).await.unwrap();
+            assert_eq!(out, data, "seed {seed}");
+            assert_eq!(hashing.into_digest().hex(), clean, "seed {seed}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency() {
+        let data = vec![7u8; 1000];
+        let mut elapsed = Vec::new();
+        for _ in 0..2 {
+            let start = tokio::time::Instant::now();
+            let mut r = LatencyIo::new(&data[..], Duration::from_millis(10), 2);
+            let mut buf = [0u8; 10];
+            for _ in 0..100 {
+                r.read_exact(&mut buf).await.unwrap();
+            }
+            elapsed.push(start.elapsed());
+        }
+        // Seeded, so the same delays again. About 5.5 ms each, sleeps round up to whole ms.
+        assert_eq!(elapsed[0], elapsed[1]);
+        let mean = elapsed[0] / 100;
+        assert!(
+            mean > Duration::from_millis(4) && mean < Duration::from_millis(7),
+            "{mean:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttling_wrappers() {
+        let data: Vec<u8> = (0..64).collect();
+        for seed in 0..5 {
+            let faulty = || FaultyIo::new(&data[..], Faults::partial(), seed);
+            let mut buf = [0u8; 64];
+            v2::ReadWrap::new(faulty())
+                .read_exact(&mut buf)
+                .await
+                .unwrap();
+            assert_eq!(buf[..], data[..]);
+            let mut buf = [0u8; 64];
+            let mut r = pin!(v5::ReadWrap::new(faulty()));
+            r.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf[..], data[..]);
+        }
+    }
+}
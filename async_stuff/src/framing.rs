@@ -0,0 +1,142 @@
+//! Length-prefixed framing over [tokio::io::AsyncRead] / [tokio::io::AsyncWrite]
+//!
+//! Each frame is a [simple::endian::Header] followed by `header.len` payload bytes. The header is
+//! parsed with the zerocopy cast from [simple::endian] after reading exactly
+//! [HEADER_LEN] bytes.
+//!
+//! | situation | [read_frame()] returns |
+//! | --- | --- |
+//! | clean EOF before a header | `Ok(None)` |
+//! | EOF inside a header or payload | `Err(UnexpectedEof)` |
+//! | wrong magic | `Err(InvalidData)` |
+//! | payload larger than [MAX_PAYLOAD] | `Err(InvalidData)` \[don't trust `len` to size an allocation\] |
+//!
+//! Read the fixture from `simple/fixtures/frames.bin`:
+//! ```sh
+//! cargo run -p async_stuff --bin framing
+//! ```
+
+use simple::endian::{HEADER_LEN, Header};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const MAX_PAYLOAD: u32 = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub header: Header,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Header with `len` filled in from `payload`
+    pub fn new(kind: u8, seq: u64, payload: Vec<u8>) -> Self {
+        let header = Header {
+            version: 1,
+            kind,
+            flags: 0,
+            len: payload.len().try_into().expect("payload too large"),
+            seq,
+        };
+        Self { header, payload }
+    }
+}
+
+pub async fn read_frame<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<Option<Frame>> {
+    let mut buf = [0u8; HEADER_LEN];
+    // NB: read_exact() can't tell "no more frames" from "truncated header", so read the first
+    //     chunk by hand
+    let n = r.read(&mut buf).await?;
+    if n == 0 {
+        return Ok(None);
+    }
+    r.read_exact(&mut buf[n..]).await?;
+    let header = Header::parse_zerocopy(&buf).map_err(io::Error::from)?;
+    if header.len > MAX_PAYLOAD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("payload of {} bytes exceeds {MAX_PAYLOAD}", header.len),
+        ));
+    }
+    let mut payload = vec![0u8; header.len as usize];
+    r.read_exact(&mut payload).await?;
+    Ok(Some(Frame { header, payload }))
+}
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(w: &mut W, frame: &Frame) -> io::Result<()> {
+    w.write_all(&frame.header.to_bytes()).await?;
+    w.write_all(&frame.payload).await
+}
+
+/// All frames until EOF
+pub async fn read_all<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<Vec<Frame>> {
+    let mut frames = Vec::new();
+    while let Some(frame) = read_frame(r).await? {
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fasterthanlime_pin::v2::ReadWrap;
+
+    const FIXTURE: &[u8] = include_bytes!("../../simple/fixtures/frames.bin");
+
+    #[tokio::test]
+    async fn test_fixture() {
+        let frames = read_all(&mut ReadWrap::new(FIXTURE)).await.unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].header.seq, 42);
+        assert_eq!(frames[0].payload, b"hello");
+        assert_eq!(frames[1].header.kind, 2);
+        assert_eq!(frames[1].payload, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let frames = vec![
+            Frame::new(1, 42, b"hello".to_vec()),
+            Frame::new(2, 43, vec![1, 2, 3]),
+        ];
+        let mut out = Vec::new();
+        for f in &frames {
+            write_frame(&mut out, f).await.unwrap();
+        }
+        assert_eq!(out, FIXTURE);
+        assert_eq!(read_all(&mut &out[..]).await.unwrap(), frames);
+    }
+
+    #[tokio::test]
+    async fn test_over_pipe() {
+        // 5 bytes at a time, so headers and payloads arrive in pieces
+        let (mut w, mut r) = crate::spsc::ring(5);
+        let frames = vec![
+            Frame::new(1, 42, b"hello".to_vec()),
+            Frame::new(2, 43, vec![7; 100]),
+        ];
+        let writer = tokio::spawn({
+            let frames = frames.clone();
+            async move {
+                for f in &frames {
+                    write_frame(&mut w, f).await?;
+                }
+                io::Result::Ok(())
+            }
+        });
+        assert_eq!(read_all(&mut r).await.unwrap(), frames);
+        writer.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let err = read_all(&mut &FIXTURE[..HEADER_LEN + 2]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut huge = Frame::new(1, 0, vec![]).header.to_bytes();
+        huge[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = read_frame(&mut &huge[..]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,260 @@
+//! Future combinators written by hand, i.e. how `futures::FutureExt` is built rather than used
+//!
+//! | combinator | output | state |
+//! | --- | --- | --- |
+//! | [Map] | `f(output)` | the future + `Option<F>` \[`take()`n on completion, `FnOnce` can only run once\] |
+//! | [AndThen] | `Err` of the first future or output of `f(ok)` | enum of first future, second future, done |
+//! | [Inspect] | output, after `f(&output)` | the future + `Option<F>` |
+//! | [InspectPoll] | output, `f(&poll)` on _every_ poll | the future + `F: FnMut` |
+//!
+//! All of them only pin the inner future \[structural pinning via [pin_project_lite]\], the
+//! closures stay unpinned. So a combinator is [Unpin] exactly when its future\(s\) are:
+//! ```
+//! use async_stuff::implements;
+//! use std::future::Ready;
+//!
+//! type Mapped = async_stuff::futcomb::Map<Ready<u8>, fn(u8) -> u16>;
+//! assert!(implements!(Mapped: Unpin));
+//! ```
+//! while mapping an `async` block, which is `!Unpin`, isn't:
+//! ```compile_fail,E0277
+//! use async_stuff::futcomb::FutureCombExt;
+//!
+//! fn is_unpin<T: Unpin>(_: &T) {}
+//! is_unpin(&async { 1u8 }.map(u16::from)); // `{async block}` cannot be unpinned
+//! ```
+//!
+//! NB: Polling again after `Ready` is a logic error for any future. These panic, like most of
+//! std's and futures' do \[`async` blocks panic with "`async fn` resumed after completion"\].
+//! Method names clash with `futures::FutureExt`, import only one of the traits.
+
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+pin_project! {
+    #[must_use = "futures do nothing unless polled"]
+    pub struct Map<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: Option<F>,
+    }
+}
+
+impl<Fut: Future, F: FnOnce(Fut::Output) -> T, T> Future for Map<Fut, F> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = self.project();
+        // Check first, the inner future may not like being polled again either
+        assert!(this.f.is_some(), "Map polled after completion");
+        let output = ready!(this.future.poll(cx));
+        let f = this.f.take().unwrap();
+        Poll::Ready(f(output))
+    }
+}
+
+pin_project! {
+    #[project = AndThenProj]
+    enum AndThenState<Fut1, Fut2, F> {
+        First { #[pin] future: Fut1, f: Option<F> },
+        Second { #[pin] future: Fut2 },
+        Done,
+    }
+}
+
+pin_project! {
+    #[must_use = "futures do nothing unless polled"]
+    pub struct AndThen<Fut1, Fut2, F> {
+        #[pin]
+        state: AndThenState<Fut1, Fut2, F>,
+    }
+}
+
+impl<Fut1, Fut2, F, T, U, E> Future for AndThen<Fut1, Fut2, F>
+where
+    Fut1: Future<Output = Result<T, E>>,
+    Fut2: Future<Output = Result<U, E>>,
+    F: FnOnce(T) -> Fut2,
+{
+    type Output = Result<U, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.project().state;
+        loop {
+            match state.as_mut().project() {
+                AndThenProj::First { future, f } => match ready!(future.poll(cx)) {
+                    Ok(t) => {
+                        let f = f.take().expect("AndThen polled after completion");
+                        // Drops the first future in place [fine while pinned] before the second
+                        // is polled, in the same poll, as it may well be ready already
+                        state.set(AndThenState::Second { future: f(t) });
+                    }
+                    Err(e) => {
+                        state.set(AndThenState::Done);
+                        return Poll::Ready(Err(e));
+                    }
+                },
+                AndThenProj::Second { future } => {
+                    let output = ready!(future.poll(cx));
+                    state.set(AndThenState::Done);
+                    return Poll::Ready(output);
+                }
+                AndThenProj::Done => panic!("AndThen polled after completion"),
+            }
+        }
+    }
+}
+
+pin_project! {
+    #[must_use = "futures do nothing unless polled"]
+    pub struct Inspect<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: Option<F>,
+    }
+}
+
+impl<Fut: Future, F: FnOnce(&Fut::Output)> Future for Inspect<Fut, F> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Fut::Output> {
+        let this = self.project();
+        // Check first, the inner future may not like being polled again either
+        assert!(this.f.is_some(), "Inspect polled after completion");
+        let output = ready!(this.future.poll(cx));
+        let f = this.f.take().unwrap();
+        f(&output);
+        Poll::Ready(output)
+    }
+}
+
+pin_project! {
+    #[must_use = "futures do nothing unless polled"]
+    pub struct InspectPoll<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: F,
+    }
+}
+
+impl<Fut: Future, F: FnMut(&Poll<Fut::Output>)> Future for InspectPoll<Fut, F> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Fut::Output> {
+        let this = self.project();
+        let poll = this.future.poll(cx);
+        (this.f)(&poll);
+        poll
+    }
+}
+
+/// The combinators as methods on every [Future]
+pub trait FutureCombExt: Future + Sized {
+    fn map<F: FnOnce(Self::Output) -> T, T>(self, f: F) -> Map<Self, F> {
+        Map {
+            future: self,
+            f: Some(f),
+        }
+    }
+
+    fn and_then<F, Fut2, T, U, E>(self, f: F) -> AndThen<Self, Fut2, F>
+    where
+        Self: Future<Output = Result<T, E>>,
+        Fut2: Future<Output = Result<U, E>>,
+        F: FnOnce(T) -> Fut2,
+    {
+        AndThen {
+            state: AndThenState::First {
+                future: self,
+                f: Some(f),
+            },
+        }
+    }
+
+    fn inspect<F: FnOnce(&Self::Output)>(self, f: F) -> Inspect<Self, F> {
+        Inspect {
+            future: self,
+            f: Some(f),
+        }
+    }
+
+    /// `f` sees every [Poll], e.g. to count how often the future was polled
+    fn inspect_poll<F: FnMut(&Poll<Self::Output>)>(self, f: F) -> InspectPoll<Self, F> {
+        InspectPoll { future: self, f }
+    }
+}
+
+impl<Fut: Future> FutureCombExt for Fut {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implements;
+    use std::cell::Cell;
+    use std::future::{Ready, ready};
+
+    #[tokio::test]
+    async fn test_map_and_inspect() {
+        let seen = Cell::new(0);
+        let n = ready(20)
+            .map(|n| n + 1)
+            .inspect(|n| seen.set(*n))
+            .map(|n| n * 2)
+            .await;
+        assert_eq!(n, 42);
+        assert_eq!(seen.get(), 21);
+    }
+
+    #[tokio::test]
+    async fn test_and_then() {
+        let parse = |s: &'static str| async move { s.parse::<u32>().map_err(|e| e.to_string()) };
+        let ok = parse("20").and_then(|n| async move { Ok::<_, String>(n + 1) });
+        assert_eq!(ok.await, Ok(21));
+
+        let called = Cell::new(false);
+        let err = parse("x").and_then(|n| {
+            called.set(true);
+            ready(Ok::<_, String>(n))
+        });
+        assert!(err.await.is_err());
+        assert!(!called.get(), "short-circuits on Err");
+    }
+
+    #[tokio::test]
+    async fn test_inspect_poll() {
+        let (mut pending, mut ready) = (0, 0);
+        let out = async {
+            // Pending once, then woken right away
+            tokio::task::yield_now().await;
+            7
+        }
+        .inspect_poll(|p| match p {
+            Poll::Pending => pending += 1,
+            Poll::Ready(_) => ready += 1,
+        })
+        .await;
+        assert_eq!(out, 7);
+        assert_eq!((pending, ready), (1, 1));
+    }
+
+    #[test]
+    fn test_unpin_follows_future() {
+        type F = fn(u8) -> u8;
+        assert!(implements!(Map<Ready<u8>, F>: Unpin));
+        assert!(!implements!(Map<tokio::time::Sleep, fn(())>: Unpin));
+        assert!(!implements!(InspectPoll<tokio::time::Sleep, fn(&Poll<()>)>: Unpin));
+        // The closure doesn't matter, it's never pinned
+        assert!(implements!(Inspect<Ready<u8>, std::marker::PhantomPinned>: Unpin));
+    }
+
+    #[test]
+    #[should_panic(expected = "Map polled after completion")]
+    fn test_poll_after_ready() {
+        let waker = std::task::Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let mut fut = std::pin::pin!(ready(1).map(|n| n + 1));
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(2));
+        let _ = fut.as_mut().poll(&mut cx);
+    }
+}
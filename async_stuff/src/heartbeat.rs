@@ -0,0 +1,320 @@
+//! A connection that sends keepalives and times out an idle peer by itself, from inside its own
+//! `poll_read`/`poll_write` \[no background task\]
+//!
+//! | timer | type | pinning | on fire |
+//! | --- | --- | --- | --- |
+//! | keepalive | [Interval] | none needed, it boxes its [Sleep] | queue a [KEEPALIVE] frame, written at the next frame boundary |
+//! | idle | [Sleep] | structural, `#[pin]` field \[no `Box` unlike [ReadWrap](crate::fasterthanlime_pin::v3::ReadWrap)\] | `poll_read` fails with [io::ErrorKind::TimedOut] |
+//!
+//! Every `poll_read` goes: finish a queued keepalive → keepalive tick \[until `Pending`\] → idle
+//! deadline → inner read \[resets the idle deadline on data\]. Polling a timer registers the
+//! waker with it, so a task parked in `read().await` is woken by either timer as well as by data.
+//!
+//! Writes must be whole frames \[e.g. [write_frame](crate::framing::write_frame)\]: the headers
+//! going through are parsed for their payload length, so a keepalive never lands inside a frame
+//! whose `write_all()` takes several `poll_write`s under back-pressure. Ticks meanwhile queue
+//! one keepalive, sent once the frame is done. A header that doesn't parse fails the write with
+//! [io::ErrorKind::InvalidData] before it goes out.
+//!
+//! The `#[pin]` [Sleep] makes [Heartbeat] `!Unpin`, so it needs `pin!` or `Box::pin` before
+//! [tokio::io::AsyncReadExt] & co. take it, same as [Sleep] itself.
+//!
+//! NB: Nothing happens while nobody polls. A connection that is only written to now and then
+//! sends keepalives only from those writes, one read in progress is what keeps it alive. A
+//! keepalive queued mid frame goes out with the next write, or a tick later from the read.
+
+use pin_project_lite::pin_project;
+use simple::endian::{HEADER_LEN, Header};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
+
+/// Frame kind of a keepalive, with an empty payload
+pub const KEEPALIVE: u8 = 0;
+
+/// Where the bytes written so far are in the user's frames
+#[derive(Debug, Clone, Copy)]
+struct Frames {
+    header: [u8; HEADER_LEN],
+    /// Bytes of `header` written
+    filled: usize,
+    /// Bytes of the payload still to come
+    payload: u64,
+}
+
+impl Frames {
+    fn at_boundary(&self) -> bool {
+        self.filled == 0 && self.payload == 0
+    }
+
+    /// Follow `written` through headers and payloads, failing on a header that doesn't parse
+    fn advance(&mut self, mut written: &[u8]) -> io::Result<()> {
+        while !written.is_empty() {
+            if self.payload > 0 {
+                let n = self.payload.min(written.len() as u64);
+                self.payload -= n;
+                written = &written[n as usize..];
+                continue;
+            }
+            let n = (HEADER_LEN - self.filled).min(written.len());
+            self.header[self.filled..][..n].copy_from_slice(&written[..n]);
+            self.filled += n;
+            written = &written[n..];
+            if self.filled == HEADER_LEN {
+                self.filled = 0;
+                self.payload = Header::parse_std(&self.header)
+                    .map_err(io::Error::from)?
+                    .len
+                    .into();
+            }
+        }
+        Ok(())
+    }
+}
+
+pin_project! {
+    pub struct Heartbeat<S> {
+        #[pin]
+        conn: S,
+        keepalive: Interval,
+        #[pin]
+        idle: Sleep,
+        idle_timeout: Duration,
+        // The keepalive queued or being written, `written == HEADER_LEN` when there is none
+        pending: [u8; HEADER_LEN],
+        written: usize,
+        seq: u64,
+        frames: Frames,
+    }
+}
+
+impl<S> Heartbeat<S> {
+    /// Keepalive every `every`, fail reads after `idle_timeout` without data
+    pub fn new(conn: S, every: Duration, idle_timeout: Duration) -> Self {
+        let now = Instant::now();
+        let mut keepalive = tokio::time::interval_at(now + every, every);
+        // A late tick means a busy task, not a reason to send a burst of keepalives
+        keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            conn,
+            keepalive,
+            idle: tokio::time::sleep_until(now + idle_timeout),
+            idle_timeout,
+            pending: [0; HEADER_LEN],
+            written: HEADER_LEN,
+            seq: 0,
+            frames: Frames {
+                header: [0; HEADER_LEN],
+                filled: 0,
+                payload: 0,
+            },
+        }
+    }
+
+    /// Keepalives queued so far, ticks during one frame count once
+    pub fn keepalives(&self) -> u64 {
+        self.seq
+    }
+}
+
+impl<S: AsyncWrite> Heartbeat<S> {
+    /// Queue a keepalive per tick and write it at a frame boundary. `Ready` once nothing is
+    /// queued or the user is mid frame, with the waker registered for the next tick.
+    fn poll_keepalive(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        loop {
+            while this.frames.at_boundary() && *this.written < HEADER_LEN {
+                let n = ready!(
+                    this.conn
+                        .as_mut()
+                        .poll_write(cx, &this.pending[*this.written..])
+                )?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                *this.written += n;
+            }
+            // Polled until Pending, or the next tick wouldn't wake us
+            if this.keepalive.poll_tick(cx).is_pending() {
+                return Poll::Ready(Ok(()));
+            }
+            if *this.written < HEADER_LEN {
+                // One is waiting for the frame to end already
+                continue;
+            }
+            let frame = crate::framing::Frame::new(KEEPALIVE, *this.seq, Vec::new());
+            *this.pending = frame.header.to_bytes();
+            *this.written = 0;
+            *this.seq += 1;
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncRead for Heartbeat<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // A keepalive stuck behind a full socket must not stop the read, only errors do
+        if let Poll::Ready(Err(e)) = self.as_mut().poll_keepalive(cx) {
+            return Poll::Ready(Err(e));
+        }
+        let mut this = self.project();
+        if this.idle.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("nothing received for {:?}", this.idle_timeout),
+            )));
+        }
+        let before = buf.filled().len();
+        ready!(this.conn.poll_read(cx, buf))?;
+        if buf.filled().len() > before {
+            this.idle.reset(Instant::now() + *this.idle_timeout);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for Heartbeat<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // Never interleave user bytes with half a keepalive
+        ready!(self.as_mut().poll_keepalive(cx))?;
+        let this = self.project();
+        // Checked before anything goes out, so what did go out can't fail below
+        this.frames.clone().advance(buf)?;
+        let n = ready!(this.conn.poll_write(cx, buf))?;
+        this.frames.advance(&buf[..n])?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_keepalive(cx))?;
+        self.project().conn.poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_keepalive(cx))?;
+        self.project().conn.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::{Frame, read_frame, write_frame};
+    use crate::io::duplex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const EVERY: Duration = Duration::from_secs(1);
+    const IDLE: Duration = Duration::from_secs(5);
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keepalives_while_reading() {
+        let (client, mut server) = duplex(1024);
+        let mut client = Box::pin(Heartbeat::new(client, EVERY, IDLE));
+        let start = Instant::now();
+        let reader = tokio::spawn(async move {
+            let mut buf = [0u8; 16];
+            let err = client.read(&mut buf).await.unwrap_err();
+            (err.kind(), client.keepalives())
+        });
+        // The client only sits in read(), its keepalives arrive anyway
+        for seq in 0..4 {
+            let frame = read_frame(&mut server).await.unwrap().unwrap();
+            assert_eq!((frame.header.kind, frame.header.seq), (KEEPALIVE, seq));
+            assert_eq!(start.elapsed(), EVERY * (seq as u32 + 1));
+        }
+        let (kind, keepalives) = reader.await.unwrap();
+        assert_eq!(kind, io::ErrorKind::TimedOut);
+        assert_eq!(start.elapsed(), IDLE);
+        // The one due at the timeout is polled first
+        assert_eq!(keepalives, 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_data_resets_idle() {
+        let (client, mut server) = duplex(1024);
+        let mut client = Box::pin(Heartbeat::new(client, EVERY, IDLE));
+        let sender = tokio::spawn(async move {
+            for seq in 0..5 {
+                tokio::time::sleep(Duration::from_secs(4)).await;
+                write_frame(&mut server, &Frame::new(1, seq, b"data".to_vec()))
+                    .await
+                    .unwrap();
+            }
+            // Keep the end open but silent, draining keepalives
+            let mut sink = Vec::new();
+            let _ = server.read_to_end(&mut sink).await;
+        });
+        for seq in 0..5 {
+            let frame = read_frame(&mut client).await.unwrap().unwrap();
+            assert_eq!((frame.header.kind, frame.header.seq), (1, seq));
+        }
+        let start = Instant::now();
+        let err = read_frame(&mut client).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(start.elapsed(), IDLE);
+        // One per second for the 20 + 5 s
+        assert_eq!(client.keepalives(), 25);
+        drop(client);
+        sender.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_interleaving() {
+        // Room for less than a keepalive, so it is written in pieces
+        let (client, mut server) = duplex(7);
+        let mut client = Box::pin(Heartbeat::new(client, EVERY, IDLE));
+        tokio::time::sleep(EVERY).await;
+        let writer = tokio::spawn(async move {
+            write_frame(&mut client, &Frame::new(1, 9, b"user".to_vec())).await
+        });
+        let first = read_frame(&mut server).await.unwrap().unwrap();
+        let second = read_frame(&mut server).await.unwrap().unwrap();
+        writer.await.unwrap().unwrap();
+        assert_eq!(first.header.kind, KEEPALIVE);
+        assert_eq!((second.header.kind, second.payload), (1, b"user".to_vec()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tick_mid_frame() {
+        let (client, mut server) = duplex(7);
+        let mut client = Box::pin(Heartbeat::new(client, EVERY, IDLE));
+        let start = Instant::now();
+        let writer = tokio::spawn(async move {
+            for seq in 0..2 {
+                write_frame(&mut client, &Frame::new(1, seq, vec![seq as u8; 64]))
+                    .await
+                    .unwrap();
+            }
+            client.keepalives()
+        });
+        // The writer is stuck 7 bytes into the first frame when the ticks fire
+        tokio::time::sleep(EVERY * 5 / 2).await;
+        let mut kinds = Vec::new();
+        for _ in 0..3 {
+            let frame = read_frame(&mut server).await.unwrap().unwrap();
+            kinds.push((frame.header.kind, frame.header.seq));
+        }
+        assert_eq!(start.elapsed(), EVERY * 5 / 2);
+        assert_eq!(writer.await.unwrap(), 1);
+        // Both ticks during the first frame make one keepalive, right after it
+        assert_eq!(kinds, [(1, 0), (KEEPALIVE, 0), (1, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_not_frames() {
+        let (client, _server) = duplex(64);
+        let mut client = Box::pin(Heartbeat::new(client, EVERY, IDLE));
+        let err = client.write_all(&[0xAA; HEADER_LEN]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
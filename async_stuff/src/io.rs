@@ -0,0 +1,155 @@
+//! [duplex]: two connected in-memory streams, like [tokio::io::duplex] but built from two
+//! [crate::spsc] rings, one per direction
+//!
+//! ```text
+//!   a.write ──▶ ring a→b ──▶ b.read
+//!   a.read  ◀── ring b→a ◀── b.write
+//! ```
+//!
+//! Enough to run client/server demos hermetically, no ports or sockets:
+//! ```
+//! use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let (mut client, mut server) = async_stuff::io::duplex(64);
+//! let echo = tokio::spawn(async move {
+//!     let mut buf = [0u8; 4];
+//!     server.read_exact(&mut buf).await.unwrap();
+//!     server.write_all(&buf).await.unwrap();
+//! });
+//! client.write_all(b"ping").await.unwrap();
+//! let mut buf = [0u8; 4];
+//! client.read_exact(&mut buf).await.unwrap();
+//! assert_eq!(&buf, b"ping");
+//! echo.await.unwrap();
+//! # }
+//! ```
+//!
+//! | other half | [tokio::io::DuplexStream] | [DuplexStream] |
+//! | --- | --- | --- |
+//! | shut down writing | read gets EOF once drained | same |
+//! | dropped | read gets EOF, write gets [std::io::ErrorKind::BrokenPipe] | same |
+//! | slow to read | write parks once `max_buf_size` bytes are buffered | write parks once `capacity` bytes are buffered |
+//!
+//! NB: tokio's is a [std::sync::Mutex] around one `BytesMut` per direction, this one is lock-free
+//! on the bytes. Either way, a half is a single value: reading and writing it from two tasks
+//! takes a split, see [tokio::io::split].
+
+use crate::spsc::{self, Reader, Writer};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// One end of [duplex]
+pub struct DuplexStream {
+    pub(crate) read: Reader,
+    pub(crate) write: Writer,
+}
+
+/// Two connected streams, each buffering up to `capacity` bytes per direction
+pub fn duplex(capacity: usize) -> (DuplexStream, DuplexStream) {
+    let (a_write, b_read) = spsc::ring(capacity);
+    let (b_write, a_read) = spsc::ring(capacity);
+    (
+        DuplexStream {
+            read: a_read,
+            write: a_write,
+        },
+        DuplexStream {
+            read: b_read,
+            write: b_write,
+        },
+    )
+}
+
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.read).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.write).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.write).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.write).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::{Frame, read_frame, write_frame};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Same steps on either duplex, returns what each step saw
+    async fn script<S: AsyncRead + AsyncWrite + Unpin>(
+        (mut a, mut b): (S, S),
+    ) -> (Vec<u8>, usize, io::ErrorKind) {
+        a.write_all(b"hello").await.unwrap();
+        a.shutdown().await.unwrap();
+        let mut got = Vec::new();
+        b.read_to_end(&mut got).await.unwrap();
+        b.write_all(b"bye").await.unwrap();
+        drop(b);
+        let mut buf = [0u8; 8];
+        let n = a.read(&mut buf).await.unwrap();
+        got.extend_from_slice(&buf[..n]);
+        // EOF after the buffered bytes
+        let eof = a.read(&mut buf).await.unwrap();
+        // Shut down, and nobody would read it anyway
+        let err = a.write_all(b"x").await.unwrap_err();
+        (got, eof, err.kind())
+    }
+
+    #[tokio::test]
+    async fn test_matches_tokio() {
+        let ours = script(duplex(64)).await;
+        let tokios = script(tokio::io::duplex(64)).await;
+        assert_eq!(ours, tokios);
+        assert_eq!(ours, (b"hellobye".to_vec(), 0, io::ErrorKind::BrokenPipe));
+    }
+
+    #[tokio::test]
+    async fn test_request_response() {
+        // A tiny capacity so both directions fill up while the other side is busy
+        let (mut client, mut server) = duplex(3);
+        let server = tokio::spawn(async move {
+            let mut served = 0;
+            while let Some(req) = read_frame(&mut server).await? {
+                let mut payload = req.payload;
+                payload.reverse();
+                write_frame(&mut server, &Frame::new(2, req.header.seq, payload)).await?;
+                served += 1;
+            }
+            io::Result::Ok(served)
+        });
+        for seq in 0..10 {
+            let payload = format!("request {seq}").into_bytes();
+            write_frame(&mut client, &Frame::new(1, seq, payload.clone()))
+                .await
+                .unwrap();
+            let resp = read_frame(&mut client).await.unwrap().unwrap();
+            assert_eq!(resp.header.seq, seq);
+            assert_eq!(resp.payload, payload.into_iter().rev().collect::<Vec<_>>());
+        }
+        client.shutdown().await.unwrap();
+        assert_eq!(server.await.unwrap().unwrap(), 10);
+    }
+}
@@ -0,0 +1,93 @@
+//! `demos --lesson`: each demo between numbered steps explaining it, pausing for Enter after each
+//!
+//! | stage | shows |
+//! | --- | --- |
+//! | [Lesson::before] | what the demo does and what to look for |
+//! | the demo | its report, as without `--lesson` |
+//! | [Lesson::after] | what the report showed |
+//!
+//! ```sh
+//! cargo run --bin demos -- --lesson --reads 2 v3 v4
+//! ```
+//!
+//! Each demo's [Lesson] sits next to its code, e.g. [crate::fasterthanlime_pin::v3::LESSON], and
+//! is found through [crate::registry].
+//!
+//! NB: Steps go to stderr, so stdout is still only the reports. If stdin is not a terminal
+//! \[piped, CI\] the pauses read EOF and the lesson runs straight through.
+
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lesson {
+    /// Steps before the demo runs
+    pub before: &'static [&'static str],
+    /// Steps after its report
+    pub after: &'static [&'static str],
+}
+
+/// Shows the steps of one demo after another, numbered per demo
+pub struct Tutor<I, O> {
+    input: I,
+    output: O,
+    step: usize,
+}
+
+impl<I: BufRead, O: Write> Tutor<I, O> {
+    pub fn new(input: I, output: O) -> Self {
+        Self {
+            input,
+            output,
+            step: 0,
+        }
+    }
+
+    /// A heading for `demo`, numbering starts over
+    pub fn start(&mut self, demo: &str) -> io::Result<()> {
+        self.step = 0;
+        writeln!(self.output, "\n== {demo} == [Enter for each next step]")
+    }
+
+    /// Each of `steps`, waiting for Enter after every one
+    pub fn steps(&mut self, steps: &[&str]) -> io::Result<()> {
+        for text in steps {
+            self.step += 1;
+            writeln!(self.output, "{}. {text}", self.step)?;
+            self.output.flush()?;
+            // Ok(0) at EOF, then there's no one to wait for
+            self.input.read_line(&mut String::new())?;
+        }
+        Ok(())
+    }
+
+    pub fn into_output(self) -> O {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LESSON: Lesson = Lesson {
+        before: &["first", "second"],
+        after: &["third"],
+    };
+
+    #[test]
+    fn test_steps() {
+        let mut tutor = Tutor::new(&b"\n\n\n"[..], Vec::new());
+        for demo in ["a", "b"] {
+            tutor.start(demo).unwrap();
+            tutor.steps(LESSON.before).unwrap();
+            tutor.steps(LESSON.after).unwrap();
+        }
+        let output = String::from_utf8(tutor.into_output()).unwrap();
+        // Input runs out during b, which then doesn't wait
+        assert_eq!(
+            output,
+            "\n== a == [Enter for each next step]\n1. first\n2. second\n3. third\n\
+             \n== b == [Enter for each next step]\n1. first\n2. second\n3. third\n"
+        );
+    }
+}
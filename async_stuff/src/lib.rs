@@ -0,0 +1,34 @@
+pub mod callbacks;
+pub mod cli;
+pub mod compression;
+pub mod config;
+#[cfg(unix)]
+pub mod copy;
+pub mod deadlock;
+pub mod digest;
+pub mod drift;
+pub mod fasterthanlime_pin;
+pub mod faulty;
+pub mod framing;
+pub mod futcomb;
+pub mod heartbeat;
+pub mod io;
+pub mod lesson;
+pub mod markers;
+pub mod metrics;
+pub mod mini_executor;
+pub mod poll_fn;
+pub mod priority;
+pub mod profiling;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod registry;
+pub mod replay;
+pub mod runner;
+pub mod seeded;
+pub mod seek;
+pub mod split;
+pub mod spsc;
+pub mod timer_wheel;
+pub mod wake_batching;
+pub mod watchdog;
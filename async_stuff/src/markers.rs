@@ -0,0 +1,199 @@
+//! Opting out of auto traits ([Send], [Sync], [Unpin]) without negative impls
+//!
+//! `impl !Send for Foo {}` is nightly only (`negative_impls`). On stable, add a zero-sized
+//! marker field whose type already lacks the auto trait:
+//!
+//! | marker field | Send | Sync | Unpin |
+//! | --- | --- | --- | --- |
+//! | `PhantomData<*const ()>` | ❌ | ❌ | ✅ |
+//! | `PhantomData<Cell<()>>` | ✅ | ❌ | ✅ |
+//! | `PhantomData<MutexGuard<'static, ()>>` | ❌ | ✅ | ✅ |
+//! | [PhantomPinned] | ✅ | ✅ | ❌ |
+//!
+//! [PhantomPinned] is what makes [v4::ReadWrap](crate::fasterthanlime_pin::v4::ReadWrap)
+//! `!Unpin` \[it's inside [tokio::time::Sleep]\].
+//!
+//! Asserting that a type does _not_ implement a trait is also not directly expressible on stable.
+//! Two tricks, both checked at compile time:
+//! - [assert_not_impl!]: ambiguity error if the trait _is_ implemented
+//! - [implements!]: `bool` via inherent-vs-trait associated const resolution \[usable in `assert!`\]
+//!
+//! re: [PhantomData patterns](https://doc.rust-lang.org/nomicon/phantom-data.html#table-of-phantomdata-patterns)
+
+use std::cell::Cell;
+use std::marker::{PhantomData, PhantomPinned};
+use std::sync::MutexGuard;
+
+/// Fails to compile if `$ty` implements `$trait`
+///
+/// Both impls of `AmbiguousIfImpl` apply when `$ty: $trait` so `_` cannot be inferred:
+/// ```compile_fail,E0283
+/// async_stuff::assert_not_impl!(u8: Send); // error[E0283]: type annotations needed
+/// ```
+#[macro_export]
+macro_rules! assert_not_impl {
+    ($ty:ty: $trait:path) => {
+        const _: fn() = || {
+            trait AmbiguousIfImpl<A> {
+                fn some_item() {}
+            }
+            impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+            struct Invalid;
+            impl<T: ?Sized + $trait> AmbiguousIfImpl<Invalid> for T {}
+            let _ = <$ty as AmbiguousIfImpl<_>>::some_item;
+        };
+    };
+}
+
+/// `true` if `$ty` implements `$trait`
+///
+/// The inherent `IMPLS` wins when its where clause holds, otherwise the trait's default is used.
+/// NB: Only works for concrete types \[inside a generic function the bound is not known yet\].
+/// ```
+/// use async_stuff::implements;
+/// assert!(implements!(u8: Send));
+/// assert!(!implements!(std::rc::Rc<u8>: Send));
+/// ```
+#[macro_export]
+macro_rules! implements {
+    ($ty:ty: $trait:path) => {{
+        struct Probe<T: ?Sized>(::std::marker::PhantomData<T>);
+        // Regarding dead_code, only one of the two `IMPLS` is ever used
+        #[allow(dead_code)]
+        trait Fallback {
+            const IMPLS: bool = false;
+        }
+        impl<T: ?Sized> Fallback for Probe<T> {}
+        #[allow(dead_code)]
+        impl<T: ?Sized + $trait> Probe<T> {
+            const IMPLS: bool = true;
+        }
+        <Probe<$ty>>::IMPLS
+    }};
+}
+
+/// Handle that must stay on the thread that created it \[think thread-local resource or FFI
+/// handle\]
+///
+/// ```compile_fail,E0277
+/// use async_stuff::markers::ThreadBound;
+/// let h = ThreadBound::new(1);
+/// std::thread::spawn(move || h.id()); // error[E0277]: `*const ()` cannot be sent between threads safely
+/// ```
+pub struct ThreadBound {
+    id: u32,
+    _not_send_sync: PhantomData<*const ()>,
+}
+
+impl ThreadBound {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            _not_send_sync: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// Can move to another thread but not be shared between threads \[like [Cell]\]
+pub struct SendNotSync {
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+/// Can be shared but must be released on the thread that acquired it \[like [MutexGuard]\]
+pub struct SyncNotSend {
+    _not_send: PhantomData<MutexGuard<'static, ()>>,
+}
+
+/// Self-referential: `cursor` points into `data`, so moving it would leave `cursor` dangling
+///
+/// Only usable through `Pin<&mut Self>`, same as v4's `ReadWrap`:
+/// ```compile_fail,E0277
+/// use async_stuff::markers::SelfRef;
+/// let mut s = SelfRef::new(*b"hello");
+/// let p = std::pin::Pin::new(&mut s); // error[E0277]: `PhantomPinned` cannot be unpinned
+/// ```
+pub struct SelfRef {
+    data: [u8; 5],
+    cursor: *const u8,
+    _pinned: PhantomPinned,
+}
+
+// SAFETY: `cursor` only ever points into our own `data`, never into memory shared with another value
+unsafe impl Send for SelfRef {}
+
+impl SelfRef {
+    /// Cursor starts null and is set on first [SelfRef::next()] \[after pinning\]
+    pub fn new(data: [u8; 5]) -> Self {
+        Self {
+            data,
+            cursor: std::ptr::null(),
+            _pinned: PhantomPinned,
+        }
+    }
+
+    pub fn next(self: std::pin::Pin<&mut Self>) -> Option<u8> {
+        // SAFETY: We don't move out of `this`
+        let this = unsafe { self.get_unchecked_mut() };
+        let start = this.data.as_ptr();
+        if this.cursor.is_null() {
+            this.cursor = start;
+        }
+        // SAFETY: cursor is within data or one past its end [as it's pinned, data has not moved]
+        let offset = unsafe { this.cursor.offset_from(start) } as usize;
+        let b = *this.data.get(offset)?;
+        // SAFETY: Still within data or one past its end
+        this.cursor = unsafe { this.cursor.add(1) };
+        Some(b)
+    }
+}
+
+// Same rows as the table in the module docs
+assert_not_impl!(ThreadBound: Send);
+assert_not_impl!(ThreadBound: Sync);
+assert_not_impl!(SendNotSync: Sync);
+assert_not_impl!(SyncNotSend: Send);
+assert_not_impl!(SelfRef: Unpin);
+assert_not_impl!(crate::fasterthanlime_pin::v4::ReadWrap<tokio::fs::File>: Unpin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fasterthanlime_pin::{v3, v4};
+
+    #[test]
+    fn test_marker_table() {
+        assert!(!implements!(ThreadBound: Send));
+        assert!(!implements!(ThreadBound: Sync));
+        assert!(implements!(ThreadBound: Unpin));
+
+        assert!(implements!(SendNotSync: Send));
+        assert!(!implements!(SendNotSync: Sync));
+
+        assert!(!implements!(SyncNotSend: Send));
+        assert!(implements!(SyncNotSend: Sync));
+
+        assert!(implements!(SelfRef: Send));
+        assert!(!implements!(SelfRef: Unpin));
+    }
+
+    #[test]
+    fn test_read_wrap_unpin() {
+        // v3 boxes the Sleep, v4 holds it inline
+        assert!(implements!(v3::ReadWrap<tokio::fs::File>: Unpin));
+        assert!(!implements!(v4::ReadWrap<tokio::fs::File>: Unpin));
+    }
+
+    #[test]
+    fn test_self_ref() {
+        let mut s = std::pin::pin!(SelfRef::new(*b"abcde"));
+        let mut out = Vec::new();
+        while let Some(b) = s.as_mut().next() {
+            out.push(b);
+        }
+        assert_eq!(out, b"abcde");
+    }
+}
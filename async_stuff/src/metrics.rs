@@ -0,0 +1,464 @@
+//! Counters, gauges and histograms in one [Registry], instead of each demo counting by hand
+//!
+//! | metric | holds | e.g. |
+//! | --- | --- | --- |
+//! | [Counter] | a `u64` that only goes up | polls, wakes, allocations |
+//! | [Gauge] | an `i64` set to the current value | queue length, tasks in flight |
+//! | [Histogram] | counts per bucket, 16 per power of two \[HDR-style\] | poll duration in ns |
+//!
+//! All of them are atomics behind an [Arc], so a handle is cheap to clone into tasks and
+//! threads. [instrument] feeds a future's polls, wakes, poll time and allocations into
+//! `<name>.polls`, `.wakes`, `.poll_ns` and `.allocs`. [report_every] hands a [Snapshot] to a
+//! closure periodically and [Snapshot::report] exports one as a [Report] \[JSON\].
+//!
+//! [crate::mini_executor] counts its polls and wakes here, [crate::wake_batching] its wakes and
+//! the consumer's polls.
+//!
+//! A histogram's quantile is the largest value of its bucket, at most 1/16 = 6.25% above the
+//! true one, from ~8 KiB of buckets covering all of `u64`.
+//!
+//! ```sh
+//! cargo run -p async_stuff --bin metrics
+//! ```
+//!
+//! NB: `.allocs` counts via [simple::alloc_counter] and stays 0 unless the binary installs its
+//! `CountingAlloc` as `#[global_allocator]`.
+
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use simple::alloc_counter;
+use simple::report::Report;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    pub fn set(&self, v: i64) {
+        self.0.store(v, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, n: i64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Sub-buckets per power of two, as bits
+const SUB_BITS: u32 = 4;
+const SUB: u64 = 1 << SUB_BITS;
+const BUCKETS: usize = ((65 - SUB_BITS) as u64 * SUB) as usize;
+
+/// Values below [SUB] get a bucket each, above that `v`'s top [SUB_BITS] + 1 bits pick one
+fn bucket(v: u64) -> usize {
+    if v < SUB {
+        return v as usize;
+    }
+    let shift = 63 - v.leading_zeros() - SUB_BITS;
+    ((shift as u64 + 1) * SUB + (v >> shift) - SUB) as usize
+}
+
+/// The largest value that goes into bucket `i`
+fn bucket_max(i: usize) -> u64 {
+    let i = i as u64;
+    if i < SUB {
+        return i;
+    }
+    let shift = i / SUB - 1;
+    let lowest = (SUB + i % SUB) << shift;
+    lowest + ((1 << shift) - 1)
+}
+
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Summary of a [Histogram]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Quantiles {
+    pub count: u64,
+    pub sum: u64,
+    pub min: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl Histogram {
+    pub fn record(&self, v: u64) {
+        self.buckets[bucket(v)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(v, Ordering::Relaxed);
+        self.min.fetch_min(v, Ordering::Relaxed);
+        self.max.fetch_max(v, Ordering::Relaxed);
+    }
+
+    /// In ns
+    pub fn record_duration(&self, d: Duration) {
+        self.record(u64::try_from(d.as_nanos()).unwrap_or(u64::MAX));
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// At least as large as `q` of the values \[and within 6.25% of the true quantile\], 0 if
+    /// empty
+    pub fn quantile(&self, q: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let max = self.max.load(Ordering::Relaxed);
+        let mut seen = 0;
+        for (i, b) in self.buckets.iter().enumerate() {
+            seen += b.load(Ordering::Relaxed);
+            if seen >= rank {
+                return bucket_max(i).min(max);
+            }
+        }
+        max
+    }
+
+    pub fn quantiles(&self) -> Quantiles {
+        let count = self.count();
+        let sum = self.sum.load(Ordering::Relaxed);
+        Quantiles {
+            count,
+            sum,
+            min: if count == 0 {
+                0
+            } else {
+                self.min.load(Ordering::Relaxed)
+            },
+            mean: match count {
+                0 => 0.0,
+                n => sum as f64 / n as f64,
+            },
+            p50: self.quantile(0.5),
+            p90: self.quantile(0.9),
+            p99: self.quantile(0.99),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Metric {
+    Counter(Arc<Counter>),
+    Gauge(Arc<Gauge>),
+    Histogram(Arc<Histogram>),
+}
+
+/// Metrics by name, cloning shares them
+#[derive(Debug, Clone, Default)]
+pub struct Registry(Arc<Mutex<BTreeMap<String, Metric>>>);
+
+macro_rules! getter {
+    ($fn:ident, $kind:ident) => {
+        /// The one named `name`, created on first use. Panics if `name` is another kind of metric.
+        pub fn $fn(&self, name: &str) -> Arc<$kind> {
+            let mut metrics = self.0.lock().unwrap();
+            let metric = metrics
+                .entry(name.to_string())
+                .or_insert_with(|| Metric::$kind(Arc::default()));
+            match metric {
+                Metric::$kind(m) => m.clone(),
+                other => panic!("metric {name:?} is a {other:?}"),
+            }
+        }
+    };
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    getter!(counter, Counter);
+    getter!(gauge, Gauge);
+    getter!(histogram, Histogram);
+
+    pub fn snapshot(&self) -> Snapshot {
+        let metrics = self.0.lock().unwrap();
+        let values = metrics
+            .iter()
+            .map(|(name, metric)| {
+                let value = match metric {
+                    Metric::Counter(c) => Value::Counter(c.get()),
+                    Metric::Gauge(g) => Value::Gauge(g.get()),
+                    Metric::Histogram(h) => Value::Histogram(h.quantiles()),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        Snapshot(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Counter(u64),
+    Gauge(i64),
+    Histogram(Quantiles),
+}
+
+/// Every metric's value at one point in time, by name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot(pub BTreeMap<String, Value>);
+
+impl Snapshot {
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.0.get(name).copied()
+    }
+
+    /// One item per metric, finished
+    pub fn report(&self, title: &str) -> Report {
+        let mut report = Report::new(title);
+        for (name, value) in &self.0 {
+            report.push(name, value);
+        }
+        report.finish();
+        report
+    }
+}
+
+/// Calls `f` with a snapshot every `every` \[the first right away\], forever. Spawn it and abort
+/// it when done.
+pub async fn report_every(registry: Registry, every: Duration, mut f: impl FnMut(&Snapshot)) {
+    let mut interval = tokio::time::interval(every);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        f(&registry.snapshot());
+    }
+}
+
+struct CountingWaker {
+    inner: Waker,
+    wakes: Arc<Counter>,
+}
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakes.inc();
+        self.inner.wake_by_ref();
+    }
+}
+
+pin_project! {
+    /// See [instrument]
+    pub struct Instrumented<F> {
+        #[pin]
+        future: F,
+        polls: Arc<Counter>,
+        wakes: Arc<Counter>,
+        poll_ns: Arc<Histogram>,
+        allocs: Arc<Counter>,
+        // The last waker polled with and its counting wrapper, rebuilt when it changes
+        waker: Option<(Waker, Waker)>,
+    }
+}
+
+/// Counts `future`'s polls, wakes, allocations and time spent in `poll` under `name.*`
+pub fn instrument<F: Future>(registry: &Registry, name: &str, future: F) -> Instrumented<F> {
+    Instrumented {
+        future,
+        polls: registry.counter(&format!("{name}.polls")),
+        wakes: registry.counter(&format!("{name}.wakes")),
+        poll_ns: registry.histogram(&format!("{name}.poll_ns")),
+        allocs: registry.counter(&format!("{name}.allocs")),
+        waker: None,
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        let waker = match this.waker {
+            Some((inner, counting)) if inner.will_wake(cx.waker()) => counting,
+            waker => {
+                let counting = Waker::from(Arc::new(CountingWaker {
+                    inner: cx.waker().clone(),
+                    wakes: this.wakes.clone(),
+                }));
+                &waker.insert((cx.waker().clone(), counting)).1
+            }
+        };
+        let mut counted = Context::from_waker(waker);
+
+        let allocs = alloc_counter::thread_snapshot();
+        let start = Instant::now();
+        let poll = this.future.poll(&mut counted);
+        this.poll_ns.record_duration(start.elapsed());
+        let allocs = alloc_counter::thread_snapshot().since(&allocs).allocs;
+        this.allocs.add(allocs);
+        this.polls.inc();
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_executor::{Executor, yield_n};
+
+    #[test]
+    fn test_buckets() {
+        // Exact below SUB, then contiguous and increasing
+        for v in 0..SUB {
+            assert_eq!(bucket_max(bucket(v)), v);
+        }
+        for i in 0..BUCKETS - 1 {
+            assert_eq!(bucket(bucket_max(i)), i);
+            assert_eq!(bucket(bucket_max(i) + 1), i + 1);
+        }
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_max(BUCKETS - 1), u64::MAX);
+        // Within 1/16 of any value
+        for v in [17, 100, 1_000, 123_456_789, u64::MAX / 3] {
+            let max = bucket_max(bucket(v));
+            assert!(max >= v && (max - v) as f64 <= v as f64 / 16.0, "{v} {max}");
+        }
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let h = Histogram::default();
+        assert_eq!(h.quantile(0.5), 0);
+        for v in 1..=1000 {
+            h.record(v);
+        }
+        let q = h.quantiles();
+        assert_eq!((q.count, q.sum, q.min, q.max), (1000, 500_500, 1, 1000));
+        assert_eq!(q.mean, 500.5);
+        assert_eq!((q.p50, q.p90, q.p99), (511, 927, 991));
+        for (p, exact) in [(q.p50, 500), (q.p90, 900), (q.p99, 990)] {
+            assert!(p >= exact && p - exact <= exact / 16);
+        }
+    }
+
+    #[test]
+    fn test_registry_report() {
+        let registry = Registry::new();
+        registry.counter("c").add(3);
+        registry.counter("c").inc();
+        registry.gauge("g").set(-2);
+        registry.histogram("h").record(7);
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.get("c"), Some(Value::Counter(4)));
+        assert_eq!(snapshot.get("g"), Some(Value::Gauge(-2)));
+
+        let report = snapshot.report("metrics");
+        let labels: Vec<_> = report.items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, ["c", "g", "h"]);
+        let json = report.to_json();
+        assert!(json.contains(r#""value": 4"#), "{json}");
+        assert!(json.contains(r#""p99": 7"#), "{json}");
+    }
+
+    #[test]
+    #[should_panic(expected = "is a Counter")]
+    fn test_kind_mismatch() {
+        let registry = Registry::new();
+        registry.counter("x");
+        registry.gauge("x");
+    }
+
+    #[test]
+    fn test_instrument() {
+        let registry = Registry::new();
+        let mut ex = Executor::new();
+        ex.spawn(instrument(&registry, "yields", yield_n(3)));
+        ex.run();
+        let s = registry.snapshot();
+        assert_eq!(s.get("yields.polls"), Some(Value::Counter(4)));
+        assert_eq!(s.get("yields.wakes"), Some(Value::Counter(3)));
+        // No CountingAlloc in tests
+        assert_eq!(s.get("yields.allocs"), Some(Value::Counter(0)));
+        let Some(Value::Histogram(q)) = s.get("yields.poll_ns") else {
+            panic!()
+        };
+        assert_eq!(q.count, 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_report_every() {
+        let registry = Registry::new();
+        let ticks = registry.counter("ticks");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen2 = seen.clone();
+        let reporter = tokio::spawn(report_every(
+            registry.clone(),
+            Duration::from_secs(1),
+            move |s| seen2.lock().unwrap().push(s.get("ticks")),
+        ));
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            ticks.inc();
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        reporter.abort();
+        let seen: Vec<_> = seen.lock().unwrap().clone();
+        let counts: Vec<_> = seen
+            .into_iter()
+            .map(|v| match v {
+                Some(Value::Counter(n)) => n,
+                other => panic!("{other:?}"),
+            })
+            .collect();
+        assert_eq!(counts[..3], [0, 1, 2]);
+    }
+}
@@ -0,0 +1,325 @@
+//! Minimal single-threaded executor \[no I/O or timer reactor\]
+//!
+//! - Each spawned future is boxed and stored in a slot indexed by its [TaskId].
+//! - Each task's [Waker] pushes the [TaskId] onto a shared ready queue, one per [Priority].
+//! - [Executor::run] pops ready tasks, highest priority first, and polls them until no task is
+//!   ready.
+//!
+//! A task's priority is read when it is woken, changing it \[[TaskHandle::set_priority]\] also
+//! moves it if it is queued already. [current] is the task being polled, e.g. for a lock to
+//! see who holds it \[[crate::priority]\].
+//!
+//! An `on_poll` callback runs before every poll. Its type `H` is a generic parameter, so the same
+//! executor works with a `fn(TaskId)` pointer, a closure or a `Box<dyn Fn(TaskId)>`
+//! \[see `callbacks.rs`\].
+//!
+//! Polls and wakes are [Counter]s, `executor.polls` and `executor.wakes` of a
+//! [crate::metrics::Registry] with [Executor::with_metrics].
+//!
+//! re: [Async in depth](https://tokio.rs/tokio/tutorial/async) (tokio tutorial builds a "mini tokio")
+
+use crate::metrics::{Counter, Registry};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+pub type TaskId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Priority {
+    Low = 0,
+    Normal = 1,
+    High = 2,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Low, Priority::Normal, Priority::High];
+
+    fn from_u8(n: u8) -> Self {
+        Self::ALL[n as usize]
+    }
+}
+
+/// One queue per [Priority], indexed by it
+type Ready = Arc<Mutex<[VecDeque<TaskId>; 3]>>;
+
+struct TaskWaker {
+    id: TaskId,
+    priority: Arc<AtomicU8>,
+    ready: Ready,
+    wakes: Arc<Counter>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakes.inc();
+        let mut ready = self.ready.lock().unwrap();
+        // Under the lock, not to race with set_priority
+        ready[self.priority.load(Ordering::Relaxed) as usize].push_back(self.id);
+    }
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    waker: Waker,
+    priority: Arc<AtomicU8>,
+}
+
+/// A task of an [Executor], see [current]
+#[derive(Clone)]
+pub struct TaskHandle {
+    pub id: TaskId,
+    priority: Arc<AtomicU8>,
+    ready: Ready,
+}
+
+impl TaskHandle {
+    pub fn priority(&self) -> Priority {
+        Priority::from_u8(self.priority.load(Ordering::Relaxed))
+    }
+
+    /// Moves it to `priority`'s queue if it is ready, else from its next wake-up on
+    pub fn set_priority(&self, priority: Priority) {
+        let mut ready = self.ready.lock().unwrap();
+        let old = self.priority.swap(priority as u8, Ordering::Relaxed) as usize;
+        if let Some(i) = ready[old].iter().position(|&id| id == self.id) {
+            ready[old].remove(i);
+            ready[priority as usize].push_back(self.id);
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<TaskHandle>> = const { RefCell::new(None) };
+}
+
+/// The task being polled by [Executor::run] on this thread, `None` outside of it
+pub fn current() -> Option<TaskHandle> {
+    CURRENT.with(|c| c.borrow().clone())
+}
+
+pub struct Executor<H = fn(TaskId)> {
+    /// `None` once the task completed
+    tasks: Vec<Option<Task>>,
+    ready: Ready,
+    on_poll: H,
+    polls: Arc<Counter>,
+    /// Including the one of each spawn
+    wakes: Arc<Counter>,
+}
+
+impl Executor {
+    pub fn new() -> Self {
+        Self::with_on_poll(|_| {})
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Fn(TaskId)> Executor<H> {
+    pub fn with_on_poll(on_poll: H) -> Self {
+        Self {
+            tasks: Vec::new(),
+            ready: Ready::default(),
+            on_poll,
+            polls: Arc::default(),
+            wakes: Arc::default(),
+        }
+    }
+
+    /// Counts into `registry`'s `executor.polls` and `executor.wakes`, call before spawning
+    pub fn with_metrics(mut self, registry: &Registry) -> Self {
+        self.polls = registry.counter("executor.polls");
+        self.wakes = registry.counter("executor.wakes");
+        self
+    }
+
+    /// At [Priority::Normal]
+    pub fn spawn(&mut self, future: impl Future<Output = ()> + 'static) -> TaskId {
+        self.spawn_with_priority(Priority::Normal, future)
+    }
+
+    pub fn spawn_with_priority(
+        &mut self,
+        priority: Priority,
+        future: impl Future<Output = ()> + 'static,
+    ) -> TaskId {
+        let id = self.tasks.len();
+        let priority = Arc::new(AtomicU8::new(priority as u8));
+        let waker = Waker::from(Arc::new(TaskWaker {
+            id,
+            priority: priority.clone(),
+            ready: self.ready.clone(),
+            wakes: self.wakes.clone(),
+        }));
+        waker.wake_by_ref();
+        self.tasks.push(Some(Task {
+            future: Box::pin(future),
+            waker,
+            priority,
+        }));
+        id
+    }
+
+    /// Number of tasks that have not completed yet
+    pub fn pending(&self) -> usize {
+        self.tasks.iter().filter(|t| t.is_some()).count()
+    }
+
+    /// Polls ready tasks until none is ready. Returns the number of polls.
+    ///
+    /// NB: Tasks still pending afterwards are waiting on a waker that nobody will call \[there is
+    /// no reactor\] unless something outside the executor holds on to it.
+    pub fn run(&mut self) -> usize {
+        let before = self.polls.get();
+        loop {
+            // NB: Separate statement so the guard is dropped before polling [which may wake, and
+            // so lock, again]. A `while let` scrutinee's temporaries live for the whole body.
+            let next = self
+                .ready
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .rev()
+                .find_map(VecDeque::pop_front);
+            let Some(id) = next else {
+                break;
+            };
+            // Stale wake-up for a task that already completed
+            let Some(task) = &mut self.tasks[id] else {
+                continue;
+            };
+            (self.on_poll)(id);
+            self.polls.inc();
+            let mut cx = Context::from_waker(&task.waker);
+            let handle = TaskHandle {
+                id,
+                priority: task.priority.clone(),
+                ready: self.ready.clone(),
+            };
+            let previous = CURRENT.with(|c| c.replace(Some(handle)));
+            let poll = task.future.as_mut().poll(&mut cx);
+            CURRENT.with(|c| *c.borrow_mut() = previous);
+            if poll.is_ready() {
+                self.tasks[id] = None;
+            }
+        }
+        (self.polls.get() - before) as usize
+    }
+}
+
+/// Returns [Poll::Pending] `n` times \[waking itself each time\] before completing
+pub async fn yield_n(n: usize) {
+    let mut remaining = n;
+    std::future::poll_fn(|cx| {
+        if remaining == 0 {
+            return Poll::Ready(());
+        }
+        remaining -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_run_to_completion() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut ex = Executor::new();
+        for name in ["a", "b"] {
+            let log = log.clone();
+            ex.spawn(async move {
+                log.borrow_mut().push(format!("{name}1"));
+                yield_n(1).await;
+                log.borrow_mut().push(format!("{name}2"));
+            });
+        }
+        assert_eq!(ex.run(), 4);
+        assert_eq!(ex.pending(), 0);
+        // Round robin: each yield puts the task at the back of the queue
+        assert_eq!(*log.borrow(), ["a1", "b1", "a2", "b2"]);
+    }
+
+    #[test]
+    fn test_on_poll() {
+        let polled = Rc::new(RefCell::new(Vec::new()));
+        let mut ex = Executor::with_on_poll({
+            let polled = polled.clone();
+            move |id| polled.borrow_mut().push(id)
+        });
+        let a = ex.spawn(yield_n(2));
+        let b = ex.spawn(yield_n(0));
+        ex.run();
+        assert_eq!(*polled.borrow(), [a, b, a, a]);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let registry = Registry::new();
+        let mut ex = Executor::new().with_metrics(&registry);
+        ex.spawn(yield_n(2));
+        ex.spawn(yield_n(0));
+        assert_eq!(ex.run(), 4);
+        assert_eq!(ex.run(), 0);
+        // One per spawn and one per yield
+        assert_eq!(registry.counter("executor.wakes").get(), 4);
+        assert_eq!(registry.counter("executor.polls").get(), 4);
+    }
+
+    #[test]
+    fn test_priorities() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut ex = Executor::new();
+        for (name, priority) in [("low", Priority::Low), ("high", Priority::High)] {
+            let log = log.clone();
+            ex.spawn_with_priority(priority, async move {
+                for i in 0..2 {
+                    log.borrow_mut().push(format!("{name}{i}"));
+                    yield_n(1).await;
+                }
+            });
+        }
+        let log2 = log.clone();
+        ex.spawn(async move {
+            let me = current().unwrap();
+            assert_eq!(me.priority(), Priority::Normal);
+            log2.borrow_mut().push("normal".into());
+            // Overtakes low from its next wake-up on
+            me.set_priority(Priority::High);
+            yield_n(1).await;
+            log2.borrow_mut().push("boosted".into());
+        });
+        ex.run();
+        assert!(current().is_none());
+        assert_eq!(
+            *log.borrow(),
+            ["high0", "high1", "normal", "boosted", "low0", "low1"]
+        );
+    }
+
+    #[test]
+    fn test_never_woken() {
+        let mut ex = Executor::new();
+        ex.spawn(std::future::pending());
+        assert_eq!(ex.run(), 1);
+        assert_eq!(ex.pending(), 1);
+    }
+}
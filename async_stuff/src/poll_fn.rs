@@ -0,0 +1,157 @@
+//! The two helpers that remove most hand-written `poll` boilerplate, written by hand:
+//! [ready!] \[like [std::task::ready!]\] and [poll_fn] \[like [std::future::poll_fn]\]
+//!
+//! | [v3::ReadWrap](crate::fasterthanlime_pin::v3::ReadWrap) `poll_read` as | lines | |
+//! | --- | --- | --- |
+//! | in the article | 14 | `match` on [Poll] with an explicit `Poll::Pending => Poll::Pending` arm |
+//! | [ReadWrap] with [ready!] | 5 | `?`-like early return of `Pending` |
+//! | [read_after] with [poll_fn] | 6 | no struct and no trait impl, the closure borrows pinned locals |
+//!
+//! [poll_fn] is for one-off futures inside an `async fn`, a named type implementing
+//! [AsyncRead] is still needed to _be_ a reader that other code can take.
+//!
+//! NB: [PollFn] is [Unpin] whatever the closure captures, as it never pins the closure. Pinned
+//! state is borrowed from outside, e.g. a `pin!`ned [Sleep] as in [read_after].
+
+use std::io;
+use std::pin::{Pin, pin};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::{self, Instant, Sleep};
+
+/// `Ready(v)` => `v`, `Pending` => `return Pending`
+///
+/// ```
+/// use async_stuff::ready;
+/// use std::task::Poll;
+///
+/// fn double(p: Poll<u8>) -> Poll<u16> {
+///     let n = ready!(p);
+///     Poll::Ready(u16::from(n) * 2)
+/// }
+/// assert_eq!(double(Poll::Ready(2)), Poll::Ready(4));
+/// assert_eq!(double(Poll::Pending), Poll::Pending);
+/// ```
+#[macro_export]
+macro_rules! ready {
+    ($e:expr $(,)?) => {
+        match $e {
+            ::std::task::Poll::Ready(v) => v,
+            ::std::task::Poll::Pending => return ::std::task::Poll::Pending,
+        }
+    };
+}
+
+/// Future calling `f` on every poll, see [poll_fn]
+#[must_use = "futures do nothing unless polled"]
+pub struct PollFn<F> {
+    f: F,
+}
+
+impl<F> Unpin for PollFn<F> {}
+
+/// A future from a closure: `poll_fn(|cx| ...).await`
+pub fn poll_fn<T, F: FnMut(&mut Context<'_>) -> Poll<T>>(f: F) -> PollFn<F> {
+    PollFn { f }
+}
+
+impl<T, F: FnMut(&mut Context<'_>) -> Poll<T>> Future for PollFn<F> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.f)(cx)
+    }
+}
+
+/// Same fields and behavior as [v3::ReadWrap](crate::fasterthanlime_pin::v3::ReadWrap)
+pub struct ReadWrap<R> {
+    read: Pin<Box<R>>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl<R> ReadWrap<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read: Box::pin(read),
+            sleep: Box::pin(time::sleep(Duration::from_secs(1))),
+        }
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for ReadWrap<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.sleep.as_mut().poll(cx));
+        self.sleep
+            .as_mut()
+            .reset(Instant::now() + Duration::from_secs(1));
+        self.read.as_mut().poll_read(cx, buf)
+    }
+}
+
+/// One read after `delay`, without a wrapper type. Returns the bytes read.
+pub async fn read_after<R: AsyncRead + Unpin>(
+    delay: Duration,
+    read: &mut R,
+    buf: &mut [u8],
+) -> io::Result<usize> {
+    let mut sleep = pin!(time::sleep(delay));
+    let mut buf = ReadBuf::new(buf);
+    poll_fn(|cx| {
+        ready!(sleep.as_mut().poll(cx));
+        ready!(Pin::new(&mut *read).poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implements;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_wrap() {
+        let mut r = ReadWrap::new(&b"hello world"[..]);
+        let start = Instant::now();
+        let mut buf = [0u8; 5];
+        r.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        r.read_exact(&mut buf).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_after() {
+        let mut src = &b"hello"[..];
+        let start = Instant::now();
+        let mut buf = [0u8; 8];
+        let n = read_after(Duration::from_millis(250), &mut src, &mut buf)
+            .await
+            .unwrap();
+        assert_eq!(&buf[..n], b"hello");
+        assert_eq!(start.elapsed(), Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn test_poll_fn() {
+        let mut polls = 0;
+        let out = poll_fn(|cx| {
+            polls += 1;
+            if polls < 3 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready("done")
+        })
+        .await;
+        assert_eq!((out, polls), ("done", 3));
+        assert!(implements!(PollFn<std::marker::PhantomPinned>: Unpin));
+    }
+}
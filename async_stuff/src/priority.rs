@@ -0,0 +1,291 @@
+//! Priority inversion on the [mini executor](crate::mini_executor) with a shared async [Mutex],
+//! and priority inheritance as the fix
+//!
+//! The classic setup: a low priority task takes the lock and works in its critical section, a
+//! high priority task then needs the lock, and normal priority tasks hog the executor meanwhile.
+//! The high task waits for the low one, which waits for all the normal ones: high effectively
+//! runs below normal.
+//!
+//! With inheritance a waiter raises the holder to its own priority until it unlocks, so the
+//! critical section runs ahead of the hogs. Latency from the moment everything is ready to
+//! completion, in polls \[[inversion], `cargo test` numbers, exact\]:
+//!
+//! | | high | normal \[slowest of 3\] | low | boosts |
+//! | --- | --- | --- | --- | --- |
+//! | plain [Mutex] | 105 | 94 | 104 | 0 |
+//! | with inheritance | 12 | 105 | 11 | 1 |
+//!
+//! The hogs pay for it with the length of the critical section, nothing else changes.
+//!
+//! NB: Only for the mini executor, the holder is found with [current]. Elsewhere \[e.g. in tokio,
+//! which has no priorities\] the [Mutex] works but never boosts.
+
+use crate::mini_executor::{Executor, Priority, TaskHandle, current, yield_n};
+use std::cell::{Cell, RefCell, RefMut};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+
+struct Holder {
+    task: Option<TaskHandle>,
+    /// Its priority when it locked, restored on unlock
+    base: Option<Priority>,
+}
+
+#[derive(Default)]
+struct State {
+    holder: Option<Holder>,
+    waiters: Vec<Waker>,
+    boosts: usize,
+}
+
+/// A single-threaded async mutex, optionally with priority inheritance
+pub struct Mutex<T> {
+    inherit: bool,
+    state: RefCell<State>,
+    value: RefCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Self::with_inheritance(value, false)
+    }
+
+    pub fn with_inheritance(value: T, inherit: bool) -> Self {
+        Self {
+            inherit,
+            state: RefCell::default(),
+            value: RefCell::new(value),
+        }
+    }
+
+    /// Times a holder was raised to a waiter's priority
+    pub fn boosts(&self) -> usize {
+        self.state.borrow().boosts
+    }
+
+    pub async fn lock(&self) -> Guard<'_, T> {
+        let me = current();
+        std::future::poll_fn(|cx| {
+            let mut state = self.state.borrow_mut();
+            let Some(holder) = &state.holder else {
+                state.holder = Some(Holder {
+                    base: me.as_ref().map(TaskHandle::priority),
+                    task: me.clone(),
+                });
+                state.waiters.retain(|w| !w.will_wake(cx.waker()));
+                return Poll::Ready(());
+            };
+            if self.inherit
+                && let (Some(me), Some(task)) = (&me, &holder.task)
+                && me.priority() > task.priority()
+            {
+                task.set_priority(me.priority());
+                state.boosts += 1;
+            }
+            if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                state.waiters.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await;
+        Guard {
+            mutex: self,
+            value: self.value.borrow_mut(),
+        }
+    }
+}
+
+pub struct Guard<'a, T> {
+    mutex: &'a Mutex<T>,
+    value: RefMut<'a, T>,
+}
+
+impl<T> Deref for Guard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Guard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for Guard<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.mutex.state.borrow_mut();
+        if let Some(Holder {
+            task: Some(task),
+            base: Some(base),
+        }) = state.holder.take()
+        {
+            task.set_priority(base);
+        }
+        // All of them retry, the executor polls the highest priority one first
+        for waker in state.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Parks tasks until [Gate::open], so they all become ready at once
+#[derive(Default)]
+struct Gate {
+    open: Cell<bool>,
+    waiters: RefCell<Vec<Waker>>,
+}
+
+impl Gate {
+    fn open(&self) {
+        self.open.set(true);
+        self.waiters.borrow_mut().drain(..).for_each(Waker::wake);
+    }
+
+    async fn wait(&self) {
+        std::future::poll_fn(|cx| {
+            if self.open.get() {
+                return Poll::Ready(());
+            }
+            self.waiters.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// Polls from the gate opening to completion, per class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latencies {
+    pub high: usize,
+    /// The slowest of the hogs
+    pub normal: usize,
+    pub low: usize,
+    pub boosts: usize,
+}
+
+/// Polls in the low task's critical section
+pub const CRITICAL: usize = 10;
+pub const HOGS: usize = 3;
+/// Polls each hog takes
+pub const HOG_WORK: usize = 30;
+
+/// The table's scenario: low locks and opens the gate, then one high task wants the lock while
+/// [HOGS] normal ones just work
+pub fn inversion(inherit: bool) -> Latencies {
+    let clock = Rc::new(Cell::new(0));
+    let tick = clock.clone();
+    let mut ex = Executor::with_on_poll(move |_| tick.set(tick.get() + 1));
+
+    let mutex = Rc::new(Mutex::with_inheritance((), inherit));
+    let gate = Rc::new(Gate::default());
+    // Set when the gate opens, then each task's completion
+    let opened = Rc::new(Cell::new(0));
+    let done = Rc::new(RefCell::new(Vec::new()));
+
+    {
+        let (mutex, gate, opened, clock, done) = (
+            mutex.clone(),
+            gate.clone(),
+            opened.clone(),
+            clock.clone(),
+            done.clone(),
+        );
+        ex.spawn_with_priority(Priority::Low, async move {
+            let _guard = mutex.lock().await;
+            opened.set(clock.get());
+            gate.open();
+            yield_n(CRITICAL).await;
+            done.borrow_mut().push((Priority::Low, clock.get()));
+        });
+    }
+    {
+        let (mutex, gate, clock, done) = (mutex.clone(), gate.clone(), clock.clone(), done.clone());
+        ex.spawn_with_priority(Priority::High, async move {
+            gate.wait().await;
+            let _guard = mutex.lock().await;
+            done.borrow_mut().push((Priority::High, clock.get()));
+        });
+    }
+    for _ in 0..HOGS {
+        let (gate, clock, done) = (gate.clone(), clock.clone(), done.clone());
+        ex.spawn(async move {
+            gate.wait().await;
+            yield_n(HOG_WORK).await;
+            done.borrow_mut().push((Priority::Normal, clock.get()));
+        });
+    }
+    ex.run();
+
+    let latency = |p| {
+        let at = done
+            .borrow()
+            .iter()
+            .filter(|&&(q, _)| q == p)
+            .map(|&(_, at)| at)
+            .max();
+        at.expect("task never completed") - opened.get()
+    };
+    Latencies {
+        high: latency(Priority::High),
+        normal: latency(Priority::Normal),
+        low: latency(Priority::Low),
+        boosts: mutex.boosts(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inversion() {
+        let plain = inversion(false);
+        // High waits for all of the hogs' work, then the critical section
+        assert!(plain.high > HOGS * HOG_WORK + CRITICAL);
+        assert!(plain.high > plain.normal);
+        assert_eq!(
+            plain,
+            Latencies {
+                high: 105,
+                normal: 94,
+                low: 104,
+                boosts: 0
+            }
+        );
+
+        let inherited = inversion(true);
+        // Only the critical section
+        assert!(inherited.high < inherited.normal);
+        assert_eq!(
+            inherited,
+            Latencies {
+                high: 12,
+                normal: 105,
+                low: 11,
+                boosts: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_plain_mutex() {
+        let mutex = Rc::new(Mutex::new(0));
+        let mut ex = Executor::new();
+        for _ in 0..3 {
+            let mutex = mutex.clone();
+            ex.spawn(async move {
+                let mut n = mutex.lock().await;
+                // Others find it locked across the yields
+                yield_n(2).await;
+                *n += 1;
+            });
+        }
+        ex.run();
+        assert_eq!(*mutex.value.borrow(), 3);
+        assert_eq!(mutex.boosts(), 0);
+    }
+}
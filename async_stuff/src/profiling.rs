@@ -0,0 +1,127 @@
+//! Flamegraphs of the heavier demos \[only with `--features profile`\]
+//!
+//! The `checksum`, `compression`, `copy` and `wake_batching` bins run under [profiled], which
+//! profiles them when `DEMOS_PROFILE` is set and otherwise only runs them. The `profile` bin
+//! profiles a fixed mix of throttled reads and hashing tasks, each in its own span.
+//!
+//! Two views of the same run:
+//!
+//! | profiler | what it samples | shows |
+//! | --- | --- | --- |
+//! | [pprof] | CPU stacks, ~1000 times a second via `SIGPROF` | where _CPU_ time goes, e.g. inside `Sha256::update` |
+//! | [tracing_flame] | entered `tracing` spans \[an instrumented future enters its span on every poll\] | where _poll_ time goes, per span, but not what happens between polls |
+//!
+//! NB: A throttled reader mostly sleeps, which neither of them shows: pprof only samples a
+//! running thread and tracing-flame only counts time while a span is entered. Wall clock time
+//! spent waiting shows up as a _gap_ rather than a wide frame.
+//!
+//! ```sh
+//! cargo run -p async_stuff --release --features profile --bin profile
+//! DEMOS_PROFILE=1 cargo run -p async_stuff --release --features profile --bin checksum -- Cargo.lock
+//! # then open target/profiles/*.svg in a browser
+//! ```
+
+use anyhow::Result;
+#[cfg(feature = "profile")]
+use std::fs::{self, File};
+#[cfg(feature = "profile")]
+use std::io::BufReader;
+#[cfg(feature = "profile")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "profile")]
+use tracing_flame::{FlameLayer, FlushGuard};
+#[cfg(feature = "profile")]
+use tracing_subscriber::prelude::*;
+
+/// Set to anything to have [profiled] profile
+pub const ENV: &str = "DEMOS_PROFILE";
+
+/// Where [profiled] writes its flamegraphs
+pub const DIR: &str = "target/profiles";
+
+/// `future` in a span named `name` under a [Profiler] if [ENV] is set, else just `future`.
+/// Without `--features profile` [ENV] only gets a warning.
+pub async fn profiled<T, E: Into<anyhow::Error>>(
+    name: &str,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T> {
+    if std::env::var_os(ENV).is_none() {
+        return future.await.map_err(Into::into);
+    }
+    #[cfg(feature = "profile")]
+    {
+        use tracing::Instrument;
+
+        let profiler = Profiler::start(name, DIR)?;
+        let out = future
+            .instrument(tracing::info_span!("demo", name))
+            .await
+            .map_err(Into::into)?;
+        for svg in profiler.finish()? {
+            eprintln!("wrote {}", svg.display());
+        }
+        Ok(out)
+    }
+    #[cfg(not(feature = "profile"))]
+    {
+        eprintln!("{ENV} needs --features profile, running {name} without");
+        future.await.map_err(Into::into)
+    }
+}
+
+/// Running CPU profile plus span recording, written out by [Profiler::finish]
+#[cfg(feature = "profile")]
+pub struct Profiler {
+    name: String,
+    dir: PathBuf,
+    cpu: pprof::ProfilerGuard<'static>,
+    flame: FlushGuard<std::io::BufWriter<File>>,
+}
+
+#[cfg(feature = "profile")]
+impl Profiler {
+    /// Installs the global tracing subscriber, so at most once per process
+    pub fn start(name: &str, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let (layer, flame) = FlameLayer::with_file(dir.join(format!("{name}.folded")))?;
+        // Merge threads [the multi-threaded runtime moves tasks between workers anyway] and skip
+        // time outside of any span
+        let layer = layer
+            .with_threads_collapsed(true)
+            .with_file_and_line(false)
+            .with_empty_samples(false);
+        tracing_subscriber::registry().with(layer).try_init()?;
+        let cpu = pprof::ProfilerGuardBuilder::default()
+            .frequency(1000)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()?;
+        Ok(Self {
+            name: name.to_string(),
+            dir,
+            cpu,
+            flame,
+        })
+    }
+
+    /// Write `<name>-cpu.svg` and `<name>-spans.svg`, returns their paths
+    pub fn finish(self) -> Result<[PathBuf; 2]> {
+        let cpu_svg = self.dir.join(format!("{}-cpu.svg", self.name));
+        self.cpu
+            .report()
+            .build()?
+            .flamegraph(File::create(&cpu_svg)?)?;
+
+        self.flame.flush()?;
+        let folded = self.dir.join(format!("{}.folded", self.name));
+        let spans_svg = self.dir.join(format!("{}-spans.svg", self.name));
+        let mut options = inferno::flamegraph::Options::default();
+        options.title = format!("{} poll time per span", self.name);
+        inferno::flamegraph::from_reader(
+            &mut options,
+            BufReader::new(File::open(&folded)?),
+            File::create(&spans_svg)?,
+        )?;
+        Ok([cpu_svg, spans_svg])
+    }
+}
@@ -0,0 +1,197 @@
+//! A [Registry] over HTTP in the Prometheus text format \[only with `--features prometheus`\]
+//!
+//! | [Value] | exposed as | e.g. for `v3.polls`, `v3.poll_ns` |
+//! | --- | --- | --- |
+//! | counter | `counter`, name + `_total` | `demos_v3_polls_total 12` |
+//! | gauge | `gauge` | |
+//! | histogram | `summary` with p50, p90, p99, `_sum` and `_count` | `demos_v3_poll_ns{quantile="0.5"} 4095` |
+//!
+//! [serve] is the smallest HTTP/1.1 that Prometheus, curl and browsers accept: `GET /metrics`
+//! gets the text, anything else a 404 or 405, one request per connection. A request head that
+//! takes longer than [HEAD_TIMEOUT] gets a 408 \[so idle connections don't pile up\].
+//!
+//! ```sh
+//! cargo run -p async_stuff --features prometheus --bin serve_metrics
+//! curl -s localhost:9898/metrics
+//! ```
+//!
+//! NB: Quantiles are computed here rather than by Prometheus, so they can't be aggregated across
+//! instances. That needs the buckets themselves as a `histogram`, at 976 series per metric here.
+
+use crate::metrics::{Registry, Snapshot, Value};
+use std::fmt::Write as _;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Longest request head read, the rest is ignored
+const MAX_HEAD: usize = 8 * 1024;
+/// For the whole request head, from accepting the connection
+pub const HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `[a-zA-Z_:][a-zA-Z0-9_:]*`, anything else becomes `_`
+fn metric_name(namespace: &str, name: &str) -> String {
+    let mut s: String = format!("{namespace}_{name}")
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | ':' => c,
+            _ => '_',
+        })
+        .collect();
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        s.insert(0, '_');
+    }
+    s
+}
+
+/// Every metric of `snapshot`, names prefixed with `namespace_`
+pub fn render(snapshot: &Snapshot, namespace: &str) -> String {
+    let mut out = String::new();
+    for (name, value) in &snapshot.0 {
+        let name = metric_name(namespace, name);
+        // Writing to a String can't fail
+        let _ = match value {
+            Value::Counter(n) => writeln!(out, "# TYPE {name}_total counter\n{name}_total {n}"),
+            Value::Gauge(n) => writeln!(out, "# TYPE {name} gauge\n{name} {n}"),
+            Value::Histogram(q) => writeln!(
+                out,
+                "# TYPE {name} summary\n\
+                 {name}{{quantile=\"0.5\"}} {}\n\
+                 {name}{{quantile=\"0.9\"}} {}\n\
+                 {name}{{quantile=\"0.99\"}} {}\n\
+                 {name}_sum {}\n\
+                 {name}_count {}",
+                q.p50, q.p90, q.p99, q.sum, q.count
+            ),
+        };
+    }
+    out
+}
+
+/// Answers scrapes until the listener fails, a task per connection
+pub async fn serve(listener: TcpListener, registry: Registry, namespace: &str) -> io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let registry = registry.clone();
+        let namespace = namespace.to_string();
+        tokio::spawn(async move {
+            // A scraper hanging up early is its problem
+            let _ = respond(stream, &registry, &namespace).await;
+        });
+    }
+}
+
+/// Up to the blank line ending the head, or [MAX_HEAD] bytes, or EOF
+async fn read_head(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_HEAD {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(head)
+}
+
+async fn respond(mut stream: TcpStream, registry: &Registry, namespace: &str) -> io::Result<()> {
+    let (status, body) = match tokio::time::timeout(HEAD_TIMEOUT, read_head(&mut stream)).await {
+        Err(_elapsed) => ("408 Request Timeout", String::new()),
+        Ok(head) => {
+            let head = head?;
+            let head = String::from_utf8_lossy(&head);
+            let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+            match (request_line.next(), request_line.next()) {
+                (Some("GET"), Some("/metrics")) => {
+                    ("200 OK", render(&registry.snapshot(), namespace))
+                }
+                (Some("GET"), _) => ("404 Not Found", "try /metrics\n".to_string()),
+                _ => ("405 Method Not Allowed", String::new()),
+            }
+        }
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let registry = Registry::new();
+        registry.counter("v3.polls").add(12);
+        registry.gauge("in-flight").set(-1);
+        let h = registry.histogram("v3.poll_ns");
+        [10, 20, 30].into_iter().for_each(|v| h.record(v));
+        assert_eq!(
+            render(&registry.snapshot(), "demos"),
+            "# TYPE demos_in_flight gauge\n\
+             demos_in_flight -1\n\
+             # TYPE demos_v3_poll_ns summary\n\
+             demos_v3_poll_ns{quantile=\"0.5\"} 20\n\
+             demos_v3_poll_ns{quantile=\"0.9\"} 30\n\
+             demos_v3_poll_ns{quantile=\"0.99\"} 30\n\
+             demos_v3_poll_ns_sum 60\n\
+             demos_v3_poll_ns_count 3\n\
+             # TYPE demos_v3_polls_total counter\n\
+             demos_v3_polls_total 12\n"
+        );
+        assert_eq!(metric_name("", "x"), "_x");
+        assert_eq!(metric_name("9", "x"), "_9_x");
+    }
+
+    async fn get(addr: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let registry = Registry::new();
+        let polls = registry.counter("polls");
+        let server = tokio::spawn(serve(listener, registry, "demos"));
+
+        polls.add(3);
+        let ok = get(addr, "GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"), "{ok}");
+        assert!(ok.contains(CONTENT_TYPE));
+        assert!(ok.ends_with("\r\n\r\n# TYPE demos_polls_total counter\ndemos_polls_total 3\n"));
+        // Scraped live, not a copy
+        polls.inc();
+        let ok = get(addr, "GET /metrics HTTP/1.1\r\n\r\n").await;
+        assert!(ok.ends_with("demos_polls_total 4\n"), "{ok}");
+
+        let missing = get(addr, "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(missing.starts_with("HTTP/1.1 404 "), "{missing}");
+        let post = get(addr, "POST /metrics HTTP/1.1\r\n\r\n").await;
+        assert!(post.starts_with("HTTP/1.1 405 "), "{post}");
+        server.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_head() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve(listener, Registry::new(), "demos"));
+
+        // Never sends the blank line, the paused clock jumps to the timeout
+        let slow = get(addr, "GET /metrics HTTP/1.1\r\n").await;
+        assert!(slow.starts_with("HTTP/1.1 408 "), "{slow}");
+        server.abort();
+    }
+}
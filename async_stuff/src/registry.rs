@@ -0,0 +1,320 @@
+//! Every demo of the runner with its metadata, for `demos list`, `--lesson` and this table
+//!
+//! | demo | summary | tags | difficulty | features | per read | allocs |
+//! | --- | --- | --- | --- | --- | --- | --- |
+//! | v2 | passes reads through, Unpin like its reader | pin, async-read | beginner | - | 0ns | 0 |
+//! | v3 | a second before each read, the Sleep boxed to stay Unpin | pin, async-read, timers, heap, editions | intermediate | - | 1s | 2 |
+//! | v4 | v3 with the Sleep inline, pinned by hand with unsafe | pin, async-read, timers, unsafe, editions | advanced | - | 1s | 0 |
+//! | v5 | v4 with the projection generated by pin_project_lite | pin, async-read, timers, macros, editions | intermediate | - | 1s | 0 |
+//!
+//! ```sh
+//! cargo run --bin demos -- list --tag pin
+//! cargo run --bin demos -- list --markdown   # the table above
+//! ```
+//!
+//! `per read` and `allocs` \[heap allocations of creating the wrapper\] are also what
+//! `demos --check` holds each demo to, see [crate::runner::check].
+//!
+//! Each demo declares its [Demo] next to its code with [demo!](crate::demo), e.g.
+//! [crate::fasterthanlime_pin::v3::DEMO], which also submits it to `inventory`. [demos]
+//! collects them at startup, so a new demo needs nothing outside its own module: no line here,
+//! no arm in [crate::runner].
+//!
+//! NB: The table is [markdown] pasted in, a test fails when they differ.
+
+use crate::config::{Config, Error};
+use crate::lesson::Lesson;
+use crate::seeded::SeededReader;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+
+// For demo!, so crates using it need no inventory of their own
+#[doc(hidden)]
+pub use inventory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // pad() rather than write_str() to honor widths, e.g. `{:<12}`
+        f.pad(match self {
+            Self::Beginner => "beginner",
+            Self::Intermediate => "intermediate",
+            Self::Advanced => "advanced",
+        })
+    }
+}
+
+/// What a demo's wrapper reads, the seeded input, through [crate::runner::chaos] with `--chaos`
+pub type Source = Box<dyn AsyncRead + Unpin + Send>;
+
+/// Future of [Demo::run], bytes read and their crc32
+pub type Reading<'a> = Pin<Box<dyn Future<Output = anyhow::Result<(usize, String)>> + Send + 'a>>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Demo {
+    /// What `demos NAME` runs
+    pub name: &'static str,
+    pub summary: &'static str,
+    /// Topics, one of [TAGS] each
+    pub tags: &'static [&'static str],
+    pub difficulty: Difficulty,
+    /// Cargo features it needs, see [Demo::missing_features]
+    pub features: &'static [&'static str],
+    /// About how long each of `--reads` takes
+    pub per_read: Duration,
+    /// Heap allocations of creating the wrapper
+    pub wrapper_allocs: u64,
+    pub lesson: Lesson,
+    /// Reads `config.reads` chunks through its wrapper, see [crate::runner::read]
+    pub run: for<'a> fn(Source, &'a Config) -> Reading<'a>,
+    /// Creates its wrapper and drops it, for counting [Demo::wrapper_allocs]
+    pub new_wrapper: fn(SeededReader),
+}
+
+inventory::collect!(Demo);
+
+/// `pub const DEMO: Demo` for [crate::registry], submitted to it. Fields in this order,
+/// `features` optional, `wrapper` a type in scope with a `new(source)`.
+#[macro_export]
+macro_rules! demo {
+    (
+        name: $name:literal,
+        wrapper: $wrapper:ident,
+        summary: $summary:literal,
+        tags: [$($tag:literal),* $(,)?],
+        difficulty: $difficulty:ident,
+        $(features: [$($feature:literal),* $(,)?],)?
+        per_read: $per_read:expr,
+        wrapper_allocs: $wrapper_allocs:literal,
+        lesson: $lesson:expr $(,)?
+    ) => {
+        /// This demo in [crate::registry]
+        pub const DEMO: $crate::registry::Demo = $crate::registry::Demo {
+            name: $name,
+            summary: $summary,
+            tags: &[$($tag),*],
+            difficulty: $crate::registry::Difficulty::$difficulty,
+            features: &[$($($feature),*)?],
+            per_read: $per_read,
+            wrapper_allocs: $wrapper_allocs,
+            lesson: $lesson,
+            run: |source, config| {
+                ::std::boxed::Box::pin($crate::runner::read($wrapper::new(source), config))
+            },
+            // black_box, or release builds may elide the allocations that are counted
+            new_wrapper: |source| drop(::std::hint::black_box($wrapper::new(source))),
+        };
+
+        $crate::registry::inventory::submit!(DEMO);
+    };
+}
+
+/// Every submitted demo by name, the order they run in when none are named
+pub fn demos() -> &'static [&'static Demo] {
+    static DEMOS: OnceLock<Vec<&'static Demo>> = OnceLock::new();
+    DEMOS.get_or_init(|| {
+        let mut demos: Vec<_> = inventory::iter::<Demo>().collect();
+        demos.sort_by_key(|d| d.name);
+        demos
+    })
+}
+
+pub const TAGS: &[&str] = &[
+    "async-read",
+    "editions",
+    "heap",
+    "macros",
+    "pin",
+    "timers",
+    "unsafe",
+];
+
+/// The cargo features this build has, of those any demo may need
+const ENABLED: &[&str] = &[
+    #[cfg(feature = "profile")]
+    "profile",
+    #[cfg(feature = "prometheus")]
+    "prometheus",
+];
+
+impl Demo {
+    /// Features it needs that this build was compiled without
+    pub fn missing_features(&self) -> Vec<&'static str> {
+        self.features
+            .iter()
+            .copied()
+            .filter(|f| !ENABLED.contains(f))
+            .collect()
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static Demo> {
+    demos().iter().copied().find(|d| d.name == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    demos().iter().map(|d| d.name).collect()
+}
+
+/// Demos tagged with `tag`, all if `None`
+pub fn tagged(tag: Option<&str>) -> Vec<&'static Demo> {
+    demos()
+        .iter()
+        .copied()
+        .filter(|d| tag.is_none_or(|t| d.tags.contains(&t)))
+        .collect()
+}
+
+/// The table of `demos`, as in the docs of this module
+pub fn markdown(demos: &[&Demo]) -> String {
+    let mut out = String::from(
+        "| demo | summary | tags | difficulty | features | per read | allocs |\n\
+         | --- | --- | --- | --- | --- | --- | --- |\n",
+    );
+    for d in demos {
+        let features = match d.features {
+            [] => "-".to_string(),
+            features => features.join(", "),
+        };
+        out += &format!(
+            "| {} | {} | {} | {} | {features} | {:?} | {} |\n",
+            d.name,
+            d.summary,
+            d.tags.join(", "),
+            d.difficulty,
+            d.per_read,
+            d.wrapper_allocs
+        );
+    }
+    out
+}
+
+/// One line per demo, for a terminal
+pub fn text(demos: &[&Demo]) -> String {
+    demos
+        .iter()
+        .map(|d| {
+            format!(
+                "{:<4} {:<12} {:>4?}/read  {}\n     [{}]\n",
+                d.name,
+                d.difficulty,
+                d.per_read,
+                d.summary,
+                d.tags.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// `demos list [--tag TAG] [--markdown]`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct List {
+    pub tag: Option<String>,
+    pub markdown: bool,
+}
+
+pub const LIST_USAGE: &str = "usage: demos list [--tag TAG] [--markdown]";
+
+impl List {
+    /// Arguments after `list`
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+        let mut list = Self::default();
+        let mut it = args.into_iter();
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "--tag" => {
+                    let tag = it.next().ok_or_else(|| {
+                        Error::Usage(format!("missing value for {arg}\n{LIST_USAGE}"))
+                    })?;
+                    if !TAGS.contains(&tag.as_str()) {
+                        return Err(Error::Invalid {
+                            origin: arg,
+                            value: tag,
+                            reason: format!("expected one of {}", TAGS.join(", ")),
+                        });
+                    }
+                    list.tag = Some(tag);
+                }
+                "--markdown" => list.markdown = true,
+                "-h" | "--help" => return Err(Error::Help(LIST_USAGE.into())),
+                _ => return Err(Error::Usage(format!("unexpected {arg}\n{LIST_USAGE}"))),
+            }
+        }
+        Ok(list)
+    }
+
+    pub fn render(&self) -> String {
+        let demos = tagged(self.tag.as_deref());
+        match self.markdown {
+            true => markdown(&demos),
+            false => text(&demos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fasterthanlime_pin::{v2, v4};
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_docs_up_to_date() {
+        let docs: String = include_str!("registry.rs")
+            .lines()
+            .filter_map(|l| l.strip_prefix("//! | "))
+            .map(|l| format!("| {l}\n"))
+            .collect();
+        assert_eq!(docs, markdown(&tagged(None)));
+    }
+
+    #[test]
+    fn test_registry() {
+        assert_eq!(names(), ["v2", "v3", "v4", "v5"]);
+        for (i, d) in demos().iter().enumerate() {
+            assert!(d.tags.iter().all(|t| TAGS.contains(t)), "{}", d.name);
+            assert!(!d.lesson.before.is_empty() && !d.lesson.after.is_empty());
+            assert!(demos()[..i].iter().all(|other| other.name != d.name));
+        }
+        assert_eq!(find("v4").map(|d| d.summary), Some(v4::DEMO.summary));
+        assert!(find("v9").is_none());
+        let needs = Demo {
+            features: &["prometheus"],
+            ..v2::DEMO
+        };
+        assert_eq!(
+            needs.missing_features().is_empty(),
+            cfg!(feature = "prometheus")
+        );
+    }
+
+    #[test]
+    fn test_list() {
+        let listed = |s: &str| -> Vec<_> {
+            let list = List::parse(args(s)).unwrap();
+            tagged(list.tag.as_deref()).iter().map(|d| d.name).collect()
+        };
+        assert_eq!(listed(""), names());
+        assert_eq!(listed("--tag unsafe"), ["v4"]);
+        assert_eq!(listed("--tag heap"), ["v3"]);
+        assert_eq!(
+            List::parse(args("--tag pni")).unwrap_err().to_string(),
+            r#"--tag="pni": expected one of async-read, editions, heap, macros, pin, timers, unsafe"#
+        );
+        assert!(matches!(List::parse(args("v3")), Err(Error::Usage(_))));
+        let list = List::parse(args("--markdown --tag heap")).unwrap();
+        assert_eq!(list.render().lines().count(), 3);
+    }
+}
@@ -0,0 +1,405 @@
+//! Record the chunks and timing of an [AsyncRead] session, replay them later: the same input
+//! schedule for every wrapper variant, run after run
+//!
+//! A live source \[network, pipe, `/dev/urandom` under load\] delivers different chunks at
+//! different times on every run, so comparing e.g. [v3](crate::fasterthanlime_pin::v3) with
+//! [v4](crate::fasterthanlime_pin::v4) on it compares the source as much as the wrappers.
+//! [Recorder] captures one session, [Replayer] plays it back with the same chunk boundaries:
+//!
+//! | [Pace] | each chunk at | |
+//! | --- | --- | --- |
+//! | [Pace::AsRecorded] | its recorded offset from the first read | timing-sensitive comparisons |
+//! | [Pace::Faster]\(n\) | offset / n | the same order and boundaries, n times sooner |
+//! | [Pace::NoDelay] | as soon as it is read | only the data matters |
+//!
+//! EOF is replayed at its recorded time too. [compare] reads one [Recording] through each
+//! wrapper at once, on tokio's paused clock the results are exact and repeatable. E.g. chunks at
+//! 0.2, 0.5 and 2.5 s and EOF at 3 s take v2 3 s and the throttled v3 to v5 5 s each: a read that
+//! finds nothing due yet has already restarted their 1 s sleep.
+//!
+//! File format of [Recording::write_to], little endian:
+//!
+//! | offset | field | type |
+//! | --- | --- | --- |
+//! | 0 | magic | `[u8; 4]` = `b"RPLY"` |
+//! | 4 | chunks | `u32` |
+//! | 8 | EOF at, ns | `u64`, `u64::MAX` if never seen |
+//! | 16 | per chunk: at, ns | `u64` |
+//! | +8 | per chunk: len | `u32`, then `len` bytes |
+//!
+//! ```sh
+//! cargo run -p async_stuff --bin replay
+//! ```
+
+use crate::digest::{Crc32, StreamDigest};
+use crate::fasterthanlime_pin::{v2, v3, v4, v5};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::pin::{Pin, pin};
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+pub const MAGIC: &[u8; 4] = b"RPLY";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Since the first read
+    pub at: Duration,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub chunks: Vec<Chunk>,
+    /// When the read returning EOF completed, if it did
+    pub eof_at: Option<Duration>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Recording {
+    pub fn bytes(&self) -> usize {
+        self.chunks.iter().map(|c| c.data.len()).sum()
+    }
+
+    pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
+        let count = u32::try_from(self.chunks.len()).map_err(|_| invalid("too many chunks"))?;
+        let eof = self.eof_at.map_or(u64::MAX, |d| d.as_nanos() as u64);
+        w.write_all(MAGIC)?;
+        w.write_all(&count.to_le_bytes())?;
+        w.write_all(&eof.to_le_bytes())?;
+        for c in &self.chunks {
+            let len = u32::try_from(c.data.len()).map_err(|_| invalid("chunk too long"))?;
+            w.write_all(&(c.at.as_nanos() as u64).to_le_bytes())?;
+            w.write_all(&len.to_le_bytes())?;
+            w.write_all(&c.data)?;
+        }
+        w.flush()
+    }
+
+    pub fn read_from(mut r: impl Read) -> io::Result<Self> {
+        let mut header = [0u8; 16];
+        r.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a recording"));
+        }
+        let count = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let eof = u64::from_le_bytes(header[8..].try_into().unwrap());
+        let mut chunks = Vec::new();
+        for _ in 0..count {
+            let mut head = [0u8; 12];
+            r.read_exact(&mut head)?;
+            let at = u64::from_le_bytes(head[..8].try_into().unwrap());
+            let len = u32::from_le_bytes(head[8..].try_into().unwrap());
+            // Not vec![0; len], a corrupt len would allocate up to 4 GiB before reading any
+            let mut data = Vec::new();
+            (&mut r).take(len.into()).read_to_end(&mut data)?;
+            if data.len() != len as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "recording ends inside a chunk",
+                ));
+            }
+            chunks.push(Chunk {
+                at: Duration::from_nanos(at),
+                data,
+            });
+        }
+        Ok(Self {
+            chunks,
+            eof_at: (eof != u64::MAX).then(|| Duration::from_nanos(eof)),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_to(io::BufWriter::new(std::fs::File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(io::BufReader::new(std::fs::File::open(path)?))
+    }
+}
+
+/// Passes reads through, recording each non-empty one as a [Chunk]
+pub struct Recorder<R> {
+    read: R,
+    start: Option<Instant>,
+    recording: Recording,
+}
+
+impl<R> Recorder<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read,
+            start: None,
+            recording: Recording::default(),
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Recorder<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let start = *this.start.get_or_insert_with(Instant::now);
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.read).poll_read(cx, buf))?;
+        let at = start.elapsed();
+        match &buf.filled()[before..] {
+            [] => this.recording.eof_at = Some(at),
+            data => this.recording.chunks.push(Chunk {
+                at,
+                data: data.to_vec(),
+            }),
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pace {
+    AsRecorded,
+    /// Delays divided by this
+    Faster(u32),
+    NoDelay,
+}
+
+impl Pace {
+    fn scale(self, at: Duration) -> Duration {
+        match self {
+            Pace::AsRecorded => at,
+            Pace::Faster(n) => at / n.max(1),
+            Pace::NoDelay => Duration::ZERO,
+        }
+    }
+}
+
+/// Plays a [Recording] back: a read returns at most the rest of the current chunk, not before
+/// it is due. [Unpin] \[the [Sleep] is boxed\], so any wrapper can take it.
+pub struct Replayer {
+    chunks: VecDeque<Chunk>,
+    /// Of the front chunk, already read
+    offset: usize,
+    eof_at: Option<Duration>,
+    pace: Pace,
+    start: Option<Instant>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording, pace: Pace) -> Self {
+        Self {
+            chunks: recording.chunks.into(),
+            offset: 0,
+            eof_at: recording.eof_at,
+            pace,
+            start: None,
+            sleep: Box::pin(tokio::time::sleep(Duration::ZERO)),
+        }
+    }
+
+    /// `Ready` once `at` \[paced\] has passed since the first read
+    fn poll_due(&mut self, cx: &mut Context<'_>, at: Duration) -> Poll<()> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let due = start + self.pace.scale(at);
+        if Instant::now() >= due {
+            return Poll::Ready(());
+        }
+        if self.sleep.deadline() != due {
+            self.sleep.as_mut().reset(due);
+        }
+        self.sleep.as_mut().poll(cx)
+    }
+}
+
+impl AsyncRead for Replayer {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let Some(at) = this.chunks.front().map(|c| c.at) else {
+            // A session that never reached EOF ends right after its last chunk
+            if let Some(eof_at) = this.eof_at {
+                ready!(this.poll_due(cx, eof_at));
+            }
+            return Poll::Ready(Ok(()));
+        };
+        if this.offset == 0 {
+            ready!(this.poll_due(cx, at));
+        }
+        let chunk = &this.chunks[0].data[this.offset..];
+        let n = chunk.len().min(buf.remaining());
+        buf.put_slice(&chunk[..n]);
+        this.offset += n;
+        if this.offset == this.chunks[0].data.len() {
+            this.chunks.pop_front();
+            this.offset = 0;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The [crate::fasterthanlime_pin] wrappers [compare] runs
+pub const VARIANTS: [&str; 4] = ["v2", "v3", "v4", "v5"];
+
+/// What one variant read from a replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub variant: &'static str,
+    pub bytes: usize,
+    pub crc32: String,
+    /// Non-empty reads
+    pub reads: usize,
+    pub elapsed: Duration,
+}
+
+/// Reads to EOF, `buf_len` bytes at most per read
+async fn read_all(read: impl AsyncRead, variant: &'static str, buf_len: usize) -> io::Result<Run> {
+    let mut read = pin!(read);
+    let start = Instant::now();
+    let mut buf = vec![0u8; buf_len];
+    let mut crc = Crc32::default();
+    let (mut bytes, mut reads) = (0, 0);
+    loop {
+        let n = read.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        crc.update(&buf[..n]);
+        bytes += n;
+        reads += 1;
+    }
+    Ok(Run {
+        variant,
+        bytes,
+        crc32: crc.hex(),
+        reads,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Every one of [VARIANTS] over its own [Replayer] of `recording`, concurrently
+pub async fn compare(recording: &Recording, pace: Pace, buf_len: usize) -> io::Result<Vec<Run>> {
+    let replay = || Replayer::new(recording.clone(), pace);
+    let (r2, r3, r4, r5) = tokio::join!(
+        read_all(v2::ReadWrap::new(replay()), "v2", buf_len),
+        read_all(v3::ReadWrap::new(replay()), "v3", buf_len),
+        read_all(v4::ReadWrap::new(replay()), "v4", buf_len),
+        read_all(v5::ReadWrap::new(replay()), "v5", buf_len),
+    );
+    Ok(vec![r2?, r3?, r4?, r5?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::duplex;
+    use tokio::io::AsyncWriteExt;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// 3 chunks 200 ms, 500 ms and 2.5 s in, then EOF at 3 s
+    async fn record() -> Recording {
+        let (mut tx, rx) = duplex(1024);
+        let start = Instant::now();
+        let writer = tokio::spawn(async move {
+            for (at, data) in [(200, &b"hello"[..]), (500, b" async"), (2_500, b" world")] {
+                tokio::time::sleep_until(start + at * MS).await;
+                tx.write_all(data).await.unwrap();
+            }
+            tokio::time::sleep(500 * MS).await;
+        });
+        let mut recorder = Recorder::new(rx);
+        let mut sink = Vec::new();
+        recorder.read_to_end(&mut sink).await.unwrap();
+        writer.await.unwrap();
+        assert_eq!(sink, b"hello async world");
+        recorder.into_recording()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_record_save_load() {
+        let recording = record().await;
+        let at: Vec<_> = recording.chunks.iter().map(|c| c.at).collect();
+        assert_eq!(at, [200 * MS, 500 * MS, 2_500 * MS]);
+        assert_eq!(recording.eof_at, Some(3_000 * MS));
+
+        let path = std::env::temp_dir().join(format!("replay-{}.bin", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, recording);
+        let e = Recording::read_from(&b"NOPE and more bytes"[..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        // A chunk claiming u32::MAX bytes, with 3 left
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        bytes.truncate(16 + 12 + 3);
+        bytes[16 + 8..16 + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let e = Recording::read_from(&bytes[..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_paces() {
+        let recording = record().await;
+        let mut elapsed = Vec::new();
+        for pace in [Pace::AsRecorded, Pace::Faster(10), Pace::NoDelay] {
+            let run = read_all(Replayer::new(recording.clone(), pace), "raw", 4).await;
+            let run = run.unwrap();
+            assert_eq!(run.bytes, 17);
+            // Chunks of 5 + 6 + 6 bytes, 4 at a time
+            assert_eq!(run.reads, 6);
+            elapsed.push(run.elapsed);
+        }
+        assert_eq!(elapsed, [3_000 * MS, 300 * MS, Duration::ZERO]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_compare_is_repeatable() {
+        let recording = record().await;
+        let first = compare(&recording, Pace::AsRecorded, 64).await.unwrap();
+        let again = compare(&recording, Pace::AsRecorded, 64).await.unwrap();
+        assert_eq!(first, again);
+        assert!(
+            first
+                .iter()
+                .all(|r| r.bytes == 17 && r.crc32 == first[0].crc32)
+        );
+        let summary: Vec<_> = first
+            .iter()
+            .map(|r| (r.variant, r.reads, r.elapsed))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                // Follows the recording
+                ("v2", 3, 3_000 * MS),
+                // 1 s before each read, and a read that finds its chunk not due yet \[the
+                // first one, at 1 s of 1.2 s\] has already restarted the 1 s
+                ("v3", 3, 5_000 * MS),
+                ("v4", 3, 5_000 * MS),
+                ("v5", 3, 5_000 * MS),
+            ]
+        );
+    }
+}
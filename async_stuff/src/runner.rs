@@ -0,0 +1,352 @@
+//! What the `demos` bin runs: the throttling wrappers of [crate::fasterthanlime_pin] on the same
+//! seeded input, each producing a [Report]
+//!
+//! ```sh
+//! cargo run --bin demos                       # all of them
+//! cargo run --bin demos -- --reads 2 v3 v4   # see crate::config for files and env vars
+//! cargo run --bin demos -- --chaos random     # prints the seed to repeat the run with
+//! cargo run --bin demos -- --lesson v3 v4     # see crate::lesson
+//! cargo run --bin demos -- list --tag unsafe  # see crate::registry
+//! cargo run --bin demos -- --check --reads 2  # exits 1 unless every demo passes
+//! ```
+//!
+//! The `crc32` of what was read is the same for every wrapper, they only differ in timing.
+//!
+//! With `--chaos SEED` each demo reads through [chaos]: `WouldBlock`, `Interrupted` and short
+//! reads from [FaultyIo], then up to [CHAOS_LATENCY] before every read from [LatencyIo]. The
+//! `crc32` must still be the same as without, only slower. Hard errors are left out, they'd only
+//! end the run.
+//!
+//! NB: v3 to v5 sleep a second before every read of the inner reader, so each fault costs them a
+//! second, e.g. 15 s for `--reads 2 v4` instead of 2 s. v2 only pays the latency.
+//!
+//! With `--check` each demo is also held to its [registry::Demo], see [check]:
+//!
+//! | | expected | e.g. `--reads 2 v3` |
+//! | --- | --- | --- |
+//! | `bytes` | reads × chunk | 64 |
+//! | `crc32` | that of the seeded input read without any wrapper | `"5c9bd231"` |
+//! | elapsed | at least [registry::Demo::per_read] × reads, at most [CHECK_SLACK] more \[no upper bound with `--chaos`\] | 2 s to 2.5 s |
+//! | allocations | [registry::Demo::wrapper_allocs], if [CountingAlloc](simple::alloc_counter::CountingAlloc) is the global allocator | 2 |
+
+use crate::config::{Config, Format};
+use crate::digest::{Crc32, StreamDigest};
+use crate::faulty::{Faults, FaultyIo, LatencyIo};
+use crate::registry;
+use crate::seeded::SeededReader;
+use anyhow::{Result, bail};
+use simple::alloc_counter;
+use simple::report::Report;
+use std::fmt;
+use std::io;
+use std::pin::pin;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub const CHAOS_LATENCY: Duration = Duration::from_millis(20);
+/// How much slower than expected `--check` lets a demo be
+pub const CHECK_SLACK: Duration = Duration::from_millis(500);
+
+/// `read` with the faults and latency of `--chaos`, both drawn from `seed`
+pub fn chaos<R: AsyncRead>(read: R, seed: u64) -> LatencyIo<FaultyIo<R>> {
+    let faults = Faults {
+        interrupted: 0.1,
+        ..Faults::partial()
+    };
+    let faulty = FaultyIo::new(read, faults, seed);
+    LatencyIo::new(faulty, CHAOS_LATENCY, seed.wrapping_add(1))
+}
+
+/// [Config::run], or all of [registry::demos] if empty. Unknown names and demos this build
+/// lacks the features of are an error before anything runs.
+pub fn selected(config: &Config) -> Result<Vec<&'static str>> {
+    if config.run.is_empty() {
+        return Ok(registry::names());
+    }
+    config
+        .run
+        .iter()
+        .map(|name| {
+            let Some(demo) = registry::find(name) else {
+                bail!(
+                    "unknown demo {name:?}, expected one of {:?}",
+                    registry::names()
+                );
+            };
+            match demo.missing_features()[..] {
+                [] => Ok(demo.name),
+                ref missing => bail!("{name} needs --features {}", missing.join(",")),
+            }
+        })
+        .collect()
+}
+
+pub async fn run(name: &str, config: &Config) -> Result<Report> {
+    let Some(demo) = registry::find(name) else {
+        bail!("unknown demo {name:?}");
+    };
+    let source = SeededReader::new(config.seed);
+    let mut report = Report::new(name);
+    let (bytes, crc32) = match config.chaos {
+        Some(seed) => (demo.run)(Box::new(chaos(source, seed)), config).await?,
+        None => (demo.run)(Box::new(source), config).await?,
+    };
+    report
+        .push("reads", config.reads)
+        .push("bytes", bytes)
+        .push("crc32", crc32);
+    if let Some(seed) = config.chaos {
+        report.push("chaos", seed);
+    }
+    report.finish();
+    Ok(report)
+}
+
+/// `config.reads` chunks of `config.chunk` bytes from `read`, the bytes and their crc32. What
+/// [registry::Demo::run] does with the demo's wrapper.
+pub async fn read(read: impl AsyncRead, config: &Config) -> Result<(usize, String)> {
+    let mut read = pin!(read);
+    let mut buf = vec![0u8; config.chunk];
+    let mut crc = Crc32::default();
+    for _ in 0..config.reads {
+        // Not read_exact(), which returns Interrupted and drops what it had read so far
+        let mut filled = 0;
+        while filled < buf.len() {
+            match read.read(&mut buf[filled..]).await {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        crc.update(&buf);
+    }
+    Ok((config.reads * config.chunk, crc.hex()))
+}
+
+/// Where a demo didn't do what its [registry::Demo] says
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub demo: &'static str,
+    pub what: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            demo,
+            what,
+            expected,
+            actual,
+        } = self;
+        write!(f, "{demo}: expected {what} {expected}, got {actual}")
+    }
+}
+
+/// [run], then what came out of it against what should have, empty if all is well
+pub async fn check(name: &str, config: &Config) -> Result<(Report, Vec<Mismatch>)> {
+    let Some(demo) = registry::find(name) else {
+        bail!("unknown demo {name:?}");
+    };
+    let allocs = wrapper_allocs(demo);
+    let (_, crc32) = read(SeededReader::new(config.seed), config).await?;
+    let start = tokio::time::Instant::now();
+    let report = run(name, config).await?;
+    let elapsed = start.elapsed();
+    let mismatches = compare(demo, config, &report, &crc32, elapsed, allocs);
+    Ok((report, mismatches))
+}
+
+/// Heap allocations of creating the wrapper of `demo`, `None` unless `CountingAlloc` counts
+fn wrapper_allocs(demo: &registry::Demo) -> Option<u64> {
+    if !alloc_counter::installed() {
+        return None;
+    }
+    let source = SeededReader::new(0);
+    let before = alloc_counter::thread_snapshot();
+    (demo.new_wrapper)(source);
+    Some(alloc_counter::thread_snapshot().since(&before).allocs)
+}
+
+fn compare(
+    demo: &registry::Demo,
+    config: &Config,
+    report: &Report,
+    crc32: &str,
+    elapsed: Duration,
+    allocs: Option<u64>,
+) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let mut expect = |what, ok: bool, expected: String, actual: String| {
+        if !ok {
+            mismatches.push(Mismatch {
+                demo: demo.name,
+                what,
+                expected,
+                actual,
+            });
+        }
+    };
+    let item = |label| report.items.iter().find(|i| i.label == label);
+    let actual = |label| item(label).map_or("nothing".into(), |i| i.value.to_string());
+
+    let bytes = config.reads * config.chunk;
+    let ok = item("bytes").is_some_and(|i| i.value == bytes);
+    expect("bytes", ok, bytes.to_string(), actual("bytes"));
+    let ok = item("crc32").is_some_and(|i| i.value == crc32);
+    expect("crc32", ok, format!("{crc32:?}"), actual("crc32"));
+
+    let min = demo.per_read * config.reads as u32;
+    let at = |elapsed: Duration| format!("{elapsed:.3?}");
+    expect(
+        "elapsed",
+        elapsed >= min,
+        format!(">= {min:?}"),
+        at(elapsed),
+    );
+    if config.chaos.is_none() {
+        let max = min + CHECK_SLACK;
+        expect(
+            "elapsed",
+            elapsed <= max,
+            format!("<= {max:?}"),
+            at(elapsed),
+        );
+    }
+    if let Some(n) = allocs {
+        let ok = n == demo.wrapper_allocs;
+        expect("allocs", ok, demo.wrapper_allocs.to_string(), n.to_string());
+    }
+    mismatches
+}
+
+pub fn print(report: &Report, format: Format) {
+    match format {
+        Format::Json => println!("{}", report.to_json()),
+        Format::Text => {
+            let items: Vec<String> = report
+                .items
+                .iter()
+                .map(|item| format!("{} {}", item.label, item.value))
+                .collect();
+            println!(
+                "{}: {} in {:.3?}",
+                report.title,
+                items.join(", "),
+                report.elapsed
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run() {
+        let config = Config {
+            reads: 2,
+            ..Config::default()
+        };
+        let mut crcs = Vec::new();
+        for name in selected(&config).unwrap() {
+            let start = tokio::time::Instant::now();
+            let report = run(name, &config).await.unwrap();
+            assert_eq!(report.items[1].value, 64);
+            crcs.push(report.items[2].value.clone());
+            let throttled = name != "v2";
+            assert_eq!(
+                start.elapsed() >= Duration::from_secs(2),
+                throttled,
+                "{name}"
+            );
+        }
+        // Same input for every wrapper
+        assert!(crcs.windows(2).all(|w| w[0] == w[1]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_chaos() {
+        let clean = Config {
+            reads: 4,
+            ..Config::default()
+        };
+        let chaos = Config {
+            chaos: Some(5),
+            ..clean.clone()
+        };
+        for name in registry::names() {
+            let expected = run(name, &clean).await.unwrap();
+            let mut elapsed = Vec::new();
+            for _ in 0..2 {
+                let start = tokio::time::Instant::now();
+                let report = run(name, &chaos).await.unwrap();
+                elapsed.push(start.elapsed());
+                assert_eq!(report.items[2].value, expected.items[2].value, "{name}");
+                assert_eq!(report.items[3].label, "chaos");
+            }
+            // The same faults and delays again
+            assert_eq!(elapsed[0], elapsed[1], "{name}");
+            assert!(elapsed[0] > Duration::ZERO, "{name}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_check() {
+        for chaos in [None, Some(3)] {
+            let config = Config {
+                reads: 2,
+                chaos,
+                ..Config::default()
+            };
+            for name in registry::names() {
+                let (report, mismatches) = check(name, &config).await.unwrap();
+                assert_eq!(mismatches, [], "{name}");
+                assert_eq!(report.title, name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_mismatches() {
+        let config = Config {
+            reads: 2,
+            ..Config::default()
+        };
+        let mut report = Report::new("v3");
+        report.push("bytes", 60).push("crc32", "0");
+        let elapsed = Duration::from_millis(1);
+        let demo = registry::find("v3").unwrap();
+        let mismatches = compare(demo, &config, &report, "1", elapsed, Some(0));
+        let lines: Vec<_> = mismatches.iter().map(|m| m.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "v3: expected bytes 64, got 60",
+                r#"v3: expected crc32 "1", got "0""#,
+                "v3: expected elapsed >= 2s, got 1.000ms",
+                "v3: expected allocs 2, got 0",
+            ]
+        );
+        // Not only too fast but too slow too
+        let slow = compare(demo, &config, &report, "0", Duration::from_secs(3), None);
+        assert_eq!(
+            slow[1].to_string(),
+            "v3: expected elapsed <= 2.5s, got 3.000s"
+        );
+    }
+
+    #[test]
+    fn test_selected() {
+        let mut config = Config::default();
+        assert_eq!(selected(&config).unwrap(), registry::names());
+        config.run = vec!["v4".into(), "v3".into()];
+        assert_eq!(selected(&config).unwrap(), ["v4", "v3"]);
+        config.run.push("v9".into());
+        assert!(selected(&config).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Deterministic [tokio::io::AsyncRead] source, a reproducible stand-in for `/dev/urandom`
+//!
+//! The bytes come from [simple::random::SeededBytes], so for a given seed they're the same as
+//! reading it synchronously:
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> std::io::Result<()> {
+//! use async_stuff::seeded::SeededReader;
+//! use simple::random::SeededBytes;
+//! use tokio::io::AsyncReadExt;
+//!
+//! let mut async_buf = [0u8; 32];
+//! SeededReader::new(42).read_exact(&mut async_buf).await?;
+//! let mut sync_buf = [0u8; 32];
+//! SeededBytes::new(42).fill(&mut sync_buf);
+//! assert_eq!(async_buf, sync_buf);
+//! # Ok(())
+//! # }
+//! ```
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use simple::random::SeededBytes;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Always ready \[generating bytes never blocks\], EOF only if limited with [SeededReader::take]
+pub struct SeededReader<R = StdRng> {
+    bytes: SeededBytes<R>,
+}
+
+impl SeededReader {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            bytes: SeededBytes::new(seed),
+        }
+    }
+}
+
+impl<R: Rng> SeededReader<R> {
+    pub fn from_rng(rng: R) -> Self {
+        Self {
+            bytes: SeededBytes::from_rng(rng),
+        }
+    }
+
+    pub fn take(self, len: u64) -> Self {
+        Self {
+            bytes: self.bytes.take(len),
+        }
+    }
+}
+
+impl<R: Rng + Unpin> AsyncRead for SeededReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = self.get_mut().bytes.fill(buf.initialize_unfilled());
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fasterthanlime_pin::v2::ReadWrap;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_take_and_wrap() {
+        let mut v = Vec::new();
+        ReadWrap::new(SeededReader::new(1).take(100))
+            .read_to_end(&mut v)
+            .await
+            .unwrap();
+        assert_eq!(v.len(), 100);
+
+        let mut again = [0u8; 100];
+        SeededReader::new(1).read_exact(&mut again).await.unwrap();
+        assert_eq!(v, again);
+    }
+
+    #[tokio::test]
+    async fn test_other_rng() {
+        let mut a = [0u8; 16];
+        let mut b = [0u8; 16];
+        SeededReader::from_rng(SmallRng::seed_from_u64(5))
+            .read_exact(&mut a)
+            .await
+            .unwrap();
+        SeededReader::new(5).read_exact(&mut b).await.unwrap();
+        assert_ne!(a, b);
+    }
+}
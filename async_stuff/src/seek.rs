@@ -0,0 +1,290 @@
+//! [AsyncSeek] next to [AsyncRead]: an in-memory [MemCursor], a [Phases] wrapper that counts how
+//! a seek goes through on [tokio::fs::File] or anything else, and a hand-written [read_at]
+//!
+//! [AsyncSeek] is two calls, not one `poll_seek`:
+//!
+//! | call | returns | |
+//! | --- | --- | --- |
+//! | `start_seek(pos)` | `io::Result<()>`, never `Pending` | submits the seek, errors if one is still in flight |
+//! | `poll_complete(cx)` | `Poll<io::Result<u64>>` | waits for it, then the new position \[the current one if nothing was submitted\] |
+//!
+//! Split because [SeekFrom] has to be kept somewhere while the seek is pending: a future would
+//! own it, a `poll_*` method is called again with fresh arguments.
+//!
+//! | [Phases] around | `poll_complete` before `Ready` | why |
+//! | --- | --- | --- |
+//! | [MemCursor] | never `Pending` | just arithmetic |
+//! | [tokio::fs::File] | `Pending` once or not at all | the `lseek` runs on the blocking pool, a race with the first `poll_complete` |
+//!
+//! NB: Call `poll_complete` until `Ready` _before_ `start_seek` as well, to drain whatever a
+//! previous seek \[or a write, for [tokio::fs::File]\] left in flight. [read_at] does.
+
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+/// Two-phase [AsyncSeek] over bytes in memory
+#[derive(Debug, Default)]
+pub struct MemCursor {
+    data: Vec<u8>,
+    pos: u64,
+    seek: Option<u64>,
+}
+
+impl MemCursor {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            pos: 0,
+            seek: None,
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl AsyncRead for MemCursor {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // Past the end reads nothing, like std::io::Cursor
+        let start = usize::try_from(self.pos).map_or(self.data.len(), |p| p.min(self.data.len()));
+        let n = buf.remaining().min(self.data.len() - start);
+        buf.put_slice(&self.data[start..start + n]);
+        self.pos += n as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for MemCursor {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        if self.seek.is_some() {
+            return Err(io::Error::other("seek in flight, poll_complete first"));
+        }
+        let (base, offset) = match position {
+            SeekFrom::Start(n) => {
+                self.seek = Some(n);
+                return Ok(());
+            }
+            SeekFrom::End(n) => (self.data.len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let target = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        self.seek = Some(target);
+        Ok(())
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        if let Some(target) = self.seek.take() {
+            self.pos = target;
+        }
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+/// How often each phase was seen, see [Phases]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseCounts {
+    pub start_seek: usize,
+    pub complete_pending: usize,
+    pub complete_ready: usize,
+}
+
+/// Passes everything through to `R`, counting the [AsyncSeek] calls
+pub struct Phases<R> {
+    inner: R,
+    pub counts: PhaseCounts,
+}
+
+impl<R> Phases<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            counts: PhaseCounts::default(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Phases<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSeek for Phases<R> {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        self.counts.start_seek += 1;
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let poll = Pin::new(&mut self.inner).poll_complete(cx);
+        match poll {
+            Poll::Pending => self.counts.complete_pending += 1,
+            Poll::Ready(_) => self.counts.complete_ready += 1,
+        }
+        poll
+    }
+}
+
+enum State {
+    /// Draining whatever was in flight before
+    Idle,
+    Seeking,
+    Reading,
+}
+
+/// Future of [read_at]
+#[must_use = "futures do nothing unless polled"]
+pub struct ReadAt<'a, R: ?Sized> {
+    r: &'a mut R,
+    pos: u64,
+    buf: &'a mut [u8],
+    state: State,
+}
+
+/// Seek to `pos`, then one read into `buf`. Returns the bytes read, the position is after them.
+pub fn read_at<'a, R>(r: &'a mut R, pos: u64, buf: &'a mut [u8]) -> ReadAt<'a, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin + ?Sized,
+{
+    ReadAt {
+        r,
+        pos,
+        buf,
+        state: State::Idle,
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin + ?Sized> Future for ReadAt<'_, R> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            match this.state {
+                State::Idle => {
+                    ready!(Pin::new(&mut *this.r).poll_complete(cx))?;
+                    Pin::new(&mut *this.r).start_seek(SeekFrom::Start(this.pos))?;
+                    this.state = State::Seeking;
+                }
+                State::Seeking => {
+                    let pos = ready!(Pin::new(&mut *this.r).poll_complete(cx))?;
+                    debug_assert_eq!(pos, this.pos);
+                    this.state = State::Reading;
+                }
+                State::Reading => {
+                    let mut buf = ReadBuf::new(this.buf);
+                    ready!(Pin::new(&mut *this.r).poll_read(cx, &mut buf))?;
+                    return Poll::Ready(Ok(buf.filled().len()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    const DATA: &[u8] = b"0123456789";
+
+    #[tokio::test]
+    async fn test_mem_cursor_matches_std_cursor() {
+        let seeks = [
+            SeekFrom::Start(3),
+            SeekFrom::Current(2),
+            SeekFrom::End(-1),
+            SeekFrom::Current(-9),
+            SeekFrom::Start(20),
+        ];
+        let mut ours = MemCursor::new(DATA.to_vec());
+        let mut std = std::io::Cursor::new(DATA.to_vec());
+        for pos in seeks {
+            assert_eq!(ours.seek(pos).await.unwrap(), std.seek(pos).await.unwrap());
+            let (mut a, mut b) = ([0u8; 3], [0u8; 3]);
+            let n = ours.read(&mut a).await.unwrap();
+            assert_eq!(n, std.read(&mut b).await.unwrap(), "{pos:?}");
+            assert_eq!(a[..n], b[..n]);
+        }
+        let err = ours.seek(SeekFrom::Current(-100)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_two_phases() {
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+        let mut c = MemCursor::new(DATA.to_vec());
+        let mut c = Pin::new(&mut c);
+        c.as_mut().start_seek(SeekFrom::Start(4)).unwrap();
+        // Not applied until completed
+        assert_eq!(c.position(), 0);
+        assert!(c.as_mut().start_seek(SeekFrom::Start(5)).is_err());
+        assert!(matches!(
+            c.as_mut().poll_complete(&mut cx),
+            Poll::Ready(Ok(4))
+        ));
+        // Nothing submitted, the current position
+        assert!(matches!(
+            c.as_mut().poll_complete(&mut cx),
+            Poll::Ready(Ok(4))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_at() {
+        let mut c = Phases::new(MemCursor::new(DATA.to_vec()));
+        let mut buf = [0u8; 4];
+        assert_eq!(read_at(&mut c, 6, &mut buf).await.unwrap(), 4);
+        assert_eq!(&buf, b"6789");
+        assert_eq!(read_at(&mut c, 1, &mut buf[..2]).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"12");
+        assert_eq!(c.counts.start_seek, 2);
+        // Drain before plus wait after, per read_at, never Pending in memory
+        assert_eq!(c.counts.complete_ready, 4);
+        assert_eq!(c.counts.complete_pending, 0);
+        assert_eq!(c.into_inner().position(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_file() {
+        let path = std::env::temp_dir().join(format!("seek-{}.bin", std::process::id()));
+        tokio::fs::write(&path, DATA).await.unwrap();
+        let file = tokio::fs::File::open(&path).await.unwrap();
+        let mut f = Phases::new(file);
+        let mut buf = [0u8; 3];
+        assert_eq!(read_at(&mut f, 7, &mut buf).await.unwrap(), 3);
+        assert_eq!(&buf, b"789");
+        assert_eq!(read_at(&mut f, 0, &mut buf).await.unwrap(), 3);
+        assert_eq!(&buf, b"012");
+        assert_eq!(f.counts.start_seek, 2);
+        assert_eq!(f.counts.complete_ready, 4);
+
+        // A second start_seek while the first is on the blocking pool
+        let mut file = f.into_inner();
+        let mut pinned = Pin::new(&mut file);
+        pinned.as_mut().start_seek(SeekFrom::Start(1)).unwrap();
+        assert!(pinned.as_mut().start_seek(SeekFrom::Start(2)).is_err());
+        assert_eq!(file.stream_position().await.unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+//! `split()` of an owned stream into a read half and a write half, for a reader task and a writer
+//! task, written by hand twice and compared with [tokio::io::split]
+//!
+//! | split | shared as | lock held | reader task waiting for an echo |
+//! | --- | --- | --- | --- |
+//! | [locked] | `Arc<tokio::sync::Mutex<S>>` | across the whole `read().await` | deadlock: holds the lock the writer needs to send what is to be echoed |
+//! | [tokio::io::split] | `Arc<std::sync::Mutex<S>>` | during one `poll_*` call, never across `Pending` | fine |
+//! | [disjoint] | `Arc<UnsafeCell<S>>` | none, `S: Disjoint` promises the halves touch different fields | fine |
+//!
+//! [locked] is what falls out of "share it, so `Arc<Mutex<_>>`". tokio's locks a `std` mutex
+//! around each `poll_read`/`poll_write` and unlocks it when the poll returns, `Pending` or not.
+//! A half that finds it taken blocks its thread, but only for the rest of the other half's poll,
+//! which is short as polls don't block.
+//!
+//! NB: [disjoint] can't take any `S: AsyncRead + AsyncWrite`. Both halves would make a
+//! `Pin<&mut S>` of the same value at the same time, aliasing `&mut` is UB even if the two paths
+//! happen to use different fields. [disjoint::Disjoint] instead polls from a raw pointer and
+//! borrows only the field each direction needs.
+
+use std::cell::UnsafeCell;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pub mod locked {
+    use std::io;
+    use std::sync::Arc;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::sync::Mutex;
+
+    pub struct ReadHalf<S>(Arc<Mutex<S>>);
+
+    pub struct WriteHalf<S>(Arc<Mutex<S>>);
+
+    pub fn split<S>(stream: S) -> (ReadHalf<S>, WriteHalf<S>) {
+        let shared = Arc::new(Mutex::new(stream));
+        (ReadHalf(shared.clone()), WriteHalf(shared))
+    }
+
+    impl<S: AsyncRead + Unpin> ReadHalf<S> {
+        /// Holds the lock until something arrived
+        pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.lock().await.read(buf).await
+        }
+    }
+
+    impl<S: AsyncWrite + Unpin> WriteHalf<S> {
+        pub async fn write_all(&self, buf: &[u8]) -> io::Result<()> {
+            let mut stream = self.0.lock().await;
+            stream.write_all(buf).await?;
+            stream.flush().await
+        }
+    }
+}
+
+pub mod disjoint {
+    use super::*;
+
+    /// Streams whose read and write sides use disjoint state
+    ///
+    /// # Safety
+    ///
+    /// The read functions and the write functions may run concurrently on the same `this`, so
+    /// neither may create a reference to all of `*this` or to anything the other side uses.
+    pub unsafe trait Disjoint {
+        /// # Safety
+        ///
+        /// `this` is valid and pinned, and only the write functions run concurrently
+        unsafe fn poll_read_raw(
+            this: *mut Self,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>>;
+
+        /// # Safety
+        ///
+        /// `this` is valid and pinned, and only [Disjoint::poll_read_raw] runs concurrently
+        unsafe fn poll_write_raw(
+            this: *mut Self,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>>;
+
+        /// # Safety
+        ///
+        /// As [Disjoint::poll_write_raw]
+        unsafe fn poll_shutdown_raw(this: *mut Self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+    }
+
+    // SAFETY: Reading only touches `read`, writing and shutting down only `write`
+    unsafe impl Disjoint for crate::io::DuplexStream {
+        unsafe fn poll_read_raw(
+            this: *mut Self,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            // SAFETY: valid per the caller, nothing else borrows `read`. Reader is Unpin.
+            let read = unsafe { &mut (*this).read };
+            Pin::new(read).poll_read(cx, buf)
+        }
+
+        unsafe fn poll_write_raw(
+            this: *mut Self,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            // SAFETY: valid per the caller, nothing else borrows `write`. Writer is Unpin.
+            let write = unsafe { &mut (*this).write };
+            Pin::new(write).poll_write(cx, buf)
+        }
+
+        unsafe fn poll_shutdown_raw(this: *mut Self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            // SAFETY: as in poll_write_raw
+            let write = unsafe { &mut (*this).write };
+            Pin::new(write).poll_shutdown(cx)
+        }
+    }
+
+    struct Shared<S>(UnsafeCell<S>);
+
+    // SAFETY: Each half only calls its side of Disjoint, which doesn't conflict with the other
+    unsafe impl<S: Send> Send for Shared<S> {}
+    unsafe impl<S: Send> Sync for Shared<S> {}
+
+    impl<S> Shared<S> {
+        fn get(&self) -> *mut S {
+            self.0.get()
+        }
+    }
+
+    pub struct ReadHalf<S>(Arc<Shared<S>>);
+
+    pub struct WriteHalf<S>(Arc<Shared<S>>);
+
+    pub fn split<S: Disjoint>(stream: S) -> (ReadHalf<S>, WriteHalf<S>) {
+        let shared = Arc::new(Shared(UnsafeCell::new(stream)));
+        (ReadHalf(shared.clone()), WriteHalf(shared))
+    }
+
+    impl<S: Disjoint> AsyncRead for ReadHalf<S> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            // SAFETY: The Arc keeps it alive and in place, the only other user is the WriteHalf
+            unsafe { S::poll_read_raw(self.0.get(), cx, buf) }
+        }
+    }
+
+    impl<S: Disjoint> AsyncWrite for WriteHalf<S> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            // SAFETY: The Arc keeps it alive and in place, the only other user is the ReadHalf
+            unsafe { S::poll_write_raw(self.0.get(), cx, buf) }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            // SAFETY: as in poll_write
+            unsafe { S::poll_shutdown_raw(self.0.get(), cx) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{DuplexStream, duplex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::timeout;
+
+    /// Echoes everything back until EOF
+    fn echo_server(mut stream: DuplexStream) -> tokio::task::JoinHandle<io::Result<u64>> {
+        tokio::spawn(async move {
+            let (mut r, mut w) = tokio::io::split(&mut stream);
+            tokio::io::copy(&mut r, &mut w).await
+        })
+    }
+
+    /// Reader task waits for the echo first, then the writer sends
+    async fn ping<R, W>(mut r: R, mut w: W) -> io::Result<[u8; 4]>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin,
+    {
+        let reader = tokio::spawn(async move {
+            let mut buf = [0u8; 4];
+            r.read_exact(&mut buf).await.map(|_| buf)
+        });
+        tokio::task::yield_now().await;
+        w.write_all(b"ping").await?;
+        reader.await.unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_locked_deadlocks_on_echo() {
+        let (client, server) = duplex(64);
+        let _server = echo_server(server);
+        let (r, w) = locked::split(client);
+        let reader = tokio::spawn(async move {
+            let mut buf = [0u8; 4];
+            r.read(&mut buf).await.map(|n| buf[..n].to_vec())
+        });
+        tokio::task::yield_now().await;
+        // The reader holds the lock while it waits, so the ping never goes out
+        let sent = timeout(Duration::from_secs(10), w.write_all(b"ping")).await;
+        assert!(sent.is_err(), "expected a deadlock");
+        reader.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_locked_works_in_lockstep() {
+        let (client, server) = duplex(64);
+        let _server = echo_server(server);
+        let (r, w) = locked::split(client);
+        // Fine as long as nobody reads before the write is done
+        w.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(r.read(&mut buf).await.unwrap(), 4);
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokio_split() {
+        let (client, server) = duplex(64);
+        let _server = echo_server(server);
+        let (r, w) = tokio::io::split(client);
+        let echoed = timeout(Duration::from_secs(10), ping(r, w)).await;
+        assert_eq!(&echoed.unwrap().unwrap(), b"ping");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_disjoint() {
+        let (client, server) = duplex(64);
+        let server = echo_server(server);
+        let (r, mut w) = disjoint::split(client);
+        let echoed = timeout(Duration::from_secs(10), ping(r, &mut w)).await;
+        assert_eq!(&echoed.unwrap().unwrap(), b"ping");
+        w.shutdown().await.unwrap();
+        assert_eq!(server.await.unwrap().unwrap(), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_disjoint_across_threads() {
+        let (client, server) = duplex(16);
+        let _server = echo_server(server);
+        let (mut r, mut w) = disjoint::split(client);
+        let data: Vec<u8> = (0..50_000u32).map(|i| i as u8).collect();
+        let reader = tokio::spawn(async move {
+            let mut out = vec![0u8; 50_000];
+            r.read_exact(&mut out).await.map(|_| out)
+        });
+        w.write_all(&data).await.unwrap();
+        assert!(reader.await.unwrap().unwrap() == data);
+    }
+}
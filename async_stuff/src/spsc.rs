@@ -0,0 +1,298 @@
+//! Single-producer single-consumer byte ring buffer, async on both ends: an in-memory pipe
+//! standing in for a socket in tests
+//!
+//! | end | full / empty | after it moved bytes | dropped or shut down |
+//! | --- | --- | --- | --- |
+//! | [Writer] \[[AsyncWrite]\] | parks its waker, `Pending` | wakes the reader | reader gets EOF once drained, later writes [io::ErrorKind::BrokenPipe] |
+//! | [Reader] \[[AsyncRead]\] | parks its waker, `Pending` | wakes the writer | writer gets [io::ErrorKind::BrokenPipe] |
+//!
+//! Lock-free on the data: `head` is only stored by the reader, `tail` only by the writer, both
+//! count bytes since the start \[`tail - head` is the length, `% capacity` the slot\]. Only the
+//! two parked wakers sit behind a [Mutex], touched when an end has to wait or moved something.
+//!
+//! Unlike `&[u8]` or `Vec<u8>` as reader/writer, a small `capacity` gives short reads and writes
+//! plus real back-pressure, as a socket would:
+//! ```
+//! use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let (mut w, mut r) = async_stuff::spsc::ring(4);
+//! let writer = tokio::spawn(async move { w.write_all(b"more than four").await });
+//! let mut out = String::new();
+//! r.read_to_string(&mut out).await.unwrap();
+//! assert_eq!(out, "more than four");
+//! writer.await.unwrap().unwrap();
+//! # }
+//! ```
+//!
+//! NB: The waker handoff is race free because the waiting end registers _before_ it looks at
+//! the other index again, and the other end stores its index _before_ it takes the waker. Either
+//! the look sees the new index or the store is followed by a wake of the new waker.
+
+use std::cell::UnsafeCell;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+struct Ring {
+    buf: Box<[UnsafeCell<u8>]>,
+    /// Bytes read so far, stored by the reader only
+    head: AtomicUsize,
+    /// Bytes written so far, stored by the writer only
+    tail: AtomicUsize,
+    reader: Mutex<Option<Waker>>,
+    writer: Mutex<Option<Waker>>,
+    reader_closed: AtomicBool,
+    writer_closed: AtomicBool,
+}
+
+// SAFETY: Slots in `head..tail` are only accessed by the one reader, the others only by the one
+// writer. The Release store of an index after the copy and the Acquire load before the next
+// copy order the accesses to a slot between the two.
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Up to two runs of slots starting at `pos`, wrapping around the end
+    fn runs(&self, pos: usize, len: usize) -> [(usize, usize); 2] {
+        let start = pos % self.capacity();
+        let first = len.min(self.capacity() - start);
+        [(start, first), (0, len - first)]
+    }
+
+    /// From the whole slice rather than `&buf[i]`, which would only cover one byte
+    fn slots(&self) -> *mut u8 {
+        UnsafeCell::raw_get(self.buf.as_ptr())
+    }
+}
+
+fn park(slot: &Mutex<Option<Waker>>, waker: &Waker) {
+    let mut slot = slot.lock().unwrap();
+    match &mut *slot {
+        Some(w) => w.clone_from(waker),
+        None => *slot = Some(waker.clone()),
+    }
+}
+
+fn unpark(slot: &Mutex<Option<Waker>>) {
+    if let Some(w) = slot.lock().unwrap().take() {
+        w.wake();
+    }
+}
+
+/// The writing end, see [ring]
+pub struct Writer(Arc<Ring>);
+
+/// The reading end, see [ring]
+pub struct Reader(Arc<Ring>);
+
+/// A pipe buffering up to `capacity` bytes
+pub fn ring(capacity: usize) -> (Writer, Reader) {
+    assert!(capacity > 0, "capacity must be positive");
+    let ring = Arc::new(Ring {
+        buf: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        reader: Mutex::default(),
+        writer: Mutex::default(),
+        reader_closed: AtomicBool::new(false),
+        writer_closed: AtomicBool::new(false),
+    });
+    (Writer(ring.clone()), Reader(ring))
+}
+
+impl Writer {
+    fn close(&self) {
+        self.0.writer_closed.store(true, Ordering::Release);
+        unpark(&self.0.reader);
+    }
+}
+
+impl AsyncWrite for Writer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let ring = &*self.0;
+        // Like a socket [EPIPE] and tokio::io::duplex
+        if ring.writer_closed.load(Ordering::Relaxed) {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let mut free = ring.capacity() - (tail - ring.head.load(Ordering::Acquire));
+        if free == 0 {
+            park(&ring.writer, cx.waker());
+            free = ring.capacity() - (tail - ring.head.load(Ordering::Acquire));
+        }
+        if ring.reader_closed.load(Ordering::Acquire) {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if free == 0 {
+            return Poll::Pending;
+        }
+        let n = free.min(data.len());
+        let mut copied = 0;
+        for (start, len) in ring.runs(tail, n) {
+            // SAFETY: `start..start + len` is in bounds and free, so not read concurrently
+            unsafe {
+                let dst = ring.slots().add(start);
+                std::ptr::copy_nonoverlapping(data[copied..].as_ptr(), dst, len);
+            }
+            copied += len;
+        }
+        ring.tail.store(tail + n, Ordering::Release);
+        unpark(&ring.reader);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl AsyncRead for Reader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let ring = &*self.0;
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let head = ring.head.load(Ordering::Relaxed);
+        let mut len = ring.tail.load(Ordering::Acquire) - head;
+        if len == 0 {
+            park(&ring.reader, cx.waker());
+            // Closed first: the last tail store happened before it
+            let closed = ring.writer_closed.load(Ordering::Acquire);
+            len = ring.tail.load(Ordering::Acquire) - head;
+            if len == 0 {
+                // EOF when closed
+                return if closed {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                };
+            }
+        }
+        let n = len.min(buf.remaining());
+        for (start, len) in ring.runs(head, n) {
+            // SAFETY: `start..start + len` is in bounds and filled, so not written concurrently
+            let run = unsafe { std::slice::from_raw_parts(ring.slots().add(start), len) };
+            buf.put_slice(run);
+        }
+        ring.head.store(head + n, Ordering::Release);
+        unpark(&ring.writer);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.0.reader_closed.store(true, Ordering::Release);
+        unpark(&self.0.writer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_roundtrip_across_threads() {
+        for capacity in [1, 7, 64, 4096] {
+            let data = pattern(100_000);
+            let (mut w, mut r) = ring(capacity);
+            let writer = tokio::spawn({
+                let data = data.clone();
+                async move {
+                    for chunk in data.chunks(1000) {
+                        w.write_all(chunk).await?;
+                    }
+                    w.shutdown().await
+                }
+            });
+            let mut out = Vec::new();
+            r.read_to_end(&mut out).await.unwrap();
+            writer.await.unwrap().unwrap();
+            assert!(out == data, "capacity {capacity}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_short_reads_and_writes() {
+        let (mut w, mut r) = ring(4);
+        assert_eq!(w.write(b"abcdef").await.unwrap(), 4);
+        let mut buf = [0u8; 3];
+        assert_eq!(r.read(&mut buf).await.unwrap(), 3);
+        assert_eq!(&buf, b"abc");
+        // Wraps around the end
+        assert_eq!(w.write(b"ef").await.unwrap(), 2);
+        let mut buf = [0u8; 8];
+        assert_eq!(r.read(&mut buf).await.unwrap(), 3);
+        assert_eq!(&buf[..3], b"def");
+    }
+
+    #[tokio::test]
+    async fn test_eof_and_broken_pipe() {
+        let (mut w, mut r) = ring(8);
+        w.write_all(b"hi").await.unwrap();
+        drop(w);
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"hi");
+
+        let (mut w, r) = ring(8);
+        drop(r);
+        let err = w.write_all(b"hi").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[tokio::test]
+    async fn test_back_pressure() {
+        use crate::futcomb::FutureCombExt;
+        use std::sync::atomic::AtomicUsize;
+
+        let (mut w, mut r) = ring(2);
+        let polls = Arc::new(AtomicUsize::new(0));
+        let writer = tokio::spawn({
+            let polls = polls.clone();
+            async move { w.write_all(b"xyz").await }
+                .inspect_poll(move |_| _ = polls.fetch_add(1, Ordering::Relaxed))
+        });
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).await.unwrap();
+        writer.await.unwrap().unwrap();
+        assert_eq!(out, b"xyz");
+        // Pending once when full, woken by the read that made room
+        assert_eq!(polls.load(Ordering::Relaxed), 2);
+    }
+}
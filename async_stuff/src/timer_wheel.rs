@@ -0,0 +1,285 @@
+//! A hashed timer wheel: thousands of timeouts in a ring of slots, compared with
+//! `tokio_util::time::DelayQueue`
+//!
+//! Time is cut into ticks, a timer due at tick `d` goes into slot `d % slots`. Each tick looks at
+//! one slot only, and expires the timers in it that are due now \[the others are due a whole turn
+//! of the wheel or more later\].
+//!
+//! | | [TimerWheel] | `DelayQueue` |
+//! | --- | --- | --- |
+//! | insert | O(1), push to a slot | O(1), into a hierarchical wheel of 6 levels × 64 slots |
+//! | remove by [Key] | O(timers in the slot) | O(1), slab + intrusive list |
+//! | per tick | O(timers in one slot) \[scans timers of later turns too\] | O(expired) |
+//! | resolution | the tick, e.g. 1 ms | 1 ms |
+//! | clock | none, [TimerWheel::advance] by hand or [TimerWheel::expired] on tokio's | tokio's, via one [tokio::time::Sleep] |
+//!
+//! 10k timers with random delays up to 1 s, insert all then expire all \[`cargo bench -p
+//! async_stuff --bench timer_wheel`, release\]:
+//!
+//! | | time | per timer |
+//! | --- | --- | --- |
+//! | [TimerWheel], 1024 slots | 0.32 ms | 32 ns |
+//! | [TimerWheel], 64 slots | 0.33 ms | 33 ns |
+//! | [TimerWheel], 1 slot | 2.37 ms | 237 ns |
+//! | `DelayQueue` on a paused clock | 2.74 ms | 274 ns |
+//!
+//! The wheel is faster mostly for doing less: no waker, no slab, no runtime timer. Fewer slots
+//! mean rescanning later turns' timers every tick, cheap down to 64 here \[~160 per slot\] but
+//! with a single slot every tick scans all that is left.
+//!
+//! NB: One level only. Delays much longer than `slots × tick` need more slots or a hierarchy of
+//! wheels, like `DelayQueue`'s \[or the kernel's\].
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Handle of an inserted timer, to [TimerWheel::remove] it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    slot: usize,
+    id: u64,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    id: u64,
+    due: u64,
+    value: T,
+}
+
+#[derive(Debug)]
+pub struct TimerWheel<T> {
+    slots: Vec<Vec<Entry<T>>>,
+    tick: Duration,
+    /// Ticks done, everything due at or before it has expired
+    now: u64,
+    next_id: u64,
+    len: usize,
+    /// For [TimerWheel::expired], set on first use
+    start: Option<Instant>,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(tick: Duration, slots: usize) -> Self {
+        assert!(!tick.is_zero() && slots > 0);
+        Self {
+            slots: (0..slots).map(|_| Vec::new()).collect(),
+            tick,
+            now: 0,
+            next_id: 0,
+            len: 0,
+            start: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Expires after `delay`, rounded up to whole ticks and at least one
+    pub fn insert(&mut self, delay: Duration, value: T) -> Key {
+        let ticks = delay.as_nanos().div_ceil(self.tick.as_nanos()).max(1);
+        // Count from tokio's clock, `now` lags it while the wheel sits idle between expired()s
+        let base = self.now.max(self.elapsed());
+        if self.is_empty() {
+            // Nothing to scan in between
+            self.now = base;
+        }
+        let due = base.saturating_add(u64::try_from(ticks).unwrap_or(u64::MAX));
+        let slot = (due % self.slots.len() as u64) as usize;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots[slot].push(Entry { id, due, value });
+        self.len += 1;
+        Key { slot, id }
+    }
+
+    /// Whole ticks on tokio's clock since [TimerWheel::expired] started it, 0 before
+    fn elapsed(&self) -> u64 {
+        self.start.map_or(0, |start| {
+            let ticks = start.elapsed().as_nanos() / self.tick.as_nanos();
+            u64::try_from(ticks).unwrap_or(u64::MAX)
+        })
+    }
+
+    /// `None` if it already expired or was removed
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        let slot = &mut self.slots[key.slot];
+        let i = slot.iter().position(|e| e.id == key.id)?;
+        self.len -= 1;
+        Some(slot.swap_remove(i).value)
+    }
+
+    /// Move `ticks` ticks forward, pushing what expires to `expired` in order of the tick
+    pub fn advance(&mut self, ticks: u64, expired: &mut Vec<T>) {
+        for done in 0..ticks {
+            if self.len == 0 {
+                // Nothing to find, skip the scans of the ticks left
+                self.now = self.now.saturating_add(ticks - done);
+                return;
+            }
+            self.now += 1;
+            let now = self.now;
+            let i = (now % self.slots.len() as u64) as usize;
+            let slot = &mut self.slots[i];
+            let before = expired.len();
+            let mut j = 0;
+            while j < slot.len() {
+                if slot[j].due <= now {
+                    expired.push(slot.swap_remove(j).value);
+                } else {
+                    j += 1;
+                }
+            }
+            self.len -= expired.len() - before;
+        }
+    }
+
+    /// Wait on tokio's clock for the next tick with anything due, `None` once empty. The first
+    /// call starts the wheel's clock.
+    pub async fn expired(&mut self) -> Option<Vec<T>> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let mut expired = Vec::new();
+        while expired.is_empty() {
+            if self.is_empty() {
+                return None;
+            }
+            tokio::time::sleep_until(deadline(start, self.tick, self.now + 1)).await;
+            self.advance(1, &mut expired);
+        }
+        Some(expired)
+    }
+}
+
+/// When tick `n` is due, `start + tick × n` in u128 ns \[`Duration * u32` would cap `n` at 49.7
+/// days of 1 ms ticks\]
+fn deadline(start: Instant, tick: Duration, n: u64) -> Instant {
+    const NS: u128 = 1_000_000_000;
+    let ns = tick.as_nanos().saturating_mul(n.into());
+    let secs = u64::try_from(ns / NS).unwrap_or(u64::MAX);
+    let after = Duration::new(secs, (ns % NS) as u32);
+    // Past what Instant holds, as far as tokio's own far future: 30 years
+    start
+        .checked_add(after)
+        .unwrap_or_else(|| start + Duration::from_secs(86400 * 365 * 30))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use rand::{RngExt, SeedableRng, rngs::StdRng};
+    use tokio_util::time::DelayQueue;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_insert_advance_remove() {
+        let mut w = TimerWheel::new(MS, 8);
+        w.insert(3 * MS, "3");
+        // Same slot as 3, a turn later
+        w.insert(11 * MS, "11");
+        let gone = w.insert(5 * MS, "5");
+        // Rounded up to 1 tick
+        w.insert(Duration::from_micros(10), "0.01");
+        assert_eq!(w.remove(gone), Some("5"));
+        assert_eq!(w.remove(gone), None);
+
+        let mut out = Vec::new();
+        w.advance(3, &mut out);
+        assert_eq!(out, ["0.01", "3"]);
+        out.clear();
+        w.advance(7, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(w.len(), 1);
+        w.advance(1, &mut out);
+        assert_eq!(out, ["11"]);
+        assert!(w.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_advance_then_expired() {
+        let start = Instant::now();
+        let mut w = TimerWheel::new(MS, 8);
+        w.insert(MS, "1");
+        assert_eq!(w.expired().await.unwrap(), ["1"]);
+        w.insert(3 * MS, "4");
+        let mut out = Vec::new();
+        // Empty after 3 of the 10 ticks, still 10 ticks on
+        w.advance(10, &mut out);
+        assert_eq!(out, ["4"]);
+        assert_eq!(w.now, 11);
+        w.insert(5 * MS, "16");
+        assert_eq!(w.expired().await.unwrap(), ["16"]);
+        assert_eq!(start.elapsed(), 16 * MS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_after_idle() {
+        let mut w = TimerWheel::new(MS, 8);
+        w.insert(MS, "1");
+        assert_eq!(w.expired().await.unwrap(), ["1"]);
+        assert_eq!(w.expired().await, None);
+        tokio::time::sleep(10 * MS).await;
+        let at = Instant::now();
+        w.insert(5 * MS, "5");
+        assert_eq!(w.expired().await.unwrap(), ["5"]);
+        assert_eq!(at.elapsed(), 5 * MS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_past_u32_ticks() {
+        let mut w = TimerWheel::new(MS, 8);
+        w.insert(MS, "1");
+        assert_eq!(w.expired().await.unwrap(), ["1"]);
+        // Past u32::MAX ticks of 1 ms
+        tokio::time::advance(Duration::from_secs(50 * 86400)).await;
+        let at = Instant::now();
+        w.insert(5 * MS, "5");
+        assert_eq!(w.expired().await.unwrap(), ["5"]);
+        assert_eq!(at.elapsed(), 5 * MS);
+        assert!(w.now > u32::MAX.into());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_same_as_delay_queue() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let delays: Vec<u32> = (0..5_000).map(|_| rng.random_range(1..=3_000)).collect();
+
+        let start = Instant::now();
+        let mut wheel = TimerWheel::new(MS, 256);
+        let mut queue = DelayQueue::new();
+        for (i, &d) in delays.iter().enumerate() {
+            wheel.insert(d * MS, i);
+            queue.insert(i, d * MS);
+        }
+
+        let ms = || start.elapsed().as_millis() as u32;
+        let (mut from_wheel, mut from_queue) = tokio::join!(
+            async {
+                let mut out = Vec::new();
+                while let Some(batch) = wheel.expired().await {
+                    out.extend(batch.into_iter().map(|i| (ms(), i)));
+                }
+                out
+            },
+            async {
+                let mut out = Vec::new();
+                while let Some(expired) = queue.next().await {
+                    out.push((ms(), expired.into_inner()));
+                }
+                out
+            }
+        );
+        from_wheel.sort();
+        from_queue.sort();
+        assert_eq!(from_wheel.len(), delays.len());
+        assert_eq!(from_wheel, from_queue);
+        // Each exactly on time
+        assert!(from_wheel.iter().all(|&(at, i)| at == delays[i]));
+    }
+}
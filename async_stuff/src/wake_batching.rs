@@ -0,0 +1,260 @@
+//! Many producers, one consumer task: wake it per event or only when it isn't already notified
+//!
+//! | [Wake] | producer per event | consumer |
+//! | --- | --- | --- |
+//! | [Wake::PerEvent] | push + `wake_by_ref()` | polled once per wake-up \[on an executor that doesn't dedupe\] |
+//! | [Wake::Coalesced] | push + `wake_by_ref()` only if `notified.swap(true)` was `false` | resets `notified`, then drains everything queued so far |
+//!
+//! Counts for 4 producers × 100 events on [crate::mini_executor], polls and wakes of the consumer
+//! via [crate::metrics::instrument]:
+//!
+//! | | wakes | consumer polls | batches |
+//! | --- | --- | --- | --- |
+//! | [Wake::PerEvent] | 401 | 402 | 397 |
+//! | [Wake::Coalesced] | 101 | 102 | 100 |
+//!
+//! Per event, the mini executor queues the consumer once per wake, so it gets polled between
+//! single sends and drains batches of about one. Coalesced, it is queued once per round of the
+//! producers and drains all four. tokio dedupes scheduling itself \[a task that is already
+//! notified isn't queued again\], so there only the wake calls are saved: the `wake_batching`
+//! bin with 8 producer threads × 100k events makes 800001 vs 3 wakes and takes about half the
+//! time coalesced \[release, numbers vary with scheduling\].
+//!
+//! NB: The consumer must reset `notified` _before_ draining. Reset after draining and an event
+//! pushed in between finds `notified == true`, skips the wake and sits in the queue.
+
+use crate::metrics::Counter;
+use crate::poll_fn::poll_fn;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wake {
+    PerEvent,
+    Coalesced,
+}
+
+struct Shared {
+    mode: Wake,
+    queue: Mutex<VecDeque<u64>>,
+    waker: Mutex<Option<Waker>>,
+    notified: AtomicBool,
+    senders: AtomicUsize,
+    wakes: Counter,
+}
+
+impl Shared {
+    fn wake(&self) {
+        self.wakes.inc();
+        if let Some(waker) = &*self.waker.lock().unwrap() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+pub struct Sender(Arc<Shared>);
+
+pub struct Receiver(Arc<Shared>);
+
+pub fn channel(mode: Wake) -> (Sender, Receiver) {
+    let shared = Arc::new(Shared {
+        mode,
+        queue: Mutex::default(),
+        waker: Mutex::default(),
+        notified: AtomicBool::new(false),
+        senders: AtomicUsize::new(1),
+        wakes: Counter::default(),
+    });
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+impl Sender {
+    pub fn send(&self, event: u64) {
+        self.0.queue.lock().unwrap().push_back(event);
+        // AcqRel: pairs with the consumer's reset, so a `true` here means the consumer will
+        // drain after this push
+        if self.0.mode == Wake::Coalesced && self.0.notified.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.0.wake();
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Self {
+        self.0.senders.fetch_add(1, Ordering::Relaxed);
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        if self.0.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Last one, always wake so the consumer sees the end
+            self.0.notified.store(true, Ordering::Release);
+            self.0.wake();
+        }
+    }
+}
+
+impl Receiver {
+    /// Everything queued so far, `None` once all senders are gone and the queue is empty
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<u64>>> {
+        let shared = &*self.0;
+        // Register before looking, a send after the look then wakes the new waker
+        {
+            let mut waker = shared.waker.lock().unwrap();
+            match &mut *waker {
+                Some(w) => w.clone_from(cx.waker()),
+                None => *waker = Some(cx.waker().clone()),
+            }
+        }
+        shared.notified.store(false, Ordering::Release);
+        // Load before draining: a last sender that pushes and drops after the drain would
+        // otherwise read as closed with its event still queued. A 0 here means every push
+        // happened before, so the drain below sees them all.
+        let closed = shared.senders.load(Ordering::Acquire) == 0;
+        let batch: Vec<u64> = shared.queue.lock().unwrap().drain(..).collect();
+        if !batch.is_empty() {
+            return Poll::Ready(Some(batch));
+        }
+        if closed {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    }
+
+    pub async fn recv_batch(&mut self) -> Option<Vec<u64>> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Times a sender called `wake_by_ref()`
+    pub fn wakes(&self) -> usize {
+        self.0.wakes.get() as usize
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counts {
+    pub events: usize,
+    pub wakes: usize,
+    pub consumer_polls: usize,
+    pub batches: usize,
+}
+
+/// `producers` tasks sending `events` each, yielding after every send, and one consumer task,
+/// all on [crate::mini_executor]
+pub fn run_mini(mode: Wake, producers: u64, events: u64) -> Counts {
+    use crate::metrics::{Registry, instrument};
+    use crate::mini_executor::{Executor, yield_n};
+
+    let registry = Registry::new();
+    let mut ex = Executor::new();
+    let (tx, mut rx) = channel(mode);
+    let (received, batches) = (
+        registry.counter("consumer.events"),
+        registry.counter("consumer.batches"),
+    );
+    ex.spawn(instrument(&registry, "consumer", async move {
+        while let Some(batch) = rx.recv_batch().await {
+            received.add(batch.len() as u64);
+            batches.inc();
+        }
+    }));
+    for p in 0..producers {
+        let tx = tx.clone();
+        ex.spawn(async move {
+            for i in 0..events {
+                tx.send(p * events + i);
+                yield_n(1).await;
+            }
+        });
+    }
+    drop(tx);
+    ex.run();
+    assert_eq!(ex.pending(), 0, "consumer missed a wake-up");
+    let count = |name| registry.counter(name).get() as usize;
+    Counts {
+        events: count("consumer.events"),
+        wakes: count("consumer.wakes"),
+        consumer_polls: count("consumer.polls"),
+        batches: count("consumer.batches"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let per_event = run_mini(Wake::PerEvent, 4, 100);
+        let coalesced = run_mini(Wake::Coalesced, 4, 100);
+        assert_eq!(per_event.events, 400);
+        assert_eq!(coalesced.events, 400);
+        // One per send plus the close
+        assert_eq!(per_event.wakes, 401);
+        // One per round of the four producers plus the close
+        assert_eq!(coalesced.wakes, 101);
+        // One per wake plus the first
+        assert_eq!(per_event.consumer_polls, 402);
+        assert_eq!(coalesced.consumer_polls, 102);
+        assert_eq!(coalesced.batches, 100);
+        assert!(per_event.batches > 3 * coalesced.batches);
+    }
+
+    #[test]
+    fn test_threads() {
+        for mode in [Wake::PerEvent, Wake::Coalesced] {
+            let (tx, mut rx) = channel(mode);
+            let producers: Vec<_> = (0..4)
+                .map(|p| {
+                    let tx = tx.clone();
+                    std::thread::spawn(move || (0..10_000).for_each(|i| tx.send(p * 10_000 + i)))
+                })
+                .collect();
+            drop(tx);
+            let received = futures::executor::block_on(async {
+                let mut all = Vec::new();
+                while let Some(batch) = rx.recv_batch().await {
+                    all.extend(batch);
+                }
+                all
+            });
+            producers.into_iter().for_each(|p| p.join().unwrap());
+            let mut sorted = received.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..40_000).collect::<Vec<_>>(), "{mode:?}");
+            if mode == Wake::PerEvent {
+                assert_eq!(rx.wakes(), 40_001);
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_then_drop() {
+        for mode in [Wake::PerEvent, Wake::Coalesced] {
+            for i in 0..10_000 {
+                let (tx, mut rx) = channel(mode);
+                let producer = tokio::spawn(async move {
+                    tx.send(i);
+                });
+                // Spin instead of awaiting, so the receiver is mid-poll when the send lands
+                let mut received = Vec::new();
+                let waker = futures::task::noop_waker();
+                let mut cx = Context::from_waker(&waker);
+                loop {
+                    match rx.poll_recv(&mut cx) {
+                        Poll::Ready(Some(batch)) => received.extend(batch),
+                        Poll::Ready(None) => break,
+                        Poll::Pending => std::hint::spin_loop(),
+                    }
+                }
+                producer.await.unwrap();
+                assert_eq!(received, [i], "{mode:?}");
+            }
+        }
+    }
+}
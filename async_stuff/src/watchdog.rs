@@ -0,0 +1,232 @@
+//! Finding the task that doesn't yield: a watchdog thread that reports tasks stuck in one `poll`
+//!
+//! A task that blocks \[`std::thread::sleep`, a sync lock, CPU-bound work\] inside `poll` stalls
+//! its worker thread, and on a current-thread runtime everything else with it. Nothing fails,
+//! other tasks just run late. [Watchdog::track] wraps a future to record when each of its polls
+//! starts and ends, and the watchdog reports a poll still running after `threshold`:
+//!
+//! | | sees the stall | |
+//! | --- | --- | --- |
+//! | [Watchdog] \[a thread\] | while it happens | names the task and how long it has been in `poll` |
+//! | a watchdog task | on a multi-thread runtime, if a worker is free | stalled along with the rest on a current thread runtime |
+//! | `Handle::dump()` | on demand | every task's backtrace at an await point, needs `--cfg tokio_unstable` and the `taskdump` feature \[Linux only\] |
+//!
+//! ```sh
+//! cargo run -p async_stuff --bin watchdog
+//! ```
+//!
+//! NB: Only tracked futures are watched, a stall in anything else shows up as other tasks'
+//! lateness only.
+
+use pin_project_lite::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A poll that has been running for longer than the threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stall {
+    pub task: String,
+    /// 1-based, of the task's polls
+    pub poll: u64,
+    /// In `poll` when the watchdog noticed
+    pub blocked_for: Duration,
+}
+
+/// Per tracked task, once the watchdog has stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStats {
+    pub name: String,
+    pub polls: u64,
+    pub longest_poll: Duration,
+}
+
+#[derive(Debug)]
+struct TaskState {
+    name: String,
+    polls: u64,
+    /// Set while in `poll`
+    polling_since: Option<Instant>,
+    longest_poll: Duration,
+    /// The poll already reported, each once
+    reported: u64,
+}
+
+type Tasks = Arc<Mutex<Vec<TaskState>>>;
+
+pub struct Watchdog {
+    tasks: Tasks,
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<Vec<Stall>>>,
+}
+
+impl Watchdog {
+    /// Checks every `check_every` for polls longer than `threshold`, calling `on_stall` from its
+    /// own thread for each
+    pub fn start(
+        threshold: Duration,
+        check_every: Duration,
+        on_stall: impl Fn(&Stall) + Send + 'static,
+    ) -> Self {
+        let tasks = Tasks::default();
+        let (stop, stopped) = mpsc::channel();
+        let watched = tasks.clone();
+        let thread = thread::spawn(move || {
+            let mut stalls = Vec::new();
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(check_every) {
+                let now = Instant::now();
+                let seen = stalls.len();
+                for task in watched.lock().unwrap().iter_mut() {
+                    let Some(since) = task.polling_since else {
+                        continue;
+                    };
+                    let blocked_for = now - since;
+                    if blocked_for >= threshold && task.reported < task.polls {
+                        task.reported = task.polls;
+                        stalls.push(Stall {
+                            task: task.name.clone(),
+                            poll: task.polls,
+                            blocked_for,
+                        });
+                    }
+                }
+                // Unlocked, so polls that end meanwhile and track() don't wait for on_stall
+                stalls[seen..].iter().for_each(&on_stall);
+            }
+            stalls
+        });
+        Self {
+            tasks,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn track<F: Future>(&self, name: impl Into<String>, future: F) -> Tracked<F> {
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.push(TaskState {
+            name: name.into(),
+            polls: 0,
+            polling_since: None,
+            longest_poll: Duration::ZERO,
+            reported: 0,
+        });
+        Tracked {
+            future,
+            tasks: self.tasks.clone(),
+            index: tasks.len() - 1,
+        }
+    }
+
+    /// Stops the thread, returning every stall it saw and the stats of each tracked task
+    pub fn stop(mut self) -> (Vec<Stall>, Vec<TaskStats>) {
+        let stalls = self.join();
+        let stats = self
+            .tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|t| TaskStats {
+                name: t.name.clone(),
+                polls: t.polls,
+                longest_poll: t.longest_poll,
+            })
+            .collect();
+        (stalls, stats)
+    }
+
+    fn join(&mut self) -> Vec<Stall> {
+        drop(self.stop.take());
+        match self.thread.take() {
+            Some(thread) => thread.join().expect("watchdog panicked"),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+pin_project! {
+    /// See [Watchdog::track]
+    pub struct Tracked<F> {
+        #[pin]
+        future: F,
+        tasks: Tasks,
+        index: usize,
+    }
+}
+
+impl<F: Future> Future for Tracked<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        let start = Instant::now();
+        {
+            let mut tasks = this.tasks.lock().unwrap();
+            let task = &mut tasks[*this.index];
+            task.polls += 1;
+            task.polling_since = Some(start);
+        }
+        let poll = this.future.poll(cx);
+        let mut tasks = this.tasks.lock().unwrap();
+        let task = &mut tasks[*this.index];
+        task.polling_since = None;
+        task.longest_poll = task.longest_poll.max(start.elapsed());
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[tokio::test]
+    async fn test_reports_the_blocking_task() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen2 = seen.clone();
+        let watchdog = Watchdog::start(100 * MS, 5 * MS, move |s| {
+            seen2.lock().unwrap().push(s.task.clone())
+        });
+        let good = tokio::spawn(watchdog.track("good", async {
+            for _ in 0..10 {
+                tokio::time::sleep(20 * MS).await;
+            }
+        }));
+        let hazard = tokio::spawn(watchdog.track("hazard", async {
+            tokio::task::yield_now().await;
+            std::thread::sleep(300 * MS);
+        }));
+        good.await.unwrap();
+        hazard.await.unwrap();
+
+        let (stalls, stats) = watchdog.stop();
+        assert_eq!(*seen.lock().unwrap(), ["hazard"]);
+        // Reported while still blocked, in the poll after the yield. The good task can be
+        // late on a busy machine, but never in poll for long itself.
+        assert!(!stalls.is_empty());
+        assert!(stalls.iter().all(|s| s.task == "hazard" && s.poll == 2));
+        assert!(stalls.iter().all(|s| s.blocked_for >= 100 * MS));
+
+        assert_eq!(stats[1].polls, 2);
+        assert!(stats[1].longest_poll >= 300 * MS);
+    }
+
+    #[test]
+    fn test_drop_stops_the_thread() {
+        let watchdog = Watchdog::start(MS, MS, |_| {});
+        let tracked = watchdog.track("never polled", async {});
+        drop(watchdog);
+        drop(tracked);
+    }
+}
@@ -0,0 +1,97 @@
+//! Exit codes and stderr of the bins, see [simple::exit]
+
+use simple::exit::Code;
+use std::process::{Command, Output};
+
+fn run(bin: &str, args: &[&str], env: &[(&str, &str)]) -> (Option<i32>, String, String) {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = Command::new(bin)
+        .args(args)
+        .envs(env.iter().copied())
+        .output()
+        .unwrap();
+    let text = |b| String::from_utf8(b).unwrap();
+    (status.code(), text(stdout), text(stderr))
+}
+
+#[test]
+fn test_checksum() {
+    let bin = env!("CARGO_BIN_EXE_checksum");
+    let (code, _, stderr) = run(bin, &[], &[]);
+    assert_eq!(code, Some(Code::Usage as i32));
+    assert_eq!(stderr, "error: usage: checksum <file>\n");
+
+    let (code, _, stderr) = run(bin, &["/nonexistent"], &[]);
+    assert_eq!(code, Some(Code::NoInput as i32));
+    assert!(
+        stderr.starts_with("error: /nonexistent\n  caused by: "),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_framing() {
+    let bin = env!("CARGO_BIN_EXE_framing");
+    assert_eq!(run(bin, &[], &[]).0, Some(0));
+    // Not frames
+    let (code, _, stderr) = run(
+        bin,
+        &[concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")],
+        &[],
+    );
+    assert_eq!(code, Some(Code::DataErr as i32));
+    assert!(stderr.contains("caused by: BadMagic"), "{stderr}");
+}
+
+#[test]
+fn test_demos() {
+    let bin = env!("CARGO_BIN_EXE_demos");
+    let (code, stdout, stderr) = run(bin, &["--reads", "0", "v2"], &[]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("v2: "), "{stdout}");
+    assert_eq!(stderr, "");
+
+    // Help goes to stdout and isn't an error
+    let (code, stdout, _) = run(bin, &["--help"], &[]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("--reads"), "{stdout}");
+
+    // Allocations counted, without reads nothing to wait for
+    let (code, stdout, stderr) = run(bin, &["--check", "--reads", "0"], &[]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert_eq!(stdout.lines().count(), 4, "{stdout}");
+
+    let (code, _, stderr) = run(bin, &["--reeds", "1"], &[]);
+    assert_eq!(code, Some(Code::Usage as i32));
+    assert!(stderr.starts_with("error: "), "{stderr}");
+
+    let (code, _, stderr) = run(bin, &["v9"], &[]);
+    assert_eq!(code, Some(Code::Usage as i32));
+    assert!(stderr.starts_with("error: unknown demo \"v9\""), "{stderr}");
+
+    let (code, _, stderr) = run(bin, &[], &[("DEMOS_READS", "x")]);
+    assert_eq!(code, Some(Code::Config as i32));
+    assert!(stderr.starts_with("error: DEMOS_READS=\"x\""), "{stderr}");
+
+    let (code, _, stderr) = run(bin, &[], &[("DEMOS_CONFIG", "/nonexistent.toml")]);
+    assert_eq!(code, Some(Code::Config as i32));
+    assert!(
+        stderr.starts_with("error: /nonexistent.toml\n  caused by: "),
+        "{stderr}"
+    );
+
+    // ./demos.toml may be missing, but not unreadable
+    let dir = std::env::temp_dir().join(format!("demos-cwd-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("demos.toml")).unwrap();
+    let Output { status, stderr, .. } = Command::new(bin).current_dir(&dir).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let stderr = String::from_utf8(stderr).unwrap();
+    assert_eq!(status.code(), Some(Code::Config as i32));
+    assert!(
+        stderr.starts_with("error: demos.toml\n  caused by: "),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,55 @@
+//! Allocation counts of the throttling wrappers under dhat \[only with `--features dhat-heap`\]
+//!
+//! Its own test binary: dhat needs to be the `#[global_allocator]` and only one
+//! [dhat::Profiler] may run at a time, so there is a single `#[test]`. A failing
+//! `dhat::assert_eq!` writes `dhat-heap.json` for <https://nnethercote.github.io/dh_view/dh_view.html>.
+
+use async_stuff::fasterthanlime_pin::{v3, v4, v5};
+use std::pin::pin;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+/// Heap blocks allocated while running `f`
+fn blocks<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    let before = dhat::HeapStats::get().total_blocks;
+    let t = f();
+    (t, dhat::HeapStats::get().total_blocks - before)
+}
+
+async fn read_twice(read: impl AsyncRead) {
+    let mut read = pin!(read);
+    let mut buf = [0u8; 5];
+    read.read_exact(&mut buf).await.unwrap();
+    read.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"world");
+}
+
+#[test]
+fn test_allocations() {
+    let _profiler = dhat::Profiler::builder().testing().build();
+    // Built before counting, the runtime itself allocates
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+
+    // v3 boxes both the reader and the sleep
+    let (r3, n) = blocks(|| v3::ReadWrap::new(&b"helloworld"[..]));
+    dhat::assert_eq!(n, 2);
+    let (r4, n) = blocks(|| v4::ReadWrap::new(&b"helloworld"[..]));
+    dhat::assert_eq!(n, 0);
+    let (r5, n) = blocks(|| v5::ReadWrap::new(&b"helloworld"[..]));
+    dhat::assert_eq!(n, 0);
+
+    // Resetting the sleep reuses its timer entry, so reads don't allocate in any version
+    let ((), n) = blocks(|| rt.block_on(read_twice(r3)));
+    dhat::assert_eq!(n, 0);
+    let ((), n) = blocks(|| rt.block_on(read_twice(r4)));
+    dhat::assert_eq!(n, 0);
+    let ((), n) = blocks(|| rt.block_on(read_twice(r5)));
+    dhat::assert_eq!(n, 0);
+}
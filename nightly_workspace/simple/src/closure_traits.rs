@@ -0,0 +1,145 @@
+//! Hand-written versions of what the compiler generates for closures
+//!
+//! A closure is an anonymous struct holding its captures plus an impl of [Fn], [FnMut] and/or
+//! [FnOnce] \[whichever ones the body allows\]. Which trait depends on what the body does with
+//! the captures, _not_ on how they are captured (`move` or not):
+//!
+//! | body | trait | `call*` receiver |
+//! | --- | --- | --- |
+//! | only reads captures | [Fn] (and FnMut + FnOnce) | `&self` |
+//! | mutates captures | [FnMut] (and FnOnce) | `&mut self` |
+//! | moves captures out | [FnOnce] only | `self` |
+//!
+//! See `closures.rs` in the stable workspace for the capture modes side.
+//!
+//! re: [Closure types](https://doc.rust-lang.org/reference/types/closure.html)
+
+/// Same as `move |name: &str| format!("{greeting}, {name}")`
+pub struct Greeter {
+    pub greeting: String,
+}
+
+impl FnOnce<(&str,)> for Greeter {
+    type Output = String;
+
+    extern "rust-call" fn call_once(self, args: (&str,)) -> Self::Output {
+        self.call(args)
+    }
+}
+
+impl FnMut<(&str,)> for Greeter {
+    extern "rust-call" fn call_mut(&mut self, args: (&str,)) -> Self::Output {
+        self.call(args)
+    }
+}
+
+impl Fn<(&str,)> for Greeter {
+    /// Only reads `self.greeting` => can be called through a shared reference
+    /// ```
+    /// use simple::closure_traits::Greeter;
+    /// let greet = Greeter { greeting: "hi".to_string() };
+    /// let by_ref = &greet;
+    /// assert_eq!(by_ref("ann"), "hi, ann");
+    /// assert_eq!(greet("bob"), "hi, bob");
+    /// ```
+    extern "rust-call" fn call(&self, (name,): (&str,)) -> Self::Output {
+        format!("{}, {name}", self.greeting)
+    }
+}
+
+/// Same as `|| { *count += 1; *count }` where `count: &mut u32` is captured by unique borrow
+pub struct Counter<'a> {
+    pub count: &'a mut u32,
+}
+
+impl FnOnce<()> for Counter<'_> {
+    type Output = u32;
+
+    extern "rust-call" fn call_once(mut self, args: ()) -> Self::Output {
+        self.call_mut(args)
+    }
+}
+
+impl FnMut<()> for Counter<'_> {
+    /// Mutates a capture => needs `&mut self` so it is _not_ [Fn]
+    /// ```
+    /// use simple::closure_traits::Counter;
+    /// let mut count = 0;
+    /// let mut next = Counter { count: &mut count };
+    /// assert_eq!(next(), 1);
+    /// assert_eq!(next(), 2);
+    /// assert_eq!(count, 2);
+    /// ```
+    ///
+    /// ```compile_fail,E0596
+    /// use simple::closure_traits::Counter;
+    /// let mut count = 0;
+    /// let next = Counter { count: &mut count };
+    /// next(); // error[E0596]: cannot borrow `next` as mutable, as it is not declared as mutable
+    /// ```
+    extern "rust-call" fn call_mut(&mut self, _args: ()) -> Self::Output {
+        *self.count += 1;
+        *self.count
+    }
+}
+
+/// Same as `move || names` \[returns the captured Vec itself\]
+pub struct Consumer {
+    pub names: Vec<String>,
+}
+
+impl FnOnce<()> for Consumer {
+    type Output = Vec<String>;
+
+    /// Moves a capture out => consumes `self` so it can only be called once
+    /// ```
+    /// use simple::closure_traits::Consumer;
+    /// let take = Consumer { names: vec!["ann".to_string()] };
+    /// assert_eq!(take(), ["ann"]);
+    /// ```
+    ///
+    /// ```compile_fail,E0382
+    /// use simple::closure_traits::Consumer;
+    /// let take = Consumer { names: vec!["ann".to_string()] };
+    /// take();
+    /// take(); // error[E0382]: use of moved value: `take`
+    /// ```
+    extern "rust-call" fn call_once(self, _args: ()) -> Self::Output {
+        self.names
+    }
+}
+
+/// Hand-written structs are accepted wherever closures are
+pub fn call_twice<F: FnMut() -> u32>(mut f: F) -> u32 {
+    f() + f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_generic_fn() {
+        let greet = Greeter {
+            greeting: "hello".to_string(),
+        };
+        let names: Vec<String> = ["ann", "bob"].into_iter().map(&greet).collect();
+        assert_eq!(names, ["hello, ann", "hello, bob"]);
+
+        let mut count = 0;
+        assert_eq!(call_twice(Counter { count: &mut count }), 1 + 2);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_as_dyn() {
+        let callbacks: Vec<Box<dyn FnOnce() -> Vec<String>>> = vec![
+            Box::new(Consumer {
+                names: vec!["ann".to_string()],
+            }),
+            Box::new(|| vec!["bob".to_string()]),
+        ];
+        let all: Vec<String> = callbacks.into_iter().flat_map(|f| f()).collect();
+        assert_eq!(all, ["ann", "bob"]);
+    }
+}
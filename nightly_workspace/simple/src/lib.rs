@@ -7,7 +7,6 @@
 //         |
 //         = help: add `#![feature(unboxed_closures)]` to the crate attributes to enable
 #![feature(unboxed_closures)]
-
 // Use nightly-only feature to silence error in memoize_fn.rs:
 //      error[E0658]: use of unstable library feature `fn_traits`
 //        --> simple/src/memoize_fn.rs:52:5
@@ -20,4 +19,5 @@
 //         = note: this compiler was built on 2026-03-19; consider upgrading it if it is out of date
 #![feature(fn_traits)]
 
+pub mod closure_traits;
 pub mod memoize_fn;
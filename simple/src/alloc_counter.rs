@@ -0,0 +1,154 @@
+//! Counting global allocator to make heap allocations visible
+//!
+//! Install it in a binary (or test crate) with
+//! ```
+//! use simple::alloc_counter::CountingAlloc;
+//!
+//! #[global_allocator]
+//! static GLOBAL: CountingAlloc = CountingAlloc;
+//! ```
+//! then diff two snapshots around the code of interest:
+//! ```
+//! # use simple::alloc_counter::{self, CountingAlloc};
+//! # #[global_allocator]
+//! # static GLOBAL: CountingAlloc = CountingAlloc;
+//! let before = alloc_counter::thread_snapshot();
+//! let v = vec![1u8; 100];
+//! let after = alloc_counter::thread_snapshot();
+//! assert_eq!(after.since(&before).allocs, 1);
+//! assert_eq!(after.since(&before).live_bytes(), 100);
+//! # drop(v);
+//! ```
+//!
+//! Counts are kept both process-wide ([snapshot()]) and per thread ([thread_snapshot()]).
+//! The per-thread numbers are what tests should use as other tests run in parallel threads.
+//!
+//! NB: Without `#[global_allocator]` all counts stay at zero, [installed()] tells.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Delegates to [System] and counts every call
+pub struct CountingAlloc;
+
+/// Counters at a point in time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub allocs: u64,
+    pub deallocs: u64,
+    pub bytes_allocated: u64,
+    pub bytes_deallocated: u64,
+}
+
+impl Stats {
+    /// Difference between `self` and an `earlier` snapshot
+    pub fn since(&self, earlier: &Stats) -> Stats {
+        Stats {
+            allocs: self.allocs - earlier.allocs,
+            deallocs: self.deallocs - earlier.deallocs,
+            bytes_allocated: self.bytes_allocated - earlier.bytes_allocated,
+            bytes_deallocated: self.bytes_deallocated - earlier.bytes_deallocated,
+        }
+    }
+
+    /// Bytes allocated but not (yet) deallocated. Negative if more was freed than allocated.
+    pub fn live_bytes(&self) -> i64 {
+        self.bytes_allocated as i64 - self.bytes_deallocated as i64
+    }
+}
+
+static ALLOCS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCS: AtomicU64 = AtomicU64::new(0);
+static BYTES_ALLOCATED: AtomicU64 = AtomicU64::new(0);
+static BYTES_DEALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // NB: Must be const-initialized and !Drop so that touching it from inside the allocator
+    // never allocates [which would recurse forever].
+    static THREAD_STATS: Cell<Stats> = const {
+        Cell::new(Stats {
+            allocs: 0,
+            deallocs: 0,
+            bytes_allocated: 0,
+            bytes_deallocated: 0,
+        })
+    };
+}
+
+fn record_alloc(size: usize) {
+    ALLOCS.fetch_add(1, Ordering::Relaxed);
+    BYTES_ALLOCATED.fetch_add(size as u64, Ordering::Relaxed);
+    // try_with => ignore allocations during thread teardown
+    let _ = THREAD_STATS.try_with(|s| {
+        let mut stats = s.get();
+        stats.allocs += 1;
+        stats.bytes_allocated += size as u64;
+        s.set(stats);
+    });
+}
+
+fn record_dealloc(size: usize) {
+    DEALLOCS.fetch_add(1, Ordering::Relaxed);
+    BYTES_DEALLOCATED.fetch_add(size as u64, Ordering::Relaxed);
+    let _ = THREAD_STATS.try_with(|s| {
+        let mut stats = s.get();
+        stats.deallocs += 1;
+        stats.bytes_deallocated += size as u64;
+        s.set(stats);
+    });
+}
+
+// SAFETY: All real work is delegated to System which upholds the GlobalAlloc contract
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_alloc(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record_dealloc(layout.size());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_alloc(layout.size());
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    /// Counted as a dealloc of the old size plus an alloc of the new size
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_dealloc(layout.size());
+        record_alloc(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Process-wide counters
+pub fn snapshot() -> Stats {
+    Stats {
+        allocs: ALLOCS.load(Ordering::Relaxed),
+        deallocs: DEALLOCS.load(Ordering::Relaxed),
+        bytes_allocated: BYTES_ALLOCATED.load(Ordering::Relaxed),
+        bytes_deallocated: BYTES_DEALLOCATED.load(Ordering::Relaxed),
+    }
+}
+
+/// Counters for the current thread only
+pub fn thread_snapshot() -> Stats {
+    THREAD_STATS.with(Cell::get)
+}
+
+/// Whether [CountingAlloc] is the `#[global_allocator]`, so counts mean something, by watching
+/// a probe allocation
+/// ```
+/// # use simple::alloc_counter::{self, CountingAlloc};
+/// # #[global_allocator]
+/// # static GLOBAL: CountingAlloc = CountingAlloc;
+/// assert!(alloc_counter::installed());
+/// ```
+pub fn installed() -> bool {
+    let before = thread_snapshot();
+    drop(std::hint::black_box(Box::new(0u8)));
+    thread_snapshot().since(&before).allocs > 0
+}
@@ -0,0 +1,206 @@
+//! Track criterion results across commits and flag regressions
+//!
+//! criterion only remembers the previous run \[`base/` vs `new/`\]. The `bench-report` bin keeps
+//! one [Report] per commit in a JSON file so any two commits can be compared:
+//! ```sh
+//! cargo bench --workspace
+//! cargo run --bin bench-report -- record     # add results of HEAD to bench-history.json
+//! cargo run --bin bench-report -- compare    # last two commits, exit code 1 on regression
+//! cargo run --bin bench-report -- compare --threshold 5 --store /tmp/history.json
+//! ```
+//!
+//! | file read | field used |
+//! | --- | --- |
+//! | `target/criterion/**/new/benchmark.json` | `full_id`, e.g. `sum/plain` |
+//! | `target/criterion/**/new/estimates.json` | `mean.point_estimate` in ns |
+//!
+//! NB: Benchmarks are noisy. A threshold below the noise \[a few % on a laptop\] flags
+//! regressions that aren't there, so compare runs from the same machine and keep it idle.
+
+use crate::report::Report;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+/// Mean time in ns per benchmark id
+pub type Results = BTreeMap<String, f64>;
+
+/// All `new/` results below `dir` \[usually `target/criterion`\]
+pub fn read_criterion(dir: &Path) -> io::Result<Results> {
+    let mut results = Results::new();
+    visit(dir, &mut results)?;
+    Ok(results)
+}
+
+fn visit(dir: &Path, results: &mut Results) -> io::Result<()> {
+    let new = dir.join("new");
+    if new.join("estimates.json").is_file() {
+        let bench: Value = read_json(&new.join("benchmark.json"))?;
+        let estimates: Value = read_json(&new.join("estimates.json"))?;
+        if let (Some(id), Some(mean)) = (
+            bench["full_id"].as_str(),
+            estimates["mean"]["point_estimate"].as_f64(),
+        ) {
+            results.insert(id.to_string(), mean);
+        }
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        // Skip criterion's html `report` dirs
+        if path.is_dir() && !path.ends_with("report") {
+            visit(&path, results)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(io::Error::other)
+}
+
+/// One [Report] per commit, oldest first. `title` is the commit, items are `id => mean ns`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Store {
+    pub runs: Vec<Report>,
+}
+
+impl Store {
+    /// Empty store if the file doesn't exist yet
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// Add or replace \[rerunning on the same commit\] the results of `commit`
+    pub fn record(&mut self, commit: &str, results: &Results) {
+        self.runs.retain(|r| r.title != commit);
+        let mut report = Report::new(commit);
+        for (id, mean) in results {
+            report.push(id, mean);
+        }
+        report.finish();
+        self.runs.push(report);
+    }
+
+    pub fn results(&self, commit: &str) -> Option<Results> {
+        let run = self.runs.iter().find(|r| r.title == commit)?;
+        Some(to_results(run))
+    }
+
+    /// Last two runs as `(older, newer)`
+    pub fn last_two(&self) -> Option<(&str, Results, &str, Results)> {
+        let [.., a, b] = self.runs.as_slice() else {
+            return None;
+        };
+        Some((&a.title, to_results(a), &b.title, to_results(b)))
+    }
+}
+
+fn to_results(run: &Report) -> Results {
+    run.items
+        .iter()
+        .filter_map(|item| Some((item.label.clone(), item.value.as_f64()?)))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub id: String,
+    pub before: f64,
+    pub after: f64,
+    /// `after / before - 1` in percent, positive is slower. `None` if `before` is no time to
+    /// compare with \[0, negative or not finite, e.g. from a hand-edited store\].
+    pub percent: Option<f64>,
+    /// Never without `percent`
+    pub regression: bool,
+}
+
+/// Benchmarks present in both, regression if slower by more than `threshold` percent
+pub fn compare(before: &Results, after: &Results, threshold: f64) -> Vec<Change> {
+    before
+        .iter()
+        .filter_map(|(id, &b)| {
+            let a = *after.get(id)?;
+            let percent = (b.is_finite() && b > 0.0).then(|| (a / b - 1.0) * 100.0);
+            Some(Change {
+                id: id.clone(),
+                before: b,
+                after: a,
+                percent,
+                regression: percent.is_some_and(|p| p > threshold),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(pairs: &[(&str, f64)]) -> Results {
+        pairs.iter().map(|(id, ns)| (id.to_string(), *ns)).collect()
+    }
+
+    #[test]
+    fn test_compare() {
+        let before = results(&[("a", 100.0), ("b", 100.0), ("gone", 1.0)]);
+        let after = results(&[("a", 105.0), ("b", 125.0), ("new", 1.0)]);
+        let changes = compare(&before, &after, 10.0);
+        assert_eq!(changes.len(), 2);
+        assert!(!changes[0].regression);
+        assert!(changes[1].regression);
+        assert!((changes[1].percent.unwrap() - 25.0).abs() < 1e-9);
+
+        // No inf or NaN from a zero baseline, and no regression either
+        let zero = compare(&results(&[("a", 0.0)]), &results(&[("a", 5.0)]), 10.0);
+        assert_eq!((zero[0].percent, zero[0].regression), (None, false));
+    }
+
+    #[test]
+    fn test_store() {
+        let path = std::env::temp_dir().join(format!("bench-store-{}.json", std::process::id()));
+        let mut store = Store::load(&path).unwrap();
+        assert!(store.last_two().is_none());
+        store.record("c1", &results(&[("a", 100.0)]));
+        store.record("c2", &results(&[("a", 90.0)]));
+        // Rerun on c2 replaces it
+        store.record("c2", &results(&[("a", 120.0)]));
+        store.save(&path).unwrap();
+
+        let store = Store::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(store.runs.len(), 2);
+        let (old, before, new, after) = store.last_two().unwrap();
+        assert_eq!((old, new), ("c1", "c2"));
+        assert!(compare(&before, &after, 10.0)[0].regression);
+        assert_eq!(store.results("c1"), Some(before));
+    }
+
+    #[test]
+    fn test_read_criterion() {
+        let dir = std::env::temp_dir().join(format!("criterion-{}", std::process::id()));
+        let new = dir.join("sum/plain/new");
+        fs::create_dir_all(&new).unwrap();
+        fs::create_dir_all(dir.join("sum/report")).unwrap();
+        fs::write(new.join("benchmark.json"), r#"{"full_id": "sum/plain"}"#).unwrap();
+        fs::write(
+            new.join("estimates.json"),
+            r#"{"mean": {"point_estimate": 538.1}}"#,
+        )
+        .unwrap();
+        let results = read_criterion(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(results, self::results(&[("sum/plain", 538.1)]));
+    }
+}
@@ -0,0 +1,107 @@
+//! Store criterion results per git commit and flag regressions. See [simple::bench_report].
+
+use simple::bench_report::{Store, compare, read_criterion};
+use simple::exit::{self, Code, Exit};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+
+const USAGE: &str =
+    "usage: bench-report [record|compare] [--store FILE] [--criterion DIR] [--threshold PERCENT]";
+
+struct Args {
+    command: String,
+    store: PathBuf,
+    criterion: PathBuf,
+    threshold: f64,
+}
+
+fn parse_args() -> Result<Args, Exit> {
+    parse(std::env::args().skip(1)).map_err(|e| Exit::usage(format!("{e}\n{USAGE}")))
+}
+
+fn parse(mut it: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = Args {
+        command: "compare".into(),
+        store: "bench-history.json".into(),
+        criterion: "target/criterion".into(),
+        threshold: 10.0,
+    };
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "record" | "compare" => args.command = arg,
+            "--store" => args.store = value()?.into(),
+            "--criterion" => args.criterion = value()?.into(),
+            "--threshold" => {
+                let threshold: f64 = value()?.parse().map_err(|e| format!("--threshold: {e}"))?;
+                // NaN would compare false against everything, turning off detection
+                if !threshold.is_finite() {
+                    return Err(format!(
+                        "--threshold: expected a finite number, got {threshold}"
+                    ));
+                }
+                args.threshold = threshold;
+            }
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(args)
+}
+
+/// Short hash of HEAD, with `-dirty` if there are uncommitted changes
+fn git_commit() -> Result<String, Exit> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .map_err(|e| Exit::new(Code::Unavailable, Exit::context("git", e)))
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    let hash = git(&["rev-parse", "--short", "HEAD"])?;
+    if hash.is_empty() {
+        return Err(Exit::new(Code::Usage, "not in a git repository"));
+    }
+    let dirty = !git(&["status", "--porcelain", "--untracked-files=no"])?.is_empty();
+    Ok(if dirty { format!("{hash}-dirty") } else { hash })
+}
+
+fn run(args: Args) -> Result<(), Exit> {
+    let in_store = |e| Exit::context(args.store.display(), e);
+    let mut store = Store::load(&args.store).map_err(in_store)?;
+    if args.command == "record" {
+        let results = read_criterion(&args.criterion)
+            .map_err(|e| Exit::context(args.criterion.display(), e))?;
+        let commit = git_commit()?;
+        println!("recorded {} benchmarks for {commit}", results.len());
+        store.record(&commit, &results);
+        return store.save(&args.store).map_err(in_store);
+    }
+
+    let Some((old, before, new, after)) = store.last_two() else {
+        return Err(Exit::new(
+            Code::NoInput,
+            format!("need two recorded runs in {}", args.store.display()),
+        ));
+    };
+    println!("{old} -> {new} (threshold {}%)", args.threshold);
+    let changes = compare(&before, &after, args.threshold);
+    for c in &changes {
+        let flag = if c.regression { "REGRESSION" } else { "" };
+        let percent = match c.percent {
+            Some(p) => format!("{p:>+7.1}%"),
+            None => "no baseline".into(),
+        };
+        println!(
+            "  {:<30} {:>12.1} ns {:>12.1} ns {percent} {flag}",
+            c.id, c.before, c.after
+        );
+    }
+    match changes.iter().filter(|c| c.regression).count() {
+        0 => Ok(()),
+        n => Err(Exit::failure(format!("{n} regression(s)"))),
+    }
+}
+
+fn main() -> ExitCode {
+    exit::report(parse_args().and_then(run))
+}
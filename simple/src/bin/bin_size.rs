@@ -0,0 +1,230 @@
+//! Binary size analysis: build demos under different profiles and compare sizes
+//!
+//! Each variant is the `release` profile plus some overrides passed via `cargo build --config ...`
+//! \[so no extra profiles are needed in `Cargo.toml`\]. Each variant gets its own `--target-dir`
+//! so they don't clobber each other.
+//!
+//! The symbol breakdown parses `nm --print-size --size-sort` output and groups symbols by crate,
+//! similar to what [cargo-bloat](https://github.com/RazrFalcon/cargo-bloat) reports.
+//!
+//! ```sh
+//! # default targets
+//! cargo run --bin bin_size
+//!
+//! # specific targets as <package>:<bin>
+//! cargo run --bin bin_size -- async_stuff:fasterthanlime_pin
+//! ```
+//!
+//! re: [min-sized-rust](https://github.com/johnthagen/min-sized-rust)
+
+use simple::exit::{self, Exit};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+/// Profile overrides on top of `--release`
+struct Variant {
+    name: &'static str,
+    overrides: &'static [&'static str],
+}
+
+const VARIANTS: &[Variant] = &[
+    Variant {
+        name: "release",
+        overrides: &[],
+    },
+    Variant {
+        name: "opt-s",
+        overrides: &["opt-level=\"s\""],
+    },
+    Variant {
+        name: "opt-z",
+        overrides: &["opt-level=\"z\""],
+    },
+    Variant {
+        name: "lto",
+        overrides: &["lto=\"fat\"", "codegen-units=1"],
+    },
+    Variant {
+        name: "panic-abort",
+        overrides: &["panic=\"abort\""],
+    },
+    Variant {
+        name: "strip",
+        overrides: &["strip=true"],
+    },
+    Variant {
+        name: "all",
+        overrides: &[
+            "opt-level=\"z\"",
+            "lto=\"fat\"",
+            "codegen-units=1",
+            "panic=\"abort\"",
+            "strip=true",
+        ],
+    },
+];
+
+const DEFAULT_TARGETS: &[&str] = &["simple:panic_strategy", "async_stuff:fasterthanlime_pin"];
+
+/// How many crates to show in the symbol breakdown
+const TOP_CRATES: usize = 5;
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("simple/ is inside the workspace")
+        .to_path_buf()
+}
+
+/// Build `bin` from `package` with `variant` and return path to the binary
+fn build(root: &Path, package: &str, bin: &str, variant: &Variant) -> Result<PathBuf, String> {
+    let target_dir = root.join("target").join("bin_size").join(variant.name);
+    // NB: `cargo run` sets $CARGO to the cargo that launched us
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut cmd = Command::new(cargo);
+    cmd.current_dir(root)
+        .args(["build", "--quiet", "--release", "-p", package, "--bin", bin])
+        .arg("--target-dir")
+        .arg(&target_dir);
+    for o in variant.overrides {
+        cmd.arg("--config").arg(format!("profile.release.{o}"));
+    }
+    let status = cmd
+        .status()
+        .map_err(|e| format!("failed to run cargo: {e}"))?;
+    if !status.success() {
+        return Err(format!("cargo build failed for {package}:{bin} ({status})"));
+    }
+    Ok(target_dir
+        .join("release")
+        .join(format!("{bin}{}", std::env::consts::EXE_SUFFIX)))
+}
+
+/// Crate a (demangled) symbol belongs to, e.g.
+/// - `std::io::stdio::_print` => `std`
+/// - `<alloc::string::String as core::fmt::Write>::write_str` => `alloc`
+fn crate_of(symbol: &str) -> &str {
+    let s = symbol.trim_start_matches('<').trim_start_matches("impl ");
+    match s.find("::") {
+        Some(i) => &s[..i],
+        None => "[unknown]",
+    }
+}
+
+/// Sum of symbol sizes per crate via `nm`. Returns `None` if `nm` is unavailable or there are no
+/// symbols (e.g. stripped binary).
+fn symbols_by_crate(binary: &Path) -> Option<Vec<(String, u64)>> {
+    let out = Command::new("nm")
+        .args(["--print-size", "--size-sort", "--demangle=rust"])
+        .arg(binary)
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let mut by_crate: HashMap<String, u64> = HashMap::new();
+    for line in String::from_utf8_lossy(&out.stdout).lines() {
+        // <addr> <size> <type> <name>
+        let mut parts = line.splitn(4, ' ');
+        let (Some(_addr), Some(size), Some(_ty), Some(name)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let Ok(size) = u64::from_str_radix(size, 16) else {
+            continue;
+        };
+        *by_crate.entry(crate_of(name).to_string()).or_default() += size;
+    }
+    if by_crate.is_empty() {
+        return None;
+    }
+    let mut by_crate: Vec<_> = by_crate.into_iter().collect();
+    by_crate.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    Some(by_crate)
+}
+
+fn kib(bytes: u64) -> String {
+    format!("{:.1} KiB", bytes as f64 / 1024.0)
+}
+
+/// `size` relative to the release build, "n/a" if that one failed
+fn vs_release(size: u64, release: Option<u64>) -> String {
+    match release {
+        Some(release) if release > 0 => format!(
+            "{:>+6.1}%",
+            (size as f64 - release as f64) * 100.0 / release as f64
+        ),
+        _ => format!("{:>7}", "n/a"),
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let root = workspace_root();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let targets: Vec<&str> = if args.is_empty() {
+        DEFAULT_TARGETS.to_vec()
+    } else {
+        args.iter().map(String::as_str).collect()
+    };
+    let targets = targets
+        .into_iter()
+        .map(|target| {
+            target.split_once(':').ok_or_else(|| {
+                Exit::usage(format!(
+                    "{target:?}: expected <package>:<bin>\nusage: bin_size [<package>:<bin>...]"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut failed = 0;
+    for (package, bin) in targets {
+        println!("== {package}:{bin}");
+        // Only ever the release variant, so a failed release build doesn't shift the comparison
+        let mut release = None;
+        for variant in VARIANTS {
+            let binary = match build(&root, package, bin, variant) {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("  {}: {e}", variant.name);
+                    failed += 1;
+                    continue;
+                }
+            };
+            let size = std::fs::metadata(&binary)
+                .map_err(|e| Exit::context(binary.display(), e))?
+                .len();
+            if variant.name == "release" {
+                release = Some(size);
+            }
+            println!(
+                "  {:<12} {:>12} ({} vs release)  [{}]",
+                variant.name,
+                kib(size),
+                vs_release(size, release),
+                variant.overrides.join(", ")
+            );
+            match symbols_by_crate(&binary) {
+                Some(by_crate) => {
+                    let top: Vec<String> = by_crate
+                        .iter()
+                        .take(TOP_CRATES)
+                        .map(|(name, size)| format!("{name} {}", kib(*size)))
+                        .collect();
+                    println!("  {:<12} top crates: {}", "", top.join(", "));
+                }
+                None => println!("  {:<12} no symbols (stripped?)", ""),
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Exit::failure(format!("{failed} build(s) failed")).into());
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    exit::report(run())
+}
@@ -0,0 +1,24 @@
+//! Read the clocks and print them as a JSON report. See [simple::clocks] and [simple::report].
+
+use chrono::Utc;
+use chrono_tz::{America::New_York, Asia::Tokyo, Europe::London};
+use simple::clocks::{in_zone, rfc3339};
+use simple::report::Report;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub fn main() {
+    let mut report = Report::new("clocks");
+    let start = Instant::now();
+    let now = SystemTime::now();
+    report
+        .push("system_time", rfc3339(now))
+        .push(
+            "unix_secs",
+            now.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        )
+        .push("instant", format!("{start:?}"));
+    for tz in [New_York, London, Tokyo] {
+        report.push(tz.name(), in_zone(Utc::now(), tz));
+    }
+    println!("{}", report.finish().to_json());
+}
@@ -0,0 +1,128 @@
+//! Closures: capture modes, which of [Fn]/[FnMut]/[FnOnce] gets implemented and returning closures
+//!
+//! Each closure is an anonymous struct with one field per capture. Printing the address of a
+//! variable vs what the closure sees tells whether it was captured by reference (same address) or
+//! by value (copied/moved into the closure struct, different address). `size_of_val` tells the
+//! size of that struct.
+//!
+//! Manual `impl Fn*` for hand-written structs needs nightly \[`unboxed_closures` + `fn_traits`\],
+//! see `nightly_workspace/simple/src/closure_traits.rs`.
+//!
+//! re: [Closure expressions](https://doc.rust-lang.org/reference/expressions/closure-expr.html)
+
+use std::mem::size_of_val;
+
+fn is_fn<R, F: Fn() -> R>(_f: &F) {}
+
+fn is_fn_mut<R, F: FnMut() -> R>(_f: &F) {}
+
+fn is_fn_once<R, F: FnOnce() -> R>(_f: &F) {}
+
+fn capture_modes() {
+    let text = String::from("hello");
+    println!("  &text            = {:p}", &text);
+
+    // Only reads => captured by shared reference => closure is just a pointer
+    let by_ref = || println!("  by_ref sees      = {:p}", &text);
+    by_ref();
+    is_fn(&by_ref);
+    println!("  size_of_val(by_ref) = {}", size_of_val(&by_ref));
+
+    // `move` => text is moved into the closure struct => different address, String-sized
+    let by_move = move || println!("  by_move sees     = {:p}", &text);
+    by_move();
+    is_fn(&by_move); // NB: still Fn, `move` only changes how captures are stored
+    println!("  size_of_val(by_move) = {}", size_of_val(&by_move));
+    // TODO Uncomment for compiler error:
+    //      error[E0382]: borrow of moved value: `text`
+    // println!("{text}");
+
+    // Mutates => captured by unique (mutable) borrow => FnMut, not Fn
+    let mut count = 0;
+    let mut incr = || count += 1;
+    incr();
+    incr();
+    is_fn_mut(&incr);
+    // TODO Uncomment for compiler error:
+    //      error[E0525]: expected a closure that implements the `Fn` trait, but this closure only implements `FnMut`
+    // is_fn(&incr);
+    println!("  count after 2 calls = {count}");
+
+    // Moves a capture out => FnOnce only
+    let names = vec![String::from("ann")];
+    let consume = move || names;
+    is_fn_once(&consume);
+    // TODO Uncomment for compiler error:
+    //      error[E0525]: expected a closure that implements the `FnMut` trait, but this closure only implements `FnOnce`
+    // is_fn_mut(&consume);
+    println!("  consumed {:?}", consume());
+
+    // Rust 2021: closures capture disjoint _fields_ rather than whole variables
+    let pair = (String::from("left"), String::from("right"));
+    let take_left = move || pair.0;
+    println!("  &pair.1 = {:p} still usable: {}", &pair.1, pair.1);
+    println!("  took {}", take_left());
+
+    // Copy types are copied in by `move`, so the original stays usable
+    let n = 42_u64;
+    let copy_n = move || n + 1;
+    println!("  &n = {:p}, copy_n() = {}, n = {n}", &n, copy_n());
+
+    // No captures => zero-sized and coerces to a fn pointer
+    let no_capture = |x: i32| x * 2;
+    let fn_ptr: fn(i32) -> i32 = no_capture;
+    println!(
+        "  size_of_val(no_capture) = {}, fn_ptr(21) = {}",
+        size_of_val(&no_capture),
+        fn_ptr(21)
+    );
+}
+
+/// Static dispatch: the concrete (unnameable) closure type is hidden, no allocation
+fn make_adder(n: i32) -> impl Fn(i32) -> i32 {
+    // NB: `move` is needed, otherwise `n` is borrowed from a stack frame that is about to go away
+    // TODO Remove `move` for compiler error:
+    //      error[E0373]: closure may outlive the current function, but it borrows `n`, which is owned by the current function
+    move |x| x + n
+}
+
+/// Dynamic dispatch: needed when different branches return different closure types
+fn make_op(name: &str) -> Box<dyn Fn(i32) -> i32> {
+    match name {
+        "double" => Box::new(|x| x * 2),
+        "negate" => Box::new(|x| -x),
+        _ => {
+            let offset = name.len() as i32;
+            Box::new(move |x| x + offset)
+        }
+    }
+}
+
+/// Each call yields the next value \[state lives inside the returned closure\]
+fn make_counter() -> impl FnMut() -> u32 {
+    let mut count = 0;
+    move || {
+        count += 1;
+        count
+    }
+}
+
+fn returning_closures() {
+    let add5 = make_adder(5);
+    println!("  make_adder(5)(1) = {}", add5(1));
+
+    for name in ["double", "negate", "other"] {
+        let op = make_op(name);
+        println!("  make_op({name:?})(7) = {}", op(7));
+    }
+
+    let mut next = make_counter();
+    println!("  make_counter: {} {} {}", next(), next(), next());
+}
+
+pub fn main() {
+    println!("== capture modes");
+    capture_modes();
+    println!("== returning closures");
+    returning_closures();
+}
@@ -0,0 +1,119 @@
+//! Conditional compilation with `#[cfg(...)]`, `cfg!(...)`, `#[cfg_attr(...)]`
+//! and build-time environment variables (`env!`, `option_env!`)
+//!
+//! Each section prints which branch was actually _compiled_ \[not just taken at runtime\].
+//! Re-run with different flags to see the output change:
+//!
+//! ```sh
+//! cargo run --bin conditional_compilation
+//! cargo run --bin conditional_compilation --release
+//! cargo run --bin conditional_compilation --features fancy
+//! DEMO_BUILD_TAG=hello cargo run --bin conditional_compilation
+//! ```
+//!
+//! re: [Conditional compilation](https://doc.rust-lang.org/reference/conditional-compilation.html)
+
+/// `#[cfg(...)]` removes the item entirely when the predicate is false.
+/// Exactly one of these functions exists in the compiled binary.
+#[cfg(target_os = "linux")]
+fn os_name() -> &'static str {
+    "linux"
+}
+
+#[cfg(target_os = "macos")]
+fn os_name() -> &'static str {
+    "macos"
+}
+
+#[cfg(target_os = "windows")]
+fn os_name() -> &'static str {
+    "windows"
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn os_name() -> &'static str {
+    "something else"
+}
+
+/// Enabled via `--features fancy` (see `[features]` in `simple/Cargo.toml`)
+#[cfg(feature = "fancy")]
+fn greeting() -> String {
+    "✨ fancy feature is ON ✨".to_string()
+}
+
+#[cfg(not(feature = "fancy"))]
+fn greeting() -> String {
+    "fancy feature is off".to_string()
+}
+
+/// `#[cfg_attr(predicate, attr)]` applies `attr` only when predicate is true.
+///
+/// Here [Debug] is only derived in debug builds.
+/// NB: Try `{:?}` on this in a release build to get a compiler error:
+///      error[E0277]: `Secret` doesn't implement `Debug`
+#[cfg_attr(debug_assertions, derive(Debug))]
+struct Secret {
+    // NB: Dead code analysis ignores derived impls, so reading via Debug does not count
+    #[cfg_attr(debug_assertions, expect(dead_code))]
+    value: u32,
+}
+
+fn describe_secret(s: &Secret) -> String {
+    #[cfg(debug_assertions)]
+    {
+        format!("{s:?}")
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        // Debug is not derived in release => cannot use {s:?} here
+        format!(
+            "Secret {{ value: <{} digits redacted> }}",
+            s.value.to_string().len()
+        )
+    }
+}
+
+pub fn main() {
+    println!("== #[cfg(target_os)]");
+    println!("compiled for: {}", os_name());
+    println!("target_family unix? {}", cfg!(target_family = "unix"));
+    println!(
+        "target_pointer_width 64? {}",
+        cfg!(target_pointer_width = "64")
+    );
+
+    println!("== #[cfg(feature)]");
+    println!("{}", greeting());
+
+    println!("== #[cfg(debug_assertions)]");
+    // NB: cfg!() expands to a literal true/false, so *both* branches must type check.
+    //     In contrast, #[cfg] removes the code before type checking.
+    if cfg!(debug_assertions) {
+        println!("debug_assertions ON (e.g. `cargo run`)");
+    } else {
+        println!("debug_assertions OFF (e.g. `cargo run --release`)");
+    }
+
+    println!("== #[cfg_attr]");
+    println!("{}", describe_secret(&Secret { value: 42 }));
+
+    println!("== env!() / option_env!()");
+    // env!() is a compile error if the var is missing at build time.
+    // Cargo always sets CARGO_PKG_* for the crate being compiled.
+    println!(
+        "package: {} v{}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    // option_env!() yields None if the var is missing at build time.
+    // NB: Value is baked in at *compile* time. Setting it when running the binary does nothing.
+    match option_env!("DEMO_BUILD_TAG") {
+        Some(tag) => println!("DEMO_BUILD_TAG (at build time) = {tag}"),
+        None => println!("DEMO_BUILD_TAG was not set at build time"),
+    }
+    // Compare with reading the var at *run* time
+    match std::env::var("DEMO_BUILD_TAG") {
+        Ok(tag) => println!("DEMO_BUILD_TAG (at run time) = {tag}"),
+        Err(_) => println!("DEMO_BUILD_TAG is not set at run time"),
+    }
+}
@@ -0,0 +1,76 @@
+//! Print the (data, metadata) halves of fat pointers to DSTs. See [simple::dst].
+//!
+//! NB: The layout of fat pointers is _not_ guaranteed. Transmuting them to `[usize; 2]` is fine
+//! for a demo but not for real code \[`std::ptr::metadata()` is nightly only\].
+
+use simple::dst::Packet;
+use std::fmt::{Debug, Display};
+use std::mem::size_of_val;
+
+fn halves<T: ?Sized>(name: &str, r: &T) {
+    assert_eq!(size_of::<&T>(), 2 * size_of::<usize>());
+    // SAFETY: Same size [asserted], every bit pattern is a valid [usize; 2]
+    let [data, meta]: [usize; 2] = unsafe { transmute_copy_fat(r) };
+    println!(
+        "  {name:<22} data {data:#014x}  meta {meta:#014x}  size_of_val {}",
+        size_of_val(r)
+    );
+}
+
+/// `transmute` needs sizes known to match at compile time, which they aren't for `&T: ?Sized`
+unsafe fn transmute_copy_fat<T: ?Sized>(r: &T) -> [usize; 2] {
+    // SAFETY: Caller checked `&T` is two words
+    unsafe { std::mem::transmute_copy(&r) }
+}
+
+/// What's in a vtable: the first 3 entries are drop_in_place, size and align
+/// \[again not guaranteed, just the current layout\]
+fn vtable(name: &str, r: &dyn Debug) {
+    // SAFETY: `&dyn Debug` is two words
+    let [_, meta]: [usize; 2] = unsafe { transmute_copy_fat(r) };
+    let entries = meta as *const usize;
+    // SAFETY: Points at a vtable which has at least these 3 entries [current rustc layout]
+    let (size, align) = unsafe { (*entries.add(1), *entries.add(2)) };
+    println!(
+        "  {name:<22} vtable {meta:#014x}  size {size} align {align}  (size_of_val {})",
+        size_of_val(r)
+    );
+}
+
+pub fn main() {
+    println!("== thin");
+    println!("  size_of::<&u64>() = {}", size_of::<&u64>());
+
+    println!("== slices and str: metadata is the length");
+    let arr = [10u16, 20, 30, 40];
+    halves("&[u16] (4 items)", &arr[..]);
+    halves("&[u16] (2 items)", &arr[1..3]);
+    let s = "héllo";
+    halves("&str (\"héllo\")", s);
+    let boxed: Box<[u16]> = arr.into();
+    halves("Box<[u16]>", &*boxed);
+
+    println!("== dyn Trait: metadata is the vtable pointer");
+    let n = 42u64;
+    let t = "text";
+    halves("&dyn Display (u64)", &n as &dyn Display);
+    halves("&dyn Display (u64) #2", &7u64 as &dyn Display);
+    halves("&dyn Display (&str)", &t as &dyn Display);
+    vtable("dyn Debug (u64)", &n);
+    vtable("dyn Debug ([u8; 5])", &[0u8; 5]);
+    vtable("dyn Debug (String)", &String::from("s"));
+
+    println!("== custom DST: metadata comes from the tail field");
+    let small = Packet {
+        id: 1,
+        payload: [1u8, 2],
+    };
+    let big = Packet {
+        id: 2,
+        payload: [0u8; 100],
+    };
+    halves("&Packet<[u8]> (2)", &small as &Packet<[u8]>);
+    halves("&Packet<[u8]> (100)", &big as &Packet<[u8]>);
+    let dyn_tail = Packet { id: 3, payload: n };
+    halves("&Packet<dyn Display>", &dyn_tail as &Packet<dyn Display>);
+}
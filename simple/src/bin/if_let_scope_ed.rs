@@ -0,0 +1,24 @@
+//! Temporaries in an `if let` scrutinee are dropped _before_ the `else` block in Rust 2024.
+//! In Rust 2021 they lived until the end of the whole `if let ... else ...`.
+//!
+//! See [simple::let_else_chains] and [simple::edition_2024] for the cross-edition tests.
+//!
+//! See Rust 2024 release notes [here](https://doc.rust-lang.org/edition-guide/rust-2024/temporary-if-let-scope.html)
+//! for details
+
+use std::sync::Mutex;
+
+pub fn main() {
+    let cache: Mutex<Option<String>> = Mutex::new(None);
+
+    for _ in 0..2 {
+        if let Some(v) = cache.lock().unwrap().as_ref() {
+            println!("cache hit: {v}");
+        } else {
+            // Rust 2024: MutexGuard from the scrutinee is already dropped => OK
+            // Rust 2021: MutexGuard still held => deadlock (or panic, depending on platform)
+            *cache.lock().unwrap() = Some("value".to_string());
+            println!("cache miss: filled");
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Leaking on purpose: [Box::leak], [ManuallyDrop] and [std::mem::forget]
+//!
+//! Leaking memory is _safe_ in Rust \[it is not UB\], so these are all safe functions.
+//! Each case is measured with [simple::alloc_counter] so the leaked bytes are visible.
+//!
+//! | tool | appropriate for | a bug when |
+//! | --- | --- | --- |
+//! | [Box::leak] | once-per-process data, e.g. global config needing `&'static` | called in a loop |
+//! | [ManuallyDrop] | handing ownership to FFI \[and taking it back later\], unions | never taken back |
+//! | [std::mem::forget] | giving up ownership of an OS handle, e.g. `into_raw_fd()` | used on guards/collections |
+//! | `Rc` cycle | never | always \[leaks without any explicit call\] |
+//!
+//! See `test_ub3` in `simple/src/to_ub_or_not_ub.rs` for a [ManuallyDrop] misuse caught by Miri.
+
+use simple::alloc_counter::{self, CountingAlloc, Stats};
+use std::cell::RefCell;
+use std::mem::{self, ManuallyDrop};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Run `f` and print heap activity it caused
+fn measure<R>(label: &str, f: impl FnOnce() -> R) -> R {
+    let before = alloc_counter::thread_snapshot();
+    let res = f();
+    let diff: Stats = alloc_counter::thread_snapshot().since(&before);
+    println!(
+        "  {label:<40} allocs={} deallocs={} leaked={} bytes",
+        diff.allocs,
+        diff.deallocs,
+        diff.live_bytes()
+    );
+    res
+}
+
+/// Pretend C function that takes ownership of a buffer.
+/// In real code this would be `extern "C" fn consume(ptr: *mut u8, len: usize, cap: usize)`.
+fn ffi_take(ptr: *mut u8, len: usize, cap: usize) -> (*mut u8, usize, usize) {
+    // ... C code does its thing and eventually hands the buffer back for freeing ...
+    (ptr, len, cap)
+}
+
+#[derive(Default)]
+struct Node {
+    other: RefCell<Option<Rc<Node>>>,
+}
+
+pub fn main() {
+    println!("== Box::leak");
+    let config: &'static str = measure("Box::leak(config) once", || {
+        Box::leak(String::from("log_level=debug").into_boxed_str())
+    });
+    println!("  config = {config:?} [usable as &'static for rest of program]");
+    measure("Box::leak in a loop (bug)", || {
+        for i in 0..10 {
+            let _: &'static mut String = Box::leak(Box::new(format!("request {i}")));
+        }
+    });
+
+    println!("== ManuallyDrop");
+    measure("hand Vec to FFI, then take it back", || {
+        // NB: Prefer ManuallyDrop over mem::forget here. We still need to read ptr/len/cap
+        // *after* giving up ownership, which would be a use-after-move with forget().
+        let mut v = ManuallyDrop::new(vec![0u8; 64]);
+        let (ptr, len, cap) = ffi_take(v.as_mut_ptr(), v.len(), v.capacity());
+        // SAFETY: ptr/len/cap came from a Vec<u8> that was never dropped
+        let v = unsafe { Vec::from_raw_parts(ptr, len, cap) };
+        drop(v);
+    });
+    measure("hand Vec to FFI, never take back (bug)", || {
+        let mut v = ManuallyDrop::new(vec![0u8; 64]);
+        let _ = ffi_take(v.as_mut_ptr(), v.len(), v.capacity());
+    });
+
+    println!("== mem::forget");
+    measure("forget(Vec) (bug)", || {
+        mem::forget(vec![0u8; 32]);
+    });
+    let m = Mutex::new(0);
+    measure("forget(MutexGuard) (bug)", || {
+        mem::forget(m.lock().unwrap());
+    });
+    // Nothing leaked on the heap, but the lock is never released
+    println!("  mutex still locked? {}", m.try_lock().is_err());
+
+    println!("== Rc cycle");
+    measure("Rc cycle a <-> b (bug)", || {
+        let a = Rc::new(Node::default());
+        let b = Rc::new(Node::default());
+        *a.other.borrow_mut() = Some(b.clone());
+        *b.other.borrow_mut() = Some(a.clone());
+        // Each has strong_count 2 so dropping a and b only brings them down to 1
+    });
+}
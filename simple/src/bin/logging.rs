@@ -0,0 +1,42 @@
+//! The same workload logged via eprintln, log or tracing. See [simple::logging].
+
+use simple::exit::{self, Exit};
+use simple::logging::{BATCHES, ReportLayer, sum_eprintln, sum_log, sum_tracing};
+use std::process::ExitCode;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, fmt};
+
+const USAGE: &str = "usage: logging [eprintln [--verbose] | log | tracing]";
+
+fn run() -> Result<(), Exit> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let summary = match args.as_slice() {
+        ["eprintln"] => sum_eprintln(BATCHES, false),
+        ["eprintln", "--verbose"] => sum_eprintln(BATCHES, true),
+        ["log"] => {
+            env_logger::init();
+            sum_log(BATCHES)
+        }
+        ["tracing"] => {
+            // RUST_LOG only filters what is printed, the report layer sees every event
+            let report = ReportLayer::default();
+            tracing_subscriber::registry()
+                .with(fmt::layer().with_filter(EnvFilter::from_default_env()))
+                .with(report.clone())
+                .init();
+            let summary = sum_tracing(BATCHES);
+            let mut report = report.report("logging");
+            report.push("summary", summary);
+            println!("{}", report.finish().to_json());
+            return Ok(());
+        }
+        _ => return Err(Exit::usage(USAGE)),
+    };
+    println!("{summary:?}");
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    exit::report(run())
+}
@@ -0,0 +1,208 @@
+//! Measure the cost of monomorphization: N instantiations of a generic function vs 1 `dyn` function
+//!
+//! For each N, two scratch crates are generated under `target/mono_cost/`:
+//! - `generic_<N>`: `fn describe<T: Shape>(shapes: &[T])` called with N different types
+//!   \[so the compiler emits N copies of `describe`\]
+//! - `dyn_<N>`: `fn describe(shapes: &[&dyn Shape])` called with the same N types
+//!   \[so there is exactly 1 copy of `describe` plus N vtables\]
+//!
+//! Each crate is built from scratch (`--release`) and we report wall clock compile time and
+//! binary size.
+//!
+//! ```sh
+//! cargo run --release --bin mono_cost
+//! cargo run --release --bin mono_cost -- 10 300
+//! ```
+//!
+//! See also `simple/src/box_dyn_is_static.rs` for `dyn` lifetimes.
+
+use simple::exit::{self, Exit};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::time::{Duration, Instant};
+
+const DEFAULT_NS: &[usize] = &[10, 50, 200];
+
+#[derive(Clone, Copy)]
+enum Dispatch {
+    Generic,
+    Dyn,
+}
+
+impl Dispatch {
+    fn name(self) -> &'static str {
+        match self {
+            Dispatch::Generic => "generic",
+            Dispatch::Dyn => "dyn",
+        }
+    }
+}
+
+fn scratch_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("simple/ is inside the workspace")
+        .join("target")
+        .join("mono_cost")
+}
+
+/// Generate `main.rs` with `n` types implementing `Shape`
+fn generate_main(dispatch: Dispatch, n: usize) -> String {
+    let mut src = String::from(
+        "use std::hint::black_box;\n\n\
+         pub trait Shape {\n    fn area(&self) -> f64;\n    fn name(&self) -> &'static str;\n}\n\n",
+    );
+    for i in 0..n {
+        writeln!(
+            src,
+            "pub struct S{i}(pub f64);\n\
+             impl Shape for S{i} {{\n    \
+                 fn area(&self) -> f64 {{ self.0 * {i}.0 + 1.0 }}\n    \
+                 fn name(&self) -> &'static str {{ \"S{i}\" }}\n\
+             }}"
+        )
+        .unwrap();
+    }
+
+    // Same body for both. Big enough that duplicating it is noticeable.
+    let body = r#"
+    let mut areas: Vec<f64> = shapes.iter().map(|s| s.area()).collect();
+    areas.sort_by(|a, b| a.total_cmp(b));
+    let names: Vec<&str> = shapes.iter().map(|s| s.name()).collect();
+    let total: f64 = areas.iter().sum();
+    format!("{} shapes [{}] total={total:.2} max={:?}", shapes.len(), names.join(","), areas.last())
+"#;
+    match dispatch {
+        Dispatch::Generic => {
+            writeln!(
+                src,
+                "#[inline(never)]\nfn describe<T: Shape>(shapes: &[T]) -> String {{{body}}}\n"
+            )
+            .unwrap();
+        }
+        Dispatch::Dyn => {
+            writeln!(
+                src,
+                "#[inline(never)]\nfn describe(shapes: &[&dyn Shape]) -> String {{{body}}}\n"
+            )
+            .unwrap();
+        }
+    }
+
+    src.push_str("fn main() {\n    let mut len = 0;\n");
+    for i in 0..n {
+        let arg = match dispatch {
+            Dispatch::Generic => format!("&[S{i}(black_box(1.0)), S{i}(black_box(2.0))]"),
+            Dispatch::Dyn => format!("&[&S{i}(black_box(1.0)), &S{i}(black_box(2.0))]"),
+        };
+        writeln!(src, "    len += describe({arg}).len();").unwrap();
+    }
+    src.push_str("    println!(\"{len}\");\n}\n");
+    src
+}
+
+/// Write scratch crate and return its directory
+fn generate_crate(dispatch: Dispatch, n: usize) -> std::io::Result<PathBuf> {
+    let name = format!("{}_{n}", dispatch.name());
+    let dir = scratch_root().join(&name);
+    if dir.exists() {
+        // Always measure a clean build
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(dir.join("src"))?;
+    // NB: Empty [workspace] so cargo doesn't think this belongs to our workspace
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[workspace]\n"
+        ),
+    )?;
+    std::fs::write(dir.join("src").join("main.rs"), generate_main(dispatch, n))?;
+    Ok(dir)
+}
+
+/// Build scratch crate and return (compile time, binary size)
+fn measure(dir: &Path, name: &str) -> Result<(Duration, u64), String> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let now = Instant::now();
+    let status = Command::new(cargo)
+        .current_dir(dir)
+        .args(["build", "--quiet", "--release"])
+        .status()
+        .map_err(|e| format!("failed to run cargo: {e}"))?;
+    let elapsed = now.elapsed();
+    if !status.success() {
+        return Err(format!("cargo build failed in {}", dir.display()));
+    }
+    let binary = dir
+        .join("target")
+        .join("release")
+        .join(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+    let size = std::fs::metadata(&binary)
+        .map_err(|e| format!("{}: {e}", binary.display()))?
+        .len();
+    Ok((elapsed, size))
+}
+
+const USAGE: &str = "usage: mono_cost [N...]";
+
+fn run() -> anyhow::Result<()> {
+    let ns: Vec<usize> = {
+        let args = std::env::args()
+            .skip(1)
+            .map(|a| {
+                a.parse().map_err(|_| {
+                    Exit::usage(format!(
+                        "{a:?}: expected a number of instantiations\n{USAGE}"
+                    ))
+                })
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        if args.is_empty() {
+            DEFAULT_NS.to_vec()
+        } else {
+            args
+        }
+    };
+
+    println!(
+        "{:>6} | {:>12} {:>12} | {:>12} {:>12}",
+        "N", "generic time", "dyn time", "generic size", "dyn size"
+    );
+    let mut failed = 0;
+    for n in ns {
+        let mut row = Vec::new();
+        for dispatch in [Dispatch::Generic, Dispatch::Dyn] {
+            let name = format!("{}_{n}", dispatch.name());
+            let res = generate_crate(dispatch, n)
+                .map_err(|e| e.to_string())
+                .and_then(|dir| measure(&dir, &name));
+            match res {
+                Ok(m) => row.push(m),
+                Err(e) => {
+                    eprintln!("{name}: {e}");
+                    failed += 1;
+                    break;
+                }
+            }
+        }
+        if let [(gt, gs), (dt, ds)] = row[..] {
+            println!(
+                "{n:>6} | {:>12} {:>12} | {:>9} KiB {:>9} KiB",
+                format!("{gt:.2?}"),
+                format!("{dt:.2?}"),
+                gs / 1024,
+                ds / 1024
+            );
+        }
+    }
+    if failed > 0 {
+        return Err(Exit::failure(format!("{failed} build(s) failed")).into());
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    exit::report(run())
+}
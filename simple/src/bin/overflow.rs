@@ -0,0 +1,14 @@
+//! Plain `+` in debug vs release. See [simple::overflow].
+
+use simple::overflow::{OVERFLOW_CHECKS, add_plain, all_adds};
+
+pub fn main() {
+    println!("overflow checks: {OVERFLOW_CHECKS}");
+    println!("250 + 10 => {:?}", all_adds(250, 10));
+    // NB: Read from args so that the compiler can't see the overflow at compile time
+    let a: u8 = std::env::args()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(255);
+    println!("{a} + 1 = {}", add_plain(a, 1));
+}
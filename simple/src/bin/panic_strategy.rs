@@ -0,0 +1,169 @@
+//! Panic strategies (`panic = "unwind"` vs `panic = "abort"`), [std::panic::catch_unwind],
+//! [std::panic::UnwindSafe], destructors during unwinding and double panics
+//!
+//! The strategy is picked by the cargo profile. See `[profile.release-abort]` in the workspace `Cargo.toml`.
+//!
+//! ```sh
+//! # panic = "unwind" (default)
+//! cargo run --bin panic_strategy
+//!
+//! # panic = "abort" => first panic kills the process, no destructors, catch_unwind is useless
+//! cargo run --bin panic_strategy --profile release-abort
+//!
+//! # Run a single case. Some cases (e.g. double_panic) abort even with panic = "unwind"
+//! cargo run --bin panic_strategy -- double_panic
+//! ```
+//!
+//! re: [The Rustonomicon: Unwinding](https://doc.rust-lang.org/nomicon/unwinding.html)
+
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+
+/// Prints when dropped so we can see which destructors run during unwinding
+struct Noisy(&'static str);
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        println!(
+            "  drop {} (thread panicking? {})",
+            self.0,
+            std::thread::panicking()
+        );
+    }
+}
+
+/// Panics in its destructor
+struct PanicOnDrop;
+
+impl Drop for PanicOnDrop {
+    fn drop(&mut self) {
+        panic!("panic inside Drop");
+    }
+}
+
+fn strategy() -> &'static str {
+    if cfg!(panic = "unwind") {
+        "unwind"
+    } else if cfg!(panic = "abort") {
+        "abort"
+    } else {
+        "unknown"
+    }
+}
+
+/// [panic::catch_unwind] turns a panic into an `Err` holding the payload
+fn case_catch_unwind() {
+    let res = panic::catch_unwind(|| {
+        panic!("boom");
+    });
+    // Payload is Box<dyn Any + Send>. panic!("literal") gives &str, panic!("{x}") gives String.
+    let payload = res.expect_err("closure should have panicked");
+    let msg = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string payload>");
+    println!("  caught panic with payload {msg:?}");
+
+    // Non-panicking closure just yields Ok
+    let res = panic::catch_unwind(|| 42);
+    println!("  no panic => {res:?}");
+}
+
+/// Locals are dropped in reverse declaration order while unwinding
+fn case_destructors_while_unwinding() {
+    let res = panic::catch_unwind(|| {
+        let _a = Noisy("a");
+        let _b = Noisy("b");
+        panic!("unwinding through a and b");
+    });
+    println!("  after catch_unwind: is_err? {}", res.is_err());
+}
+
+/// [panic::catch_unwind] requires [panic::UnwindSafe] closures
+fn case_unwind_safe() {
+    let counter = RefCell::new(0);
+
+    // TODO Uncomment next lines for compiler error:
+    //      error[E0277]: the type `UnsafeCell<i32>` may contain interior mutability and a reference may not be safely transferable across a catch_unwind boundary
+    // let _ = panic::catch_unwind(|| {
+    //     *counter.borrow_mut() += 1;
+    // });
+
+    // AssertUnwindSafe is us promising that a half-updated `counter` is fine to observe afterwards
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        *counter.borrow_mut() += 1;
+        panic!("panic after mutating");
+    }));
+    println!(
+        "  is_err? {}, counter (mutated before panic) = {}",
+        res.is_err(),
+        counter.borrow()
+    );
+
+    // Mutex *is* UnwindSafe (via RefUnwindSafe) because it tracks panics by poisoning
+    let m = Mutex::new(0);
+    let res = panic::catch_unwind(|| {
+        let mut guard = m.lock().unwrap();
+        *guard += 1;
+        panic!("panic while holding lock");
+    });
+    println!(
+        "  is_err? {}, mutex poisoned? {}",
+        res.is_err(),
+        m.is_poisoned()
+    );
+}
+
+/// Panicking while already unwinding aborts the process (even with panic = "unwind")
+fn case_double_panic() {
+    let _ = panic::catch_unwind(|| {
+        let _d = PanicOnDrop;
+        panic!("first panic");
+    });
+    unreachable!("process should have aborted");
+}
+
+/// With panic = "abort" this is the end of the process.
+/// Note that [Noisy] never prints its drop message.
+fn case_uncaught() {
+    let _n = Noisy("uncaught");
+    panic!("nobody catches this");
+}
+
+type Case = (&'static str, fn());
+
+const CASES: &[Case] = &[
+    ("catch_unwind", case_catch_unwind),
+    ("destructors", case_destructors_while_unwinding),
+    ("unwind_safe", case_unwind_safe),
+    // Following are fatal so only run when explicitly requested
+    ("double_panic", case_double_panic),
+    ("uncaught", case_uncaught),
+];
+
+const FATAL_CASES: &[&str] = &["double_panic", "uncaught"];
+
+pub fn main() {
+    // Keep default panic message short, i.e. no backtrace hint
+    panic::set_hook(Box::new(|info| {
+        println!("  [panic hook] {info}");
+    }));
+
+    println!("panic strategy: {}", strategy());
+
+    let selected: Vec<String> = std::env::args().skip(1).collect();
+    for (name, case) in CASES {
+        let run = if selected.is_empty() {
+            // NB: With panic = "abort", the very first case already aborts
+            !FATAL_CASES.contains(name)
+        } else {
+            selected.iter().any(|s| s == name)
+        };
+        if run {
+            println!("== {name}");
+            case();
+        }
+    }
+}
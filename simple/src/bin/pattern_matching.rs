@@ -0,0 +1,191 @@
+//! Pattern matching: binding modes (match ergonomics), `@` bindings, slice patterns,
+//! or-patterns and exhaustiveness with `#[non_exhaustive]`
+//!
+//! Like `arr_into_iter_ed.rs`, the `assert_*` functions only compile if the binding has exactly
+//! that type \[i.e. proof of by-value vs by-reference binding\].
+//!
+//! re: [Patterns](https://doc.rust-lang.org/reference/patterns.html) and
+//! [Rust 2024 match ergonomics reservations](https://doc.rust-lang.org/edition-guide/rust-2024/match-ergonomics.html)
+
+use std::io::ErrorKind;
+
+fn assert_owned(_s: String) {}
+
+fn assert_borrowed(_s: &String) {}
+
+fn assert_borrowed_mut(_s: &mut String) {}
+
+fn assert_slice(_s: &[String]) {}
+
+fn assert_array<const N: usize>(_s: [String; N]) {}
+
+/// `#[non_exhaustive]` has no effect inside the defining crate
+#[non_exhaustive]
+enum LocalKind {
+    A,
+    B,
+}
+
+// Regarding clippy, yeah, `if let` and dropping the `&`s are nicer but we're demoing the
+// binding modes of each spelling
+#[allow(
+    clippy::single_match,
+    clippy::match_ref_pats,
+    clippy::needless_borrowed_reference
+)]
+fn binding_modes() {
+    // Matching an owned value => bindings move (binding mode "move")
+    let opt = Some(String::from("owned"));
+    match opt {
+        Some(s) => assert_owned(s),
+        None => {}
+    }
+
+    // Matching through a reference => non-reference pattern switches the default binding
+    // mode to "ref" (match ergonomics, RFC 2005)
+    let opt = Some(String::from("borrowed"));
+    match &opt {
+        Some(s) => assert_borrowed(s),
+        None => {}
+    }
+    // opt was not moved
+    assert!(opt.is_some());
+
+    // Same as the pre-2018 explicit way: &Some(ref s)
+    match &opt {
+        &Some(ref s) => assert_borrowed(s),
+        &None => {}
+    }
+
+    // ... or explicit `ref` on an owned value
+    match opt {
+        Some(ref s) => assert_borrowed(s),
+        None => {}
+    }
+
+    // &mut => default binding mode "ref mut"
+    let mut opt = Some(String::from("mutable"));
+    if let Some(s) = &mut opt {
+        assert_borrowed_mut(s);
+        s.push('!');
+    }
+    assert_eq!(opt.as_deref(), Some("mutable!"));
+
+    // TODO Uncomment for compiler error in Rust 2024 (allowed in Rust 2021):
+    //      error: cannot explicitly borrow within an implicitly-borrowing pattern
+    // NB: Rust 2024 reserves this so a future edition can change what `ref` means here
+    // match &opt {
+    //     Some(ref s) => assert_borrowed(s),
+    //     None => {}
+    // }
+
+    // Copy types: binding by value just copies
+    let pair = &(1, 2);
+    let (a, b) = pair;
+    let _: (&i32, &i32) = (a, b);
+    let &(a, b) = pair;
+    let _: (i32, i32) = (a, b);
+}
+
+fn at_bindings() {
+    // Bind the whole value while also testing it against a sub-pattern
+    for n in [0, 5, 42] {
+        let desc = match n {
+            0 => "zero".to_string(),
+            small @ 1..=9 => format!("small {small}"),
+            other => format!("big {other}"),
+        };
+        println!("  {n} => {desc}");
+    }
+
+    // `@` with sub-bindings. By reference because we match on &Option<String>.
+    let opt = Some(String::from("whole"));
+    if let whole @ Some(inner) = &opt {
+        let _: &Option<String> = whole;
+        assert_borrowed(inner);
+    }
+}
+
+fn slice_patterns() {
+    let v = vec![String::from("a"), String::from("b"), String::from("c")];
+
+    // Matching a slice => elements are borrowed, rest is a sub-slice
+    match v.as_slice() {
+        [] => unreachable!(),
+        [only] => assert_borrowed(only),
+        [first, rest @ ..] => {
+            assert_borrowed(first);
+            assert_slice(rest);
+        }
+    }
+
+    match v.as_slice() {
+        [first, .., last] => {
+            assert_borrowed(first);
+            assert_borrowed(last);
+        }
+        _ => unreachable!(),
+    }
+
+    // Matching an *array* by value => elements are moved, rest is an array
+    let arr = [String::from("x"), String::from("y"), String::from("z")];
+    let [first, rest @ ..] = arr;
+    assert_owned(first);
+    assert_array::<2>(rest);
+}
+
+fn or_patterns() {
+    for c in ['a', 'x', '7', ' '] {
+        let kind = match c {
+            'a' | 'e' | 'i' | 'o' | 'u' => "vowel",
+            '0'..='9' => "digit",
+            c if c.is_whitespace() => "whitespace",
+            _ => "other",
+        };
+        println!("  {c:?} => {kind}");
+    }
+
+    // Nested or-pattern (stable since 1.53). All alternatives must bind the same names and types.
+    let pairs = [(1, "x"), (2, "y"), (3, "z")];
+    for p in pairs {
+        if let (1 | 2, name) = p {
+            println!("  {p:?} => matched 1|2 with {name}");
+        }
+    }
+}
+
+fn exhaustiveness() {
+    // Local #[non_exhaustive] enum can be matched exhaustively without `_`
+    for k in [LocalKind::A, LocalKind::B] {
+        match k {
+            LocalKind::A => println!("  LocalKind::A"),
+            LocalKind::B => println!("  LocalKind::B"),
+        }
+    }
+
+    // std::io::ErrorKind is #[non_exhaustive] in another crate => `_` arm is mandatory
+    // TODO Remove the `_` arm for compiler error:
+    //      error[E0004]: non-exhaustive patterns: `_` not covered
+    //        = note: `ErrorKind` is marked as non-exhaustive, so a wildcard `_` is necessary to match exhaustively
+    for kind in [ErrorKind::NotFound, ErrorKind::TimedOut] {
+        let retry = match kind {
+            ErrorKind::TimedOut | ErrorKind::Interrupted | ErrorKind::WouldBlock => true,
+            ErrorKind::NotFound | ErrorKind::PermissionDenied => false,
+            _ => false,
+        };
+        println!("  {kind:?} => retry? {retry}");
+    }
+}
+
+pub fn main() {
+    println!("== binding modes");
+    binding_modes();
+    println!("== @ bindings");
+    at_bindings();
+    println!("== slice patterns");
+    slice_patterns();
+    println!("== or-patterns");
+    or_patterns();
+    println!("== exhaustiveness");
+    exhaustiveness();
+}
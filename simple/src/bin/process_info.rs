@@ -0,0 +1,55 @@
+//! Print stack sizes, thread count, page size and rlimits as a JSON report, asserting what
+//! should hold. See [simple::process_info].
+
+use simple::process_info::{
+    Resource, page_size, rlimit, stack_bounds, stack_remaining, thread_count, with_stack,
+};
+use simple::report::Report;
+
+const MIB: usize = 1 << 20;
+
+pub fn main() {
+    let mut report = Report::new("process_info");
+    let stack_size = || stack_bounds().map(|(_, size)| size);
+
+    report
+        .push("page_size", page_size())
+        .push("rlimit_stack", rlimit(Resource::Stack))
+        .push("rlimit_nofile", rlimit(Resource::NoFile))
+        .push("rlimit_as", rlimit(Resource::AddressSpace))
+        .push("threads", thread_count());
+
+    let main_stack = stack_size();
+    report
+        .push("main_stack", main_stack)
+        .push("main_stack_remaining", stack_remaining());
+    if let (Some(size), Some(limit)) = (main_stack, rlimit(Resource::Stack).and_then(|l| l.soft)) {
+        // glibc reports the rlimit minus what is already mapped above the stack
+        assert!(size as u64 <= limit, "main stack {size} > rlimit {limit}");
+    }
+
+    let spawned = std::thread::spawn(move || (stack_size(), thread_count()))
+        .join()
+        .unwrap();
+    report
+        .push("spawned_stack", spawned.0)
+        .push("threads_while_spawned", spawned.1);
+    if let Some(size) = spawned.0 {
+        let expected = std::env::var("RUST_MIN_STACK")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(2 * MIB);
+        assert!(size >= expected, "spawned stack {size} < {expected}");
+    }
+    if let (Some(before), Some(during)) = (thread_count(), spawned.1) {
+        assert_eq!(during, before + 1);
+    }
+
+    let custom = with_stack(16 * MIB, stack_size).unwrap();
+    report.push("custom_stack", custom);
+    if let Some(size) = custom {
+        assert!(size >= 16 * MIB);
+    }
+
+    println!("{}", report.finish().to_json());
+}
@@ -0,0 +1,37 @@
+//! Return position `impl Trait` (RPIT) captures _all_ in-scope lifetimes in Rust 2024.
+//! In Rust 2021, it only captured the type parameters \[and lifetimes named in the bounds\].
+//!
+//! Use precise capturing `+ use<...>` (stable since 1.82) to opt out.
+//!
+//! See [simple::edition_2024] for the cross-edition compile tests.
+//!
+//! See Rust 2024 release notes [here](https://doc.rust-lang.org/edition-guide/rust-2024/rpit-lifetime-capture.html)
+//! for details
+
+/// Rust 2024: returned iterator captures the lifetime of `slice` (even though it doesn't use it)
+/// => `slice` stays borrowed as long as the iterator is alive
+fn indices_captures<T>(slice: &[T]) -> impl Iterator<Item = usize> {
+    0..slice.len()
+}
+
+/// Opt out: only capture `T`, not the lifetime of `slice`.
+/// Same as what Rust 2021 did implicitly.
+fn indices_precise<T>(slice: &[T]) -> impl Iterator<Item = usize> + use<T> {
+    0..slice.len()
+}
+
+pub fn main() {
+    let mut v = vec![1, 2, 3];
+
+    let it = indices_captures(&v);
+    // Following will *not* compile in newer Rust 2024 as `it` still borrows `v`
+    //      error[E0502]: cannot borrow `v` as mutable because it is also borrowed as immutable
+    // Following will compile in older Rust 2021
+    // v.push(4);
+    println!("{:?}", it.collect::<Vec<_>>());
+
+    let it = indices_precise(&v);
+    // Compiles in all Rust editions thanks to use<T>
+    v.push(4);
+    println!("{:?}", it.collect::<Vec<_>>());
+}
@@ -0,0 +1,42 @@
+//! Taking a reference to a `static mut` is a (deny-by-default) error in Rust 2024.
+//! It was a warning in Rust 2021 \[and silently accepted before that\].
+//!
+//! A `&STATIC_MUT` is UB as soon as anything else writes to it while the reference is alive,
+//! which is almost impossible to rule out. Alternatives:
+//! - raw pointers via `&raw const` / `&raw mut` (stable since 1.82)
+//! - atomics or `Mutex` in a plain `static` \[best\]
+//!
+//! See [simple::edition_2024] for the cross-edition compile tests.
+//!
+//! See Rust 2024 release notes [here](https://doc.rust-lang.org/edition-guide/rust-2024/static-mut-references.html)
+//! for details
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static mut COUNTER: u32 = 0;
+
+static ATOMIC_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+pub fn main() {
+    // SAFETY: Single threaded and no references to COUNTER exist
+    unsafe {
+        COUNTER += 1;
+    }
+
+    // Following will *not* compile in newer Rust 2024
+    //      error: creating a shared reference to mutable static
+    // Following will compile (with a warning) in older Rust 2021
+    // let r: &u32 = unsafe { &COUNTER };
+
+    // Raw pointer instead of reference
+    let p: *const u32 = &raw const COUNTER;
+    // SAFETY: Single threaded so nothing writes while we read
+    println!("COUNTER = {}", unsafe { p.read() });
+
+    // No unsafe at all
+    ATOMIC_COUNTER.fetch_add(1, Ordering::Relaxed);
+    println!(
+        "ATOMIC_COUNTER = {}",
+        ATOMIC_COUNTER.load(Ordering::Relaxed)
+    );
+}
@@ -0,0 +1,127 @@
+//! Constant promotion and the two meanings of `'static`
+//!
+//! 1. `&5` and `&[1, 2, 3]` are _promoted_: the compiler puts the value in read-only static
+//!    memory \[instead of the stack\] so the reference is `&'static`.
+//! 2. `T: 'static` (a bound) means "T owns its data" \[i.e. contains no non-static borrows\],
+//!    _not_ "T lives forever". A `String` is `'static` yet is dropped like any other value.
+//! 3. `&'static T` (a reference) means the pointee really lives forever, e.g. promoted constants,
+//!    `static` items or memory deliberately leaked with [Box::leak].
+//!
+//! Addresses are printed to prove where things live. Promoted values sit next to `static` items,
+//! far away from both stack and heap.
+//!
+//! re: [Constant promotion](https://doc.rust-lang.org/reference/destructors.html#constant-promotion)
+//! and [Common Rust Lifetime Misconceptions](https://github.com/pretzelhammer/rust-blog/blob/master/posts/common-rust-lifetime-misconceptions.md#2-if-t-static-then-t-must-be-valid-for-the-entire-program)
+
+use std::fmt::Debug;
+
+static ANSWER: i32 = 42;
+
+/// Rough location of a pointer, found by comparing against known stack/heap/static addresses
+fn region<T: ?Sized>(p: *const T, stack: usize, heap: usize, statik: usize) -> &'static str {
+    let addr = p.cast::<u8>() as usize;
+    let dist = |base: usize| addr.abs_diff(base);
+    let (s, h, st) = (dist(stack), dist(heap), dist(statik));
+    if st <= s && st <= h {
+        "static"
+    } else if s <= h {
+        "stack"
+    } else {
+        "heap"
+    }
+}
+
+/// Returns a reference to a temporary that would normally die at end of function.
+/// Compiles only because `&5` is promoted to a static.
+fn promoted_five() -> &'static i32 {
+    &5
+}
+
+fn promoted_slice() -> &'static [i32] {
+    &[1, 2, 3]
+}
+
+const fn make_five() -> i32 {
+    5
+}
+
+// TODO Uncomment for compiler error. Function calls (even const fn) are not promoted outside of
+// const contexts:
+//      error[E0515]: cannot return reference to temporary value
+// fn not_promoted() -> &'static i32 {
+//     &make_five()
+// }
+
+/// ... but are fine in const context where everything is evaluated at compile time
+const FIVE_REF: &i32 = &make_five();
+
+/// `T: 'static` bound: T must not contain non-static references.
+/// Says nothing about how long the *value* lives.
+fn take_static<T: Debug + 'static>(t: T) -> String {
+    format!("{t:?}")
+    // t is dropped here even though T: 'static
+}
+
+/// Global config that is created at run time but lives "forever"
+fn leak_config(name: &str) -> &'static str {
+    // NB: Memory is never freed. Fine for once-per-process data like config.
+    Box::leak(format!("config for {name}").into_boxed_str())
+}
+
+pub fn main() {
+    let local = 0;
+    let boxed = Box::new(0);
+    let stack = &local as *const i32 as usize;
+    let heap = &*boxed as *const i32 as usize;
+    let statik = &ANSWER as *const i32 as usize;
+    let at = |label: &str, p: *const ()| {
+        println!(
+            "  {label:<28} {p:>16p} => {}",
+            region(p, stack, heap, statik)
+        );
+    };
+
+    println!("== reference points");
+    at("local (stack)", &local as *const i32 as _);
+    at("Box::new(0) (heap)", &*boxed as *const i32 as _);
+    at("static ANSWER", &ANSWER as *const i32 as _);
+
+    println!("== promoted constants");
+    let a = promoted_five();
+    let b = promoted_five();
+    at("promoted_five() #1", a as *const i32 as _);
+    at("promoted_five() #2", b as *const i32 as _);
+    // Same address every call => not a fresh temporary on the stack
+    assert!(std::ptr::eq(a, b));
+    at("promoted_slice()", promoted_slice().as_ptr() as _);
+    at("const FIVE_REF", FIVE_REF as *const i32 as _);
+
+    let r: &'static i32 = &5;
+    at("let r: &'static i32 = &5", r as *const i32 as _);
+
+    // Not promoted: function calls, and values with Drop or interior mutability
+    let not_promoted = &String::new();
+    at(
+        "&String::new() (temporary)",
+        not_promoted as *const String as _,
+    );
+    // TODO Uncomment for compiler error:
+    //      error[E0716]: temporary value dropped while borrowed
+    // let _s: &'static String = &String::new();
+
+    println!("== T: 'static means \"owns its data\"");
+    let owned = String::from("owned String is 'static");
+    println!("  {}", take_static(owned));
+    // `owned` has been moved and dropped inside take_static() despite being 'static.
+    let borrowed_from_local = String::from("local");
+    let _borrowed: &str = &borrowed_from_local;
+    // TODO Uncomment for compiler error:
+    //      error[E0597]: `borrowed_from_local` does not live long enough
+    // take_static(_borrowed);
+    println!("  {}", take_static("string literal is &'static str"));
+
+    println!("== Box::leak");
+    let config = leak_config("demo");
+    at("Box::leak(...)", config.as_ptr() as _);
+    println!("  {config:?} is &'static str but lives on the heap");
+}
@@ -0,0 +1,34 @@
+//! Body of an `unsafe fn` is no longer an implicit `unsafe {}` block in Rust 2024.
+//! The `unsafe_op_in_unsafe_fn` lint is warn-by-default in Rust 2024 \[and allow-by-default before\].
+//!
+//! `unsafe fn` now only means "callers must uphold a contract". Each unsafe operation inside
+//! needs its own `unsafe {}` block \[and ideally its own SAFETY comment\].
+//!
+//! See [simple::edition_2024] for the cross-edition compile tests.
+//!
+//! See Rust 2024 release notes [here](https://doc.rust-lang.org/edition-guide/rust-2024/unsafe-op-in-unsafe-fn.html)
+//! for details
+
+/// # Safety
+/// `ptr` must be valid for reads
+unsafe fn read_old_style(ptr: *const i32) -> i32 {
+    // Following gives a warning in newer Rust 2024 (and compiles silently in older Rust 2021)
+    //      warning[E0133]: dereference of raw pointer is unsafe and requires unsafe block
+    // *ptr
+    unsafe { *ptr }
+}
+
+/// # Safety
+/// `ptr` must be valid for reads
+unsafe fn read_new_style(ptr: *const i32) -> i32 {
+    // SAFETY: Caller guarantees ptr is valid for reads
+    unsafe { *ptr }
+}
+
+pub fn main() {
+    let x = 42;
+    // Calling an unsafe fn still needs unsafe in all Rust editions
+    // SAFETY: &x is valid
+    let (a, b) = unsafe { (read_old_style(&x), read_new_style(&x)) };
+    println!("{a} {b}");
+}
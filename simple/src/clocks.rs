@@ -0,0 +1,219 @@
+//! Time: monotonic vs wall clocks, skew, `Duration` overflow and time zones
+//!
+//! | type | clock | can go backwards? | serializable? | use for |
+//! | --- | --- | --- | --- | --- |
+//! | [Instant] | monotonic | ❌ never | ❌ opaque, only meaningful within one process | measuring elapsed time, timeouts |
+//! | [SystemTime] | wall | ✅ NTP, admin, VM resume | via [UNIX_EPOCH] | timestamps |
+//! | [time::OffsetDateTime] | wall + fixed UTC offset | same | ✅ RFC 3339, unix | formatting, [crate::report] |
+//! | [chrono::DateTime] with [chrono_tz::Tz] | wall + IANA time zone | same | ✅ | local time with DST rules |
+//!
+//! NB: `Instant - earlier` and [Instant::duration_since] saturate to zero \[since 1.60\] but
+//! [SystemTime::duration_since] returns `Err` when the clock went backwards, so elapsed time
+//! measured with `SystemTime` needs a fallback. See [FakeClock] for simulating that.
+//!
+//! | overflow | panics | instead |
+//! | --- | --- | --- |
+//! | `Duration::MAX + Duration::from_secs(1)` | ✅ `overflow when adding durations` | [Duration::checked_add] / [Duration::saturating_add] |
+//! | `Duration::ZERO - Duration::from_secs(1)` | ✅ | [Duration::saturating_sub] |
+//! | `Instant::now() + Duration::MAX` | ✅ | [Instant::checked_add] |
+//! | `Duration::from_secs_f64(-1.0)` \[or NaN\] | ✅ | [Duration::try_from_secs_f64] |
+//!
+//! re: [Falsehoods programmers believe about time](https://gist.github.com/timvisee/fcda9bbdff88d45cc9061606b4b923ca)
+
+use chrono::{DateTime, LocalResult, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::cell::Cell;
+use std::time::{Duration, Instant, SystemTime, SystemTimeError, UNIX_EPOCH};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Source of wall clock time, so tests can control it
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Wall clock that only moves when told to \[forwards or backwards\]
+pub struct FakeClock(Cell<SystemTime>);
+
+impl FakeClock {
+    pub fn at_unix(secs: u64) -> Self {
+        Self(Cell::new(UNIX_EPOCH + Duration::from_secs(secs)))
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.0.set(self.0.get() + d);
+    }
+
+    /// E.g. NTP correcting a clock that ran fast
+    pub fn rewind(&self, d: Duration) {
+        self.0.set(self.0.get() - d);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        self.0.get()
+    }
+}
+
+/// Another host's clock: `offset` seconds ahead \[or behind if negative\] of `inner`
+pub struct SkewedClock<C> {
+    pub inner: C,
+    pub offset: i64,
+}
+
+impl<C: Clock> Clock for SkewedClock<C> {
+    fn now(&self) -> SystemTime {
+        let d = Duration::from_secs(self.offset.unsigned_abs());
+        if self.offset >= 0 {
+            self.inner.now() + d
+        } else {
+            self.inner.now() - d
+        }
+    }
+}
+
+/// Elapsed wall time, `Err` if the clock went backwards
+pub fn wall_elapsed(clock: &impl Clock, start: SystemTime) -> Result<Duration, SystemTimeError> {
+    clock.now().duration_since(start)
+}
+
+/// Timestamps from different hosts sorted by wall time: with skew the order can be wrong
+pub fn order_by_timestamp<'a>(events: &[(&'a str, SystemTime)]) -> Vec<&'a str> {
+    let mut sorted = events.to_vec();
+    sorted.sort_by_key(|(_, t)| *t);
+    sorted.into_iter().map(|(name, _)| name).collect()
+}
+
+/// Deadline for a timeout, `None` instead of a panic when `timeout` is huge
+/// \[e.g. `Duration::MAX` meaning "no timeout"\]
+pub fn deadline(now: Instant, timeout: Duration) -> Option<Instant> {
+    now.checked_add(timeout)
+}
+
+/// Parse a user supplied number of seconds without panicking
+pub fn parse_secs(s: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(s.parse().ok()?).ok()
+}
+
+pub fn rfc3339(t: SystemTime) -> String {
+    OffsetDateTime::from(t).format(&Rfc3339).unwrap()
+}
+
+/// Same instant, formatted in the given IANA zone \[offset changes with DST\]
+pub fn in_zone(t: DateTime<Utc>, tz: Tz) -> String {
+    t.with_timezone(&tz)
+        .format("%Y-%m-%d %H:%M %Z (%:z)")
+        .to_string()
+}
+
+/// Local wall time to UTC: may not exist \[DST gap\] or be ambiguous \[DST overlap\]
+pub fn local_to_utc(tz: Tz, date: NaiveDate, hour: u32, min: u32) -> LocalResult<DateTime<Utc>> {
+    let naive = date.and_hms_opt(hour, min, 0).unwrap();
+    tz.from_local_datetime(&naive)
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::New_York;
+
+    #[test]
+    fn test_monotonic_vs_wall() {
+        let clock = FakeClock::at_unix(1_000);
+        let start = clock.now();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(wall_elapsed(&clock, start).unwrap(), Duration::from_secs(5));
+        clock.rewind(Duration::from_secs(10));
+        let err = wall_elapsed(&clock, start).unwrap_err();
+        assert_eq!(err.duration(), Duration::from_secs(5));
+
+        // Instant can't be set, and subtracting the wrong way round saturates
+        let a = Instant::now();
+        let b = Instant::now();
+        assert_eq!(a - b, Duration::ZERO);
+        assert_eq!(a.duration_since(b), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_skew() {
+        let base = FakeClock::at_unix(1_000);
+        let fast = SkewedClock {
+            inner: FakeClock::at_unix(1_000),
+            offset: 3,
+        };
+        // "sent" on the fast host, "received" 1s later on the accurate one
+        let sent = fast.now();
+        base.advance(Duration::from_secs(1));
+        let received = base.now();
+        assert_eq!(
+            order_by_timestamp(&[("sent", sent), ("received", received)]),
+            ["received", "sent"]
+        );
+
+        let slow = SkewedClock {
+            inner: SystemClock,
+            offset: -60,
+        };
+        assert!(slow.now() < SystemTime::now());
+    }
+
+    #[test]
+    fn test_duration_overflow() {
+        assert_eq!(Duration::MAX.checked_add(Duration::from_secs(1)), None);
+        assert_eq!(
+            Duration::ZERO.saturating_sub(Duration::from_secs(1)),
+            Duration::ZERO
+        );
+        assert_eq!(deadline(Instant::now(), Duration::MAX), None);
+        assert!(deadline(Instant::now(), Duration::from_secs(1)).is_some());
+        assert_eq!(parse_secs("1.5"), Some(Duration::from_millis(1_500)));
+        assert_eq!(parse_secs("-1"), None);
+        assert_eq!(parse_secs("NaN"), None);
+        assert_eq!(parse_secs("1e30"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when adding durations")]
+    fn test_duration_add_panics() {
+        let _ = std::hint::black_box(Duration::MAX) + Duration::from_secs(1);
+    }
+
+    #[test]
+    fn test_formatting() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(rfc3339(t), "2023-11-14T22:13:20Z");
+
+        // Same instant, different offsets in winter and summer
+        let winter = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2026, 7, 15, 12, 0, 0).unwrap();
+        assert_eq!(in_zone(winter, New_York), "2026-01-15 07:00 EST (-05:00)");
+        assert_eq!(in_zone(summer, New_York), "2026-07-15 08:00 EDT (-04:00)");
+    }
+
+    #[test]
+    fn test_dst_gaps_and_overlaps() {
+        // Clocks jump 02:00 -> 03:00 on 2026-03-08, so 02:30 never happens
+        let spring = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        assert_eq!(local_to_utc(New_York, spring, 2, 30), LocalResult::None);
+        // and 01:00 -> 02:00 happens twice on 2026-11-01
+        let fall = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+        let LocalResult::Ambiguous(first, second) = local_to_utc(New_York, fall, 1, 30) else {
+            panic!("expected ambiguous");
+        };
+        assert_eq!(second - first, chrono::Duration::hours(1));
+        assert!(matches!(
+            local_to_utc(New_York, fall, 12, 0),
+            LocalResult::Single(_)
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! `const fn` and compile-time evaluation
+//!
+//! - [CRC32_TABLE] is a lookup table computed entirely by the compiler via [crc32_table()]
+//! - `const _: () = assert!(...)` and `const { assert!(...) }` fail the *build* instead of a test
+//! - [PowerOfTwoBuf] shows the interplay with const generics: the assertion is checked per
+//!   instantiation \[i.e. a "post-monomorphization" error\]
+//! - [CRC32_TABLE_LAZY] computes the same table at run time on first access via [LazyLock]
+//!
+//! Things that are *not* allowed in `const fn` (see compile_fail doctests on [crc32_table()]):
+//! heap allocation, calling non-const functions, `for` loops \[which need `Iterator::next()`\],
+//! and calling trait methods.
+//!
+//! re: [Constant evaluation](https://doc.rust-lang.org/reference/const_eval.html)
+
+use std::sync::LazyLock;
+
+/// Reflected CRC-32 (IEEE 802.3) polynomial
+pub const CRC32_POLY: u32 = 0xEDB8_8320;
+
+/// Computed at compile time. The binary just contains the 1 KiB of data.
+pub const CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// Same table computed at run time the first time it is dereferenced
+pub static CRC32_TABLE_LAZY: LazyLock<[u32; 256]> = LazyLock::new(crc32_table);
+
+// Compile-time assertions: break the build if the table is wrong.
+// TODO Change 0x7707_3096 to something else to get compiler error:
+//      error[E0080]: evaluation panicked: assertion failed: CRC32_TABLE[1] == 0x7707_3096
+const _: () = assert!(CRC32_TABLE[0] == 0);
+const _: () = assert!(CRC32_TABLE[1] == 0x7707_3096);
+const _: () = assert!(CRC32_TABLE[255] == 0x2D02_EF8D);
+
+/// Build CRC-32 lookup table.
+///
+/// Usable both at compile time (see [CRC32_TABLE]) and run time (see [CRC32_TABLE_LAZY]).
+///
+/// NB: `while` instead of `for` because `for` desugars to [Iterator::next()] which is not const:
+/// ```compile_fail,E0015
+/// const fn sum(n: u32) -> u32 {
+///     let mut total = 0;
+///     for i in 0..n {
+///         total += i;
+///     }
+///     total
+/// }
+/// ```
+///
+/// No heap allocations in const context:
+/// ```compile_fail,E0015
+/// const V: Vec<u8> = vec![1, 2, 3];
+/// ```
+///
+/// No calls to non-const functions:
+/// ```compile_fail,E0015
+/// const S: String = String::from("hi");
+/// ```
+///
+/// No trait method calls \[const traits are not stable yet\]:
+/// ```compile_fail,E0015
+/// const fn make<T: Default>() -> T {
+///     T::default()
+/// }
+/// ```
+pub const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ CRC32_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 checksum using the compile-time [CRC32_TABLE]. Also a `const fn`:
+/// ```
+/// use simple::const_eval::crc32;
+/// const CHECK: u32 = crc32(b"123456789");
+/// assert_eq!(CHECK, 0xCBF4_3926);
+/// ```
+pub const fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    let mut i = 0;
+    while i < data.len() {
+        crc = CRC32_TABLE[((crc ^ data[i] as u32) & 0xFF) as usize] ^ (crc >> 8);
+        i += 1;
+    }
+    !crc
+}
+
+/// `const fn` can compute array lengths for const generics
+pub const fn bytes_for_bits(bits: usize) -> usize {
+    bits.div_ceil(8)
+}
+
+/// Bit set stored in exactly as many bytes as needed for `BITS`
+///
+/// NB: `[u8; bytes_for_bits(BITS)]` where `BITS` is a generic parameter needs the unstable
+/// `generic_const_exprs`, so the byte count is a 2nd const parameter that is checked instead.
+pub struct BitSet<const BITS: usize, const BYTES: usize> {
+    bytes: [u8; BYTES],
+}
+
+impl<const BITS: usize, const BYTES: usize> BitSet<BITS, BYTES> {
+    pub const fn new() -> Self {
+        const { assert!(BYTES == bytes_for_bits(BITS), "BYTES must fit BITS exactly") };
+        Self { bytes: [0; BYTES] }
+    }
+
+    pub const fn set(&mut self, bit: usize) {
+        self.bytes[bit / 8] |= 1 << (bit % 8);
+    }
+
+    pub const fn get(&self, bit: usize) -> bool {
+        self.bytes[bit / 8] & (1 << (bit % 8)) != 0
+    }
+}
+
+impl<const BITS: usize, const BYTES: usize> Default for BitSet<BITS, BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Ring buffer whose capacity must be a power of two \[so index wrapping is just a bit mask\]
+///
+/// The check runs at compile time but only when `new()` is instantiated for a concrete `N`:
+/// ```
+/// use simple::const_eval::PowerOfTwoBuf;
+/// let _ok = PowerOfTwoBuf::<8>::new();
+/// ```
+///
+/// ```compile_fail,E0080
+/// use simple::const_eval::PowerOfTwoBuf;
+/// let _bad = PowerOfTwoBuf::<3>::new(); // error: evaluation panicked: N must be a power of two
+/// ```
+pub struct PowerOfTwoBuf<const N: usize> {
+    buf: [u8; N],
+    pos: usize,
+}
+
+impl<const N: usize> PowerOfTwoBuf<N> {
+    const MASK: usize = {
+        assert!(N.is_power_of_two(), "N must be a power of two");
+        N - 1
+    };
+
+    pub const fn new() -> Self {
+        // NB: Referencing MASK forces its evaluation [and the assertion in it]
+        let _ = Self::MASK;
+        Self {
+            buf: [0; N],
+            pos: 0,
+        }
+    }
+
+    pub fn push(&mut self, b: u8) {
+        self.buf[self.pos & Self::MASK] = b;
+        self.pos = self.pos.wrapping_add(1);
+    }
+
+    pub fn last(&self) -> Option<u8> {
+        (self.pos > 0).then(|| self.buf[(self.pos - 1) & Self::MASK])
+    }
+}
+
+impl<const N: usize> Default for PowerOfTwoBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_const_table_matches_lazy_table() {
+        assert_eq!(CRC32_TABLE, *CRC32_TABLE_LAZY);
+    }
+
+    #[test]
+    fn test_const_vs_lazy_access() {
+        // First access of the lazy table pays for computing it. The const table never does.
+        let now = Instant::now();
+        let lazy = CRC32_TABLE_LAZY[255];
+        let lazy_elapsed = now.elapsed();
+        let now = Instant::now();
+        let konst = std::hint::black_box(&CRC32_TABLE)[255];
+        let const_elapsed = now.elapsed();
+        println!("lazy first access {lazy_elapsed:?}, const access {const_elapsed:?}");
+        assert_eq!(lazy, konst);
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        // Same function at compile time
+        const HELLO: u32 = crc32(b"hello");
+        assert_eq!(HELLO, crc32(b"hello"));
+    }
+
+    #[test]
+    fn test_bitset() {
+        let mut bits = BitSet::<12, { bytes_for_bits(12) }>::new();
+        assert_eq!(size_of_val(&bits), 2);
+        bits.set(0);
+        bits.set(11);
+        assert!(bits.get(0));
+        assert!(!bits.get(5));
+        assert!(bits.get(11));
+    }
+
+    #[test]
+    fn test_power_of_two_buf() {
+        let mut buf = PowerOfTwoBuf::<4>::new();
+        assert_eq!(buf.last(), None);
+        for b in 0..10 {
+            buf.push(b);
+        }
+        assert_eq!(buf.last(), Some(9));
+    }
+}
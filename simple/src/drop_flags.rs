@@ -0,0 +1,180 @@
+//! Partial moves, drop flags and moving out of things that implement [Drop]
+//!
+//! - Partial move: moving one field out of a struct leaves the _other_ fields usable
+//!   \[and they are still dropped at end of scope\], but the struct as a whole is not.
+//! - Drop flags: when a value is moved out only on _some_ paths, the compiler adds a hidden
+//!   boolean on the stack to remember whether to drop it at end of scope.
+//! - `impl Drop` forbids moving fields out \[as `drop()` needs a whole `&mut self`\].
+//!   Workarounds are [Option::take] and [Option::replace] \[or [std::mem::take] and
+//!   [std::mem::replace] for non-`Option` fields\].
+//!
+//! Every rejected case has a `compile_fail` twin in the docs below. Tests record the order in
+//! which [Noisy] values are dropped.
+//!
+//! re: [Drop flags](https://doc.rust-lang.org/nomicon/drop-flags.html)
+
+use std::cell::RefCell;
+
+thread_local! {
+    static DROP_LOG: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns (and clears) names of [Noisy] values dropped so far on this thread
+pub fn take_drop_log() -> Vec<&'static str> {
+    DROP_LOG.with_borrow_mut(std::mem::take)
+}
+
+/// Records its name in the drop log when dropped
+#[derive(Debug)]
+pub struct Noisy(pub &'static str);
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        DROP_LOG.with_borrow_mut(|log| log.push(self.0));
+    }
+}
+
+/// No [Drop] impl => fields can be moved out individually
+pub struct Pair {
+    pub a: Noisy,
+    pub b: Noisy,
+}
+
+/// Partial move out of [Pair]. `pair.b` is still dropped at end of scope.
+///
+/// Twin: using the whole struct after a partial move is rejected
+/// ```compile_fail,E0382
+/// use simple::drop_flags::{Noisy, Pair};
+/// let pair = Pair { a: Noisy("a"), b: Noisy("b") };
+/// let a = pair.a;
+/// let whole = pair; // error[E0382]: use of partially moved value: `pair`
+/// ```
+pub fn partial_move() -> &'static str {
+    let pair = Pair {
+        a: Noisy("a"),
+        b: Noisy("b"),
+    };
+    let a = pair.a;
+    // pair.b is still usable
+    let b_name = pair.b.0;
+    drop(a);
+    b_name
+    // pair.b dropped here [but not pair.a, which was moved]
+}
+
+/// `x` is moved out only if `consume` is true.
+/// A hidden drop flag decides at run time whether `x` is dropped at end of scope.
+///
+/// Twin: a value that *might* have been moved cannot be used afterwards
+/// ```compile_fail,E0382
+/// use simple::drop_flags::Noisy;
+/// let x = Noisy("x");
+/// if std::env::args().count() > 0 {
+///     drop(x);
+/// }
+/// println!("{:?}", x); // error[E0382]: borrow of moved value: `x`
+/// ```
+pub fn conditional_move(consume: bool) {
+    let x = Noisy("x");
+    let _y = Noisy("y");
+    if consume {
+        drop(x);
+    }
+    // If !consume: drop order is _y then x (reverse declaration order).
+    // If consume: x already dropped above, only _y here.
+}
+
+/// Has a [Drop] impl => fields cannot be moved out
+///
+/// Twin: moving a field out of a `Drop` type is rejected
+/// ```compile_fail,E0509
+/// use simple::drop_flags::{Guarded, Noisy};
+/// let g = Guarded { inner: Some(Noisy("inner")) };
+/// let inner = g.inner; // error[E0509]: cannot move out of type `Guarded`, which implements the `Drop` trait
+/// ```
+pub struct Guarded {
+    pub inner: Option<Noisy>,
+}
+
+impl Drop for Guarded {
+    fn drop(&mut self) {
+        DROP_LOG.with_borrow_mut(|log| log.push("Guarded"));
+    }
+}
+
+impl Guarded {
+    /// [Option::take] leaves `None` behind so `Guarded::drop()` still sees a valid value
+    ///
+    /// Twin: moving out from behind `&mut self` is rejected
+    /// ```compile_fail,E0507
+    /// use simple::drop_flags::{Guarded, Noisy};
+    /// fn take(g: &mut Guarded) -> Option<Noisy> {
+    ///     g.inner // error[E0507]: cannot move out of `g.inner` which is behind a mutable reference
+    /// }
+    /// ```
+    pub fn take_inner(&mut self) -> Option<Noisy> {
+        self.inner.take()
+    }
+
+    /// [Option::replace] swaps in a new value and gives back the old one.
+    /// Same as `std::mem::replace(&mut self.inner, Some(new))` which works for any type.
+    pub fn replace_inner(&mut self, new: Noisy) -> Option<Noisy> {
+        self.inner.replace(new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_move() {
+        take_drop_log();
+        assert_eq!(partial_move(), "b");
+        // a dropped explicitly first, b at end of scope
+        assert_eq!(take_drop_log(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_drop_flag_not_consumed() {
+        take_drop_log();
+        conditional_move(false);
+        assert_eq!(take_drop_log(), ["y", "x"]);
+    }
+
+    #[test]
+    fn test_drop_flag_consumed() {
+        take_drop_log();
+        conditional_move(true);
+        // x is not dropped twice thanks to the drop flag
+        assert_eq!(take_drop_log(), ["x", "y"]);
+    }
+
+    #[test]
+    fn test_take_out_of_drop_type() {
+        take_drop_log();
+        let mut g = Guarded {
+            inner: Some(Noisy("inner")),
+        };
+        let inner = g.take_inner();
+        assert!(g.inner.is_none());
+        drop(g);
+        assert_eq!(take_drop_log(), ["Guarded"]);
+        drop(inner);
+        assert_eq!(take_drop_log(), ["inner"]);
+    }
+
+    #[test]
+    fn test_replace_in_drop_type() {
+        take_drop_log();
+        let mut g = Guarded {
+            inner: Some(Noisy("old")),
+        };
+        let old = g.replace_inner(Noisy("new"));
+        drop(old);
+        assert_eq!(take_drop_log(), ["old"]);
+        drop(g);
+        // Guarded::drop() runs first, then its fields
+        assert_eq!(take_drop_log(), ["Guarded", "new"]);
+    }
+}
@@ -0,0 +1,136 @@
+//! Dynamically sized types (DSTs): `[T]`, `str`, `dyn Trait` and structs with an unsized tail
+//!
+//! A DST has no size known at compile time, so it can only live behind a pointer. That pointer
+//! is "fat": data address + metadata.
+//!
+//! | pointee | metadata |
+//! | --- | --- |
+//! | `[T]`, `str` | length \[in elements / bytes\] |
+//! | `dyn Trait` | vtable pointer \[drop, size, align, trait methods\] |
+//! | `Packet<[u8]>` | same as the tail field's, i.e. length |
+//!
+//! A custom DST is a struct whose _last_ field is unsized. It can't be built directly \[there is
+//! no way to write a `[u8]` value\], instead build the sized version and let unsizing coercion
+//! turn `&Packet<[u8; 4]>` into `&Packet<[u8]>` \[or `Box`, `Rc`, ...\].
+//!
+//! DSTs can't be passed or returned by value:
+//! ```compile_fail,E0277
+//! use simple::dst::Packet;
+//! fn make() -> Packet<[u8]> { // error[E0277]: the size for values of type `[u8]` cannot be known at compilation time
+//!     todo!()
+//! }
+//! ```
+//!
+//! Only the last field can be unsized:
+//! ```compile_fail,E0277
+//! struct Bad {
+//!     payload: [u8], // error[E0277]: the size for values of type `[u8]` cannot be known at compilation time
+//!     id: u32,
+//! }
+//! ```
+//!
+//! See `dst.rs` in `src/bin` for the fat pointer layouts printed at run time.
+//!
+//! re: [Dynamically Sized Types](https://doc.rust-lang.org/nomicon/exotic-sizes.html#dynamically-sized-types-dsts)
+
+use std::fmt::Display;
+use std::rc::Rc;
+
+/// `T` is the unsized tail \[note the `?Sized`\]
+#[derive(Debug)]
+pub struct Packet<T: ?Sized> {
+    pub id: u32,
+    pub payload: T,
+}
+
+impl Packet<[u8]> {
+    pub fn checksum(&self) -> u8 {
+        self.payload.iter().fold(0, |acc, b| acc.wrapping_add(*b))
+    }
+}
+
+impl<T: ?Sized + Display> Display for Packet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}: {}", self.id, &self.payload)
+    }
+}
+
+/// Different sized packets behind one type \[the length is in the fat pointer\]
+pub fn boxed_packets() -> Vec<Box<Packet<[u8]>>> {
+    vec![
+        Box::new(Packet {
+            id: 1,
+            payload: [1, 2],
+        }),
+        Box::new(Packet {
+            id: 2,
+            payload: [3, 4, 5, 6],
+        }),
+    ]
+}
+
+/// Unsized tail can also be a trait object
+pub fn describe_all(packets: &[&Packet<dyn Display>]) -> Vec<String> {
+    packets.iter().map(|p| p.to_string()).collect()
+}
+
+/// Shared slices and strings without the extra `Vec`/`String` indirection
+pub fn shared_names(names: &[&str]) -> (Rc<[Rc<str>]>, usize) {
+    let shared: Rc<[Rc<str>]> = names.iter().map(|n| Rc::<str>::from(*n)).collect();
+    let total: usize = shared.iter().map(|n| n.len()).sum();
+    (shared, total)
+}
+
+// Thin vs fat pointers
+const _: () = assert!(size_of::<&u8>() == size_of::<usize>());
+const _: () = assert!(size_of::<&[u8]>() == 2 * size_of::<usize>());
+const _: () = assert!(size_of::<&str>() == 2 * size_of::<usize>());
+const _: () = assert!(size_of::<&dyn Display>() == 2 * size_of::<usize>());
+const _: () = assert!(size_of::<&Packet<[u8]>>() == 2 * size_of::<usize>());
+const _: () = assert!(size_of::<Box<Packet<[u8]>>>() == 2 * size_of::<usize>());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::size_of_val;
+
+    #[test]
+    fn test_unsizing_coercion() {
+        let sized = Packet {
+            id: 7,
+            payload: [1u8, 2, 3],
+        };
+        let unsized_: &Packet<[u8]> = &sized;
+        assert_eq!(unsized_.payload.len(), 3);
+        assert_eq!(unsized_.checksum(), 6);
+        // Same bytes, so same size: u32 + 3 u8s, padded to align 4
+        assert_eq!(size_of_val(unsized_), size_of_val(&sized));
+        assert_eq!(size_of_val(unsized_), 8);
+    }
+
+    #[test]
+    fn test_boxed_packets() {
+        let packets = boxed_packets();
+        let lens: Vec<_> = packets.iter().map(|p| p.payload.len()).collect();
+        assert_eq!(lens, [2, 4]);
+        assert_eq!(packets[1].checksum(), 18);
+    }
+
+    #[test]
+    fn test_dyn_tail() {
+        let a = Packet { id: 1, payload: 42 };
+        let b = Packet {
+            id: 2,
+            payload: "hi",
+        };
+        assert_eq!(describe_all(&[&a, &b]), ["#1: 42", "#2: hi"]);
+    }
+
+    #[test]
+    fn test_shared_names() {
+        let (shared, total) = shared_names(&["ann", "bob"]);
+        assert_eq!(shared.len(), 2);
+        assert_eq!(total, 6);
+        assert_eq!(&*shared[1], "bob");
+    }
+}
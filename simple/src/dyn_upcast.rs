@@ -0,0 +1,211 @@
+//! `dyn Trait` lifetime bounds and trait upcasting (`dyn Sub` -> `dyn Super`)
+//!
+//! ## Default object lifetime bounds
+//!
+//! A `dyn Trait` without `+ 'x` gets a default lifetime bound from its _container_:
+//!
+//! | written | means |
+//! | --- | --- |
+//! | `Box<dyn Shape>` | `Box<dyn Shape + 'static>` |
+//! | `&'a dyn Shape` | `&'a (dyn Shape + 'a)` |
+//! | `&'a mut dyn Shape` | `&'a mut (dyn Shape + 'a)` |
+//! | `Rc<dyn Shape>`, `Arc<dyn Shape>` | `+ 'static` (same as `Box`) |
+//!
+//! The table is checked at compile time by the `*_default()` functions below. See also
+//! `box_dyn_is_static.rs` for the "lifetime may not live long enough" error this causes.
+//!
+//! NB: Checked in _signatures_ only. Inside function bodies elided bounds are inferred instead.
+//!
+//! ```compile_fail
+//! use simple::dyn_upcast::Shape;
+//! // Box defaults to 'static, not 'a
+//! fn box_default<'a>(b: Box<dyn Shape + 'a>) -> Box<dyn Shape> {
+//!     b // error: lifetime may not live long enough
+//! }
+//! ```
+//!
+//! ## Trait upcasting (stable since 1.86)
+//!
+//! `&dyn Named` coerces to `&dyn Shape` when `Shape` is a supertrait of `Named` \[the vtable of
+//! `Named` embeds a pointer to the `Shape` vtable\]. Also works for `Box`, `Rc`, `Arc` and raw
+//! pointers, and to `dyn Any` \[if it is a supertrait\] which makes downcasting possible.
+//!
+//! Only to supertraits though:
+//! ```compile_fail,E0308
+//! use simple::dyn_upcast::{Named, Square};
+//! let named: &dyn Named = &Square(2.0);
+//! let display: &dyn std::fmt::Display = named; // error[E0308]: mismatched types
+//! ```
+//!
+//! Before 1.86 the workaround was a method on the subtrait returning the supertrait object, see
+//! [AsShape].
+//!
+//! re: [Default trait object lifetime bounds](https://doc.rust-lang.org/reference/lifetime-elision.html#default-trait-object-lifetimes)
+//! and [trait upcasting](https://blog.rust-lang.org/2025/04/03/Rust-1.86.0/#trait-upcasting)
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+pub trait Shape: Debug {
+    fn area(&self) -> f64;
+}
+
+/// NB: `Any` implies `'static` so it is only a supertrait here \[and not of [Shape] which then
+/// could not be implemented by types holding references\]
+pub trait Named: Shape + Any {
+    fn name(&self) -> &str;
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Square(pub f64);
+
+impl Shape for Square {
+    fn area(&self) -> f64 {
+        self.0 * self.0
+    }
+}
+
+impl Named for Square {
+    fn name(&self) -> &str {
+        "square"
+    }
+}
+
+#[derive(Debug)]
+pub struct Circle(pub f64);
+
+impl Shape for Circle {
+    fn area(&self) -> f64 {
+        std::f64::consts::PI * self.0 * self.0
+    }
+}
+
+impl Named for Circle {
+    fn name(&self) -> &str {
+        "circle"
+    }
+}
+
+// Static assertions of the default object lifetime bounds table. A coercion can only shorten
+// the bound of a trait object, never lengthen it:
+// - `Box`/`Rc`: elided -> `'static` compiles => elided bound is `'static`
+// - `&'a`/`&'a mut`: `'a` -> elided compiles => elided bound is at most `'a` [and at least `'a`
+//   as `&'a T` needs `T: 'a`]
+
+#[allow(dead_code)]
+fn box_default(b: Box<dyn Shape>) -> Box<dyn Shape + 'static> {
+    b
+}
+
+#[allow(dead_code)]
+fn rc_default(r: Rc<dyn Shape>) -> Rc<dyn Shape + 'static> {
+    r
+}
+
+#[allow(dead_code)]
+fn ref_default<'a>(r: &'a (dyn Shape + 'a)) -> &'a dyn Shape {
+    r
+}
+
+#[allow(dead_code)]
+fn mut_ref_default<'a>(r: &'a mut (dyn Shape + 'a)) -> &'a mut dyn Shape {
+    r
+}
+
+// Both are fat pointers [data + vtable], upcasting just swaps the vtable pointer
+const _: () = assert!(size_of::<&dyn Named>() == 2 * size_of::<usize>());
+const _: () = assert!(size_of::<&dyn Shape>() == size_of::<&dyn Named>());
+
+/// Upcasting coercion
+pub fn total_area(shapes: &[Box<dyn Named>]) -> f64 {
+    shapes
+        .iter()
+        .map(|named| {
+            let shape: &dyn Shape = named.as_ref();
+            shape.area()
+        })
+        .sum()
+}
+
+/// Upcast all the way to `dyn Any`, then downcast to the concrete type
+pub fn find_square(shapes: &[Box<dyn Named>]) -> Option<&Square> {
+    shapes.iter().find_map(|named| {
+        let any: &dyn Any = named.as_ref();
+        any.downcast_ref::<Square>()
+    })
+}
+
+/// Owned upcast: `Box<dyn Named>` -> `Box<dyn Shape>`
+pub fn forget_names(shapes: Vec<Box<dyn Named>>) -> Vec<Box<dyn Shape>> {
+    shapes.into_iter().map(|s| s as Box<dyn Shape>).collect()
+}
+
+/// Pre-1.86 workaround: subtrait method that returns `self` as the supertrait object.
+/// The blanket impl saves writing it for every type \[needs `Sized` for the unsizing coercion\].
+pub trait AsShape {
+    fn as_shape(&self) -> &dyn Shape;
+}
+
+impl<T: Shape> AsShape for T {
+    fn as_shape(&self) -> &dyn Shape {
+        self
+    }
+}
+
+/// Same as [Named] but with the workaround as a supertrait
+pub trait NamedOld: Shape + AsShape {}
+
+impl<T: Shape> NamedOld for T {}
+
+pub fn total_area_old(shapes: &[Box<dyn NamedOld>]) -> f64 {
+    shapes.iter().map(|s| s.as_shape().area()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shapes() -> Vec<Box<dyn Named>> {
+        vec![Box::new(Circle(1.0)), Box::new(Square(2.0))]
+    }
+
+    #[test]
+    fn test_upcast() {
+        let expected = std::f64::consts::PI + 4.0;
+        assert_eq!(total_area(&shapes()), expected);
+        let old: Vec<Box<dyn NamedOld>> = vec![Box::new(Circle(1.0)), Box::new(Square(2.0))];
+        assert_eq!(total_area_old(&old), expected);
+
+        let names: Vec<_> = shapes().iter().map(|s| s.name().to_string()).collect();
+        assert_eq!(names, ["circle", "square"]);
+        let unnamed = forget_names(shapes());
+        assert_eq!(format!("{:?}", unnamed[1]), "Square(2.0)");
+    }
+
+    #[test]
+    fn test_downcast_after_upcast() {
+        let shapes = shapes();
+        assert_eq!(find_square(&shapes), Some(&Square(2.0)));
+        assert_eq!(find_square(&shapes[..1]), None);
+    }
+
+    #[test]
+    fn test_borrowed_dyn_lifetime() {
+        // &'a dyn Shape is &'a (dyn Shape + 'a), so it can hold short-lived data
+        #[derive(Debug)]
+        struct Borrowed<'s>(&'s f64);
+        impl Shape for Borrowed<'_> {
+            fn area(&self) -> f64 {
+                *self.0
+            }
+        }
+        let area = 3.0;
+        let b = Borrowed(&area);
+        let shape: &dyn Shape = &b;
+        assert_eq!(shape.area(), 3.0);
+        // Box needs the explicit bound
+        let boxed: Box<dyn Shape + '_> = Box::new(Borrowed(&area));
+        assert_eq!(boxed.area(), 3.0);
+    }
+}
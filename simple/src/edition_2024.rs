@@ -0,0 +1,138 @@
+//! Cross-edition compile tests for the Rust 2024 edition changes
+//!
+//! The same snippet is compiled as Rust 2021 and Rust 2024 via rustdoc's `edition20xx` attribute.
+//! See the `*_ed.rs` binaries for the runnable side of each change:
+//!
+//! | change | binary |
+//! | --- | --- |
+//! | RPIT lifetime capture | `rpit_capture_ed.rs` |
+//! | `unsafe_op_in_unsafe_fn` | `unsafe_op_in_unsafe_fn_ed.rs` |
+//! | `static mut` references | `static_mut_refs_ed.rs` |
+//! | `if let` temporary scope | `if_let_scope_ed.rs` (and [crate::let_else_chains]) |
+//! | `unsafe extern` blocks | (doctests only) |
+//!
+//! ## RPIT lifetime capture
+//!
+//! Rust 2021: `impl Iterator` does not capture the lifetime of `slice`
+//! ```edition2021
+//! fn indices<T>(slice: &[T]) -> impl Iterator<Item = usize> {
+//!     0..slice.len()
+//! }
+//! let mut v = vec![1, 2, 3];
+//! let it = indices(&v);
+//! v.push(4);
+//! assert_eq!(it.count(), 3);
+//! ```
+//!
+//! Rust 2024: same code, but now `it` keeps `v` borrowed
+//! ```edition2024,compile_fail,E0502
+//! fn indices<T>(slice: &[T]) -> impl Iterator<Item = usize> {
+//!     0..slice.len()
+//! }
+//! let mut v = vec![1, 2, 3];
+//! let it = indices(&v);
+//! v.push(4);
+//! assert_eq!(it.count(), 3);
+//! ```
+//!
+//! Rust 2024 with precise capturing `use<T>`
+//! ```edition2024
+//! fn indices<T>(slice: &[T]) -> impl Iterator<Item = usize> + use<T> {
+//!     0..slice.len()
+//! }
+//! let mut v = vec![1, 2, 3];
+//! let it = indices(&v);
+//! v.push(4);
+//! assert_eq!(it.count(), 3);
+//! ```
+//!
+//! ## `unsafe_op_in_unsafe_fn`
+//!
+//! Rust 2021: lint is allow-by-default
+//! ```edition2021
+//! #![deny(warnings)]
+//! unsafe fn read(ptr: *const i32) -> i32 {
+//!     *ptr
+//! }
+//! assert_eq!(unsafe { read(&42) }, 42);
+//! ```
+//!
+//! Rust 2024: lint is warn-by-default (denied here to make it a compile failure)
+//! ```edition2024,compile_fail
+//! #![deny(warnings)]
+//! unsafe fn read(ptr: *const i32) -> i32 {
+//!     *ptr
+//! }
+//! assert_eq!(unsafe { read(&42) }, 42);
+//! ```
+//!
+//! ## `static mut` references
+//!
+//! Rust 2021: a warning only
+//! ```edition2021
+//! static mut COUNTER: u32 = 0;
+//! #[allow(static_mut_refs)]
+//! let r: &u32 = unsafe { &COUNTER };
+//! assert_eq!(*r, 0);
+//! ```
+//!
+//! Rust 2024: deny-by-default
+//! ```edition2024,compile_fail
+//! static mut COUNTER: u32 = 0;
+//! let r: &u32 = unsafe { &COUNTER };
+//! assert_eq!(*r, 0);
+//! ```
+//!
+//! ## `if let` temporary scope
+//!
+//! Rust 2021: the guard from `lock()` is still held in `else` => `try_lock()` fails
+//! ```edition2021
+//! use std::sync::Mutex;
+//! let m = Mutex::new(None::<i32>);
+//! let relocked = if let Some(v) = *m.lock().unwrap() {
+//!     v > 0
+//! } else {
+//!     m.try_lock().is_ok()
+//! };
+//! assert!(!relocked);
+//! ```
+//!
+//! Rust 2024: the guard is dropped before `else`
+//! ```edition2024
+//! use std::sync::Mutex;
+//! let m = Mutex::new(None::<i32>);
+//! let relocked = if let Some(v) = *m.lock().unwrap() {
+//!     v > 0
+//! } else {
+//!     m.try_lock().is_ok()
+//! };
+//! assert!(relocked);
+//! ```
+//!
+//! ## `unsafe extern` blocks
+//!
+//! Rust 2021: plain `extern` block
+//! ```edition2021
+//! extern "C" {
+//!     fn abs(x: i32) -> i32;
+//! }
+//! assert_eq!(unsafe { abs(-3) }, 3);
+//! ```
+//!
+//! Rust 2024: must be `unsafe extern` \[declaring foreign signatures is itself an unchecked promise\]
+//! ```edition2024,compile_fail
+//! extern "C" {
+//!     fn abs(x: i32) -> i32;
+//! }
+//! assert_eq!(unsafe { abs(-3) }, 3);
+//! ```
+//!
+//! ```edition2024
+//! unsafe extern "C" {
+//!     // `safe` items can be called without unsafe
+//!     safe fn abs(x: i32) -> i32;
+//! }
+//! assert_eq!(abs(-3), 3);
+//! ```
+//!
+//! re: [Rust 2024 edition guide](https://doc.rust-lang.org/edition-guide/rust-2024/index.html)
@@ -0,0 +1,223 @@
+//! Endianness: parse the same binary header three ways
+//!
+//! The header deliberately mixes byte orders \[as real formats do\]:
+//!
+//! | offset | field | type | byte order |
+//! | --- | --- | --- | --- |
+//! | 0 | `magic` | `[u8; 4]` = `b"DEMO"` | n/a |
+//! | 4 | `version` | `u16` | little endian |
+//! | 6 | `kind` | `u8` | n/a |
+//! | 7 | `flags` | `u8` | n/a |
+//! | 8 | `len` \[payload bytes that follow\] | `u32` | big endian (network order) |
+//! | 12 | `seq` | `u64` | little endian |
+//!
+//! | parser | how |
+//! | --- | --- |
+//! | [Header::parse_std] | [u32::from_be_bytes] and friends on sub-slices |
+//! | [Header::parse_byteorder] | [byteorder::ReadBytesExt] on any [std::io::Read] |
+//! | [RawHeader::ref_from_prefix] | [zerocopy] casts `&[u8]` to `&RawHeader` in place \[no copy, fields are byte arrays that convert on access\] |
+//!
+//! NB: A plain `#[repr(C)] struct { len: u32 }` cast from bytes would read `len` in _native_
+//! order (little endian on x86 and ARM) and may be misaligned. Hence the `U32<BigEndian>` fields
+//! which have alignment 1.
+//!
+//! The fixture `fixtures/frames.bin` holds two header + payload frames and is read by the framing
+//! codec in `async_stuff::framing`. Dump it with `xxd simple/fixtures/frames.bin`:
+//! ```text
+//! 00000000: 4445 4d4f 0100 0100 0000 0005 2a00 0000  DEMO........*...
+//! 00000010: 0000 0000 6865 6c6c 6f44 454d 4f01 0002  ....helloDEMO...
+//! 00000020: 0000 0000 032b 0000 0000 0000 0001 0203  .....+..........
+//! ```
+//!
+//! re: [byteorder](https://docs.rs/byteorder) and [zerocopy](https://docs.rs/zerocopy)
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use std::io::{self, Read};
+use zerocopy::byteorder::{BigEndian as BE, LittleEndian as LE, U16, U32, U64};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+pub const MAGIC: [u8; 4] = *b"DEMO";
+
+/// Size of the header on the wire \[not `size_of::<Header>()` which has padding\]
+pub const HEADER_LEN: usize = 20;
+
+/// Parsed header in native types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u16,
+    pub kind: u8,
+    pub flags: u8,
+    pub len: u32,
+    pub seq: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    TooShort,
+    BadMagic([u8; 4]),
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::TooShort => io::ErrorKind::UnexpectedEof,
+            Error::BadMagic(_) => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, format!("{e:?}"))
+    }
+}
+
+impl Header {
+    pub fn parse_std(buf: &[u8]) -> Result<Self, Error> {
+        let buf: &[u8; HEADER_LEN] = buf
+            .get(..HEADER_LEN)
+            .and_then(|b| b.try_into().ok())
+            .ok_or(Error::TooShort)?;
+        // NB: try_into() turns a sub-slice into a fixed size array [can't fail after the check above]
+        let magic: [u8; 4] = buf[0..4].try_into().unwrap();
+        if magic != MAGIC {
+            return Err(Error::BadMagic(magic));
+        }
+        Ok(Self {
+            version: u16::from_le_bytes(buf[4..6].try_into().unwrap()),
+            kind: buf[6],
+            flags: buf[7],
+            len: u32::from_be_bytes(buf[8..12].try_into().unwrap()),
+            seq: u64::from_le_bytes(buf[12..20].try_into().unwrap()),
+        })
+    }
+
+    /// Reads exactly [HEADER_LEN] bytes from `r`
+    pub fn parse_byteorder(mut r: impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(Error::BadMagic(magic).into());
+        }
+        Ok(Self {
+            version: r.read_u16::<LittleEndian>()?,
+            kind: r.read_u8()?,
+            flags: r.read_u8()?,
+            len: r.read_u32::<BigEndian>()?,
+            seq: r.read_u64::<LittleEndian>()?,
+        })
+    }
+
+    pub fn parse_zerocopy(buf: &[u8]) -> Result<Self, Error> {
+        let (raw, _rest) = RawHeader::ref_from_prefix(buf).map_err(|_| Error::TooShort)?;
+        if raw.magic != MAGIC {
+            return Err(Error::BadMagic(raw.magic));
+        }
+        Ok(raw.into())
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let raw = RawHeader::from(*self);
+        raw.as_bytes().try_into().unwrap()
+    }
+}
+
+/// Wire layout, castable from/to bytes in place
+#[derive(FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned, Debug)]
+#[repr(C)]
+pub struct RawHeader {
+    pub magic: [u8; 4],
+    pub version: U16<LE>,
+    pub kind: u8,
+    pub flags: u8,
+    pub len: U32<BE>,
+    pub seq: U64<LE>,
+}
+
+const _: () = assert!(size_of::<RawHeader>() == HEADER_LEN);
+const _: () = assert!(align_of::<RawHeader>() == 1);
+
+impl From<&RawHeader> for Header {
+    fn from(raw: &RawHeader) -> Self {
+        Self {
+            version: raw.version.get(),
+            kind: raw.kind,
+            flags: raw.flags,
+            len: raw.len.get(),
+            seq: raw.seq.get(),
+        }
+    }
+}
+
+impl From<Header> for RawHeader {
+    fn from(h: Header) -> Self {
+        Self {
+            magic: MAGIC,
+            version: h.version.into(),
+            kind: h.kind,
+            flags: h.flags,
+            len: h.len.into(),
+            seq: h.seq.into(),
+        }
+    }
+}
+
+/// Reading a `u32` in the wrong order silently gives a different number
+pub fn misread_len(buf: &[u8; 4]) -> (u32, u32) {
+    (u32::from_be_bytes(*buf), u32::from_le_bytes(*buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../fixtures/frames.bin");
+
+    const FIRST: Header = Header {
+        version: 1,
+        kind: 1,
+        flags: 0,
+        len: 5,
+        seq: 42,
+    };
+
+    #[test]
+    fn test_fixture_all_parsers_agree() {
+        assert_eq!(Header::parse_std(FIXTURE), Ok(FIRST));
+        assert_eq!(Header::parse_zerocopy(FIXTURE), Ok(FIRST));
+        assert_eq!(Header::parse_byteorder(FIXTURE).unwrap(), FIRST);
+
+        // Second frame starts after first header + payload
+        let second = &FIXTURE[HEADER_LEN + 5..];
+        let h = Header::parse_std(second).unwrap();
+        assert_eq!((h.version, h.kind, h.len, h.seq), (1, 2, 3, 43));
+        assert_eq!(Header::parse_zerocopy(second), Ok(h));
+        assert_eq!(Header::parse_byteorder(second).unwrap(), h);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        assert_eq!(FIRST.to_bytes(), FIXTURE[..HEADER_LEN]);
+        let h = Header {
+            version: 0xABCD,
+            kind: 7,
+            flags: 0x80,
+            len: 0x0102_0304,
+            seq: u64::MAX - 1,
+        };
+        assert_eq!(Header::parse_std(&h.to_bytes()), Ok(h));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Header::parse_std(&FIXTURE[..10]), Err(Error::TooShort));
+        assert_eq!(Header::parse_zerocopy(&FIXTURE[..10]), Err(Error::TooShort));
+        let err = Header::parse_byteorder(&FIXTURE[..10]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut bad = FIXTURE.to_vec();
+        bad[0] = b'X';
+        assert_eq!(Header::parse_std(&bad), Err(Error::BadMagic(*b"XEMO")));
+    }
+
+    #[test]
+    fn test_misread() {
+        // len 5 in network order
+        assert_eq!(misread_len(&[0, 0, 0, 5]), (5, 0x0500_0000));
+    }
+}
@@ -0,0 +1,257 @@
+//! Exit codes and error output of the bins, instead of what `main() -> Result` does
+//!
+//! Returning `Err` from `main` prints its [Debug] \[`Error: Os { code: 2, kind: NotFound, .. }`\]
+//! and always exits with 1. [report] prints the [Display] of every error in the chain and picks
+//! a [sysexits](https://man.freebsd.org/cgi/man.cgi?query=sysexits) style code:
+//! ```text
+//! $ cargo run -q --bin checksum -- /nonexistent; echo $?
+//! error: /nonexistent
+//!   caused by: No such file or directory (os error 2)
+//! 66
+//! ```
+//!
+//! | [Code] | | e.g. |
+//! | --- | --- | --- |
+//! | [Code::Failure] | 1 | ran fine but the answer is no, e.g. a benchmark regressed |
+//! | [Code::Usage] | 64 | unknown flag, missing argument |
+//! | [Code::DataErr] | 65 | input that doesn't parse, e.g. [std::io::ErrorKind::InvalidData] or bad JSON |
+//! | [Code::NoInput] | 66 | input file doesn't exist |
+//! | [Code::Unavailable] | 69 | a tool the bin runs is missing, e.g. `git` |
+//! | [Code::Software] | 70 | anything not categorized \[a bug, as far as the bin knows\] |
+//! | [Code::IoErr] | 74 | any other [std::io::Error] |
+//! | [Code::Config] | 78 | bad config file or env var |
+//!
+//! NB: A panic still exits with 101 \[or aborts, see the `panic_strategy` bin\].
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::process::ExitCode;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Code {
+    Failure = 1,
+    Usage = 64,
+    DataErr = 65,
+    NoInput = 66,
+    Unavailable = 69,
+    Software = 70,
+    IoErr = 74,
+    Config = 78,
+}
+
+impl From<Code> for ExitCode {
+    fn from(code: Code) -> Self {
+        ExitCode::from(code as u8)
+    }
+}
+
+impl Code {
+    /// The first error in the chain with a known category, [Code::Software] if there is none
+    pub fn of(e: &(dyn Error + 'static)) -> Self {
+        chain(e).find_map(category).unwrap_or(Self::Software)
+    }
+}
+
+fn category(e: &(dyn Error + 'static)) -> Option<Code> {
+    if let Some(e) = e.downcast_ref::<Exit>() {
+        return Some(e.code);
+    }
+    if let Some(e) = e.downcast_ref::<io::Error>() {
+        // NB: io::Error::other(inner).source() is inner.source() rather than inner, look inside
+        if let Some(code) = e.get_ref().and_then(|inner| category(inner)) {
+            return Some(code);
+        }
+        return Some(match e.kind() {
+            io::ErrorKind::NotFound => Code::NoInput,
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => Code::DataErr,
+            _ => Code::IoErr,
+        });
+    }
+    if e.is::<serde_json::Error>()
+        || e.is::<std::num::ParseIntError>()
+        || e.is::<std::num::ParseFloatError>()
+    {
+        return Some(Code::DataErr);
+    }
+    None
+}
+
+fn chain<'a>(e: &'a (dyn Error + 'static)) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+    std::iter::successors(Some(e), |&e| e.source())
+}
+
+/// Any error or message with an explicit [Code], transparent otherwise
+#[derive(Debug)]
+pub struct Exit {
+    pub code: Code,
+    error: BoxError,
+}
+
+impl Exit {
+    pub fn new(code: Code, error: impl Into<BoxError>) -> Self {
+        Self {
+            code,
+            error: error.into(),
+        }
+    }
+
+    pub fn usage(error: impl Into<BoxError>) -> Self {
+        Self::new(Code::Usage, error)
+    }
+
+    pub fn failure(error: impl Into<BoxError>) -> Self {
+        Self::new(Code::Failure, error)
+    }
+
+    /// `error` as the source of `context` \[e.g. the path it is about\], code from `error`
+    pub fn context(context: impl fmt::Display, error: impl Error + Send + Sync + 'static) -> Self {
+        let code = Code::of(&error);
+        Self::new(
+            code,
+            Context {
+                context: context.to_string(),
+                source: Box::new(error),
+            },
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Context {
+    context: String,
+    source: BoxError,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.context)
+    }
+}
+
+impl Error for Context {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl Error for Exit {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// `error: ..` plus a `caused by: ..` line per source, without a trailing newline
+pub fn render(e: &(dyn Error + 'static)) -> String {
+    let mut lines = chain(e).map(ToString::to_string);
+    let mut out = format!("error: {}", lines.next().unwrap_or_default());
+    for line in lines {
+        out.push_str("\n  caused by: ");
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Print the error \[if any\] to stderr and turn it into the exit code, e.g.
+/// ```no_run
+/// # fn run() -> Result<(), std::io::Error> { Ok(()) }
+/// fn main() -> std::process::ExitCode {
+///     simple::exit::report(run())
+/// }
+/// ```
+/// Takes any error [anyhow::Error] can be made from, and [anyhow::Error] itself with its contexts.
+///
+/// NB: Going through `anyhow` rather than `Box<dyn Error>` matters: the `Box` anyhow converts
+/// into hides the original error from [Error::downcast_ref], [anyhow::Error::as_ref] doesn't.
+pub fn report<E: Into<anyhow::Error>>(result: Result<(), E>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let e: anyhow::Error = e.into();
+            eprintln!("{}", render(e.as_ref()));
+            Code::of(e.as_ref()).into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Outer(io::Error);
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("reading config")
+        }
+    }
+
+    impl Error for Outer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_code_of() {
+        let not_found = || io::Error::new(io::ErrorKind::NotFound, "gone");
+        assert_eq!(Code::of(&not_found()), Code::NoInput);
+        assert_eq!(Code::of(&io::Error::other("disk")), Code::IoErr);
+        assert_eq!(Code::of(&"x".parse::<u8>().unwrap_err()), Code::DataErr);
+        let json = serde_json::from_str::<u8>("x").unwrap_err();
+        assert_eq!(Code::of(&io::Error::other(json)), Code::DataErr);
+        // Found via source()
+        assert_eq!(Code::of(&Outer(not_found())), Code::NoInput);
+        // Explicit code wins over what it wraps
+        assert_eq!(
+            Code::of(&Exit::new(Code::Config, Outer(not_found()))),
+            Code::Config
+        );
+        assert_eq!(Code::of(&Exit::usage("usage: x")), Code::Usage);
+        assert_eq!(Code::of(&fmt::Error), Code::Software);
+    }
+
+    #[test]
+    fn test_render() {
+        let e = Outer(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(render(&e), "error: reading config\n  caused by: gone");
+        // Exit is transparent, no extra line
+        assert_eq!(
+            render(&Exit::failure(e)),
+            "error: reading config\n  caused by: gone"
+        );
+
+        let e = Exit::context(
+            "demos.toml",
+            io::Error::new(io::ErrorKind::NotFound, "gone"),
+        );
+        assert_eq!(e.code, Code::NoInput);
+        assert_eq!(render(&e), "error: demos.toml\n  caused by: gone");
+    }
+
+    #[test]
+    fn test_through_anyhow() {
+        use anyhow::Context;
+        let e = Err::<(), _>(Exit::usage("usage: x"))
+            .context("parsing args")
+            .unwrap_err();
+        assert_eq!(Code::of(e.as_ref()), Code::Usage);
+        assert_eq!(
+            render(e.as_ref()),
+            "error: parsing args\n  caused by: usage: x"
+        );
+        // The Box anyhow converts into hides Exit
+        let boxed: BoxError = anyhow::Error::from(Exit::usage("usage: x")).into();
+        assert_eq!(Code::of(&*boxed), Code::Software);
+    }
+}
@@ -0,0 +1,213 @@
+//! Floating point pitfalls: NaN, ordering, associativity and decimal alternatives
+//!
+//! | pitfall | example | instead |
+//! | --- | --- | --- |
+//! | NaN propagates through arithmetic | `f64::NAN + 1.0` is NaN | check with [f64::is_nan] at the boundary |
+//! | NaN is not equal to itself | `NAN == NAN` is `false` | [f64::is_nan], or compare [f64::to_bits] |
+//! | `f64` is only [PartialOrd] | `partial_cmp(NAN)` is `None` so `sort_by(partial_cmp.unwrap())` panics | [f64::total_cmp] |
+//! | `min`/`max` _ignore_ NaN | `NAN.max(1.0)` is `1.0` \[unlike `+`\] | filter NaN first if it should win |
+//! | `+` is not associative | `(0.1 + 0.2) + 0.3 != 0.1 + (0.2 + 0.3)` | [kahan_sum()] or [rust_decimal::Decimal] |
+//! | `0.1` is not representable | `0.1 + 0.2 != 0.3` | [rust_decimal::Decimal] for money |
+//!
+//! NB: Rust never reassociates float math \[no `-ffast-math`\], so `iter().sum()` adds strictly left
+//! to right and the same code gives the same result in debug and release. The _order_ of the
+//! items is what changes the result, e.g. summing in parallel chunks or after sorting.
+//!
+//! The invariants are encoded as property tests \[proptest\] in the tests below.
+//!
+//! re: [What Every Computer Scientist Should Know About Floating-Point Arithmetic](https://docs.oracle.com/cd/E19957-01/806-3568/ncg_goldberg.html)
+
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+
+/// Panics on the first NaN \[`partial_cmp()` returns `None`\]
+pub fn sort_partial(v: &mut [f64]) {
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+}
+
+/// Never panics. Order is `-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN`.
+pub fn sort_total(v: &mut [f64]) {
+    v.sort_by(f64::total_cmp);
+}
+
+/// Largest value ignoring NaN, `None` if there is none
+pub fn max_ignoring_nan(v: &[f64]) -> Option<f64> {
+    v.iter()
+        .copied()
+        .filter(|x| !x.is_nan())
+        .max_by(f64::total_cmp)
+}
+
+/// Same as `iter().sum()` \[strict left to right\]
+pub fn naive_sum(v: &[f64]) -> f64 {
+    v.iter().sum()
+}
+
+/// Sum in `chunk`-sized partial sums, like a parallel or vectorized reduction would
+pub fn chunked_sum(v: &[f64], chunk: usize) -> f64 {
+    v.chunks(chunk).map(naive_sum).sum()
+}
+
+/// Compensated summation: carries the lost low bits along so the order matters much less
+pub fn kahan_sum(v: &[f64]) -> f64 {
+    let (mut sum, mut c) = (0.0, 0.0);
+    for x in v {
+        let y = x - c;
+        let t = sum + y;
+        // Algebraically 0, actually the part of `y` that didn't make it into `t`
+        c = (t - sum) - y;
+        sum = t;
+    }
+    sum
+}
+
+/// `price * qty` summed exactly in base 10
+pub fn total_decimal(items: &[(Decimal, u32)]) -> Decimal {
+    items
+        .iter()
+        .map(|(price, qty)| price * Decimal::from(*qty))
+        .sum()
+}
+
+pub fn total_f64(items: &[(f64, u32)]) -> f64 {
+    items
+        .iter()
+        .map(|(price, qty)| price * f64::from(*qty))
+        .sum()
+}
+
+/// Tolerance based comparison \[there's no universally right epsilon\]
+pub fn approx_eq(a: f64, b: f64, rel: f64) -> bool {
+    a == b || (a - b).abs() <= rel * a.abs().max(b.abs())
+}
+
+/// `Ordering` wrapper so `f64` can be a `BTreeMap` key or go in a `BinaryHeap`
+#[derive(Debug, Clone, Copy)]
+pub struct TotalF64(pub f64);
+
+impl PartialEq for TotalF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalF64 {}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
+    // Regarding clippy, comparing floats exactly is the point of these tests
+    #[allow(clippy::float_cmp)]
+    #[test]
+    fn test_nan() {
+        let nan = f64::NAN;
+        assert!((nan + 1.0).is_nan());
+        assert!((nan * 0.0).is_nan());
+        assert!((f64::INFINITY * 0.0).is_nan());
+        assert_ne!(nan, nan);
+        assert_eq!(nan.to_bits(), f64::NAN.to_bits());
+        assert_eq!(nan.partial_cmp(&1.0), None);
+        // min/max pick the non-NaN side
+        assert_eq!(nan.max(1.0), 1.0);
+        assert_eq!(1.0_f64.min(nan), 1.0);
+        // -0.0 == 0.0 but total_cmp tells them apart
+        assert_eq!(-0.0, 0.0);
+        assert_eq!((-0.0_f64).total_cmp(&0.0), Ordering::Less);
+    }
+
+    #[test]
+    #[should_panic(expected = "called `Option::unwrap()` on a `None` value")]
+    fn test_sort_partial_panics() {
+        sort_partial(&mut [1.0, f64::NAN, 0.5]);
+    }
+
+    #[test]
+    fn test_sort_total() {
+        let mut v = [1.0, f64::NAN, -f64::NAN, 0.5, f64::NEG_INFINITY, -0.0];
+        sort_total(&mut v);
+        assert!(v[0].is_nan() && v[0].is_sign_negative());
+        assert_eq!(v[1..5], [f64::NEG_INFINITY, -0.0, 0.5, 1.0]);
+        assert!(v[5].is_nan());
+        assert_eq!(max_ignoring_nan(&v), Some(1.0));
+    }
+
+    #[allow(clippy::float_cmp)]
+    #[test]
+    fn test_associativity() {
+        assert_ne!((0.1 + 0.2) + 0.3, 0.1 + (0.2 + 0.3));
+        assert_ne!(0.1 + 0.2, 0.3);
+
+        // Large + many small: the small ones get lost one by one
+        let mut v = vec![1e16];
+        v.extend(std::iter::repeat_n(1.0, 1_000));
+        assert_eq!(naive_sum(&v), 1e16);
+        assert_eq!(chunked_sum(&v, 100), 1e16 + 900.0);
+        assert_eq!(kahan_sum(&v), 1e16 + 1_000.0);
+    }
+
+    #[test]
+    fn test_decimal() {
+        let d = |s| Decimal::from_str(s).unwrap();
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+        let items = [(d("0.10"), 3), (d("19.99"), 2)];
+        assert_eq!(total_decimal(&items), d("40.28"));
+        let as_f64 = total_f64(&[(0.10, 3), (19.99, 2)]);
+        assert_ne!(as_f64.to_string(), "40.28");
+        assert!(approx_eq(as_f64, 40.28, 1e-12));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_nan_propagates(x in any::<f64>()) {
+            prop_assert!((x + f64::NAN).is_nan());
+            prop_assert!((f64::NAN * x).is_nan());
+        }
+
+        #[test]
+        fn prop_total_cmp_sort_is_sorted(mut v in prop::collection::vec(any::<f64>(), 0..50)) {
+            sort_total(&mut v);
+            prop_assert!(v.is_sorted_by(|a, b| a.total_cmp(b).is_le()));
+        }
+
+        /// Addition is commutative, just not associative
+        #[test]
+        fn prop_commutative(a in any::<f64>(), b in any::<f64>()) {
+            // NB: Two NaNs may keep different payloads depending on the operand order
+            prop_assume!(!a.is_nan() && !b.is_nan());
+            prop_assert_eq!((a + b).to_bits(), (b + a).to_bits());
+        }
+
+        /// Reordering changes the naive sum by at most a few ulps per item, Kahan by far less
+        #[test]
+        fn prop_kahan_order_insensitive(v in prop::collection::vec(-1e6..1e6f64, 1..200)) {
+            let mut rev = v.clone();
+            rev.reverse();
+            let scale: f64 = v.iter().map(|x| x.abs()).sum();
+            prop_assert!((kahan_sum(&v) - kahan_sum(&rev)).abs() <= scale * 4.0 * f64::EPSILON);
+            let n = v.len() as f64;
+            prop_assert!((naive_sum(&v) - kahan_sum(&v)).abs() <= n * f64::EPSILON * scale);
+        }
+
+        /// Decimal addition is exact, so associative \[within its 28 digits\]
+        #[test]
+        fn prop_decimal_associative(a in -1_000_000i64..1_000_000, b in -1_000_000i64..1_000_000, c in -1_000_000i64..1_000_000) {
+            let (a, b, c) = (Decimal::new(a, 2), Decimal::new(b, 2), Decimal::new(c, 2));
+            prop_assert_eq!((a + b) + c, a + (b + c));
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Higher-ranked trait bounds (`for<'a>`) and closure lifetime inference
+//!
+//! A tiny parser-combinator API where every parser is a closure
+//! `for<'a> Fn(&'a str) -> Option<(&'a str, &'a str)>` returning `(matched, rest)`. The `for<'a>`
+//! says the closure works for _any_ input lifetime the caller picks \[rather than one specific
+//! lifetime fixed when the closure was created\].
+//!
+//! NB: `Fn(&str) -> Option<(&str, &str)>` is the same bound, just with the `for<'a>` elided.
+//!
+//! The classic error: a closure stored in a `let` _before_ being passed to something that needs
+//! `for<'a>` has its signature inferred without that bound, which picks one specific lifetime:
+//! ```compile_fail
+//! use simple::hrtb::parse;
+//! let empty = |s| Some(("", s));
+//! parse(&empty, "abc");
+//! // error: implementation of `FnOnce` is not general enough
+//! //   = note: closure with signature `fn(&'2 str) -> Option<(&str, &str)>` must implement `FnOnce<(&'1 str,)>`, for any lifetime `'1`...
+//! //   = note: ...but it actually implements `FnOnce<(&'2 str,)>`, for some specific lifetime `'2`
+//! ```
+//!
+//! Annotating the argument type is not enough, as the elided output lifetimes are _not_ tied to
+//! the input in closures \[unlike `fn` items\]:
+//! ```compile_fail
+//! use simple::hrtb::parse;
+//! let first = |s: &str| Some((&s[..1], &s[1..]));
+//! parse(&first, "abc");
+//! // error: lifetime may not live long enough
+//! //   returning this value requires that `'1` must outlive `'2`
+//! ```
+//!
+//! Fixes:
+//! 1. Pass the closure directly where the HRTB bound is \[its signature is then deduced from the
+//!    bound\]: `parse(&|s| Some((&s[..1], &s[1..])), "abc")`
+//! 2. Helper function that does nothing but impose the bound: [recognizer]
+//! 3. A `fn` item instead of a closure \[elision rules tie output to input lifetimes\]
+//! 4. Nightly only: `for<'a> |s: &'a str| -> Option<(&'a str, &'a str)> { ... }`
+//!    (`closure_lifetime_binder`)
+//!
+//! ```
+//! use simple::hrtb::{parse, recognizer};
+//! // 1.
+//! assert_eq!(parse(&|s| Some((&s[..1], &s[1..])), "abc"), Some(("a", "bc")));
+//! // 2.
+//! let empty = recognizer(|s| Some(("", s)));
+//! assert_eq!(parse(&empty, "abc"), Some(("", "abc")));
+//! let first = recognizer(|s| Some((&s[..1], &s[1..])));
+//! assert_eq!(parse(&first, "abc"), Some(("a", "bc")));
+//! // 3.
+//! fn first_fn(s: &str) -> Option<(&str, &str)> {
+//!     Some((&s[..1], &s[1..]))
+//! }
+//! assert_eq!(parse(&first_fn, "abc"), Some(("a", "bc")));
+//! ```
+//!
+//! re: [Higher-ranked trait bounds](https://doc.rust-lang.org/nomicon/hrtb.html)
+
+/// Returns `(matched, rest)` on success
+pub trait Recognizer: for<'a> Fn(&'a str) -> Option<(&'a str, &'a str)> {}
+
+impl<F> Recognizer for F where F: for<'a> Fn(&'a str) -> Option<(&'a str, &'a str)> {}
+
+/// Identity function whose only job is the `for<'a>` bound \[so closures passed to it get a
+/// higher-ranked signature\]
+pub fn recognizer<F>(f: F) -> F
+where
+    F: for<'a> Fn(&'a str) -> Option<(&'a str, &'a str)>,
+{
+    f
+}
+
+pub fn parse<'a>(p: &impl Recognizer, input: &'a str) -> Option<(&'a str, &'a str)> {
+    p(input)
+}
+
+pub fn tag(expected: &'static str) -> impl Recognizer {
+    recognizer(move |s| {
+        s.strip_prefix(expected)
+            .map(|rest| (consumed(s, rest), rest))
+    })
+}
+
+/// Longest (possibly empty) prefix whose chars all satisfy `pred`
+pub fn take_while(pred: fn(char) -> bool) -> impl Recognizer {
+    recognizer(move |s| {
+        let end = s.find(|c| !pred(c)).unwrap_or(s.len());
+        Some(s.split_at(end))
+    })
+}
+
+/// Like [take_while] but must match at least one char
+pub fn take_while1(pred: fn(char) -> bool) -> impl Recognizer {
+    let p = take_while(pred);
+    recognizer(move |s| p(s).filter(|(matched, _)| !matched.is_empty()))
+}
+
+/// `p1` then `p2`. The match spans both.
+pub fn seq(p1: impl Recognizer, p2: impl Recognizer) -> impl Recognizer {
+    recognizer(move |s| {
+        let (_, rest) = p1(s)?;
+        let (_, rest) = p2(rest)?;
+        Some((consumed(s, rest), rest))
+    })
+}
+
+/// `p` or nothing
+pub fn opt(p: impl Recognizer) -> impl Recognizer {
+    recognizer(move |s| p(s).or(Some(("", s))))
+}
+
+/// The prefix of `input` that was consumed to get to `rest`
+fn consumed<'a>(input: &'a str, rest: &str) -> &'a str {
+    &input[..input.len() - rest.len()]
+}
+
+/// HRTB outside of closures: "`&C` is iterable for _every_ borrow of `c`". `c` is owned, so its
+/// borrows are of a local of the body, which no lifetime parameter of the function can name
+/// \[the caller picks those, and they outlive the call\].
+///
+/// ```
+/// use simple::hrtb::sum_twice;
+/// assert_eq!(sum_twice(vec![1, 2, 3]), 12);
+/// assert_eq!(sum_twice(std::collections::BTreeSet::from([1, 2])), 6);
+/// ```
+///
+/// With a lifetime parameter instead, `&c` would have to live for `'a`, past the end of the
+/// body where `c` is dropped:
+///
+/// ```compile_fail,E0597
+/// fn sum_twice<'a, C>(c: C) -> u32
+/// where
+///     &'a C: IntoIterator<Item = &'a u32>,
+/// {
+///     (&c).into_iter().sum()
+/// }
+/// ```
+///
+/// NB: Taking `c: &'a C` would need no HRTB, `where &'a C: IntoIterator<Item = &'a u32>` then
+/// describes the one borrow there is.
+pub fn sum_twice<C>(c: C) -> u32
+where
+    for<'a> &'a C: IntoIterator<Item = &'a u32>,
+{
+    let first: u32 = (&c).into_iter().sum();
+    let second: u32 = (&c).into_iter().sum();
+    first + second
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number() -> impl Recognizer {
+        let digits = || take_while1(|c| c.is_ascii_digit());
+        seq(seq(opt(tag("-")), digits()), opt(seq(tag("."), digits())))
+    }
+
+    #[test]
+    fn test_number() {
+        let p = number();
+        assert_eq!(parse(&p, "42rest"), Some(("42", "rest")));
+        assert_eq!(parse(&p, "-3.14)"), Some(("-3.14", ")")));
+        assert_eq!(parse(&p, "1."), Some(("1", ".")));
+        assert_eq!(parse(&p, "abc"), None);
+    }
+
+    #[test]
+    fn test_any_lifetime() {
+        let p = number();
+        // Same parser used on inputs with unrelated lifetimes
+        let owned = String::from("7 days");
+        let matched_owned = parse(&p, &owned).unwrap().0;
+        let matched_static = parse(&p, "8 days").unwrap().0;
+        assert_eq!((matched_owned, matched_static), ("7", "8"));
+    }
+}
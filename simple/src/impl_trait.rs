@@ -0,0 +1,187 @@
+//! `impl Trait` in argument position (APIT) vs return position (RPIT) vs in trait methods (RPITIT)
+//!
+//! - APIT is (almost) sugar for an anonymous generic parameter. The difference: callers cannot
+//!   name it with turbofish.
+//! - RPIT is an _opaque_ type chosen by the callee. Callers only see the trait, and the function
+//!   must return a single concrete type.
+//! - RPITIT (stable since 1.75) allows `-> impl Trait` in trait methods \[each impl picks its own
+//!   hidden type\].
+//! - Captured lifetimes: In Rust 2024, RPIT captures _every_ in-scope lifetime. Use `use<..>` to
+//!   capture fewer. `+ 'a` is an _outlives_ bound which is not the same as capturing.
+//!
+//! See also `rpit_capture_ed.rs` and [crate::edition_2024] for the Rust 2021 vs 2024 difference.
+//!
+//! re: [impl Trait](https://doc.rust-lang.org/reference/types/impl-trait.html)
+
+use std::fmt::Display;
+
+/// APIT
+///
+/// Turbofish is _not_ allowed with APIT:
+/// ```compile_fail,E0107
+/// use simple::impl_trait::sum_apit;
+/// sum_apit::<Vec<u32>>(vec![1, 2]); // error[E0107]: function takes 0 generic arguments but 1 generic argument was supplied
+/// ```
+pub fn sum_apit(items: impl IntoIterator<Item = u32>) -> u32 {
+    items.into_iter().sum()
+}
+
+/// Equivalent generic version. Turbofish works:
+/// ```
+/// use simple::impl_trait::sum_generic;
+/// assert_eq!(sum_generic::<Vec<u32>>(vec![1, 2]), 3);
+/// ```
+pub fn sum_generic<I: IntoIterator<Item = u32>>(items: I) -> u32 {
+    items.into_iter().sum()
+}
+
+/// RPIT: the caller cannot tell this is a `Filter<Range<u32>, _>`
+///
+/// Only the trait's methods are available:
+/// ```compile_fail,E0609
+/// use simple::impl_trait::evens;
+/// let it = evens(10);
+/// it.start; // error[E0609]: no field `start` on type `impl Iterator<Item = u32>`
+/// ```
+///
+/// All return paths must have the _same_ concrete type:
+/// ```compile_fail,E0308
+/// fn evens_or_odds(even: bool) -> impl Iterator<Item = u32> {
+///     if even {
+///         (0..10).filter(|n| n % 2 == 0)
+///     } else {
+///         (0..10).map(|n| n * 2 + 1) // error[E0308]: mismatched types
+///     }
+/// }
+/// ```
+pub fn evens(below: u32) -> impl Iterator<Item = u32> {
+    (0..below).filter(|n| n % 2 == 0)
+}
+
+/// Fix for the above: box it \[dynamic dispatch\] or use an enum like `either::Either`
+pub fn evens_or_odds(even: bool, below: u32) -> Box<dyn Iterator<Item = u32>> {
+    if even {
+        Box::new((0..below).filter(|n| n % 2 == 0))
+    } else {
+        Box::new((0..below).filter(|n| n % 2 == 1))
+    }
+}
+
+/// RPITIT: each impl has its own hidden iterator type
+pub trait Source {
+    fn items(&self) -> impl Iterator<Item = u32>;
+}
+
+pub struct VecSource(pub Vec<u32>);
+
+impl Source for VecSource {
+    /// Hidden type borrows self: `Copied<slice::Iter<'_, u32>>`
+    fn items(&self) -> impl Iterator<Item = u32> {
+        self.0.iter().copied()
+    }
+}
+
+pub struct RangeSource(pub u32);
+
+impl Source for RangeSource {
+    /// Hidden type: `Range<u32>`
+    fn items(&self) -> impl Iterator<Item = u32> {
+        0..self.0
+    }
+}
+
+/// Generic over any [Source] \[but not usable as `dyn Source` since RPITIT is not dyn-compatible\]
+///
+/// ```compile_fail,E0038
+/// use simple::impl_trait::Source;
+/// fn total(s: &dyn Source) -> u32 {
+///     s.items().sum()
+/// }
+/// ```
+pub fn total(s: &impl Source) -> u32 {
+    s.items().sum()
+}
+
+/// Rust 2024: captures the (elided) lifetimes of both `s` and `sep` \[even though the result is
+/// an owned `String`\]
+///
+/// So `sep` must outlive the result:
+/// ```compile_fail,E0597
+/// use simple::impl_trait::first_word_captures_all;
+/// let s = String::from("hello world");
+/// let word = {
+///     let sep = String::from(" ");
+///     first_word_captures_all(&s, &sep) // error[E0597]: `sep` does not live long enough
+/// };
+/// println!("{word}");
+/// ```
+pub fn first_word_captures_all(s: &str, sep: &str) -> impl Display {
+    s.split(sep).next().unwrap_or("").to_string()
+}
+
+/// Precise capturing: the result only captures `'a`
+///
+/// ```
+/// use simple::impl_trait::first_word_precise;
+/// let s = String::from("hello world");
+/// let word = {
+///     let sep = String::from(" ");
+///     first_word_precise(&s, &sep)
+/// };
+/// assert_eq!(word.to_string(), "hello");
+/// ```
+///
+/// NB: Returning `&'a str` would actually need `'a` captured. Forgetting it in `use<>` is an error:
+/// ```compile_fail,E0700
+/// fn first_word<'a, 'b>(s: &'a str, sep: &'b str) -> impl std::fmt::Display + use<'b> {
+///     s.split(sep).next().unwrap_or("") // error[E0700]: hidden type for `impl std::fmt::Display` captures lifetime that does not appear in bounds
+/// }
+/// ```
+pub fn first_word_precise<'a>(s: &'a str, sep: &str) -> impl Display + use<'a> {
+    // Returns a &'a str (no allocation) so `'a` must be captured. `sep` is only used during the call.
+    s.split(sep).next().unwrap_or("")
+}
+
+/// `+ 'a` is an outlives bound, _not_ a capture list.
+/// In Rust 2021 it was the common trick to make RPIT capture `'a`. In Rust 2024 it is redundant
+/// \[as `'a` is captured anyway\].
+pub fn chars_of<'a>(s: &'a str) -> impl Iterator<Item = char> + 'a {
+    s.chars()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apit_vs_generic() {
+        assert_eq!(sum_apit(vec![1, 2, 3]), sum_generic(vec![1, 2, 3]));
+        assert_eq!(sum_apit([1, 2, 3]), 6);
+        assert_eq!(sum_generic::<[u32; 3]>([1, 2, 3]), 6);
+    }
+
+    #[test]
+    fn test_rpit() {
+        assert_eq!(evens(7).collect::<Vec<_>>(), [0, 2, 4, 6]);
+        assert_eq!(evens_or_odds(false, 7).collect::<Vec<_>>(), [1, 3, 5]);
+    }
+
+    #[test]
+    fn test_rpitit() {
+        assert_eq!(total(&VecSource(vec![1, 2, 3])), 6);
+        assert_eq!(total(&RangeSource(4)), 6);
+    }
+
+    #[test]
+    fn test_captures() {
+        let s = String::from("hi there");
+        let word = {
+            let sep = String::from(" ");
+            first_word_precise(&s, &sep)
+        };
+        assert_eq!(word.to_string(), "hi");
+        assert_eq!(chars_of(&s).count(), 8);
+        let sep = String::from(" ");
+        assert_eq!(first_word_captures_all(&s, &sep).to_string(), "hi");
+    }
+}
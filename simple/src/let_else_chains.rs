@@ -0,0 +1,191 @@
+//! Same validation pipeline four ways: nested `match`, nested `if let`, `let else` and let chains
+//!
+//! Parse `"name:age:email"` into a [User]. Each step can fail.
+//!
+//! | version | nesting | notes |
+//! | --- | --- | --- |
+//! | [parse_match] | deep | most explicit, rightward drift |
+//! | [parse_if_let] | deep | less noise, still rightward drift |
+//! | [parse_let_else] | flat | early return on failure (stable since 1.65) |
+//! | [parse_let_chains] | flat | `if let ... && let ... && cond` (stable since 1.88 _only_ in Rust 2024, no feature gate needed) |
+//!
+//! ## `if let` temporary scope changed in Rust 2024
+//!
+//! Temporaries created in the `if let` scrutinee used to live until the end of the _whole_
+//! `if let ... else ...`, i.e. they were still alive inside `else`. In Rust 2024 they are dropped
+//! before entering `else`. With a [std::cell::RefCell] this is the difference between a panic and
+//! working code (and with a `Mutex` it's a deadlock):
+//!
+//! ```edition2021,should_panic
+//! use std::cell::RefCell;
+//! let cell = RefCell::new(Vec::<i32>::new());
+//! if let Some(first) = cell.borrow().first() {
+//!     println!("first = {first}");
+//! } else {
+//!     // Rust 2021: the Ref from cell.borrow() is still alive here => already borrowed: BorrowMutError
+//!     cell.borrow_mut().push(1);
+//! }
+//! drop(cell); // NB: Without this statement the if let is the tail expression [a different rule]
+//! ```
+//!
+//! ```edition2024
+//! use std::cell::RefCell;
+//! let cell = RefCell::new(Vec::<i32>::new());
+//! if let Some(first) = cell.borrow().first() {
+//!     println!("first = {first}");
+//! } else {
+//!     // Rust 2024: the Ref was dropped before entering else
+//!     cell.borrow_mut().push(1);
+//! }
+//! assert_eq!(*cell.borrow(), [1]);
+//! ```
+//!
+//! Let chains are rejected in older editions:
+//! ```edition2021,compile_fail
+//! let (a, b) = (Some(1), Some(2));
+//! if let Some(a) = a && let Some(b) = b {
+//!     println!("{a} {b}");
+//! }
+//! ```
+//!
+//! re: [if let temporary scope](https://doc.rust-lang.org/edition-guide/rust-2024/temporary-if-let-scope.html)
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct User<'a> {
+    pub name: &'a str,
+    pub age: u8,
+    pub email: &'a str,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    MissingField(&'static str),
+    BadAge,
+    Underage,
+    BadEmail,
+}
+
+fn split3(s: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = s.splitn(3, ':');
+    Some((parts.next()?, parts.next()?, parts.next()?))
+}
+
+pub fn parse_match(s: &str) -> Result<User<'_>, Error> {
+    match split3(s) {
+        Some((name, age, email)) => match age.parse::<u8>() {
+            Ok(age) => {
+                if age >= 18 {
+                    match email.split_once('@') {
+                        Some((_, domain)) if !domain.is_empty() => Ok(User { name, age, email }),
+                        _ => Err(Error::BadEmail),
+                    }
+                } else {
+                    Err(Error::Underage)
+                }
+            }
+            Err(_) => Err(Error::BadAge),
+        },
+        None => Err(Error::MissingField("name:age:email")),
+    }
+}
+
+// Regarding clippy, collapsing the nested `if`s needs let chains which is parse_let_chains()
+#[allow(clippy::collapsible_if)]
+pub fn parse_if_let(s: &str) -> Result<User<'_>, Error> {
+    if let Some((name, age, email)) = split3(s) {
+        if let Ok(age) = age.parse::<u8>() {
+            if age < 18 {
+                return Err(Error::Underage);
+            }
+            if let Some((_, domain)) = email.split_once('@') {
+                if !domain.is_empty() {
+                    return Ok(User { name, age, email });
+                }
+            }
+            Err(Error::BadEmail)
+        } else {
+            Err(Error::BadAge)
+        }
+    } else {
+        Err(Error::MissingField("name:age:email"))
+    }
+}
+
+pub fn parse_let_else(s: &str) -> Result<User<'_>, Error> {
+    let Some((name, age, email)) = split3(s) else {
+        return Err(Error::MissingField("name:age:email"));
+    };
+    let Ok(age) = age.parse::<u8>() else {
+        return Err(Error::BadAge);
+    };
+    if age < 18 {
+        return Err(Error::Underage);
+    }
+    // NB: The else block must diverge (return, break, continue, panic, ...)
+    let Some((_, domain)) = email.split_once('@') else {
+        return Err(Error::BadEmail);
+    };
+    if domain.is_empty() {
+        return Err(Error::BadEmail);
+    }
+    Ok(User { name, age, email })
+}
+
+pub fn parse_let_chains(s: &str) -> Result<User<'_>, Error> {
+    let Some((name, age, email)) = split3(s) else {
+        return Err(Error::MissingField("name:age:email"));
+    };
+    // Pattern matches and boolean conditions mixed in one `if`
+    if let Ok(age) = age.parse::<u8>()
+        && age >= 18
+        && let Some((_, domain)) = email.split_once('@')
+        && !domain.is_empty()
+    {
+        return Ok(User { name, age, email });
+    }
+    // NB: Downside of chains: we no longer know *which* condition failed without re-checking
+    match age.parse::<u8>() {
+        Err(_) => Err(Error::BadAge),
+        Ok(age) if age < 18 => Err(Error::Underage),
+        Ok(_) => Err(Error::BadEmail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Parser = for<'a> fn(&'a str) -> Result<User<'a>, Error>;
+
+    const PARSERS: &[(&str, Parser)] = &[
+        ("match", parse_match),
+        ("if_let", parse_if_let),
+        ("let_else", parse_let_else),
+        ("let_chains", parse_let_chains),
+    ];
+
+    #[test]
+    fn test_all_parsers_agree() {
+        let cases = [
+            (
+                "ann:30:ann@example.com",
+                Ok(User {
+                    name: "ann",
+                    age: 30,
+                    email: "ann@example.com",
+                }),
+            ),
+            ("bob:30", Err(Error::MissingField("name:age:email"))),
+            ("bob:old:bob@example.com", Err(Error::BadAge)),
+            ("bob:999:bob@example.com", Err(Error::BadAge)),
+            ("kid:12:kid@example.com", Err(Error::Underage)),
+            ("eve:40:eve.example.com", Err(Error::BadEmail)),
+            ("eve:40:eve@", Err(Error::BadEmail)),
+        ];
+        for (input, expected) in cases {
+            for (name, parse) in PARSERS {
+                assert_eq!(parse(input), expected, "parser {name} on {input:?}");
+            }
+        }
+    }
+}
@@ -1,8 +1,30 @@
+pub mod alloc_counter;
 pub mod anon_lifetime;
+pub mod bench_report;
 pub mod box_dyn_is_static;
+pub mod clocks;
+pub mod const_eval;
+pub mod drop_flags;
+pub mod dst;
+pub mod dyn_upcast;
+pub mod edition_2024;
+pub mod endian;
+pub mod exit;
+pub mod floats;
 pub mod generic_implicit_sized;
+pub mod hrtb;
+pub mod impl_trait;
+pub mod let_else_chains;
+pub mod logging;
+pub mod overflow;
+pub mod process_info;
+pub mod provenance;
+pub mod random;
+pub mod report;
 pub mod to_ub_or_not_ub;
 pub mod too_many_lists;
+pub mod typestate;
+pub mod zst;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,216 @@
+//! The same workload reporting what it does three ways: `eprintln!`, [log] and [tracing]
+//!
+//! | | `eprintln!` | [log] + [env_logger] | [tracing] + [tracing_subscriber] |
+//! | --- | --- | --- | --- |
+//! | filtering | hand-rolled `if verbose` | `RUST_LOG=debug` | `RUST_LOG=debug` via [EnvFilter](tracing_subscriber::EnvFilter) |
+//! | fields | formatted into the message | formatted into the message \[key-values are behind the `kv` feature\] | structured `key = value`, typed until a layer formats them |
+//! | context \[which batch?\] | repeated in every message | repeated in every message | a span, inherited by every event inside it |
+//! | consumers | stderr | exactly one global [log::Log] | any number of layers, e.g. [ReportLayer] |
+//! | cost when disabled | the `if` | a level check | a level check, callsite interest is cached |
+//!
+//! ```sh
+//! cargo run --bin logging -- eprintln --verbose
+//! RUST_LOG=debug cargo run --bin logging -- log
+//! RUST_LOG=info cargo run --bin logging -- tracing   # plus a Report of _all_ events as JSON
+//! ```
+//!
+//! NB: [tracing_subscriber]'s default `tracing-log` feature can forward [log] records into
+//! tracing, which is how crates using either end up in one output.
+
+use crate::report::Report;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::{Event, Subscriber, span};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Sample input, with a few items that don't parse
+pub const BATCHES: &[&[&str]] = &[&["1", "2", "3"], &["4", "five", "6"], &["-7", "", "8"]];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Summary {
+    pub parsed: usize,
+    pub skipped: usize,
+    pub sum: i64,
+}
+
+/// Parses every item, reporting progress via `eprintln!` if `verbose`
+pub fn sum_eprintln(batches: &[&[&str]], verbose: bool) -> Summary {
+    let mut summary = Summary::default();
+    for (batch, items) in batches.iter().enumerate() {
+        let mut sum = 0;
+        for item in *items {
+            match item.parse::<i64>() {
+                Ok(n) => {
+                    if verbose {
+                        eprintln!("DEBUG batch {batch}: parsed {n}");
+                    }
+                    sum += n;
+                    summary.parsed += 1;
+                }
+                Err(e) => {
+                    // Warnings always, there's no level to turn them off separately
+                    eprintln!("WARN batch {batch}: skipping {item:?}: {e}");
+                    summary.skipped += 1;
+                }
+            }
+        }
+        if verbose {
+            eprintln!("INFO batch {batch}: {} items, sum {sum}", items.len());
+        }
+        summary.sum += sum;
+    }
+    summary
+}
+
+/// Same as [sum_eprintln] via the [log] facade, a no-op until a logger is installed
+pub fn sum_log(batches: &[&[&str]]) -> Summary {
+    let mut summary = Summary::default();
+    for (batch, items) in batches.iter().enumerate() {
+        let mut sum = 0;
+        for item in *items {
+            match item.parse::<i64>() {
+                Ok(n) => {
+                    log::debug!("batch {batch}: parsed {n}");
+                    sum += n;
+                    summary.parsed += 1;
+                }
+                Err(e) => {
+                    log::warn!("batch {batch}: skipping {item:?}: {e}");
+                    summary.skipped += 1;
+                }
+            }
+        }
+        log::info!("batch {batch}: {} items, sum {sum}", items.len());
+        summary.sum += sum;
+    }
+    summary
+}
+
+/// Same as [sum_eprintln] via [tracing], the batch number lives in a span instead of each message
+pub fn sum_tracing(batches: &[&[&str]]) -> Summary {
+    let mut summary = Summary::default();
+    for (batch, items) in batches.iter().enumerate() {
+        let _span = tracing::info_span!("batch", batch).entered();
+        let mut sum = 0;
+        for item in *items {
+            match item.parse::<i64>() {
+                Ok(n) => {
+                    tracing::debug!(n, "parsed");
+                    sum += n;
+                    summary.parsed += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(item, error = %e, "skipping");
+                    summary.skipped += 1;
+                }
+            }
+        }
+        tracing::info!(items = items.len(), sum, "done");
+        summary.sum += sum;
+    }
+    summary
+}
+
+/// What [ReportLayer] saw
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Counts {
+    /// Events per level, e.g. `WARN`
+    pub events: BTreeMap<String, u64>,
+    /// Events per innermost span name \[`-` outside of any span\]
+    pub by_span: BTreeMap<String, u64>,
+    /// Spans created per name
+    pub spans: BTreeMap<String, u64>,
+}
+
+/// A [Layer] counting events and spans, to be turned into a [Report] at the end of a run.
+/// Cloning shares the counts, keep a clone to read them after handing one to the subscriber.
+#[derive(Debug, Default, Clone)]
+pub struct ReportLayer {
+    counts: Arc<Mutex<Counts>>,
+}
+
+impl ReportLayer {
+    pub fn counts(&self) -> Counts {
+        self.counts.lock().unwrap().clone()
+    }
+
+    pub fn report(&self, title: &str) -> Report {
+        let counts = self.counts();
+        let mut report = Report::new(title);
+        report
+            .push("events", &counts.events)
+            .push("by_span", &counts.by_span)
+            .push("spans", &counts.spans);
+        report
+    }
+}
+
+impl<S> Layer<S> for ReportLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: Context<'_, S>) {
+        let mut counts = self.counts.lock().unwrap();
+        *counts
+            .spans
+            .entry(attrs.metadata().name().into())
+            .or_default() += 1;
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let span = ctx.event_span(event).map_or("-", |s| s.name());
+        let mut counts = self.counts.lock().unwrap();
+        *counts
+            .events
+            .entry(event.metadata().level().to_string())
+            .or_default() += 1;
+        *counts.by_span.entry(span.into()).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const EXPECTED: Summary = Summary {
+        parsed: 7,
+        skipped: 2,
+        sum: 17,
+    };
+
+    #[test]
+    fn test_same_result() {
+        assert_eq!(sum_eprintln(BATCHES, false), EXPECTED);
+        assert_eq!(sum_log(BATCHES), EXPECTED);
+        // No subscriber for this thread, events go nowhere
+        assert_eq!(sum_tracing(BATCHES), EXPECTED);
+    }
+
+    #[test]
+    fn test_report_layer() {
+        let layer = ReportLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let summary = tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("outside");
+            sum_tracing(BATCHES)
+        });
+        assert_eq!(summary, EXPECTED);
+
+        let counts = layer.counts();
+        let n = |map: &BTreeMap<String, u64>, key: &str| map.get(key).copied();
+        assert_eq!(n(&counts.events, "DEBUG"), Some(7));
+        assert_eq!(n(&counts.events, "WARN"), Some(2));
+        assert_eq!(n(&counts.events, "INFO"), Some(3 + 1));
+        assert_eq!(n(&counts.by_span, "batch"), Some(7 + 2 + 3));
+        assert_eq!(n(&counts.by_span, "-"), Some(1));
+        assert_eq!(n(&counts.spans, "batch"), Some(3));
+
+        let report = layer.report("logging");
+        assert_eq!(report.items[0].label, "events");
+        assert_eq!(report.items[0].value["WARN"], 2);
+    }
+}
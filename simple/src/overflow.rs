@@ -0,0 +1,159 @@
+//! Integer overflow: what `+` does depends on the profile, the explicit methods don't
+//!
+//! | profile | `overflow-checks` | `u8::MAX + 1` |
+//! | --- | --- | --- |
+//! | dev / test | on \[follows `debug-assertions`\] | panics `attempt to add with overflow` |
+//! | release / bench | off | wraps to `0` \[well defined, _not_ UB like C's signed overflow\] |
+//!
+//! The methods behave the same everywhere:
+//!
+//! | method | `250u8 + 10` | `i8::MIN / -1` |
+//! | --- | --- | --- |
+//! | `checked_add` / `checked_div` | `None` | `None` |
+//! | `wrapping_add` / `wrapping_div` | `4` | `-128` |
+//! | `saturating_add` / `saturating_div` | `255` | `127` |
+//! | `overflowing_add` / `overflowing_div` | `(4, true)` | `(-128, true)` |
+//! | [std::num::Wrapping] `+` | `4` | `-128` |
+//!
+//! NB: Division by zero always panics, even `wrapping_div` \[only `checked_div` avoids it\].
+//!
+//! See both behaviors of `+`:
+//! ```sh
+//! cargo run --bin overflow             # panics
+//! cargo run --bin overflow --release   # wraps
+//! ```
+//! The cost of checking is benchmarked in `benches/overflow.rs`:
+//! ```sh
+//! cargo bench -p simple --bench overflow
+//! ```
+//!
+//! A constant overflow is caught at compile time regardless of profile:
+//! ```compile_fail
+//! let x: u8 = 255 + 1; // error: this arithmetic operation will overflow
+//! ```
+//!
+//! re: [Overflow](https://doc.rust-lang.org/reference/expressions/operator-expr.html#overflow)
+
+use std::num::Wrapping;
+
+/// Whether plain `+` panics on overflow in the current build
+///
+/// NB: Only a guess. `overflow-checks` can be set independently in the profile but
+/// `cfg(overflow_checks)` is nightly only.
+pub const OVERFLOW_CHECKS: bool = cfg!(debug_assertions);
+
+/// Plain `+`: panics or wraps depending on the profile
+pub fn add_plain(a: u8, b: u8) -> u8 {
+    a + b
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AllAdds {
+    pub checked: Option<u8>,
+    pub wrapping: u8,
+    pub saturating: u8,
+    pub overflowing: (u8, bool),
+    pub wrapping_type: Wrapping<u8>,
+}
+
+pub fn all_adds(a: u8, b: u8) -> AllAdds {
+    AllAdds {
+        checked: a.checked_add(b),
+        wrapping: a.wrapping_add(b),
+        saturating: a.saturating_add(b),
+        overflowing: a.overflowing_add(b),
+        wrapping_type: Wrapping(a) + Wrapping(b),
+    }
+}
+
+/// Sums used by the benchmark. `black_box` is applied by the caller.
+///
+/// NB: `sum()` is plain `+` too, it follows the caller's `overflow-checks` even though std is
+/// precompiled in release \[`#[rustc_inherit_overflow_checks]`\]
+pub fn sum_plain(v: &[u32]) -> u32 {
+    v.iter().sum()
+}
+
+pub fn sum_wrapping(v: &[u32]) -> u32 {
+    v.iter().fold(0, |acc: u32, x| acc.wrapping_add(*x))
+}
+
+/// Stops at the first overflow
+pub fn sum_checked(v: &[u32]) -> Option<u32> {
+    v.iter().try_fold(0, |acc: u32, x| acc.checked_add(*x))
+}
+
+pub fn sum_saturating(v: &[u32]) -> u32 {
+    v.iter().fold(0, |acc: u32, x| acc.saturating_add(*x))
+}
+
+/// Hash-style mixing where wrapping is the intent \[`Wrapping` saves writing `wrapping_*` on every
+/// operation\]
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = Wrapping(0xcbf2_9ce4_8422_2325u64);
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash *= 0x0000_0100_0000_01b3;
+    }
+    hash.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_adds() {
+        assert_eq!(
+            all_adds(250, 10),
+            AllAdds {
+                checked: None,
+                wrapping: 4,
+                saturating: 255,
+                overflowing: (4, true),
+                wrapping_type: Wrapping(4),
+            }
+        );
+        assert_eq!(all_adds(1, 2).checked, Some(3));
+
+        assert_eq!(i8::MIN.checked_div(-1), None);
+        assert_eq!(i8::MIN.wrapping_div(-1), -128);
+        assert_eq!(i8::MIN.saturating_div(-1), 127);
+        assert_eq!(i8::MIN.overflowing_div(-1), (-128, true));
+        assert_eq!(1u8.checked_div(0), None);
+        // Signed: abs and negation of MIN overflow too
+        assert_eq!(i32::MIN.checked_abs(), None);
+        assert_eq!(i32::MIN.wrapping_neg(), i32::MIN);
+    }
+
+    #[test]
+    fn test_sums() {
+        let v = [u32::MAX, 2];
+        assert_eq!(sum_wrapping(&v), 1);
+        assert_eq!(sum_checked(&v), None);
+        assert_eq!(sum_saturating(&v), u32::MAX);
+        assert_eq!(sum_plain(&[1, 2, 3]), 6);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    /// `cargo test` uses the dev profile, so overflow checks are on \[`cargo test --release`
+    /// skips this test\]
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "attempt to add with overflow")]
+    fn test_plain_add_panics() {
+        add_plain(std::hint::black_box(255), 1);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_plain_add_wraps() {
+        assert_eq!(add_plain(std::hint::black_box(255), 1), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn test_wrapping_div_by_zero() {
+        let _ = std::hint::black_box(1u8).wrapping_div(std::hint::black_box(0));
+    }
+}
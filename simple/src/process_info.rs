@@ -0,0 +1,193 @@
+//! Process level facts the other demos lean on: how big the stacks are, how many threads
+//! there are, what the page size is and which resource limits apply
+//!
+//! | fact | main thread | spawned thread |
+//! | --- | --- | --- |
+//! | stack size | `RLIMIT_STACK` soft limit \[often 8 MiB, grows on demand up to it\] | 2 MiB \[std's default, `RUST_MIN_STACK` or [std::thread::Builder::stack_size] to change\] |
+//! | stack bounds | [stack_bounds] via `pthread_getattr_np` \[Linux only\] | same |
+//!
+//! ```sh
+//! cargo run --bin process_info
+//! RUST_MIN_STACK=16777216 cargo run --bin process_info   # spawned threads get 16 MiB
+//! ```
+//!
+//! NB: Everything returns `Option`, `None` where the platform can't tell. Only Linux answers all
+//! of it: thread count comes from `/proc/self/status` and stack bounds from glibc/musl.
+
+use std::thread;
+
+/// `sysconf(_SC_PAGESIZE)`, the unit of `mmap`, stack guard pages and RSS
+pub fn page_size() -> Option<usize> {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf has no preconditions
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        usize::try_from(size).ok()
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// Max size of the main thread's stack
+    Stack,
+    /// Max open file descriptors
+    NoFile,
+    /// Max address space
+    AddressSpace,
+}
+
+/// `None` is `RLIM_INFINITY`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Rlimit {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+/// `getrlimit`
+pub fn rlimit(resource: Resource) -> Option<Rlimit> {
+    #[cfg(unix)]
+    {
+        let resource = match resource {
+            Resource::Stack => libc::RLIMIT_STACK,
+            Resource::NoFile => libc::RLIMIT_NOFILE,
+            Resource::AddressSpace => libc::RLIMIT_AS,
+        };
+        let mut lim = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: `lim` is a valid out pointer
+        if unsafe { libc::getrlimit(resource, &mut lim) } != 0 {
+            return None;
+        }
+        // Regarding clippy, rlim_t is u64 on Linux but not on every unix
+        #[allow(clippy::unnecessary_cast)]
+        let finite = |v: libc::rlim_t| (v != libc::RLIM_INFINITY).then_some(v as u64);
+        Some(Rlimit {
+            soft: finite(lim.rlim_cur),
+            hard: finite(lim.rlim_max),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = resource;
+        None
+    }
+}
+
+/// Threads of this process, from the `Threads:` line of `/proc/self/status`
+pub fn thread_count() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))
+        .and_then(|n| n.trim().parse().ok())
+}
+
+/// `(lowest address, size)` of the calling thread's stack, including the guard page
+pub fn stack_bounds() -> Option<(usize, usize)> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut attr = std::mem::MaybeUninit::<libc::pthread_attr_t>::uninit();
+        let mut addr = std::ptr::null_mut();
+        let mut size = 0;
+        // SAFETY: pthread_getattr_np initializes `attr` on success, which is destroyed again
+        // after reading the stack out of it
+        unsafe {
+            if libc::pthread_getattr_np(libc::pthread_self(), attr.as_mut_ptr()) != 0 {
+                return None;
+            }
+            let rc = libc::pthread_attr_getstack(attr.as_ptr(), &mut addr, &mut size);
+            libc::pthread_attr_destroy(attr.as_mut_ptr());
+            if rc != 0 {
+                return None;
+            }
+        }
+        Some((addr as usize, size))
+    }
+    #[cfg(not(target_os = "linux"))]
+    None
+}
+
+/// Bytes left below the caller's frame \[stacks grow down on every platform Rust supports\]
+#[inline(never)]
+pub fn stack_remaining() -> Option<usize> {
+    let (lo, _) = stack_bounds()?;
+    let here = 0u8;
+    Some((&here as *const u8 as usize).saturating_sub(lo))
+}
+
+/// Run `f` on a thread with a `stack_size` bytes stack and wait for it, a panic in `f` goes on
+/// in the caller
+pub fn with_stack<T: Send + 'static>(
+    stack_size: usize,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> std::io::Result<T> {
+    let handle = thread::Builder::new()
+        .name(format!("stack-{stack_size}"))
+        .stack_size(stack_size)
+        .spawn(f)?;
+    Ok(handle
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: usize = 1 << 20;
+
+    /// Uses at least `depth` KiB of stack, returns what is left at the deepest point
+    fn recurse(depth: usize) -> usize {
+        let buf = std::hint::black_box([0u8; 1024]);
+        let left = match depth {
+            0 => stack_remaining().unwrap(),
+            _ => recurse(depth - 1),
+        };
+        std::hint::black_box(&buf);
+        left
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_page_size_and_rlimit() {
+        let page = page_size().unwrap();
+        assert!(page >= 4096 && page.is_power_of_two());
+        let stack = rlimit(Resource::Stack).unwrap();
+        if let (Some(soft), Some(hard)) = (stack.soft, stack.hard) {
+            assert!(soft <= hard);
+        }
+        assert!(rlimit(Resource::NoFile).unwrap().soft.unwrap() > 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_thread_count() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let handle = thread::spawn(move || rx.recv());
+        // Other tests start and stop threads concurrently, so only a lower bound holds
+        assert!(thread_count().unwrap() >= 2);
+        drop(tx);
+        handle.join().unwrap().unwrap_err();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_with_stack() {
+        for size in [MIB, 4 * MIB] {
+            let (_, actual) = with_stack(size, || stack_bounds().unwrap()).unwrap();
+            // NB: Not always equal, glibc hands out a cached stack of an exited thread if it is
+            // big enough [other tests exit threads with 2 MiB stacks all the time]
+            assert!(actual >= size, "{size} => {actual}");
+        }
+        // 2 MiB of frames would overflow the 2 MiB default
+        let (left_before, left_deepest) =
+            with_stack(8 * MIB, || (stack_remaining().unwrap(), recurse(2 * 1024))).unwrap();
+        assert!(left_before > 7 * MIB);
+        // Debug builds use about twice that, frames are bigger without optimizations
+        assert!(left_before - left_deepest >= 2 * MIB);
+    }
+}
@@ -0,0 +1,130 @@
+//! Pointer provenance: a pointer is more than its address
+//!
+//! Every pointer carries _provenance_, i.e. which allocation (and which part of it) it may access.
+//! A `usize` has none. So `ptr as usize as *const T` has to _guess_ the provenance when turning
+//! the integer back into a pointer, which is what makes such round trips hard for compilers and
+//! tools like Miri to reason about.
+//!
+//! | API | provenance | Miri `-Zmiri-strict-provenance` |
+//! | --- | --- | --- |
+//! | `ptr as usize` then `addr as *const T` | implicitly exposed, then guessed | ❌ error on the int-to-ptr cast |
+//! | [expose_provenance()](pointer::expose_provenance) / [std::ptr::with_exposed_provenance()] | same, but explicit | ❌ same error |
+//! | [addr()](pointer::addr) + [with_addr()](pointer::with_addr) / [map_addr()](pointer::map_addr) | kept from the original pointer | ✅ ok |
+//!
+//! The strict APIs (stable since 1.84) never turn an integer into a pointer on its own. The new
+//! pointer always inherits provenance from an existing one.
+//!
+//! Run the tests under Miri to see which patterns get flagged. Without the flag Miri only warns
+//! `integer-to-pointer cast`. With it, the first such cast aborts the run, so run one at a time:
+//! ```sh
+//! cargo +nightly miri test -p simple --lib provenance
+//! MIRIFLAGS=-Zmiri-strict-provenance cargo +nightly miri test -p simple --lib provenance::tests::test_xor
+//! ```
+//! For actual UB \[right address, wrong provenance\] see `test_ub4()` in `to_ub_or_not_ub.rs`.
+//!
+//! re: [Strict provenance](https://doc.rust-lang.org/std/ptr/index.html#strict-provenance)
+
+/// Low bits of an aligned pointer are always zero, so they can store a small tag
+pub const TAG_MASK: usize = align_of::<u64>() - 1;
+
+/// Pack `tag` into the low bits of `ptr` the old way \[round trip through `usize`\]
+///
+/// Works, but the int-to-ptr cast makes Miri with `-Zmiri-strict-provenance` fail.
+pub fn tag_via_usize(ptr: *const u64, tag: usize) -> *const u64 {
+    assert!(tag <= TAG_MASK);
+    ((ptr as usize) | tag) as *const u64
+}
+
+/// Undo [tag_via_usize()]
+pub fn untag_via_usize(tagged: *const u64) -> (*const u64, usize) {
+    let addr = tagged as usize;
+    ((addr & !TAG_MASK) as *const u64, addr & TAG_MASK)
+}
+
+/// Same as [tag_via_usize()] but provenance comes along with the pointer
+pub fn tag_strict(ptr: *const u64, tag: usize) -> *const u64 {
+    assert!(tag <= TAG_MASK);
+    ptr.map_addr(|addr| addr | tag)
+}
+
+/// Undo [tag_strict()]
+pub fn untag_strict(tagged: *const u64) -> (*const u64, usize) {
+    (
+        tagged.map_addr(|addr| addr & !TAG_MASK),
+        tagged.addr() & TAG_MASK,
+    )
+}
+
+/// Index into `slice` by computing an address \[e.g. received from somewhere as a number\].
+/// [with_addr()](pointer::with_addr) borrows the provenance of the slice pointer.
+///
+/// NB: Provenance only covers the original allocation. An address outside of it gives a pointer
+/// that must not be dereferenced, even if some other allocation happens to live there.
+pub fn element_at_addr(slice: &[u32], addr: usize) -> Option<u32> {
+    let base = slice.as_ptr();
+    let range = base.addr()..base.addr() + size_of_val(slice);
+    if !range.contains(&addr) || !(addr - base.addr()).is_multiple_of(size_of::<u32>()) {
+        return None;
+    }
+    // SAFETY: In bounds and aligned [checked above], and `with_addr` keeps the slice's provenance
+    Some(unsafe { *base.with_addr(addr) })
+}
+
+/// XOR linked list trick: store `prev ^ next` in one word. Only works with exposed provenance as
+/// the result is not derived from any single pointer.
+pub fn xor_addrs(a: *const u64, b: *const u64) -> usize {
+    a.expose_provenance() ^ b.expose_provenance()
+}
+
+/// Recover `b` from `xor_addrs(a, b)` and `a`
+pub fn unxor(x: usize, a: *const u64) -> *const u64 {
+    std::ptr::with_exposed_provenance(x ^ a.addr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_strict() {
+        let value = 42u64;
+        let tagged = tag_strict(&value, 5);
+        let (ptr, tag) = untag_strict(tagged);
+        assert_eq!(tag, 5);
+        // SAFETY: Same address and provenance as &value
+        assert_eq!(unsafe { *ptr }, 42);
+    }
+
+    /// Fails under Miri with -Zmiri-strict-provenance:
+    /// ```text
+    /// error: unsupported operation: integer-to-pointer casts and `ptr::with_exposed_provenance` are not supported with `-Zmiri-strict-provenance`
+    /// ```
+    #[test]
+    fn test_tag_via_usize() {
+        let value = 42u64;
+        let tagged = tag_via_usize(&value, 5);
+        let (ptr, tag) = untag_via_usize(tagged);
+        assert_eq!(tag, 5);
+        // SAFETY: Same address and the provenance of &value was exposed by the `as usize` cast
+        assert_eq!(unsafe { *ptr }, 42);
+    }
+
+    #[test]
+    fn test_element_at_addr() {
+        let v = [10, 20, 30];
+        let addr_of_1 = (&v[1] as *const u32).addr();
+        assert_eq!(element_at_addr(&v, addr_of_1), Some(20));
+        assert_eq!(element_at_addr(&v, addr_of_1 + 1), None);
+        assert_eq!(element_at_addr(&v[..1], addr_of_1), None);
+    }
+
+    /// Same error as [test_tag_via_usize] under Miri with -Zmiri-strict-provenance
+    #[test]
+    fn test_xor() {
+        let (a, b) = (1u64, 2u64);
+        let x = xor_addrs(&a, &b);
+        let b_ptr = unxor(x, &a);
+        // SAFETY: Provenance of &b was exposed by xor_addrs()
+        assert_eq!(unsafe { *b_ptr }, 2);
+    }
+}
@@ -0,0 +1,186 @@
+//! Tour of the [rand] crate: generators, distributions and filling buffers
+//!
+//! | generator | seeded? | crypto? | portable output? | use for |
+//! | --- | --- | --- | --- | --- |
+//! | [rand::rng()] \[`ThreadRng`\] | from the OS, reseeds periodically | ✅ | n/a | default choice |
+//! | [StdRng] | [SeedableRng::seed_from_u64] | ✅ | ❌ algorithm may change between rand versions | reproducible runs of _this_ build |
+//! | [SmallRng] | same | ❌ | ❌ also may differ per platform | fast simulations |
+//! | `rand::rngs::SysRng` | n/a, asks the OS every time | ✅ | n/a | keys, seeds |
+//!
+//! NB: For output that must stay the same across releases \[e.g. checked in test fixtures\] pick
+//! a concrete algorithm crate such as `rand_chacha` or `rand_pcg` instead of `StdRng`/`SmallRng`.
+//!
+//! | distribution | samples |
+//! | --- | --- |
+//! | `StandardUniform` \[`rng.random::<T>()`\] | full range for ints, `[0, 1)` for floats |
+//! | [Uniform] \[`rng.random_range(a..b)`\] | `a..b` \[unlike `next_u32() % n` which favors the low values\] |
+//! | [Bernoulli] \[`rng.random_bool(p)`\] | `true` with probability `p` |
+//! | [WeightedIndex] | index `i` with probability `w[i] / sum(w)` |
+//! | [Alphanumeric] | `[A-Za-z0-9]` as `u8` \[or a `String` via `sample_string`\] |
+//!
+//! [SeededBytes] is the deterministic byte stream also behind the async
+//! `async_stuff::seeded::SeededReader`, so sync and async readers give identical bytes for a
+//! seed.
+//!
+//! re: [The Rust Rand Book](https://rust-random.github.io/book/)
+
+use rand::distr::{Alphanumeric, Bernoulli, SampleString, Uniform, weighted::WeightedIndex};
+use rand::prelude::*;
+use std::io::{self, Read};
+
+/// Same seed, same numbers \[for this build of rand, see the NB above\]
+pub fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+pub fn dice(rng: &mut impl Rng, n: usize) -> Vec<u8> {
+    let d6 = Uniform::new_inclusive(1, 6).unwrap();
+    rng.sample_iter(d6).take(n).collect()
+}
+
+/// How many of `n` flips came up heads
+pub fn coin_flips(rng: &mut impl Rng, n: usize, p: f64) -> usize {
+    let coin = Bernoulli::new(p).unwrap();
+    (0..n).filter(|_| rng.sample(coin)).count()
+}
+
+/// Pick items proportionally to their weight
+pub fn weighted_picks<'a>(rng: &mut impl Rng, items: &[(&'a str, u32)], n: usize) -> Vec<&'a str> {
+    let dist = WeightedIndex::new(items.iter().map(|(_, w)| *w)).unwrap();
+    (0..n).map(|_| items[dist.sample(rng)].0).collect()
+}
+
+pub fn token(rng: &mut impl Rng, len: usize) -> String {
+    Alphanumeric.sample_string(rng, len)
+}
+
+pub fn shuffled<T: Clone>(rng: &mut impl Rng, items: &[T]) -> Vec<T> {
+    let mut v = items.to_vec();
+    v.shuffle(rng);
+    v
+}
+
+/// Deterministic, optionally bounded stream of random bytes
+///
+/// Generates whole 8 byte blocks \[`next_u64().to_le_bytes()`\] and hands them out in whatever
+/// sizes are asked for. Calling `fill_bytes()` directly on every read would _not_ do: a generator
+/// may throw away the unused rest of a word, so reading 3 + 5 bytes could differ from reading 8.
+pub struct SeededBytes<R = StdRng> {
+    rng: R,
+    block: [u8; 8],
+    pos: usize,
+    remaining: Option<u64>,
+}
+
+impl SeededBytes {
+    pub fn new(seed: u64) -> Self {
+        Self::from_rng(seeded(seed))
+    }
+}
+
+impl<R: Rng> SeededBytes<R> {
+    pub fn from_rng(rng: R) -> Self {
+        Self {
+            rng,
+            block: [0; 8],
+            pos: 8,
+            remaining: None,
+        }
+    }
+
+    /// End the stream \[EOF\] after `len` bytes
+    pub fn take(mut self, len: u64) -> Self {
+        self.remaining = Some(len);
+        self
+    }
+
+    /// Fill as much of `buf` as the limit allows, returns the count \[0 means EOF\]
+    pub fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let n = match self.remaining {
+            Some(rem) => buf.len().min(usize::try_from(rem).unwrap_or(usize::MAX)),
+            None => buf.len(),
+        };
+        for b in &mut buf[..n] {
+            if self.pos == self.block.len() {
+                self.block = self.rng.next_u64().to_le_bytes();
+                self.pos = 0;
+            }
+            *b = self.block[self.pos];
+            self.pos += 1;
+        }
+        if let Some(rem) = &mut self.remaining {
+            *rem -= n as u64;
+        }
+        n
+    }
+}
+
+impl<R: Rng> Read for SeededBytes<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.fill(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_is_reproducible() {
+        let a: Vec<u32> = seeded(7).random_iter().take(4).collect();
+        let b: Vec<u32> = seeded(7).random_iter().take(4).collect();
+        let c: Vec<u32> = seeded(8).random_iter().take(4).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        let mut small = SmallRng::seed_from_u64(7);
+        assert_eq!(
+            dice(&mut small, 3),
+            dice(&mut SmallRng::seed_from_u64(7), 3)
+        );
+    }
+
+    #[test]
+    fn test_distributions() {
+        let mut rng = seeded(1);
+        assert!(dice(&mut rng, 1_000).iter().all(|d| (1..=6).contains(d)));
+        let heads = coin_flips(&mut rng, 10_000, 0.25);
+        assert!((2_000..3_000).contains(&heads), "{heads}");
+        let picks = weighted_picks(&mut rng, &[("never", 0), ("always", 1)], 10);
+        assert!(picks.iter().all(|p| *p == "always"));
+        let t = token(&mut rng, 16);
+        assert!(t.len() == 16 && t.chars().all(|c| c.is_ascii_alphanumeric()));
+        let mut s = shuffled(&mut rng, &[1, 2, 3, 4, 5]);
+        s.sort();
+        assert_eq!(s, [1, 2, 3, 4, 5]);
+        // Thread local generator, different every run
+        let x: f64 = rand::rng().random();
+        assert!((0.0..1.0).contains(&x));
+    }
+
+    #[test]
+    fn test_seeded_bytes_chunking() {
+        let mut whole = [0u8; 20];
+        assert_eq!(SeededBytes::new(3).fill(&mut whole), 20);
+
+        let mut parts = [0u8; 20];
+        let mut src = SeededBytes::new(3);
+        for chunk in parts.chunks_mut(3) {
+            src.fill(chunk);
+        }
+        assert_eq!(whole, parts);
+
+        let mut filled = [0u8; 20];
+        seeded(3).fill(&mut filled[..]);
+        assert_ne!(filled, [0; 20]);
+    }
+
+    #[test]
+    fn test_seeded_bytes_read() {
+        let mut v = Vec::new();
+        SeededBytes::new(3).take(13).read_to_end(&mut v).unwrap();
+        assert_eq!(v.len(), 13);
+        let mut first = [0u8; 13];
+        SeededBytes::new(3).fill(&mut first);
+        assert_eq!(v, first);
+    }
+}
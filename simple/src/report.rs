@@ -0,0 +1,125 @@
+//! JSON report of a demo run: what ran, when, how long and what it measured
+//!
+//! ```
+//! use simple::report::Report;
+//!
+//! let mut report = Report::new("overflow");
+//! report.push("checked_add", 250u8.checked_add(10));
+//! report.push("wrapping_add", 250u8.wrapping_add(10));
+//! let json = report.finish().to_json();
+//! assert!(json.contains(r#""label": "wrapping_add""#));
+//! assert!(json.contains(r#""value": 4"#));
+//! ```
+//!
+//! Timestamps are RFC 3339 in UTC and durations are seconds as `f64`, see [crate::clocks] for why.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Report {
+    pub title: String,
+    /// Wall clock, for humans and for ordering reports
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    /// Monotonic, unaffected by clock changes during the run
+    #[serde(with = "secs_f64")]
+    pub elapsed: Duration,
+    pub items: Vec<Item>,
+    #[serde(skip)]
+    start: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Item {
+    pub label: String,
+    pub value: Value,
+}
+
+impl Report {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            started_at: OffsetDateTime::now_utc(),
+            elapsed: Duration::ZERO,
+            items: Vec::new(),
+            start: Some(Instant::now()),
+        }
+    }
+
+    /// Anything serializable, e.g. numbers, strings, `Option`s or structs deriving [Serialize]
+    pub fn push(&mut self, label: impl Into<String>, value: impl Serialize) -> &mut Self {
+        let value = serde_json::to_value(value).unwrap_or_else(|e| Value::String(e.to_string()));
+        self.items.push(Item {
+            label: label.into(),
+            value,
+        });
+        self
+    }
+
+    /// Stop the clock
+    pub fn finish(&mut self) -> &mut Self {
+        if let Some(start) = self.start.take() {
+            self.elapsed = start.elapsed();
+        }
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Report is always serializable")
+    }
+
+    pub fn from_json(s: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(s)
+    }
+}
+
+/// `Duration` as fractional seconds \[serde's default is `{"secs": .., "nanos": ..}`\]
+pub mod secs_f64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_f64(d.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(d)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut report = Report::new("demo");
+        report
+            .push("n", 42)
+            .push("s", "text")
+            .push("none", None::<u8>);
+        report.finish();
+        let json = report.to_json();
+        let back = Report::from_json(&json).unwrap();
+        // Nanoseconds may not survive the trip through f64
+        assert!(back.elapsed.abs_diff(report.elapsed) < Duration::from_micros(1));
+        assert_eq!(back.started_at, report.started_at);
+        assert_eq!(back.items, report.items);
+        assert_eq!(back.items[2].value, Value::Null);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let report = Report::from_json(
+            r#"{"title": "t", "started_at": "2026-01-02T03:04:05Z", "elapsed": 1.5, "items": []}"#,
+        )
+        .unwrap();
+        assert_eq!(report.elapsed, Duration::from_millis(1_500));
+        assert_eq!(report.started_at.year(), 2026);
+        assert!(Report::from_json(r#"{"title": "t", "started_at": "2026-01-02T03:04:05Z", "elapsed": -1, "items": []}"#).is_err());
+    }
+}
@@ -84,4 +84,67 @@ mod tests {
             assert_eq!(x, [3, 1, 0, 0, 0, 0, 0, 0]);
         }
     }
+
+    #[test]
+    fn test_ub3() {
+        use std::hint::black_box;
+        use std::mem::ManuallyDrop;
+
+        let mut s = ManuallyDrop::new(String::from("hi"));
+        unsafe {
+            // ManuallyDrop::drop() is unsafe because nothing stops us from using the value after
+            ManuallyDrop::drop(&mut s);
+        }
+
+        // miri will flag UB:
+        // error: Undefined Behavior: constructing invalid value of type &[u8]: encountered a dangling reference (use-after-free)
+        //     --> /root/.rustup/toolchains/nightly-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/alloc/src/vec/mod.rs:1864:13
+        //      |
+        // 1864 |             &*core::intrinsics::aggregate_raw_ptr::<*const [T], _, _>(self.as_ptr(), self.len)
+        //      |             ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ Undefined Behavior occurred here
+        //      |
+        //      = help: this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior
+        //      = help: see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information
+        //      = note: this is on thread `to_ub_or_not_ub`
+        //      = note: stack backtrace:
+        //              0: std::vec::Vec::<u8>::as_slice
+        //                  at /root/.rustup/toolchains/nightly-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/alloc/src/vec/mod.rs:1864:13: 1864:95
+        //              1: std::string::String::as_bytes
+        //                  at /root/.rustup/toolchains/nightly-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/alloc/src/string.rs:1455:9: 1455:28
+        //              2: to_ub_or_not_ub::tests::test_ub3
+        //                  at simple/src/to_ub_or_not_ub.rs:117:21: 117:33
+        // Regular tests don't notice as the freed memory is usually still mapped.
+        let first = s.as_bytes()[0]; // use after free
+        black_box(first);
+    }
+
+    #[test]
+    fn test_ub4() {
+        let a = [1u32, 2];
+        let b = 3u32;
+        // Address of b but provenance of a (see provenance.rs)
+        let p = a.as_ptr().with_addr((&b as *const u32).addr());
+        // miri will flag UB:
+        // error: Undefined Behavior: memory access failed: attempting to access 4 bytes, but got alloc59743+0xc which is at or beyond the end of the allocation of size 8 bytes
+        //    --> simple/src/to_ub_or_not_ub.rs:147:28
+        //     |
+        // 147 |         let val = unsafe { *p };
+        //     |                            ^^ Undefined Behavior occurred here
+        //     |
+        //     = help: this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior
+        //     = help: see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information
+        // help: alloc59743 was allocated here:
+        //    --> simple/src/to_ub_or_not_ub.rs:123:13
+        //     |
+        // 123 |         let a = [1u32, 2];
+        //     |             ^
+        //     = note: this is on thread `to_ub_or_not_ub`
+        //     = note: stack backtrace:
+        //             0: to_ub_or_not_ub::tests::test_ub4
+        //                 at simple/src/to_ub_or_not_ub.rs:147:28: 147:30
+        //             1: to_ub_or_not_ub::tests::test_ub4::{closure#0}
+        //                 at simple/src/to_ub_or_not_ub.rs:122:18: 122:18
+        let val = unsafe { *p };
+        assert_eq!(val, 3);
+    }
 }
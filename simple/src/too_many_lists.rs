@@ -944,7 +944,7 @@ mod cursor_tests {
         cursor.move_next();
         cursor.move_prev();
         let tmp = cursor.split_before();
-        assert_eq!(m.into_iter().collect::<Vec<_>>(), &[]);
+        assert_eq!(m.into_iter().collect::<Vec<_>>(), &[] as &[u32]);
         m = tmp;
         let mut cursor = m.cursor_mut();
         cursor.move_next();
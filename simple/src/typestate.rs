@@ -0,0 +1,152 @@
+//! Typestate: encode a state machine in the type so invalid transitions don't compile
+//!
+//! The states are zero-sized marker types \[see [crate::zst]\], so `Connection<Connected>` is
+//! exactly as big as the data it holds. Transitions take `self` by value so the old state can't be
+//! used afterwards.
+//!
+//! ```text
+//! Disconnected --connect()--> Connected --login()--> Authenticated
+//!       ^                         |                        |
+//!       +------ disconnect() -----+------------------------+
+//! ```
+//!
+//! Capability token: [AdminToken] is a ZST with a private field. Only [Connection::elevate()] can
+//! create one, so holding a `&AdminToken` proves an admin logged in \[for free at run time\].
+//!
+//! re: [The Typestate Pattern in Rust](https://cliffle.com/blog/rust-typestate/)
+
+use std::marker::PhantomData;
+
+pub struct Disconnected;
+pub struct Connected;
+pub struct Authenticated;
+
+pub struct Connection<S> {
+    host: String,
+    user: Option<String>,
+    sent: Vec<String>,
+    _state: PhantomData<S>,
+}
+
+/// Proof of admin rights \[cannot be constructed outside this module\]
+///
+/// ```compile_fail,E0423
+/// use simple::typestate::AdminToken;
+/// let forged = AdminToken(()); // error[E0423]: cannot initialize a tuple struct which contains private fields
+/// ```
+#[derive(Debug)]
+pub struct AdminToken(());
+
+impl<S> Connection<S> {
+    fn into_state<T>(self) -> Connection<T> {
+        Connection {
+            host: self.host,
+            user: self.user,
+            sent: self.sent,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+}
+
+impl Connection<Disconnected> {
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            user: None,
+            sent: Vec::new(),
+            _state: PhantomData,
+        }
+    }
+
+    /// Can't send before connecting:
+    /// ```compile_fail,E0599
+    /// use simple::typestate::Connection;
+    /// let mut conn = Connection::new("db");
+    /// conn.send("hi"); // error[E0599]: no method named `send` found for struct `Connection<Disconnected>`
+    /// ```
+    pub fn connect(self) -> Connection<Connected> {
+        self.into_state()
+    }
+}
+
+impl Connection<Connected> {
+    /// The old state is moved into the new one:
+    /// ```compile_fail,E0382
+    /// use simple::typestate::Connection;
+    /// let conn = Connection::new("db").connect();
+    /// let authed = conn.login("ann");
+    /// conn.disconnect(); // error[E0382]: use of moved value: `conn`
+    /// ```
+    pub fn login(mut self, user: &str) -> Connection<Authenticated> {
+        self.user = Some(user.to_string());
+        self.into_state()
+    }
+
+    pub fn disconnect(self) -> Connection<Disconnected> {
+        self.into_state()
+    }
+}
+
+impl Connection<Authenticated> {
+    pub fn user(&self) -> &str {
+        self.user.as_deref().unwrap_or_default()
+    }
+
+    pub fn send(&mut self, msg: &str) {
+        self.sent.push(msg.to_string());
+    }
+
+    pub fn sent(&self) -> &[String] {
+        &self.sent
+    }
+
+    /// Hands out the capability token if the user is an admin
+    pub fn elevate(&self) -> Option<AdminToken> {
+        (self.user() == "admin").then_some(AdminToken(()))
+    }
+
+    /// Requires proof of admin rights \[no run-time check needed here\]
+    pub fn clear(&mut self, _proof: &AdminToken) {
+        self.sent.clear();
+    }
+
+    pub fn disconnect(mut self) -> Connection<Disconnected> {
+        self.user = None;
+        self.into_state()
+    }
+}
+
+// State markers cost nothing
+const _: () =
+    assert!(size_of::<Connection<Disconnected>>() == size_of::<Connection<Authenticated>>());
+const _: () = assert!(size_of::<AdminToken>() == 0);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        let conn = Connection::new("db").connect();
+        let mut authed = conn.login("ann");
+        authed.send("hello");
+        assert_eq!(authed.user(), "ann");
+        assert_eq!(authed.sent(), ["hello"]);
+        assert!(authed.elevate().is_none());
+        let conn = authed.disconnect().connect().disconnect();
+        assert_eq!(conn.host(), "db");
+    }
+
+    #[test]
+    fn test_capability_token() {
+        let mut admin = Connection::new("db").connect().login("admin");
+        admin.send("drop table");
+        let token = admin.elevate().unwrap();
+        admin.clear(&token);
+        assert!(admin.sent().is_empty());
+    }
+}
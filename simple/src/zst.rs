@@ -0,0 +1,113 @@
+//! Zero-sized types (ZSTs): `()`, unit structs, `[T; 0]`, `PhantomData<T>`, ...
+//!
+//! - `size_of` is 0 so values take no memory: arrays of them, struct fields of them, `Vec`s of
+//!   them.
+//! - Allocating a ZST never calls the allocator. `Box::new(())` and `Vec<()>` use a dangling (but
+//!   non-null and aligned) pointer instead, which is valid for zero-sized reads and writes.
+//! - `Vec<ZST>` has capacity `usize::MAX` from the start \[it never needs to grow\] but `len` is
+//!   still counted, so iterating yields `len` items.
+//! - Since ZST values carry no data, they're useful as type-level markers, e.g. the states and
+//!   capability token in [crate::typestate].
+//!
+//! No allocations, checked with [crate::alloc_counter]:
+//! ```
+//! # use simple::alloc_counter::{self, CountingAlloc};
+//! # #[global_allocator]
+//! # static GLOBAL: CountingAlloc = CountingAlloc;
+//! let before = alloc_counter::thread_snapshot();
+//! let b = Box::new(());
+//! let mut v = Vec::new();
+//! for _ in 0..1_000 {
+//!     v.push(());
+//! }
+//! let after = alloc_counter::thread_snapshot();
+//! assert_eq!(after.since(&before).allocs, 0);
+//! assert_eq!(v.len(), 1_000);
+//! # drop(b);
+//! ```
+//!
+//! re: [Zero Sized Types](https://doc.rust-lang.org/nomicon/exotic-sizes.html#zero-sized-types-zsts)
+//! and [Handling Zero-Sized Types](https://doc.rust-lang.org/nomicon/vec/vec-zsts.html)
+
+use std::ptr::NonNull;
+
+/// Unit struct, zero-sized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Unit;
+
+/// Also zero-sized, but with alignment 8 \[dangling pointers must still be aligned\]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlignedUnit {
+    _align: [u64; 0],
+}
+
+const _: () = assert!(size_of::<Unit>() == 0);
+const _: () = assert!(size_of::<[Unit; 1_000_000]>() == 0);
+const _: () = assert!(size_of::<(Unit, (), [String; 0])>() == 0);
+const _: () = assert!(size_of::<AlignedUnit>() == 0 && align_of::<AlignedUnit>() == 8);
+// A ZST field adds nothing (but alignment could add padding)
+const _: () = assert!(size_of::<(u32, Unit)>() == size_of::<u32>());
+
+/// Where `Vec<T>` points when nothing was allocated: [NonNull::dangling()], i.e. the alignment
+/// as an address
+pub fn vec_ptr<T>(v: &[T]) -> usize {
+    v.as_ptr() as usize
+}
+
+/// "Set" built on a `HashMap<K, ()>` costs no memory for the values \[that's how
+/// `HashSet<K>` is implemented in std\]
+pub fn dedup_count(words: &[&str]) -> usize {
+    let map: std::collections::HashMap<&str, ()> = words.iter().map(|w| (*w, ())).collect();
+    map.len()
+}
+
+/// Reading and writing through a dangling pointer is fine for ZSTs \[no bytes are accessed\]
+pub fn read_dangling() -> Unit {
+    let p = NonNull::<Unit>::dangling();
+    // SAFETY: For zero-sized reads any non-null, aligned pointer is valid
+    unsafe { p.as_ptr().read() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vec_of_zst() {
+        let mut v: Vec<Unit> = Vec::new();
+        assert_eq!(v.capacity(), usize::MAX);
+        v.extend(std::iter::repeat_n(Unit, 5));
+        assert_eq!(v.len(), 5);
+        // Iterator still yields `len` items even though they all live at the same address
+        assert_eq!(v.iter().fold(0, |n, _| n + 1), 5);
+        assert_eq!(v.pop(), Some(Unit));
+        assert_eq!(v.len(), 4);
+        // Never allocated => still dangling
+        assert_eq!(vec_ptr(&v), align_of::<Unit>());
+
+        let v: Vec<AlignedUnit> = vec![AlignedUnit::default(); 3];
+        assert_eq!(vec_ptr(&v), 8);
+
+        // Non-ZST for comparison
+        let v: Vec<u8> = Vec::new();
+        assert_eq!(v.capacity(), 0);
+    }
+
+    #[test]
+    fn test_zst_addresses() {
+        // All elements share one address (their offsets are all 0)
+        let arr = [Unit; 3];
+        assert_eq!(&arr[0] as *const Unit, &arr[2] as *const Unit);
+        let b1 = Box::new(Unit);
+        let b2 = Box::new(Unit);
+        assert_eq!(&*b1 as *const Unit, &*b2 as *const Unit);
+    }
+
+    #[test]
+    fn test_zst_values() {
+        assert_eq!(dedup_count(&["a", "b", "a"]), 2);
+        assert_eq!(read_dangling(), Unit);
+        // Iterating over a ZST iterator still counts
+        assert_eq!(std::iter::repeat_n((), 10).count(), 10);
+    }
+}
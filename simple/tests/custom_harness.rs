@@ -0,0 +1,212 @@
+//! Minimal custom test harness \[instead of libtest\]
+//!
+//! `harness = false` in `simple/Cargo.toml` tells cargo to *not* generate a `main()` for this
+//! test target. Without libtest there is no `#[test]` attribute to discover tests, so we provide:
+//! - discovery: [register_tests!] macro collects functions into the static [TESTS] registry
+//! - filtering: positional args are substring filters, `--skip` ones exclude, `--exact` makes both
+//!   exact
+//! - `--list`, `--ignored` and `--include-ignored` like libtest
+//! - timing: each test is timed with [Instant]
+//! - isolation: panics are caught with [std::panic::catch_unwind] so one failure doesn't stop the run
+//!
+//! ```sh
+//! cargo test --test custom_harness
+//! cargo test --test custom_harness -- list_ --list
+//! cargo test --test custom_harness -- --include-ignored
+//! ```
+//!
+//! re: [libtest-mimic](https://crates.io/crates/libtest-mimic) for a more complete implementation
+//! and [inventory](https://crates.io/crates/inventory) for distributed (cross-file) registration
+
+use std::panic;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use simple::too_many_lists::LinkedList;
+
+/// Entry in the registry
+struct Test {
+    name: &'static str,
+    ignored: bool,
+    run: fn(),
+}
+
+/// Define test functions and collect them into a `static TESTS: &[Test]` registry.
+///
+/// Prefix a function with `#[ignore]` to skip it unless `--ignored`/`--include-ignored` is passed.
+macro_rules! register_tests {
+    ($( $(#[$ignore:ident])? fn $name:ident() $body:block )*) => {
+        $( fn $name() $body )*
+
+        static TESTS: &[Test] = &[
+            $( Test {
+                name: stringify!($name),
+                ignored: false $( || { let _ = stringify!($ignore); true } )?,
+                run: $name,
+            }, )*
+        ];
+    };
+}
+
+register_tests! {
+    fn list_push_pop_front() {
+        let mut list = LinkedList::new();
+        list.push_front(1);
+        list.push_front(2);
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), None);
+    }
+
+    fn list_push_pop_back() {
+        let mut list = LinkedList::new();
+        list.push_back(1);
+        list.push_back(2);
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_back(), Some(1));
+        assert_eq!(list.pop_back(), None);
+    }
+
+    fn list_len() {
+        let list: LinkedList<_> = (0..10).collect();
+        assert_eq!(list.len(), 10);
+    }
+
+    fn args_flags() {
+        let parse = |s: &str| Args::from(s.split_whitespace().map(String::from));
+        let args = parse("--format terse --test-threads 1 --color=never -Z unstable-options list_");
+        assert_eq!(args.filters, ["list_"]);
+        assert!(args.skip.is_empty());
+        let args = parse("--skip list_len --skip=slow list_");
+        assert_eq!(args.skip, ["list_len", "slow"]);
+        assert!(args.matches("list_push_back") && !args.matches("list_len"));
+        let args = parse("--exact --skip list list_len");
+        assert!(args.matches("list_len"));
+    }
+
+    #[ignore]
+    fn slow_sleep() {
+        std::thread::sleep(Duration::from_secs(2));
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    filters: Vec<String>,
+    skip: Vec<String>,
+    exact: bool,
+    list: bool,
+    ignored: bool,
+    include_ignored: bool,
+}
+
+impl Args {
+    fn parse() -> Self {
+        Self::from(std::env::args().skip(1))
+    }
+
+    fn from(raw: impl IntoIterator<Item = String>) -> Self {
+        let mut args = Self::default();
+        let mut raw = raw.into_iter();
+        while let Some(arg) = raw.next() {
+            // `--flag=value` as well as `--flag value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with('-') => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || inline.clone().or_else(|| raw.next()).unwrap_or_default();
+            match flag {
+                "--exact" => args.exact = true,
+                "--list" => args.list = true,
+                "--ignored" => args.ignored = true,
+                "--include-ignored" => args.include_ignored = true,
+                "--skip" => args.skip.push(value()),
+                // Accepted but ignored, with their values so those don't become filters
+                "--format" | "--test-threads" | "--color" | "-Z" => drop(value()),
+                // Silently accept other libtest flags (e.g. --nocapture) that cargo/IDEs may pass
+                s if s.starts_with('-') => {}
+                s => args.filters.push(s.to_string()),
+            }
+        }
+        args
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let hit = |f: &String| {
+            if self.exact {
+                name == f
+            } else {
+                name.contains(f.as_str())
+            }
+        };
+        (self.filters.is_empty() || self.filters.iter().any(hit)) && !self.skip.iter().any(hit)
+    }
+
+    fn should_run(&self, test: &Test) -> bool {
+        if self.include_ignored {
+            true
+        } else {
+            test.ignored == self.ignored
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let selected: Vec<&Test> = TESTS.iter().filter(|t| args.matches(t.name)).collect();
+
+    if args.list {
+        for t in &selected {
+            println!("{}: test", t.name);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    // Silence the default panic message. We report failures ourselves below.
+    panic::set_hook(Box::new(|_| {}));
+
+    println!("\nrunning {} tests (custom harness)", selected.len());
+    let (mut passed, mut failed, mut ignored) = (0, Vec::new(), 0);
+    let start = Instant::now();
+    for t in selected {
+        if !args.should_run(t) {
+            println!("test {} ... ignored", t.name);
+            ignored += 1;
+            continue;
+        }
+        let now = Instant::now();
+        let res = panic::catch_unwind(t.run);
+        let elapsed = now.elapsed();
+        match res {
+            Ok(()) => {
+                println!("test {} ... ok ({elapsed:?})", t.name);
+                passed += 1;
+            }
+            Err(payload) => {
+                println!("test {} ... FAILED ({elapsed:?})", t.name);
+                let msg = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                failed.push((t.name, msg));
+            }
+        }
+    }
+
+    for (name, msg) in &failed {
+        println!("\n---- {name} ----\n{msg}");
+    }
+    let verdict = if failed.is_empty() { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {verdict}. {passed} passed; {} failed; {ignored} ignored; finished in {:?}\n",
+        failed.len(),
+        start.elapsed()
+    );
+
+    if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,64 @@
+//! Exit codes and stderr of the bins, see [simple::exit]
+
+use simple::exit::Code;
+use std::process::{Command, Output};
+
+fn run(bin: &str, args: &[&str]) -> (Option<i32>, String) {
+    let Output { status, stderr, .. } = Command::new(bin).args(args).output().unwrap();
+    (status.code(), String::from_utf8(stderr).unwrap())
+}
+
+#[test]
+fn test_bench_report() {
+    let bin = env!("CARGO_BIN_EXE_bench-report");
+    let (code, stderr) = run(bin, &["--bogus"]);
+    assert_eq!(code, Some(Code::Usage as i32));
+    assert!(
+        stderr.starts_with("error: unknown argument --bogus\nusage: "),
+        "{stderr}"
+    );
+
+    let store = std::env::temp_dir().join(format!("exit-codes-{}.json", std::process::id()));
+    let (code, stderr) = run(bin, &["compare", "--store", store.to_str().unwrap()]);
+    assert_eq!(code, Some(Code::NoInput as i32));
+    assert!(
+        stderr.starts_with("error: need two recorded runs"),
+        "{stderr}"
+    );
+
+    std::fs::write(&store, "not json").unwrap();
+    let (code, stderr) = run(bin, &["compare", "--store", store.to_str().unwrap()]);
+    std::fs::remove_file(&store).unwrap();
+    assert_eq!(code, Some(Code::DataErr as i32));
+    // The path, then why
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2, "{stderr}");
+    assert!(lines[0].ends_with(".json"), "{stderr}");
+    assert!(
+        lines[1].starts_with("  caused by: expected ident"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_logging() {
+    let bin = env!("CARGO_BIN_EXE_logging");
+    assert_eq!(run(bin, &["eprintln"]).0, Some(0));
+    let (code, stderr) = run(bin, &["yaml"]);
+    assert_eq!(code, Some(Code::Usage as i32));
+    assert_eq!(
+        stderr,
+        "error: usage: logging [eprintln [--verbose] | log | tracing]\n"
+    );
+}
+
+#[test]
+fn test_mono_cost() {
+    let bin = env!("CARGO_BIN_EXE_mono_cost");
+    let (code, stderr) = run(bin, &["10", "x"]);
+    assert_eq!(code, Some(Code::Usage as i32));
+    assert_eq!(
+        stderr,
+        "error: \"x\": expected a number of instantiations\nusage: mono_cost [N...]\n"
+    );
+}